@@ -0,0 +1,10 @@
+// Print the test vectors as JSON. Takes an optional seed as the first argument.
+// `cargo run --example test_vectors --features test_vectors -- <seed>`
+
+use ps_sig::test_vectors::{generate, DEFAULT_SEED};
+use std::env;
+
+fn main() {
+    let seed = env::args().nth(1).map(|s| s.into_bytes()).unwrap_or_else(|| DEFAULT_SEED.to_vec());
+    println!("{}", generate(&seed).unwrap().to_json());
+}
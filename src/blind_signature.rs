@@ -1,11 +1,13 @@
 // Scheme defined in in 2016 paper, CT-RSA 2016 (eprint 2015/525), section 6.1 supporting blind signatures
 
 use crate::errors::PSError;
-use crate::keys::{Params, Sigkey, Verkey};
+use crate::keys::{wipe, Params, PreparedSigkey, Signer, Sigkey, Verkey};
 use crate::signature::Signature;
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 use crate::{ate_2_pairing, VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec};
+use rand::{CryptoRng, RngCore};
+use std::collections::HashMap;
 
 // The public key described in the paper is split into `BlindingKey` and `Verkey`. Only `Verkey` is
 // needed by the verifier. `BlindingKey` is used by the user to request a blind signature.
@@ -25,19 +27,362 @@ impl BlindingKey {
         Self { X, Y }
     }
 
+    /// Same as `new` but computed using `signer` when the signing key is not in memory. X = g^x and
+    /// Y_i = g^(x + y_i) / X.
+    pub fn from_signer<S: Signer>(signer: &S, params: &Params) -> Result<Self, PSError> {
+        let X = signer.sign_exponent(&params.g, &[])?;
+        let one = FieldElement::one();
+        let mut Y = vec![];
+        for i in 0..signer.key_size() {
+            Y.push(signer.sign_exponent(&params.g, &[(i, &one)])? - &X);
+        }
+        Ok(Self { X, Y })
+    }
+
     pub fn msg_count(&self) -> usize {
         self.Y.len()
     }
+
+    /// Whether this blinding key has the same exponents as `verkey`, i.e. e(X, g_tilde) == e(g, X_tilde) and
+    /// e(Y_i, g_tilde) == e(g, Y_tilde_i), for the holder to check before committing with it
+    pub fn is_consistent_with(&self, verkey: &Verkey, params: &Params) -> bool {
+        if self.Y.len() != verkey.Y_tilde.len() {
+            return false;
+        }
+        let neg_g = params.g.negation();
+        ate_2_pairing(&self.X, &params.g_tilde, &neg_g, &verkey.X_tilde).is_one()
+            && self
+                .Y
+                .iter()
+                .zip(verkey.Y_tilde.iter())
+                .all(|(Y, Y_tilde)| ate_2_pairing(Y, &params.g_tilde, &neg_g, Y_tilde).is_one())
+    }
+}
+
+// Proof of knowledge of committed values in a vector commitment for `SignatureGroup`
+pub use crate::pok_vc::{
+    ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+};
+
+/// Domain separation tag for deriving the randomness of blind signing with `IssuerPRFKey`
+pub const BLIND_SIGNING_PRF_DST: &[u8] = b"PS-SIG-BLIND-SIGNING-PRF";
+
+/// Long-term secret of the issuer used to derive the randomness of blind signing from the request so that
+/// the issuer does not need to keep per-session state and re-processing a request gives the same signature.
+/// `Debug` does not print the key.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IssuerPRFKey {
+    pub key: Vec<u8>,
+}
+
+impl std::fmt::Debug for IssuerPRFKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IssuerPRFKey").field("key", &"<redacted>").finish()
+    }
+}
+
+impl Default for IssuerPRFKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for IssuerPRFKey {
+    fn zeroize(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.key);
+    }
 }
 
-// Implement proof of knowledge of committed values in a vector commitment for `SignatureGroup`
-impl_PoK_VC!(
-    ProverCommittingSignatureGroup,
-    ProverCommittedSignatureGroup,
-    ProofSignatureGroup,
-    SignatureGroup,
-    SignatureGroupVec
-);
+#[cfg(feature = "zeroize")]
+impl Drop for IssuerPRFKey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for IssuerPRFKey {}
+
+impl IssuerPRFKey {
+    pub fn new() -> Self {
+        Self {
+            key: FieldElement::random().to_bytes(),
+        }
+    }
+
+    /// Derive `u` from the commitment, the known messages and `context`, which can be any other
+    /// request content like a session id. `context` is prefixed with its length so it can't be confused with
+    /// a known message.
+    pub fn derive_u(
+        &self,
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        context: &[u8],
+    ) -> FieldElement {
+        let mut bytes = BLIND_SIGNING_PRF_DST.to_vec();
+        bytes.extend_from_slice(&(self.key.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.key);
+        bytes.append(&mut commitment.to_bytes());
+        let mut indices = messages.keys().cloned().collect::<Vec<usize>>();
+        indices.sort();
+        for i in indices {
+            bytes.extend_from_slice(&(i as u64).to_be_bytes());
+            bytes.append(&mut messages[&i].to_bytes());
+        }
+        bytes.extend_from_slice(&(context.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(context);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Which messages of a partially blind issuance are hidden from the issuer in the holder's commitment, like a link
+/// secret, and which are known to it, like the issuance date. Agreed on by both parties before issuance so they
+/// place the messages at the same indices.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuanceLayout {
+    pub msg_count: usize,
+    /// Indices of the hidden messages in increasing order
+    pub hidden_indices: Vec<usize>,
+}
+
+impl IssuanceLayout {
+    /// At least one message should be hidden
+    pub fn new(msg_count: usize, hidden_indices: &[usize]) -> Result<Self, PSError> {
+        let mut hidden = hidden_indices.to_vec();
+        hidden.sort();
+        hidden.dedup();
+        if hidden.is_empty() || hidden.len() != hidden_indices.len() || hidden[hidden.len() - 1] >= msg_count {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Hidden indices {:?} should be distinct, non-empty and less than {}",
+                    hidden_indices, msg_count
+                ),
+            });
+        }
+        Ok(Self {
+            msg_count,
+            hidden_indices: hidden,
+        })
+    }
+
+    /// Indices of the messages known to the issuer in increasing order
+    pub fn known_indices(&self) -> Vec<usize> {
+        (0..self.msg_count).filter(|i| !self.hidden_indices.contains(i)).collect()
+    }
+
+    /// Bases of the commitment, Y_i for each hidden index followed by g, for the holder's proof of knowledge of
+    /// the committed messages (`ProverCommittingSignatureGroup`)
+    pub fn commitment_bases(&self, blinding_key: &BlindingKey, params: &Params) -> Result<Vec<SignatureGroup>, PSError> {
+        self.check_blinding_key(blinding_key)?;
+        let mut bases = self
+            .hidden_indices
+            .iter()
+            .map(|i| blinding_key.Y[*i].clone())
+            .collect::<Vec<_>>();
+        bases.push(params.g.clone());
+        Ok(bases)
+    }
+
+    /// Called by the holder to commit to `hidden_messages`, in order of `hidden_indices`, with `blinding` which
+    /// is later used to unblind the signature
+    pub fn commit(
+        &self,
+        hidden_messages: &[FieldElement],
+        blinding: &FieldElement,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<SignatureGroup, PSError> {
+        if hidden_messages.len() != self.hidden_indices.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.hidden_indices.len(),
+                given: hidden_messages.len(),
+            });
+        }
+        let mut bases = SignatureGroupVec::with_capacity(hidden_messages.len() + 1);
+        for b in self.commitment_bases(blinding_key, params)? {
+            bases.push(b);
+        }
+        let mut scalars = FieldElementVector::with_capacity(hidden_messages.len() + 1);
+        for m in hidden_messages {
+            scalars.push(m.clone());
+        }
+        scalars.push(blinding.clone());
+        Ok(bases.multi_scalar_mul_const_time(&scalars).unwrap())
+    }
+
+    /// Called by the issuer to sign `commitment` and `known_messages`, in order of `known_indices`, after verifying
+    /// the holder's proof of knowledge of the committed messages. The holder unblinds the result with
+    /// `BlindSignature::unblind`.
+    pub fn sign<S: Signer>(
+        &self,
+        commitment: &SignatureGroup,
+        known_messages: &[FieldElement],
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        self.check_blinding_key(blinding_key)?;
+        let known_indices = self.known_indices();
+        if known_messages.len() != known_indices.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: known_indices.len(),
+                given: known_messages.len(),
+            });
+        }
+        let messages = known_indices
+            .into_iter()
+            .zip(known_messages.iter().cloned())
+            .collect::<HashMap<usize, FieldElement>>();
+        BlindSignature::new_with_known_messages_at_indices(commitment, &messages, sigkey, blinding_key, params)
+    }
+
+    /// All messages in order of their indices, to verify the unblinded signature
+    pub fn messages(
+        &self,
+        hidden_messages: &[FieldElement],
+        known_messages: &[FieldElement],
+    ) -> Result<Vec<FieldElement>, PSError> {
+        if hidden_messages.len() + known_messages.len() != self.msg_count
+            || hidden_messages.len() != self.hidden_indices.len()
+        {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.msg_count,
+                given: hidden_messages.len() + known_messages.len(),
+            });
+        }
+        let mut hidden = hidden_messages.iter();
+        let mut known = known_messages.iter();
+        Ok((0..self.msg_count)
+            .map(|i| {
+                if self.hidden_indices.contains(&i) {
+                    hidden.next().unwrap().clone()
+                } else {
+                    known.next().unwrap().clone()
+                }
+            })
+            .collect())
+    }
+
+    fn check_blinding_key(&self, blinding_key: &BlindingKey) -> Result<(), PSError> {
+        if blinding_key.msg_count() != self.msg_count {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.msg_count,
+                given: blinding_key.msg_count(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Domain separation tag for the challenge of the proof in `BlindSignatureRequest`
+pub const BLIND_SIGNATURE_REQUEST_DST: &[u8] = b"PS-SIG-BLIND-SIGNATURE-REQUEST";
+
+/// Request of the holder for a blind signature, the commitment to the hidden messages of an `IssuanceLayout` with
+/// a proof of knowledge of its opening bound to a nonce chosen by the signer so that the request cannot be replayed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindSignatureRequest {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub commitment: SignatureGroup,
+    pub proof: ProofSignatureGroup,
+}
+
+impl BlindSignatureRequest {
+    /// Called by the holder with `hidden_messages` in order of `hidden_indices` of `layout`. Returns the request
+    /// and the blinding to unblind the signature with.
+    pub fn new(
+        layout: &IssuanceLayout,
+        hidden_messages: &[FieldElement],
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<(Self, FieldElement), PSError> {
+        let blinding = FieldElement::random();
+        let commitment = layout.commit(hidden_messages, &blinding, blinding_key, params)?;
+        let mut committing = ProverCommittingSignatureGroup::new();
+        for b in &layout.commitment_bases(blinding_key, params)? {
+            committing.commit(b, None);
+        }
+        let committed = committing.finish();
+        let challenge = Self::challenge(committed.to_bytes(), &commitment, nonce);
+        let mut secrets = hidden_messages.to_vec();
+        secrets.push(blinding.clone());
+        let proof = committed.gen_proof(&challenge, secrets.as_slice())?;
+        for s in secrets.iter_mut() {
+            wipe(s);
+        }
+        Ok((Self { commitment, proof }, blinding))
+    }
+
+    /// Called by the signer with the layout it expects and the nonce it gave the holder
+    pub fn verify_request(
+        &self,
+        layout: &IssuanceLayout,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let bases = layout.commitment_bases(blinding_key, params)?;
+        // Same bytes as `to_bytes` of the prover's committed state
+        let mut committed_bytes = vec![];
+        for b in &bases {
+            committed_bytes.append(&mut b.to_bytes());
+        }
+        committed_bytes.append(&mut self.proof.commitment.to_bytes());
+        let challenge = Self::challenge(committed_bytes, &self.commitment, nonce);
+        self.proof.verify(bases.as_slice(), &self.commitment, &challenge)
+    }
+
+    /// Called by the signer to sign the request and `known_messages`, in order of `known_indices` of `layout`.
+    /// Fails if the request does not verify.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign<S: Signer>(
+        &self,
+        layout: &IssuanceLayout,
+        known_messages: &[FieldElement],
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Signature, PSError> {
+        if !self.verify_request(layout, blinding_key, params, nonce)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Proof of knowledge of committed messages failed"),
+            });
+        }
+        layout.sign(&self.commitment, known_messages, sigkey, blinding_key, params)
+    }
+
+    fn challenge(mut committed_bytes: Vec<u8>, commitment: &SignatureGroup, nonce: &[u8]) -> FieldElement {
+        let mut bytes = BLIND_SIGNATURE_REQUEST_DST.to_vec();
+        bytes.append(&mut committed_bytes);
+        bytes.append(&mut commitment.to_bytes());
+        bytes.extend_from_slice(nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Domain separation tag for the challenge of `BlindSigningProof`
+pub const BLIND_SIGNING_PROOF_DST: &[u8] = b"PS-SIG-BLIND-SIGNING-PROOF";
+
+/// Proof by the signer that a blind signature (sigma_1, sigma_2) over a commitment C and known messages m_i was
+/// computed correctly with the signing key of its verkey, i.e. knowledge of u, x and y_i for the known messages
+/// such that sigma_1 = g^u, sigma_2 = sigma_1^(x + sum(y_i*m_i)) * C^u, X_tilde = g_tilde^x and
+/// Y_tilde_i = g_tilde^y_i. The holder verifies it before unblinding, so a misbehaving issuer is detected without
+/// verifying the unblinded signature. The part of the signature for the hidden messages is correct if the blinding
+/// key is consistent with the verkey (`BlindingKey::is_consistent_with`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindSigningProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub u_response: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub x_response: FieldElement,
+    /// Responses for y_i of the known messages in increasing order of index
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub y_responses: Vec<FieldElement>,
+}
 
 pub struct BlindSignature {}
 
@@ -52,25 +397,188 @@ impl BlindSignature {
     /// (XC)^u.Y_2^u.Y_3^u...Y_10^u can be computed using efficient multi-exponentiation techniques but it would be more efficient 
     /// if the signer could instead compute (g^u, C^u.g^{(x+y_2.m_2+y_3.m_3+...y_10.m_10).u}). The resulting signature will have the same form
     /// and can be unblinded in the same way as described in the paper.
-    pub fn new(
+    pub fn new<S: Signer>(
         commitment: &SignatureGroup,
         messages: &[FieldElement],
-        sigkey: &Sigkey,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        Self::new_with_rng(commitment, messages, sigkey, blinding_key, params, &mut rand::thread_rng())
+    }
+
+    /// Same as `new` but the random value is generated using `rng`
+    pub fn new_with_rng<S: Signer, R: RngCore + CryptoRng>(
+        commitment: &SignatureGroup,
+        messages: &[FieldElement],
+        sigkey: &S,
         blinding_key: &BlindingKey,
         params: &Params,
+        rng: &mut R,
     ) -> Result<Signature, PSError> {
         // There should be commitment to at least one message
         Self::check_blinding_key_and_messages_compat(messages, blinding_key)?;
 
-        let u = FieldElement::random();
+        let mut u = FieldElement::random_using_rng(rng);
         let offset = blinding_key.msg_count() - messages.len();
         let (sigma_1, mut sigma_2) = Signature::sign_with_sigma_1_generated_from_given_exp(
             messages, sigkey, &u, offset, &params.g,
         )?;
         sigma_2 += (commitment * &u);
+        wipe(&mut u);
+        Ok(Signature { sigma_1, sigma_2 })
+    }
+
+    /// Similar to `new` but the committed messages need not be the first ones. `messages` maps the
+    /// index of each known message to the message and `commitment` is over the messages at the
+    /// remaining indices.
+    pub fn new_with_known_messages_at_indices<S: Signer>(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        Self::new_with_known_messages_at_indices_and_rng(
+            commitment,
+            messages,
+            sigkey,
+            blinding_key,
+            params,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Same as `new_with_known_messages_at_indices` but the random value is generated using `rng`
+    pub fn new_with_known_messages_at_indices_and_rng<S: Signer, R: RngCore + CryptoRng>(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<Signature, PSError> {
+        Self::new_with_known_messages_at_indices_and_u(
+            commitment,
+            messages,
+            sigkey,
+            blinding_key,
+            params,
+            FieldElement::random_using_rng(rng),
+        )
+    }
+
+    /// Similar to `new_with_known_messages_at_indices` but the randomness is derived from the request using
+    /// `prf_key` so signing the same request again gives the same signature. `context` should contain any
+    /// request content not in `commitment` or `messages`, like a session id.
+    pub fn new_stateless_with_known_messages_at_indices<S: Signer>(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        prf_key: &IssuerPRFKey,
+        context: &[u8],
+    ) -> Result<Signature, PSError> {
+        let u = prf_key.derive_u(commitment, messages, context);
+        Self::new_with_known_messages_at_indices_and_u(commitment, messages, sigkey, blinding_key, params, u)
+    }
+
+    fn new_with_known_messages_at_indices_and_u<S: Signer>(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        u: FieldElement,
+    ) -> Result<Signature, PSError> {
+        Self::check_known_messages(messages, sigkey, blinding_key)?;
+
+        // sigma_1 = g^u
+        let sigma_1 = &params.g * &u;
+        // sigma_2 = sigma_1^{x + sum(y_i*m_i)} * commitment^u for all known m_i
+        let indexed_messages = messages.iter().map(|(i, m)| (*i, m)).collect::<Vec<_>>();
+        let sigma_2 = sigkey.sign_exponent(&sigma_1, &indexed_messages)? + (commitment * &u);
+        let mut u = u;
+        wipe(&mut u);
+        Ok(Signature { sigma_1, sigma_2 })
+    }
+
+    /// Same as `new_with_known_messages_at_indices` but with a key prepared for repeated signing, sigma_1 = g^u and
+    /// g^(u*(x + sum(y_i*m_i))) are computed with the key's table of multiples of g. The table is scanned in constant
+    /// time so neither u nor the signing key leaks through timing or cache accesses.
+    pub fn new_with_prepared_sigkey(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &PreparedSigkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        sigkey.check_params(params)?;
+        Self::check_known_messages(messages, sigkey, blinding_key)?;
+        let indexed_messages = messages.iter().map(|(i, m)| (*i, m)).collect::<Vec<_>>();
+        let mut exp = sigkey.signing_exponent(&indexed_messages)?;
+        let mut u = FieldElement::random();
+        exp = &exp * &u;
+        let sigma_1 = sigkey.g_table.mul(&u);
+        // sigma_2 = g^(u*(x + sum(y_i*m_i))) * commitment^u
+        let sigma_2 = sigkey.g_table.mul(&exp) + (commitment * &u);
+        wipe(&mut u);
+        wipe(&mut exp);
         Ok(Signature { sigma_1, sigma_2 })
     }
 
+    fn check_known_messages<S: Signer>(
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &S,
+        blinding_key: &BlindingKey,
+    ) -> Result<(), PSError> {
+        if messages.len() >= blinding_key.msg_count() || sigkey.key_size() != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: messages.len(),
+            });
+        }
+        for idx in messages.keys() {
+            if *idx >= blinding_key.msg_count() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", idx, blinding_key.msg_count()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `new_with_known_messages_at_indices` but also returns a proof that the signature was computed
+    /// correctly with the signing key of `verkey`. Needs the signing key rather than a `Signer` since the proof
+    /// is over its exponents.
+    pub fn new_with_proof(
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &Sigkey,
+        blinding_key: &BlindingKey,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> Result<(Signature, BlindSigningProof), PSError> {
+        if !sigkey.is_key_for(verkey, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signing key does not correspond to the verkey"),
+            });
+        }
+        let mut u = FieldElement::random();
+        let sig = Self::new_with_known_messages_at_indices_and_u(
+            commitment,
+            messages,
+            sigkey,
+            blinding_key,
+            params,
+            u.clone(),
+        )?;
+        let proof = BlindSigningProof::new(&sig, commitment, messages, sigkey, &u, verkey, params);
+        wipe(&mut u);
+        Ok((sig, proof))
+    }
+
     /// Scheme as described in the paper
     pub fn new_from_paper(
         commitment: &SignatureGroup,
@@ -111,6 +619,50 @@ impl BlindSignature {
         Signature { sigma_1, sigma_2 }
     }
 
+    /// Unblind and verify the result over all `messages`, where the messages at `hidden_indices` are the committed
+    /// ones. Fails with `PSError::WrongBlinding` if `blinding` and the hidden messages do not open `commitment` and
+    /// with `PSError::InvalidIssuedSignature` if they do but the signature is still invalid, i.e. the issuer did not
+    /// sign correctly.
+    #[allow(clippy::too_many_arguments)]
+    pub fn unblind_checked(
+        sig: &Signature,
+        blinding: &FieldElement,
+        commitment: &SignatureGroup,
+        messages: Vec<FieldElement>,
+        hidden_indices: &[usize],
+        blinding_key: &BlindingKey,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        if messages.len() != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: messages.len(),
+            });
+        }
+        let mut bases = SignatureGroupVec::with_capacity(hidden_indices.len() + 1);
+        let mut exponents = FieldElementVector::with_capacity(hidden_indices.len() + 1);
+        for i in hidden_indices {
+            if *i >= messages.len() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", i, messages.len()),
+                });
+            }
+            bases.push(blinding_key.Y[*i].clone());
+            exponents.push(messages[*i].clone());
+        }
+        bases.push(params.g.clone());
+        exponents.push(blinding.clone());
+        if bases.multi_scalar_mul_const_time(&exponents).unwrap() != *commitment {
+            return Err(PSError::WrongBlinding);
+        }
+        let unblinded = Self::unblind(sig, blinding);
+        if !unblinded.verify(messages, verkey, params)? {
+            return Err(PSError::InvalidIssuedSignature);
+        }
+        Ok(unblinded)
+    }
+
     pub fn check_blinding_key_and_messages_compat(
         messages: &[FieldElement],
         blinding_key: &BlindingKey,
@@ -125,6 +677,128 @@ impl BlindSignature {
     }
 }
 
+impl BlindSigningProof {
+    fn new(
+        sig: &Signature,
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        sigkey: &Sigkey,
+        u: &FieldElement,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> Self {
+        let indices = Self::sorted_indices(messages);
+        let mut r_u = FieldElement::random();
+        let mut r_x = FieldElement::random();
+        let mut r_y = FieldElementVector::random(indices.len());
+        let mut exp = r_x.clone();
+        for (r, i) in r_y.iter().zip(indices.iter()) {
+            exp += r * &messages[i];
+        }
+        let t_sigma_1 = &params.g * &r_u;
+        let t_sigma_2 = (&sig.sigma_1 * &exp) + (commitment * &r_u);
+        let t_X = &params.g_tilde * &r_x;
+        let t_Y = r_y.iter().map(|r| &params.g_tilde * r).collect::<Vec<_>>();
+        wipe(&mut exp);
+        let challenge = Self::challenge(
+            sig, commitment, messages, &indices, verkey, params, &t_sigma_1, &t_sigma_2, &t_X, &t_Y,
+        );
+
+        let u_response = &r_u - &(&challenge * u);
+        let x_response = &r_x - &(&challenge * &sigkey.x);
+        let y_responses = r_y
+            .iter()
+            .zip(indices.iter())
+            .map(|(r, i)| r - &(&challenge * &sigkey.y[*i]))
+            .collect();
+        wipe(&mut r_u);
+        wipe(&mut r_x);
+        for r in r_y.iter_mut() {
+            wipe(r);
+        }
+        Self {
+            challenge,
+            u_response,
+            x_response,
+            y_responses,
+        }
+    }
+
+    /// Called by the holder with the blind signature, its commitment and the known messages before unblinding
+    pub fn verify(
+        &self,
+        sig: &Signature,
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        verkey: &Verkey,
+        params: &Params,
+    ) -> bool {
+        let indices = Self::sorted_indices(messages);
+        if self.y_responses.len() != indices.len() || indices.iter().any(|i| *i >= verkey.Y_tilde.len()) {
+            return false;
+        }
+        let mut exp = self.x_response.clone();
+        for (z, i) in self.y_responses.iter().zip(indices.iter()) {
+            exp += z * &messages[i];
+        }
+        // g^z_u * sigma_1^c, sigma_1^(z_x + sum(z_i*m_i)) * C^z_u * sigma_2^c, g_tilde^z_x * X_tilde^c and
+        // g_tilde^z_i * Y_tilde_i^c
+        let t_sigma_1 = (&params.g * &self.u_response) + (&sig.sigma_1 * &self.challenge);
+        let t_sigma_2 =
+            (&sig.sigma_1 * &exp) + (commitment * &self.u_response) + (&sig.sigma_2 * &self.challenge);
+        let t_X = (&params.g_tilde * &self.x_response) + (&verkey.X_tilde * &self.challenge);
+        let t_Y = self
+            .y_responses
+            .iter()
+            .zip(indices.iter())
+            .map(|(z, i)| (&params.g_tilde * z) + (&verkey.Y_tilde[*i] * &self.challenge))
+            .collect::<Vec<_>>();
+        Self::challenge(
+            sig, commitment, messages, &indices, verkey, params, &t_sigma_1, &t_sigma_2, &t_X, &t_Y,
+        ) == self.challenge
+    }
+
+    fn sorted_indices(messages: &HashMap<usize, FieldElement>) -> Vec<usize> {
+        let mut indices = messages.keys().cloned().collect::<Vec<usize>>();
+        indices.sort();
+        indices
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn challenge(
+        sig: &Signature,
+        commitment: &SignatureGroup,
+        messages: &HashMap<usize, FieldElement>,
+        indices: &[usize],
+        verkey: &Verkey,
+        params: &Params,
+        t_sigma_1: &SignatureGroup,
+        t_sigma_2: &SignatureGroup,
+        t_X: &VerkeyGroup,
+        t_Y: &[VerkeyGroup],
+    ) -> FieldElement {
+        let mut bytes = BLIND_SIGNING_PROOF_DST.to_vec();
+        bytes.append(&mut params.g.to_bytes());
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut commitment.to_bytes());
+        bytes.append(&mut sig.sigma_1.to_bytes());
+        bytes.append(&mut sig.sigma_2.to_bytes());
+        for i in indices {
+            bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+            bytes.append(&mut messages[i].to_bytes());
+            bytes.append(&mut verkey.Y_tilde[*i].to_bytes());
+        }
+        bytes.append(&mut verkey.X_tilde.to_bytes());
+        bytes.append(&mut t_sigma_1.to_bytes());
+        bytes.append(&mut t_sigma_2.to_bytes());
+        bytes.append(&mut t_X.to_bytes());
+        for t in t_Y {
+            bytes.append(&mut t.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,6 +817,52 @@ mod tests {
         assert_eq!(blinding_key.msg_count(), count_msgs);
     }
 
+    #[test]
+    fn test_signer() {
+        // Stands for a key held in an HSM, only the `Signer` operations are available
+        struct RemoteSigner {
+            sigkey: Sigkey,
+        }
+
+        impl Signer for RemoteSigner {
+            fn key_size(&self) -> usize {
+                self.sigkey.key_size()
+            }
+
+            fn sign_exponent(
+                &self,
+                h: &SignatureGroup,
+                messages: &[(usize, &FieldElement)],
+            ) -> Result<SignatureGroup, PSError> {
+                self.sigkey.sign_exponent(h, messages)
+            }
+        }
+
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let signer = RemoteSigner { sigkey: sk };
+        let signer_ref: &dyn Signer = &signer;
+
+        let blinding_key_1 = BlindingKey::from_signer(&signer, &params).unwrap();
+        assert_eq!(blinding_key_1.X, blinding_key.X);
+        assert_eq!(blinding_key_1.Y, blinding_key.Y);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(&msgs, &signer_ref, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        let blinding = FieldElement::random();
+        let commitment = &blinding_key.Y[0] * &msgs[0] + &params.g * &blinding;
+        let blinded_sig = BlindSignature::new(&commitment, &msgs[1..], &signer, &blinding_key, &params).unwrap();
+        let sig = BlindSignature::unblind(&blinded_sig, &blinding);
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        assert!(signer.sign_exponent(&params.g, &[(count_msgs, &msgs[0])]).is_err());
+        assert!(signer.sign_exponent(&params.g, &[(0, &msgs[0]), (0, &msgs[1])]).is_err());
+    }
+
     #[test]
     fn test_signature_single_blinded_message() {
         // Only 1 blinded message, no message known to signer
@@ -219,6 +939,243 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stateless_blind_signature() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let prf_key = IssuerPRFKey::new();
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        // Messages at index 1 and 3 are committed
+        let comm = (&blinding_key.Y[1] * &msgs[1]) + (&blinding_key.Y[3] * &msgs[3]) + (&params.g * &blinding);
+        let known = vec![0, 2, 4]
+            .into_iter()
+            .map(|i| (i, msgs[i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+
+        let sig_blinded = BlindSignature::new_stateless_with_known_messages_at_indices(
+            &comm, &known, &sk, &blinding_key, &params, &prf_key, "session 1".as_bytes(),
+        )
+        .unwrap();
+        let sig_unblinded = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(sig_unblinded.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Re-processing the same request gives the same signature
+        let sig_again = BlindSignature::new_stateless_with_known_messages_at_indices(
+            &comm, &known, &sk, &blinding_key, &params, &prf_key, "session 1".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(sig_blinded.sigma_1, sig_again.sigma_1);
+        assert_eq!(sig_blinded.sigma_2, sig_again.sigma_2);
+
+        // A different request or a different PRF key gives a different signature
+        let sig_other = BlindSignature::new_stateless_with_known_messages_at_indices(
+            &comm, &known, &sk, &blinding_key, &params, &prf_key, "session 2".as_bytes(),
+        )
+        .unwrap();
+        assert_ne!(sig_blinded.sigma_1, sig_other.sigma_1);
+        let sig_other = BlindSignature::new_stateless_with_known_messages_at_indices(
+            &comm, &known, &sk, &blinding_key, &params, &IssuerPRFKey::new(), "session 1".as_bytes(),
+        )
+        .unwrap();
+        assert_ne!(sig_blinded.sigma_1, sig_other.sigma_1);
+
+        // A known message moved into the context does not give the same u
+        let mut fewer_known = known.clone();
+        fewer_known.remove(&4);
+        let mut context = 4u64.to_be_bytes().to_vec();
+        context.append(&mut msgs[4].to_bytes());
+        assert_ne!(
+            prf_key.derive_u(&comm, &known, &[]),
+            prf_key.derive_u(&comm, &fewer_known, &context)
+        );
+
+        // The key is not printed
+        let printed = format!("{:?}", prf_key);
+        assert!(printed.contains("redacted"));
+        assert!(!printed.contains(&format!("{:?}", prf_key.key)));
+        assert_eq!(IssuerPRFKey::default().key.len(), prf_key.key.len());
+    }
+
+    #[test]
+    fn test_partially_blind_issuance() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(5, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        // Link secret at 1 and a holder chosen attribute at 3 are hidden, the rest are known to the issuer
+        let layout = IssuanceLayout::new(5, &[3, 1]).unwrap();
+        assert_eq!(layout.hidden_indices, vec![1, 3]);
+        assert_eq!(layout.known_indices(), vec![0, 2, 4]);
+
+        let hidden = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let known = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        let comm = layout.commit(&hidden, &blinding, &blinding_key, &params).unwrap();
+
+        // Holder proves knowledge of the committed messages
+        let bases = layout.commitment_bases(&blinding_key, &params).unwrap();
+        let mut committing = ProverCommittingSignatureGroup::new();
+        for b in &bases {
+            committing.commit(b, None);
+        }
+        let committed = committing.finish();
+        let challenge = committed.gen_challenge(comm.to_bytes());
+        let mut secrets = hidden.clone();
+        secrets.push(blinding.clone());
+        let proof = committed.gen_proof(&challenge, secrets.as_slice()).unwrap();
+        assert!(proof.verify(bases.as_slice(), &comm, &challenge).unwrap());
+
+        let sig_blinded = layout.sign(&comm, &known, &sk, &blinding_key, &params).unwrap();
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        let msgs = layout.messages(&hidden, &known).unwrap();
+        assert_eq!(msgs[1], hidden[0]);
+        assert_eq!(msgs[2], known[1]);
+        assert!(sig.verify(msgs, &vk, &params).unwrap());
+
+        // Issuer using a different layout gives an invalid signature
+        let other = IssuanceLayout::new(5, &[0, 1]).unwrap();
+        let sig_blinded = other.sign(&comm, &known, &sk, &blinding_key, &params).unwrap();
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(!sig.verify(layout.messages(&hidden, &known).unwrap(), &vk, &params).unwrap());
+
+        assert!(IssuanceLayout::new(5, &[]).is_err());
+        assert!(IssuanceLayout::new(5, &[1, 1]).is_err());
+        assert!(IssuanceLayout::new(5, &[5]).is_err());
+        assert!(layout.sign(&comm, &known[..2], &sk, &blinding_key, &params).is_err());
+    }
+
+    #[test]
+    fn test_blind_signature_with_prepared_sigkey() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let psk = PreparedSigkey::new(sk, &params);
+        let layout = IssuanceLayout::new(4, &[1]).unwrap();
+        let hidden = vec![FieldElement::random()];
+        let known = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        let comm = layout.commit(&hidden, &blinding, &blinding_key, &params).unwrap();
+        let messages = layout
+            .known_indices()
+            .into_iter()
+            .zip(known.iter().cloned())
+            .collect::<HashMap<usize, FieldElement>>();
+        let sig_blinded =
+            BlindSignature::new_with_prepared_sigkey(&comm, &messages, &psk, &blinding_key, &params).unwrap();
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(sig.verify(layout.messages(&hidden, &known).unwrap(), &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_blind_signature_request() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let layout = IssuanceLayout::new(4, &[0, 2]).unwrap();
+        let hidden = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let known = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let nonce = FieldElement::random().to_bytes();
+
+        let (request, blinding) = BlindSignatureRequest::new(&layout, &hidden, &blinding_key, &params, &nonce).unwrap();
+        assert!(request.verify_request(&layout, &blinding_key, &params, &nonce).unwrap());
+        let sig_blinded = request.sign(&layout, &known, &sk, &blinding_key, &params, &nonce).unwrap();
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(sig.verify(layout.messages(&hidden, &known).unwrap(), &vk, &params).unwrap());
+
+        // Replay with a different nonce, a different layout or a swapped commitment fails
+        let other_nonce = FieldElement::random().to_bytes();
+        assert!(!request.verify_request(&layout, &blinding_key, &params, &other_nonce).unwrap());
+        assert!(request.sign(&layout, &known, &sk, &blinding_key, &params, &other_nonce).is_err());
+        let other_layout = IssuanceLayout::new(4, &[0, 1]).unwrap();
+        assert!(!request.verify_request(&other_layout, &blinding_key, &params, &nonce).unwrap());
+        let mut swapped = request.clone();
+        swapped.commitment = layout
+            .commit(&hidden, &FieldElement::random(), &blinding_key, &params)
+            .unwrap();
+        assert!(!swapped.verify_request(&layout, &blinding_key, &params, &nonce).unwrap());
+    }
+
+    #[test]
+    fn test_unblind_checked() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let layout = IssuanceLayout::new(4, &[1, 3]).unwrap();
+        let hidden = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let known = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        let comm = layout.commit(&hidden, &blinding, &blinding_key, &params).unwrap();
+        let msgs = layout.messages(&hidden, &known).unwrap();
+
+        let sig_blinded = layout.sign(&comm, &known, &sk, &blinding_key, &params).unwrap();
+        let sig = BlindSignature::unblind_checked(
+            &sig_blinded, &blinding, &comm, msgs.clone(), &layout.hidden_indices, &blinding_key, &vk, &params,
+        )
+        .unwrap();
+        assert_eq!(sig.sigma_2, BlindSignature::unblind(&sig_blinded, &blinding).sigma_2);
+
+        // Wrong blinding
+        match BlindSignature::unblind_checked(
+            &sig_blinded, &FieldElement::random(), &comm, msgs.clone(), &layout.hidden_indices, &blinding_key, &vk,
+            &params,
+        ) {
+            Err(PSError::WrongBlinding) => (),
+            _ => panic!("Expected WrongBlinding"),
+        }
+
+        // Issuer signing different known messages
+        let other_known = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_blinded = layout.sign(&comm, &other_known, &sk, &blinding_key, &params).unwrap();
+        match BlindSignature::unblind_checked(
+            &sig_blinded, &blinding, &comm, msgs, &layout.hidden_indices, &blinding_key, &vk, &params,
+        ) {
+            Err(PSError::InvalidIssuedSignature) => (),
+            _ => panic!("Expected InvalidIssuedSignature"),
+        }
+    }
+
+    #[test]
+    fn test_verifiable_blind_signature() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        assert!(blinding_key.is_consistent_with(&vk, &params));
+        let (_, vk_1) = keygen(4, &params);
+        assert!(!blinding_key.is_consistent_with(&vk_1, &params));
+
+        let layout = IssuanceLayout::new(4, &[2]).unwrap();
+        let hidden = vec![FieldElement::random()];
+        let known = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        let comm = layout.commit(&hidden, &blinding, &blinding_key, &params).unwrap();
+        let messages = layout
+            .known_indices()
+            .into_iter()
+            .zip(known.iter().cloned())
+            .collect::<HashMap<usize, FieldElement>>();
+
+        let (sig_blinded, proof) =
+            BlindSignature::new_with_proof(&comm, &messages, &sk, &blinding_key, &vk, &params).unwrap();
+        assert!(proof.verify(&sig_blinded, &comm, &messages, &vk, &params));
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(sig.verify(layout.messages(&hidden, &known).unwrap(), &vk, &params).unwrap());
+
+        // Tampered signature, different messages or verkey fail
+        let mut wrong = sig_blinded.clone();
+        wrong.sigma_2 = &wrong.sigma_2 + &params.g;
+        assert!(!proof.verify(&wrong, &comm, &messages, &vk, &params));
+        let mut other_messages = messages.clone();
+        other_messages.insert(0, FieldElement::random());
+        assert!(!proof.verify(&sig_blinded, &comm, &other_messages, &vk, &params));
+        assert!(!proof.verify(&sig_blinded, &comm, &messages, &vk_1, &params));
+
+        let (sk_1, _) = keygen(4, &params);
+        assert!(BlindSignature::new_with_proof(&comm, &messages, &sk_1, &blinding_key, &vk, &params).is_err());
+    }
+
     #[test]
     fn test_signature_blinded_messages() {
         let count_msgs = 5;
@@ -0,0 +1,476 @@
+// Canonical CBOR encoding (RFC 8949, section 4.2.1, core deterministic encoding) of keys, signatures and proofs
+// for hashing, storage and consumption from other languages. Each struct is encoded as an array of its fields in
+// declaration order, vectors as arrays and field and group elements as byte strings of their `to_bytes`
+// representation. Lengths and integers always use the shortest form and indefinite lengths are not used, so each
+// value has exactly one encoding. Decoding rejects any other encoding, including field and group elements whose bytes
+// are not the ones `to_bytes` gives for them (like an unreduced scalar), and validates the group elements.
+// Enabled with the feature `cbor`.
+
+use crate::errors::PSError;
+use crate::keys::{CommitmentGenerators, Params, Sigkey, Verkey};
+use crate::pok_sig::{PoKOfSignatureProof, ProofOtherGroup};
+use crate::signature::Signature;
+use crate::signature_2018::Signature as Signature18;
+use crate::validation::Validate;
+use crate::{SignatureGroup, VerkeyGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_ARRAY: u8 = 4;
+
+pub trait CanonicalCbor: Sized {
+    fn encode_cbor(&self, out: &mut Vec<u8>);
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError>;
+
+    fn to_cbor(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode_cbor(&mut out);
+        out
+    }
+
+    /// Decode from `bytes` which should contain nothing else
+    fn from_cbor(bytes: &[u8]) -> Result<Self, PSError> {
+        let mut reader = CborReader::new(bytes);
+        let value = Self::decode_cbor(&mut reader)?;
+        reader.finish()?;
+        Ok(value)
+    }
+}
+
+/// Write the initial byte(s) of a data item in the shortest form
+fn write_head(major: u8, value: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::max_value() as u64 {
+        out.push(major | 24);
+        out.push(value as u8);
+    } else if value <= u16::max_value() as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::max_value() as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+pub fn write_uint(value: u64, out: &mut Vec<u8>) {
+    write_head(MAJOR_UNSIGNED, value, out);
+}
+
+pub fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    write_head(MAJOR_BYTES, bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+pub fn write_array_header(len: usize, out: &mut Vec<u8>) {
+    write_head(MAJOR_ARRAY, len as u64, out);
+}
+
+pub struct CborReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CborReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn err(&self, msg: &str) -> PSError {
+        PSError::InvalidBytes {
+            msg: format!("CBOR at position {}: {}", self.pos, msg),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PSError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.err("unexpected end of input"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    /// Read the initial byte(s) of a data item of the expected major type and return its argument
+    fn read_head(&mut self, expected_major: u8) -> Result<u64, PSError> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != expected_major {
+            return Err(self.err(&format!("expected major type {}", expected_major)));
+        }
+        let (value, min) = match initial & 0x1f {
+            v @ 0..=23 => (v as u64, 0),
+            24 => (self.take(1)?[0] as u64, 24),
+            25 => {
+                let mut b = [0u8; 2];
+                b.copy_from_slice(self.take(2)?);
+                (u16::from_be_bytes(b) as u64, u8::max_value() as u64 + 1)
+            }
+            26 => {
+                let mut b = [0u8; 4];
+                b.copy_from_slice(self.take(4)?);
+                (u32::from_be_bytes(b) as u64, u16::max_value() as u64 + 1)
+            }
+            27 => {
+                let mut b = [0u8; 8];
+                b.copy_from_slice(self.take(8)?);
+                (u64::from_be_bytes(b), u32::max_value() as u64 + 1)
+            }
+            _ => return Err(self.err("indefinite or reserved length")),
+        };
+        if value < min {
+            return Err(self.err("length not in shortest form"));
+        }
+        Ok(value)
+    }
+
+    pub fn read_uint(&mut self) -> Result<u64, PSError> {
+        self.read_head(MAJOR_UNSIGNED)
+    }
+
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], PSError> {
+        let len = self.read_head(MAJOR_BYTES)?;
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err(self.err("unexpected end of input"));
+        }
+        self.take(len as usize)
+    }
+
+    pub fn read_array_header(&mut self) -> Result<usize, PSError> {
+        let len = self.read_head(MAJOR_ARRAY)?;
+        // Each item takes at least 1 byte
+        if len > (self.bytes.len() - self.pos) as u64 {
+            return Err(self.err("array longer than input"));
+        }
+        Ok(len as usize)
+    }
+
+    /// Read an array header with exactly `len` items
+    pub fn expect_array(&mut self, len: usize) -> Result<(), PSError> {
+        if self.read_array_header()? != len {
+            return Err(self.err(&format!("expected array of {} items", len)));
+        }
+        Ok(())
+    }
+
+    /// Check that `bytes`, read as a field or group element, are what `to_bytes` gives for the decoded element
+    fn check_canonical_element(&self, bytes: &[u8], encoded: &[u8]) -> Result<(), PSError> {
+        if bytes != encoded {
+            return Err(self.err("element not in canonical form"));
+        }
+        Ok(())
+    }
+
+    /// Check that all input has been consumed
+    pub fn finish(&self) -> Result<(), PSError> {
+        if self.pos != self.bytes.len() {
+            return Err(self.err("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+impl CanonicalCbor for FieldElement {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_bytes(&self.to_bytes(), out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        let bytes = reader.read_bytes()?;
+        let elem = FieldElement::from_bytes(bytes)?;
+        reader.check_canonical_element(bytes, &elem.to_bytes())?;
+        Ok(elem)
+    }
+}
+
+macro_rules! impl_cbor_for_group_element {
+    ( $group_element:ident ) => {
+        impl CanonicalCbor for $group_element {
+            fn encode_cbor(&self, out: &mut Vec<u8>) {
+                write_bytes(&self.to_bytes(), out);
+            }
+
+            fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+                let bytes = reader.read_bytes()?;
+                let elem = $group_element::from_bytes(bytes)?;
+                reader.check_canonical_element(bytes, &elem.to_bytes())?;
+                elem.validate()?;
+                Ok(elem)
+            }
+        }
+    };
+}
+
+impl_cbor_for_group_element!(SignatureGroup);
+impl_cbor_for_group_element!(VerkeyGroup);
+
+impl<T: CanonicalCbor> CanonicalCbor for Vec<T> {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(self.len(), out);
+        for item in self {
+            item.encode_cbor(out);
+        }
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        let len = reader.read_array_header()?;
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(T::decode_cbor(reader)?);
+        }
+        Ok(items)
+    }
+}
+
+impl CanonicalCbor for Sigkey {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(2, out);
+        self.x.encode_cbor(out);
+        self.y.encode_cbor(out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(2)?;
+        let x = FieldElement::decode_cbor(reader)?;
+        let y = Vec::<FieldElement>::decode_cbor(reader)?;
+        Ok(Self { x, y, metadata: None })
+    }
+}
+
+impl CanonicalCbor for Verkey {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(2, out);
+        self.X_tilde.encode_cbor(out);
+        self.Y_tilde.encode_cbor(out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(2)?;
+        let X_tilde = VerkeyGroup::decode_cbor(reader)?;
+        let Y_tilde = Vec::<VerkeyGroup>::decode_cbor(reader)?;
+        Ok(Self {
+            X_tilde,
+            Y_tilde,
+            metadata: None,
+        })
+    }
+}
+
+/// Array of g and g_tilde, followed by h_0 and the array of h if there are commitment generators
+impl CanonicalCbor for Params {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        match &self.commitment_gens {
+            None => write_array_header(2, out),
+            Some(_) => write_array_header(4, out),
+        }
+        self.g.encode_cbor(out);
+        self.g_tilde.encode_cbor(out);
+        if let Some(gens) = &self.commitment_gens {
+            gens.h_0.encode_cbor(out);
+            gens.h.encode_cbor(out);
+        }
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        let len = reader.read_array_header()?;
+        if len != 2 && len != 4 {
+            return Err(reader.err("expected array of 2 or 4 items for params"));
+        }
+        let g = SignatureGroup::decode_cbor(reader)?;
+        let g_tilde = VerkeyGroup::decode_cbor(reader)?;
+        let commitment_gens = if len == 4 {
+            let h_0 = SignatureGroup::decode_cbor(reader)?;
+            let h = Vec::<SignatureGroup>::decode_cbor(reader)?;
+            Some(CommitmentGenerators { h_0, h })
+        } else {
+            None
+        };
+        Ok(Self {
+            g,
+            g_tilde,
+            commitment_gens,
+        })
+    }
+}
+
+impl CanonicalCbor for Signature {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(2, out);
+        self.sigma_1.encode_cbor(out);
+        self.sigma_2.encode_cbor(out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(2)?;
+        let sigma_1 = SignatureGroup::decode_cbor(reader)?;
+        let sigma_2 = SignatureGroup::decode_cbor(reader)?;
+        Ok(Self { sigma_1, sigma_2 })
+    }
+}
+
+impl CanonicalCbor for Signature18 {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(2, out);
+        self.m_prime.encode_cbor(out);
+        self.sig.encode_cbor(out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(2)?;
+        let m_prime = FieldElement::decode_cbor(reader)?;
+        let sig = Signature::decode_cbor(reader)?;
+        Ok(Self { m_prime, sig })
+    }
+}
+
+impl CanonicalCbor for ProofOtherGroup {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(2, out);
+        self.commitment.encode_cbor(out);
+        write_array_header(self.responses.len(), out);
+        for r in self.responses.iter() {
+            r.encode_cbor(out);
+        }
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(2)?;
+        let commitment = VerkeyGroup::decode_cbor(reader)?;
+        let len = reader.read_array_header()?;
+        let mut responses = FieldElementVector::with_capacity(len);
+        for _ in 0..len {
+            responses.push(FieldElement::decode_cbor(reader)?);
+        }
+        Ok(Self {
+            commitment,
+            responses,
+        })
+    }
+}
+
+impl CanonicalCbor for PoKOfSignatureProof {
+    fn encode_cbor(&self, out: &mut Vec<u8>) {
+        write_array_header(3, out);
+        self.sig.encode_cbor(out);
+        self.J.encode_cbor(out);
+        self.proof_vc.encode_cbor(out);
+    }
+
+    fn decode_cbor(reader: &mut CborReader) -> Result<Self, PSError> {
+        reader.expect_array(3)?;
+        let sig = Signature::decode_cbor(reader)?;
+        let J = VerkeyGroup::decode_cbor(reader)?;
+        let proof_vc = ProofOtherGroup::decode_cbor(reader)?;
+        Ok(Self { sig, J, proof_vc })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, keygen_2018};
+    use crate::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_cbor_head() {
+        for (value, expected) in &[
+            (0u64, vec![0x00]),
+            (23, vec![0x17]),
+            (24, vec![0x18, 0x18]),
+            (255, vec![0x18, 0xff]),
+            (256, vec![0x19, 0x01, 0x00]),
+            (65536, vec![0x1a, 0x00, 0x01, 0x00, 0x00]),
+            (1 << 32, vec![0x1b, 0, 0, 0, 1, 0, 0, 0, 0]),
+        ] {
+            let mut out = vec![];
+            write_uint(*value, &mut out);
+            assert_eq!(&out, expected);
+            let mut reader = CborReader::new(&out);
+            assert_eq!(reader.read_uint().unwrap(), *value);
+            reader.finish().unwrap();
+        }
+        // Non-shortest form is rejected
+        assert!(CborReader::new(&[0x18, 0x17]).read_uint().is_err());
+        // Indefinite length is rejected
+        assert!(CborReader::new(&[0x9f]).read_array_header().is_err());
+    }
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let sk_1 = Sigkey::from_cbor(&sk.to_cbor()).unwrap();
+        assert_eq!(sk_1.to_cbor(), sk.to_cbor());
+        let vk_1 = Verkey::from_cbor(&vk.to_cbor()).unwrap();
+        let params_1 = Params::from_cbor(&params.to_cbor()).unwrap();
+        let params_gens = Params::new_with_commitment_generators("test".as_bytes(), 2);
+        let params_gens_1 = Params::from_cbor(&params_gens.to_cbor()).unwrap();
+        assert_eq!(params_gens_1.commitment_gens.unwrap().h, params_gens.commitment_gens.unwrap().h);
+        let sig_1 = Signature::from_cbor(&sig.to_cbor()).unwrap();
+        assert!(sig_1.verify(msgs.clone(), &vk_1, &params_1).unwrap());
+
+        let (sk_2018, vk_2018) = keygen_2018(count_msgs, &params);
+        let sig_2018 = Signature18::new(msgs.as_slice(), &sk_2018).unwrap();
+        let sig_2018_1 = Signature18::from_cbor(&sig_2018.to_cbor()).unwrap();
+        assert!(sig_2018_1.verify(msgs.clone(), &vk_2018, &params).unwrap());
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, HashSet::new()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let bytes = proof.to_cbor();
+        let proof_1 = PoKOfSignatureProof::from_cbor(&bytes).unwrap();
+        assert_eq!(proof_1.to_cbor(), bytes);
+        let chal_bytes = proof_1.get_bytes_for_challenge(HashSet::new(), &vk, &params);
+        assert_eq!(FieldElement::from_msg_hash(&chal_bytes), chal);
+
+        // Trailing bytes and truncation are rejected
+        let mut extra = sig.to_cbor();
+        extra.push(0);
+        assert!(Signature::from_cbor(&extra).is_err());
+        let sig_bytes = sig.to_cbor();
+        assert!(Signature::from_cbor(&sig_bytes[..sig_bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_cbor_non_canonical_elements() {
+        // An unreduced scalar, all bytes set is larger than the group order, decodes to an element with other bytes
+        let elem = FieldElement::random();
+        let len = elem.to_bytes().len();
+        let mut out = vec![];
+        write_bytes(&vec![0xff; len], &mut out);
+        assert!(FieldElement::from_cbor(&out).is_err());
+        // order + 1, i.e. the bytes of -1 plus 2, is 1 when reduced
+        let mut x_bytes = FieldElement::one().to_bytes();
+        let minus_one = (&FieldElement::zero() - &FieldElement::one()).to_bytes();
+        let mut carry = 2u16;
+        for (i, b) in minus_one.iter().enumerate().rev() {
+            let sum = *b as u16 + carry;
+            x_bytes[i] = sum as u8;
+            carry = sum >> 8;
+        }
+        let mut out = vec![];
+        write_bytes(&x_bytes, &mut out);
+        assert!(FieldElement::from_cbor(&out).is_err());
+        assert_eq!(FieldElement::from_cbor(&FieldElement::one().to_cbor()).unwrap(), FieldElement::one());
+
+        // A group element with a byte string of a wrong length
+        let g = SignatureGroup::random();
+        let mut g_bytes = g.to_bytes();
+        g_bytes.push(0);
+        let mut out = vec![];
+        write_bytes(&g_bytes, &mut out);
+        assert!(SignatureGroup::from_cbor(&out).is_err());
+        assert_eq!(SignatureGroup::from_cbor(&g.to_cbor()).unwrap(), g);
+    }
+}
@@ -0,0 +1,429 @@
+// Threshold issuance of credentials with hidden messages (Coconut, Sonnino et al., NDSS 2019) for the 2016 scheme.
+// The issuing authorities run the DKG (`dkg`) so that each has a `SigkeyShare` and any `threshold` of them can issue.
+// The holder has an ElGamal key (d, gamma = g^d) and commits to the hidden messages with cm = g^o * H_j^m_j * ...
+// for generators H_j derived from the params. sigma_1 = h is derived by hashing cm and the known messages so no
+// one knows its discrete log and all authorities sign over the same h. Each hidden message is encrypted as
+// (a_j, b_j) = (g^k_j, gamma^k_j * h^m_j) and the holder proves knowledge of o, m_j and k_j (`CredentialRequest`).
+// An authority with share (x, y_1, y_2, ...) replies with (a_j^y_j * ..., h^(x + y_i*m_i + ...) * b_j^y_j * ...)
+// over the hidden messages j and known messages i (`BlindPartialCredential`), which the holder decrypts to the
+// partial signature (h, h^(x + y_1*m_1 + ...)) of the share. Any `threshold` valid partial signatures aggregate to a
+// signature verifiable with the joint verkey and presented with `PoKOfSignature` like any other signature.
+
+use crate::errors::PSError;
+use crate::keys::Params;
+use crate::signature::Signature;
+use crate::threshold::{aggregate_partials_robust, FeldmanCommitments, PartialSignature, SigkeyShare};
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for the generators of the holder's commitment to the hidden messages
+pub const COCONUT_GENERATOR_DST: &[u8] = b"PS-SIG-COCONUT-GENERATOR";
+
+/// Domain separation tag for deriving sigma_1 from the commitment and the known messages
+pub const COCONUT_SIGMA_1_DST: &[u8] = b"PS-SIG-COCONUT-SIGMA-1";
+
+/// Domain separation tag for the challenge of the proof in `CredentialRequest`
+pub const COCONUT_REQUEST_DST: &[u8] = b"PS-SIG-COCONUT-REQUEST";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HolderSecretKey {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub d: FieldElement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HolderPublicKey {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub gamma: SignatureGroup,
+}
+
+/// Generate holder's secret key d and public key g^d
+pub fn holder_keygen(params: &Params) -> (HolderSecretKey, HolderPublicKey) {
+    let d = FieldElement::random();
+    let gamma = &params.g * &d;
+    (HolderSecretKey { d }, HolderPublicKey { gamma })
+}
+
+/// ElGamal encryption (g^k, gamma^k * h^m) of a hidden message m to the holder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiddenMessageCiphertext {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub a: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub b: SignatureGroup,
+}
+
+/// Sent by the holder to each authority
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialRequest {
+    /// Indices of the hidden messages in increasing order
+    pub hidden_indices: Vec<usize>,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub commitment: SignatureGroup,
+    /// Encryption of the hidden message at each of `hidden_indices`
+    pub ciphertexts: Vec<HiddenMessageCiphertext>,
+    pub holder_pk: HolderPublicKey,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub opening_response: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub message_responses: Vec<FieldElement>,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub randomness_responses: Vec<FieldElement>,
+}
+
+/// Sent by an authority to the holder in response to a `CredentialRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindPartialCredential {
+    /// Id of the authority's share
+    pub id: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub a: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub b: SignatureGroup,
+}
+
+/// Generator H_j of the commitment for the message at `index`
+fn message_generator(index: usize, params: &Params) -> SignatureGroup {
+    let mut bytes = COCONUT_GENERATOR_DST.to_vec();
+    bytes.append(&mut params.g.to_bytes());
+    bytes.extend_from_slice(&(index as u64).to_be_bytes());
+    SignatureGroup::from_msg_hash(&bytes)
+}
+
+/// sigma_1 of the credential, the hash of the commitment and the known messages
+fn sigma_1_for_request(commitment: &SignatureGroup, known_messages: &HashMap<usize, FieldElement>) -> SignatureGroup {
+    let mut indices = known_messages.keys().cloned().collect::<Vec<usize>>();
+    indices.sort();
+    let mut bytes = COCONUT_SIGMA_1_DST.to_vec();
+    bytes.append(&mut commitment.to_bytes());
+    for i in indices {
+        bytes.extend_from_slice(&(i as u64).to_be_bytes());
+        bytes.append(&mut known_messages[&i].to_bytes());
+    }
+    SignatureGroup::from_msg_hash(&bytes)
+}
+
+impl CredentialRequest {
+    /// Called by the holder with the messages hidden from the authorities and the ones known to them, by index
+    pub fn new(
+        hidden_messages: &HashMap<usize, FieldElement>,
+        known_messages: &HashMap<usize, FieldElement>,
+        holder_sk: &HolderSecretKey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        if hidden_messages.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("At least one message should be hidden"),
+            });
+        }
+        if let Some(i) = hidden_messages.keys().find(|i| known_messages.contains_key(i)) {
+            return Err(PSError::GeneralError {
+                msg: format!("Message at index {} is both hidden and known", i),
+            });
+        }
+        let mut hidden_indices = hidden_messages.keys().cloned().collect::<Vec<usize>>();
+        hidden_indices.sort();
+        let holder_pk = HolderPublicKey {
+            gamma: &params.g * &holder_sk.d,
+        };
+        let generators = hidden_indices
+            .iter()
+            .map(|i| message_generator(*i, params))
+            .collect::<Vec<_>>();
+
+        let opening = FieldElement::random();
+        let mut commitment = &params.g * &opening;
+        for (g, i) in generators.iter().zip(hidden_indices.iter()) {
+            commitment += g * &hidden_messages[i];
+        }
+        let h = sigma_1_for_request(&commitment, known_messages);
+
+        let randomness = hidden_indices.iter().map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let ciphertexts = hidden_indices
+            .iter()
+            .zip(randomness.iter())
+            .map(|(i, k)| HiddenMessageCiphertext {
+                a: &params.g * k,
+                b: (&holder_pk.gamma * k) + (&h * &hidden_messages[i]),
+            })
+            .collect::<Vec<_>>();
+
+        let r_opening = FieldElement::random();
+        let r_messages = hidden_indices.iter().map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let r_randomness = hidden_indices.iter().map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let mut t_commitment = &params.g * &r_opening;
+        for (g, r) in generators.iter().zip(r_messages.iter()) {
+            t_commitment += g * r;
+        }
+        let t_ciphertexts = r_messages
+            .iter()
+            .zip(r_randomness.iter())
+            .map(|(r_m, r_k)| HiddenMessageCiphertext {
+                a: &params.g * r_k,
+                b: (&holder_pk.gamma * r_k) + (&h * r_m),
+            })
+            .collect::<Vec<_>>();
+
+        let mut request = Self {
+            hidden_indices,
+            commitment,
+            ciphertexts,
+            holder_pk,
+            challenge: FieldElement::zero(),
+            opening_response: FieldElement::zero(),
+            message_responses: vec![],
+            randomness_responses: vec![],
+        };
+        let challenge = request.compute_challenge(&h, &generators, &t_commitment, &t_ciphertexts, params);
+        request.opening_response = &r_opening - &(&challenge * &opening);
+        request.message_responses = r_messages
+            .iter()
+            .zip(request.hidden_indices.iter())
+            .map(|(r, i)| r - &(&challenge * &hidden_messages[i]))
+            .collect();
+        request.randomness_responses = r_randomness
+            .iter()
+            .zip(randomness.iter())
+            .map(|(r, k)| r - &(&challenge * k))
+            .collect();
+        request.challenge = challenge;
+        Ok(request)
+    }
+
+    /// sigma_1 of the credential for the `known_messages`
+    pub fn sigma_1(&self, known_messages: &HashMap<usize, FieldElement>) -> SignatureGroup {
+        sigma_1_for_request(&self.commitment, known_messages)
+    }
+
+    /// Called by an authority to check the holder's proof of knowledge of the hidden messages and that the
+    /// ciphertexts encrypt the committed messages
+    pub fn verify(&self, known_messages: &HashMap<usize, FieldElement>, params: &Params) -> bool {
+        let count = self.hidden_indices.len();
+        if count == 0
+            || self.ciphertexts.len() != count
+            || self.message_responses.len() != count
+            || self.randomness_responses.len() != count
+            || self.hidden_indices.windows(2).any(|w| w[0] >= w[1])
+            || self.hidden_indices.iter().any(|i| known_messages.contains_key(i))
+        {
+            return false;
+        }
+        let h = self.sigma_1(known_messages);
+        let generators = self
+            .hidden_indices
+            .iter()
+            .map(|i| message_generator(*i, params))
+            .collect::<Vec<_>>();
+        // g^z_o * H_j^z_m_j * ... * cm^c, g^z_k_j * a_j^c and gamma^z_k_j * h^z_m_j * b_j^c
+        let mut t_commitment = (&params.g * &self.opening_response) + (&self.commitment * &self.challenge);
+        for (g, z) in generators.iter().zip(self.message_responses.iter()) {
+            t_commitment += g * z;
+        }
+        let t_ciphertexts = self
+            .ciphertexts
+            .iter()
+            .zip(self.message_responses.iter().zip(self.randomness_responses.iter()))
+            .map(|(ct, (z_m, z_k))| HiddenMessageCiphertext {
+                a: (&params.g * z_k) + (&ct.a * &self.challenge),
+                b: (&self.holder_pk.gamma * z_k) + (&h * z_m) + (&ct.b * &self.challenge),
+            })
+            .collect::<Vec<_>>();
+        self.compute_challenge(&h, &generators, &t_commitment, &t_ciphertexts, params) == self.challenge
+    }
+
+    fn compute_challenge(
+        &self,
+        h: &SignatureGroup,
+        generators: &[SignatureGroup],
+        t_commitment: &SignatureGroup,
+        t_ciphertexts: &[HiddenMessageCiphertext],
+        params: &Params,
+    ) -> FieldElement {
+        let mut bytes = COCONUT_REQUEST_DST.to_vec();
+        bytes.append(&mut params.g.to_bytes());
+        bytes.append(&mut self.holder_pk.gamma.to_bytes());
+        bytes.append(&mut self.commitment.to_bytes());
+        bytes.append(&mut h.to_bytes());
+        for ((i, g), ct) in self.hidden_indices.iter().zip(generators.iter()).zip(self.ciphertexts.iter()) {
+            bytes.extend_from_slice(&(*i as u64).to_be_bytes());
+            bytes.append(&mut g.to_bytes());
+            bytes.append(&mut ct.a.to_bytes());
+            bytes.append(&mut ct.b.to_bytes());
+        }
+        bytes.append(&mut t_commitment.to_bytes());
+        for t in t_ciphertexts {
+            bytes.append(&mut t.a.to_bytes());
+            bytes.append(&mut t.b.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+impl BlindPartialCredential {
+    /// Called by an authority to issue on `request` and the `known_messages` with its share. Fails if the request
+    /// does not verify or the hidden and known messages are not exactly the messages of the share.
+    pub fn new(
+        request: &CredentialRequest,
+        known_messages: &HashMap<usize, FieldElement>,
+        share: &SigkeyShare,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        let msg_count = request.hidden_indices.len() + known_messages.len();
+        if msg_count != share.y.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: share.y.len(),
+                given: msg_count,
+            });
+        }
+        let indices = request
+            .hidden_indices
+            .iter()
+            .chain(known_messages.keys())
+            .cloned()
+            .collect::<HashSet<usize>>();
+        if let Some(i) = indices.iter().find(|i| **i >= msg_count) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, msg_count),
+            });
+        }
+        if !request.verify(known_messages, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Proof of knowledge of hidden messages failed"),
+            });
+        }
+        let h = request.sigma_1(known_messages);
+        let mut exp = share.x.clone();
+        for (i, m) in known_messages {
+            exp += &share.y[*i] * m;
+        }
+        let mut a = SignatureGroup::identity();
+        let mut b = &h * &exp;
+        for (i, ct) in request.hidden_indices.iter().zip(request.ciphertexts.iter()) {
+            a += &ct.a * &share.y[*i];
+            b += &ct.b * &share.y[*i];
+        }
+        Ok(Self { id: share.id, a, b })
+    }
+
+    /// Called by the holder to decrypt the partial signature of the authority's share
+    pub fn unblind(
+        &self,
+        request: &CredentialRequest,
+        known_messages: &HashMap<usize, FieldElement>,
+        holder_sk: &HolderSecretKey,
+    ) -> PartialSignature {
+        PartialSignature {
+            sigma_1: request.sigma_1(known_messages),
+            sigma_2: &self.b + &(&self.a * &holder_sk.d).negation(),
+        }
+    }
+}
+
+/// Called by the holder to unblind the `partials` of at least threshold authorities and aggregate them into a
+/// signature over all `messages` verifiable with the joint verkey `commitments.verkey()`. Partial credentials which
+/// do not verify with the verkey of the authority's share are excluded and the ids of their authorities returned.
+/// Fails if fewer than threshold are valid.
+pub fn aggregate_credential(
+    partials: &[BlindPartialCredential],
+    request: &CredentialRequest,
+    known_messages: &HashMap<usize, FieldElement>,
+    messages: &[FieldElement],
+    holder_sk: &HolderSecretKey,
+    commitments: &FeldmanCommitments,
+    params: &Params,
+) -> Result<(Signature, Vec<usize>), PSError> {
+    let unblinded = partials
+        .iter()
+        .map(|p| p.unblind(request, known_messages, holder_sk))
+        .collect::<Vec<_>>();
+    let ids = partials.iter().map(|p| p.id).collect::<Vec<_>>();
+    let sigma_1 = request.sigma_1(known_messages);
+    aggregate_partials_robust(&unblinded, &ids, messages, &sigma_1, commitments, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dkg::tests::run_dkg;
+    use crate::dkg::DkgConfig;
+    use crate::keys::Scheme;
+    use crate::pok_sig::PoKOfSignature;
+
+    #[test]
+    fn test_coconut_issuance() {
+        let params = Params::new("test".as_bytes());
+        let config = DkgConfig::new(3, 5, Scheme::PS2016, 4).unwrap();
+        let outputs = run_dkg(config, &params, None, true);
+        let commitments = outputs[0].commitments.clone();
+        let vk = commitments.verkey().unwrap();
+
+        let messages = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let mut hidden = HashMap::new();
+        hidden.insert(0, messages[0].clone());
+        hidden.insert(2, messages[2].clone());
+        let mut known = HashMap::new();
+        known.insert(1, messages[1].clone());
+        known.insert(3, messages[3].clone());
+
+        let (holder_sk, _) = holder_keygen(&params);
+        let request = CredentialRequest::new(&hidden, &known, &holder_sk, &params).unwrap();
+        assert!(request.verify(&known, &params));
+
+        let mut partials = outputs
+            .iter()
+            .map(|o| BlindPartialCredential::new(&request, &known, &o.share, &params).unwrap())
+            .collect::<Vec<_>>();
+        for p in &partials {
+            let vk_share = commitments.share_verkey(p.id).unwrap();
+            assert!(p.unblind(&request, &known, &holder_sk).verify(messages.clone(), &vk_share, &params).unwrap());
+        }
+
+        // Any threshold of the authorities suffice
+        let (sig, cheaters) =
+            aggregate_credential(&partials[2..], &request, &known, &messages, &holder_sk, &commitments, &params)
+                .unwrap();
+        assert!(cheaters.is_empty());
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+
+        // Presented like any other signature
+        let mut revealed = HashSet::new();
+        revealed.insert(3);
+        let pok = PoKOfSignature::init(&sig, &vk, &params, messages.clone(), None, revealed.clone()).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(3, messages[3].clone());
+        let chal_bytes = proof.get_bytes_for_challenge(revealed, &vk, &params);
+        assert!(proof
+            .verify(&vk, &params, revealed_msgs, &FieldElement::from_msg_hash(&chal_bytes))
+            .unwrap());
+
+        // A misbehaving authority is excluded while the threshold is still met, else aggregation fails
+        partials[1].b = &partials[1].b + &params.g;
+        let (sig, cheaters) =
+            aggregate_credential(&partials, &request, &known, &messages, &holder_sk, &commitments, &params).unwrap();
+        assert_eq!(cheaters, vec![2]);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+        partials[3].a = &partials[3].a + &params.g;
+        assert!(
+            aggregate_credential(&partials[1..4], &request, &known, &messages, &holder_sk, &commitments, &params)
+                .is_err()
+        );
+
+        // Authorities reject a request for different known messages or with a tampered ciphertext
+        let mut other_known = known.clone();
+        other_known.insert(1, FieldElement::random());
+        assert!(BlindPartialCredential::new(&request, &other_known, &outputs[0].share, &params).is_err());
+        let mut tampered = request.clone();
+        tampered.ciphertexts[0].b = &tampered.ciphertexts[0].b + &params.g;
+        assert!(!tampered.verify(&known, &params));
+        assert!(BlindPartialCredential::new(&tampered, &known, &outputs[0].share, &params).is_err());
+        let mut partial_known = known.clone();
+        partial_known.remove(&3);
+        assert!(BlindPartialCredential::new(&request, &partial_known, &outputs[0].share, &params).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+// Issuer-assisted update of some of the messages of an existing credential (signature from the 2016 scheme).
+// The holder proves knowledge of the old signature revealing only its serial (the message at `serial_idx`), commits
+// to the unchanged messages using the issuer's `BlindingKey` and proves, using the same challenge and blindings,
+// that the committed messages are the same as the ones under the old signature. The issuer checks that the serial
+// has not been revoked, blind-signs the commitment along with the updated messages and a new serial and revokes
+// the old serial. The unchanged messages are never revealed to the issuer.
+
+use crate::blind_signature::{BlindSignature, BlindingKey, ProofSignatureGroup};
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{PoKOfSignatureAndCommitment, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Serials of credentials that have been replaced by an update and must not be updated again.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RevocationList {
+    revoked: HashSet<Vec<u8>>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revoke(&mut self, serial: &FieldElement) {
+        self.revoked.insert(serial.to_bytes());
+    }
+
+    pub fn is_revoked(&self, serial: &FieldElement) -> bool {
+        self.revoked.contains(&serial.to_bytes())
+    }
+}
+
+/// Sent by the holder to the issuer to request an update of the messages at `updated_indices`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialUpdateRequest {
+    /// Serial of the old credential, revealed so that the issuer can revoke it.
+    pub serial: FieldElement,
+    /// Commitment to the unchanged messages
+    pub commitment: SignatureGroup,
+    /// Proof of knowledge of the old signature
+    pub pok_sig: PoKOfSignatureProof,
+    /// Proof of knowledge of the opening of `commitment`
+    pub pok_commitment: ProofSignatureGroup,
+}
+
+pub struct CredentialUpdate {}
+
+impl CredentialUpdate {
+    /// Called by the holder of signature `sig` over `messages`. Returns the request and the blinding used in
+    /// the commitment which is needed to unblind the new signature. `nonce` should be chosen by the issuer.
+    pub fn request(
+        sig: &Signature,
+        messages: Vec<FieldElement>,
+        serial_idx: usize,
+        updated_indices: &HashSet<usize>,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<(CredentialUpdateRequest, FieldElement), PSError> {
+        let unchanged = Self::unchanged_indices(messages.len(), serial_idx, updated_indices)?;
+        Self::check_blinding_key(vk, blinding_key)?;
+
+        let serial = messages[serial_idx].clone();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(serial_idx);
+
+        // commitment = Y_i^m_i * Y_j^m_j * ... * g^blinding for all unchanged i, j, ...
+        let blinding = FieldElement::random();
+        let committed = unchanged
+            .into_iter()
+            .map(|i| (i, blinding_key.Y[i].clone()))
+            .collect::<Vec<_>>();
+        let pok = PoKOfSignatureAndCommitment::init(
+            sig,
+            vk,
+            params,
+            messages,
+            revealed_msg_indices,
+            &committed,
+            &[(params.g.clone(), blinding.clone())],
+        )?;
+        let commitment = pok.commitment().clone();
+
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        let (pok_sig, pok_commitment) = pok.gen_proof(&challenge)?;
+        Ok((
+            CredentialUpdateRequest {
+                serial,
+                commitment,
+                pok_sig,
+                pok_commitment,
+            },
+            blinding,
+        ))
+    }
+
+    /// Called by the issuer to check that the holder has a valid signature and the committed messages are the
+    /// unchanged messages of that signature.
+    pub fn verify_request(
+        request: &CredentialUpdateRequest,
+        serial_idx: usize,
+        updated_indices: &HashSet<usize>,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let unchanged = Self::unchanged_indices(vk.Y_tilde.len(), serial_idx, updated_indices)?;
+        Self::check_blinding_key(vk, blinding_key)?;
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(serial_idx);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(serial_idx, request.serial.clone());
+
+        let mut bases = Vec::<SignatureGroup>::with_capacity(unchanged.len() + 1);
+        for i in &unchanged {
+            bases.push(blinding_key.Y[*i].clone());
+        }
+        bases.push(params.g.clone());
+
+        let mut chal_bytes = request.pok_sig.get_bytes_for_challenge_with_commitment(
+            revealed_msg_indices,
+            vk,
+            params,
+            &bases,
+            &request.pok_commitment,
+            &request.commitment,
+        );
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        // Responses for the unchanged messages should be same in both proofs
+        request.pok_sig.verify_with_commitment(
+            vk,
+            params,
+            revealed_msgs,
+            &request.pok_commitment,
+            &bases,
+            &request.commitment,
+            &unchanged,
+            &challenge,
+        )
+    }
+
+    /// Called by the issuer after verifying the request. `updated_messages` should contain the new serial at
+    /// `serial_idx` and the new messages at the indices being updated. The serial of the old credential is
+    /// revoked so the same credential cannot be updated again.
+    pub fn issue(
+        request: &CredentialUpdateRequest,
+        serial_idx: usize,
+        updated_messages: &HashMap<usize, FieldElement>,
+        sigkey: &Sigkey,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+        revocation_list: &mut RevocationList,
+    ) -> Result<Signature, PSError> {
+        if revocation_list.is_revoked(&request.serial) {
+            return Err(PSError::RevokedCredential);
+        }
+        if !updated_messages.contains_key(&serial_idx) {
+            return Err(PSError::GeneralError {
+                msg: format!("New serial should be provided at index {}", serial_idx),
+            });
+        }
+        let updated_indices = updated_messages
+            .keys()
+            .filter(|i| **i != serial_idx)
+            .cloned()
+            .collect::<HashSet<usize>>();
+        if !Self::verify_request(
+            request,
+            serial_idx,
+            &updated_indices,
+            vk,
+            blinding_key,
+            params,
+            nonce,
+        )? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Invalid credential update request"),
+            });
+        }
+        let sig = BlindSignature::new_with_known_messages_at_indices(
+            &request.commitment,
+            updated_messages,
+            sigkey,
+            blinding_key,
+            params,
+        )?;
+        revocation_list.revoke(&request.serial);
+        Ok(sig)
+    }
+
+    /// Indices of messages which are neither the serial nor being updated, in increasing order
+    fn unchanged_indices(
+        msg_count: usize,
+        serial_idx: usize,
+        updated_indices: &HashSet<usize>,
+    ) -> Result<Vec<usize>, PSError> {
+        if serial_idx >= msg_count {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", serial_idx, msg_count),
+            });
+        }
+        for idx in updated_indices {
+            if *idx >= msg_count || *idx == serial_idx {
+                return Err(PSError::GeneralError {
+                    msg: format!("Invalid index {} for updated message", idx),
+                });
+            }
+        }
+        Ok((0..msg_count)
+            .filter(|i| *i != serial_idx && !updated_indices.contains(i))
+            .collect())
+    }
+
+    fn check_blinding_key(vk: &Verkey, blinding_key: &BlindingKey) -> Result<(), PSError> {
+        if vk.Y_tilde.len() != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: blinding_key.msg_count(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_credential_update() {
+        let count_msgs = 6;
+        let serial_idx = 0;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Update messages at index 2 and 4
+        let mut updated_indices = HashSet::new();
+        updated_indices.insert(2);
+        updated_indices.insert(4);
+        let nonce = FieldElement::random().to_bytes();
+
+        let (request, blinding) = CredentialUpdate::request(
+            &sig,
+            msgs.clone(),
+            serial_idx,
+            &updated_indices,
+            &vk,
+            &blinding_key,
+            &params,
+            &nonce,
+        )
+        .unwrap();
+        assert!(CredentialUpdate::verify_request(
+            &request,
+            serial_idx,
+            &updated_indices,
+            &vk,
+            &blinding_key,
+            &params,
+            &nonce
+        )
+        .unwrap());
+
+        let mut updated_msgs = HashMap::new();
+        updated_msgs.insert(serial_idx, FieldElement::random());
+        updated_msgs.insert(2, FieldElement::random());
+        updated_msgs.insert(4, FieldElement::random());
+
+        let mut revocation_list = RevocationList::new();
+        let sig_blinded = CredentialUpdate::issue(
+            &request,
+            serial_idx,
+            &updated_msgs,
+            &sk,
+            &vk,
+            &blinding_key,
+            &params,
+            &nonce,
+            &mut revocation_list,
+        )
+        .unwrap();
+        let new_sig = BlindSignature::unblind(&sig_blinded, &blinding);
+
+        let mut new_msgs = msgs.clone();
+        for (i, m) in &updated_msgs {
+            new_msgs[*i] = m.clone();
+        }
+        assert!(new_sig.verify(new_msgs, &vk, &params).unwrap());
+        assert!(revocation_list.is_revoked(&msgs[serial_idx]));
+
+        // Old credential cannot be updated again
+        assert!(CredentialUpdate::issue(
+            &request,
+            serial_idx,
+            &updated_msgs,
+            &sk,
+            &vk,
+            &blinding_key,
+            &params,
+            &nonce,
+            &mut revocation_list,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_credential_update_request_with_different_nonce_fails() {
+        let count_msgs = 5;
+        let serial_idx = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut updated_indices = HashSet::new();
+        updated_indices.insert(1);
+        let (request, _) = CredentialUpdate::request(
+            &sig,
+            msgs,
+            serial_idx,
+            &updated_indices,
+            &vk,
+            &blinding_key,
+            &params,
+            "nonce 1".as_bytes(),
+        )
+        .unwrap();
+        assert!(!CredentialUpdate::verify_request(
+            &request,
+            serial_idx,
+            &updated_indices,
+            &vk,
+            &blinding_key,
+            &params,
+            "nonce 2".as_bytes()
+        )
+        .unwrap());
+    }
+}
@@ -0,0 +1,190 @@
+// Minimal DER (ITU-T X.690) encoding and decoding of the few ASN.1 types needed to export keys: INTEGER (only
+// non-negative values fitting in u64), OCTET STRING, UTF8String and SEQUENCE. Decoding is strict, non-minimal
+// lengths and integers are rejected so each value has exactly one encoding.
+
+use crate::errors::PSError;
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_SEQUENCE: u8 = 0x30;
+
+fn write_tlv(tag: u8, value: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    let len = value.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let len_bytes = (len as u64).to_be_bytes();
+        let skip = len_bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (8 - skip) as u8);
+        out.extend_from_slice(&len_bytes[skip..]);
+    }
+    out.extend_from_slice(value);
+}
+
+pub fn write_uint(value: u64, out: &mut Vec<u8>) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let mut v = vec![];
+    // Leading 0 byte so that the value is not interpreted as negative
+    if bytes[skip] & 0x80 != 0 {
+        v.push(0);
+    }
+    v.extend_from_slice(&bytes[skip..]);
+    write_tlv(TAG_INTEGER, &v, out);
+}
+
+pub fn write_octet_string(bytes: &[u8], out: &mut Vec<u8>) {
+    write_tlv(TAG_OCTET_STRING, bytes, out);
+}
+
+pub fn write_utf8_string(s: &str, out: &mut Vec<u8>) {
+    write_tlv(TAG_UTF8_STRING, s.as_bytes(), out);
+}
+
+/// `contents` is the concatenation of the encodings of the items of the sequence
+pub fn write_sequence(contents: &[u8], out: &mut Vec<u8>) {
+    write_tlv(TAG_SEQUENCE, contents, out);
+}
+
+pub struct DerReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn err(&self, msg: &str) -> PSError {
+        PSError::InvalidBytes {
+            msg: format!("DER at position {}: {}", self.pos, msg),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PSError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.err("unexpected end of input"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    /// Read an item with the expected tag and return its value
+    fn read_tlv(&mut self, expected_tag: u8) -> Result<&'a [u8], PSError> {
+        if self.take(1)?[0] != expected_tag {
+            return Err(self.err(&format!("expected tag {:#x}", expected_tag)));
+        }
+        let first = self.take(1)?[0];
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 8 {
+                return Err(self.err("unsupported length"));
+            }
+            let len_bytes = self.take(n)?;
+            if len_bytes[0] == 0 {
+                return Err(self.err("length not minimal"));
+            }
+            let mut len = 0u64;
+            for b in len_bytes {
+                len = (len << 8) | *b as u64;
+            }
+            if len < 0x80 {
+                return Err(self.err("length not minimal"));
+            }
+            if len > (self.bytes.len() - self.pos) as u64 {
+                return Err(self.err("unexpected end of input"));
+            }
+            len as usize
+        };
+        self.take(len)
+    }
+
+    pub fn read_uint(&mut self) -> Result<u64, PSError> {
+        let v = self.read_tlv(TAG_INTEGER)?;
+        if v.is_empty() || v[0] & 0x80 != 0 {
+            return Err(self.err("integer empty or negative"));
+        }
+        if v.len() > 1 && v[0] == 0 && v[1] & 0x80 == 0 {
+            return Err(self.err("integer not minimal"));
+        }
+        let v = if v[0] == 0 && v.len() > 1 { &v[1..] } else { v };
+        if v.len() > 8 {
+            return Err(self.err("integer too large"));
+        }
+        Ok(v.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8], PSError> {
+        self.read_tlv(TAG_OCTET_STRING)
+    }
+
+    pub fn read_utf8_string(&mut self) -> Result<&'a str, PSError> {
+        let v = self.read_tlv(TAG_UTF8_STRING)?;
+        std::str::from_utf8(v).map_err(|_| self.err("invalid UTF-8"))
+    }
+
+    /// Read a sequence and return a reader over its contents
+    pub fn read_sequence(&mut self) -> Result<DerReader<'a>, PSError> {
+        Ok(DerReader::new(self.read_tlv(TAG_SEQUENCE)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// Check that all input has been consumed
+    pub fn finish(&self) -> Result<(), PSError> {
+        if !self.is_empty() {
+            return Err(self.err("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der_uint() {
+        for (value, expected) in &[
+            (0u64, vec![0x02, 0x01, 0x00]),
+            (127, vec![0x02, 0x01, 0x7f]),
+            (128, vec![0x02, 0x02, 0x00, 0x80]),
+            (256, vec![0x02, 0x02, 0x01, 0x00]),
+        ] {
+            let mut out = vec![];
+            write_uint(*value, &mut out);
+            assert_eq!(&out, expected);
+            let mut reader = DerReader::new(&out);
+            assert_eq!(reader.read_uint().unwrap(), *value);
+            reader.finish().unwrap();
+        }
+        let mut out = vec![];
+        write_uint(u64::max_value(), &mut out);
+        assert_eq!(DerReader::new(&out).read_uint().unwrap(), u64::max_value());
+
+        // Non-minimal and negative integers are rejected
+        assert!(DerReader::new(&[0x02, 0x02, 0x00, 0x01]).read_uint().is_err());
+        assert!(DerReader::new(&[0x02, 0x01, 0x80]).read_uint().is_err());
+    }
+
+    #[test]
+    fn test_der_long_length() {
+        let bytes = vec![7u8; 300];
+        let mut out = vec![];
+        write_octet_string(&bytes, &mut out);
+        assert_eq!(&out[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        let mut reader = DerReader::new(&out);
+        assert_eq!(reader.read_octet_string().unwrap(), bytes.as_slice());
+        reader.finish().unwrap();
+        // Non-minimal length is rejected
+        assert!(DerReader::new(&[0x04, 0x81, 0x01, 0x00]).read_octet_string().is_err());
+    }
+}
@@ -0,0 +1,451 @@
+// Pedersen's distributed key generation (Eurocrypt 1991, as described by Gennaro et al. in "Secure Distributed Key
+// Generation for Discrete-Log Based Cryptosystems") for PS signing keys so no single party ever holds the issuer's key.
+// Each of the `total` participants, with ids 1 to `total`, deals a random polynomial of degree `threshold - 1` for x
+// and for each y_i. It broadcasts commitments g_tilde^coefficient to the coefficients (`CommitmentMessage`) and sends
+// each participant its share (`ShareMessage`), the evaluations of the polynomials at the receiver's id. A receiver
+// checks its share against the commitments and broadcasts a `Complaint` if it does not match or is missing. The
+// accused dealer answers with a `ComplaintResponse` revealing that share to everyone, a dealer whose revealed share
+// does not match or who does not answer is disqualified. The key share of a participant is the sum of the shares from
+// the qualified dealers and the joint verkey is the sum of the commitments to their constant terms, so the signing key
+// is the sum of the secrets of the qualified dealers and any `threshold` shares determine it.
+// Share messages must be sent over private authenticated channels and the other messages over an authenticated
+// broadcast channel, neither is provided here. As shown by Gennaro et al., a dishonest dealer can bias the verkey by
+// choosing whether to get disqualified so the key is not uniformly random, which does not help in forging signatures.
+
+use crate::errors::PSError;
+use crate::keys::{KeyMetadata, Params, Scheme, Verkey};
+use crate::threshold::{check_threshold, evaluate, FeldmanCommitments, SigkeyShare};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use std::collections::{HashMap, HashSet};
+
+/// Parameters of a key generation, same for all participants
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DkgConfig {
+    /// No of shares needed to sign
+    pub threshold: usize,
+    /// No of participants
+    pub total: usize,
+    pub scheme: Scheme,
+    pub count_messages: usize,
+}
+
+/// Broadcast by each dealer, commitments to the coefficients of the polynomial for x followed by those for each y_i
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitmentMessage {
+    pub sender: usize,
+    pub commitments: FeldmanCommitments,
+}
+
+/// Sent privately by a dealer to `receiver`, the shares of x and each y_i
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShareMessage {
+    pub sender: usize,
+    pub receiver: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub shares: Vec<FieldElement>,
+}
+
+/// Broadcast by `complainer` when the share from `accused` does not match its commitments or is missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Complaint {
+    pub complainer: usize,
+    pub accused: usize,
+}
+
+/// Broadcast by the accused dealer, the share for the complainer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComplaintResponse {
+    pub share: ShareMessage,
+}
+
+/// Result of the key generation for a participant
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DkgOutput {
+    pub share: SigkeyShare,
+    pub verkey: Verkey,
+    /// Ids of the dealers which were not disqualified, in increasing order
+    pub qualified: Vec<usize>,
+    /// Sum of the commitments of the qualified dealers, to verify shares and get the verkey of each share
+    pub commitments: FeldmanCommitments,
+}
+
+/// State of a participant during the key generation
+pub struct Participant {
+    id: usize,
+    config: DkgConfig,
+    /// Coefficients of the polynomials for x followed by each y_i, constant term first
+    coefficients: Vec<FieldElementVector>,
+    commitments: HashMap<usize, FeldmanCommitments>,
+    /// Verified shares received from each dealer, including this participant
+    shares: HashMap<usize, Vec<FieldElement>>,
+    /// Unanswered complaints
+    complaints: HashSet<Complaint>,
+    disqualified: HashSet<usize>,
+}
+
+impl DkgConfig {
+    pub fn new(threshold: usize, total: usize, scheme: Scheme, count_messages: usize) -> Result<Self, PSError> {
+        check_threshold(threshold, total)?;
+        Ok(Self {
+            threshold,
+            total,
+            scheme,
+            count_messages,
+        })
+    }
+
+    /// No of secrets shared, x and each y_i
+    pub fn secret_count(&self) -> usize {
+        self.scheme.key_size(self.count_messages) + 1
+    }
+
+    fn check_id(&self, id: usize) -> Result<(), PSError> {
+        if id == 0 || id > self.total {
+            return Err(PSError::GeneralError {
+                msg: format!("Participant id {} should be between 1 and {}", id, self.total),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Participant {
+    /// Choose the polynomials, returns the state and the commitments to broadcast
+    pub fn new(id: usize, config: DkgConfig, params: &Params) -> Result<(Self, CommitmentMessage), PSError> {
+        config.check_id(id)?;
+        let coefficients = (0..config.secret_count())
+            .map(|_| FieldElementVector::random(config.threshold))
+            .collect::<Vec<FieldElementVector>>();
+        let commitments = FeldmanCommitments::new(&coefficients, params);
+        let own_shares = coefficients.iter().map(|coeffs| evaluate(coeffs, id)).collect();
+        let mut participant = Self {
+            id,
+            config,
+            coefficients,
+            commitments: HashMap::new(),
+            shares: HashMap::new(),
+            complaints: HashSet::new(),
+            disqualified: HashSet::new(),
+        };
+        participant.commitments.insert(id, commitments.clone());
+        participant.shares.insert(id, own_shares);
+        Ok((
+            participant,
+            CommitmentMessage {
+                sender: id,
+                commitments,
+            },
+        ))
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Share for `receiver` to be sent privately
+    pub fn share_for(&self, receiver: usize) -> Result<ShareMessage, PSError> {
+        self.config.check_id(receiver)?;
+        Ok(ShareMessage {
+            sender: self.id,
+            receiver,
+            shares: self.coefficients.iter().map(|coeffs| evaluate(coeffs, receiver)).collect(),
+        })
+    }
+
+    /// Shares for all other participants
+    pub fn share_messages(&self) -> Vec<ShareMessage> {
+        (1..=self.config.total)
+            .filter(|i| *i != self.id)
+            .map(|i| self.share_for(i).unwrap())
+            .collect()
+    }
+
+    /// Process the commitments broadcast by another dealer
+    pub fn receive_commitments(&mut self, msg: CommitmentMessage) -> Result<(), PSError> {
+        self.config.check_id(msg.sender)?;
+        if self.commitments.contains_key(&msg.sender) {
+            return Err(PSError::GeneralError {
+                msg: format!("Commitments from {} already received", msg.sender),
+            });
+        }
+        if !msg
+            .commitments
+            .is_well_formed(self.config.secret_count(), self.config.threshold)
+        {
+            // Everyone sees the same broadcast so everyone disqualifies the dealer
+            self.disqualified.insert(msg.sender);
+            return Ok(());
+        }
+        self.commitments.insert(msg.sender, msg.commitments);
+        Ok(())
+    }
+
+    /// Process the share sent by another dealer. Returns a complaint to broadcast if the share does not match the
+    /// dealer's commitments, which should have been received before.
+    pub fn receive_share(&mut self, msg: ShareMessage, params: &Params) -> Result<Option<Complaint>, PSError> {
+        if msg.receiver != self.id {
+            return Err(PSError::GeneralError {
+                msg: format!("Share is for {} but this is {}", msg.receiver, self.id),
+            });
+        }
+        let commitments = self.commitments.get(&msg.sender).ok_or_else(|| PSError::GeneralError {
+            msg: format!("Commitments from {} not received", msg.sender),
+        })?;
+        if !commitments.share_matches(&msg.shares.iter().collect::<Vec<_>>(), self.id, params) {
+            let complaint = Complaint {
+                complainer: self.id,
+                accused: msg.sender,
+            };
+            self.complaints.insert(complaint);
+            return Ok(Some(complaint));
+        }
+        self.shares.insert(msg.sender, msg.shares);
+        Ok(None)
+    }
+
+    /// Complaints against the dealers whose commitments were received but whose share was not, to broadcast once
+    /// the deadline for shares has passed
+    pub fn complaints_for_missing_shares(&mut self) -> Vec<Complaint> {
+        let mut complaints = vec![];
+        for dealer in self.commitments.keys() {
+            if !self.shares.contains_key(dealer) {
+                complaints.push(Complaint {
+                    complainer: self.id,
+                    accused: *dealer,
+                });
+            }
+        }
+        self.complaints.extend(complaints.iter().cloned());
+        complaints
+    }
+
+    /// Process a broadcast complaint. Returns the response to broadcast if this participant is the accused. The
+    /// response should be processed by all participants including the accused.
+    pub fn receive_complaint(&mut self, complaint: Complaint) -> Result<Option<ComplaintResponse>, PSError> {
+        self.config.check_id(complaint.complainer)?;
+        self.config.check_id(complaint.accused)?;
+        self.complaints.insert(complaint);
+        if complaint.accused == self.id {
+            return Ok(Some(ComplaintResponse {
+                share: self.share_for(complaint.complainer)?,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Process the response of an accused dealer. The dealer is disqualified if the revealed share does not match
+    /// its commitments.
+    pub fn receive_complaint_response(&mut self, response: ComplaintResponse, params: &Params) -> Result<(), PSError> {
+        let share = response.share;
+        let complaint = Complaint {
+            complainer: share.receiver,
+            accused: share.sender,
+        };
+        if !self.complaints.remove(&complaint) {
+            return Err(PSError::GeneralError {
+                msg: format!("No complaint by {} against {}", complaint.complainer, complaint.accused),
+            });
+        }
+        let valid = match self.commitments.get(&share.sender) {
+            Some(commitments) => {
+                commitments.share_matches(&share.shares.iter().collect::<Vec<_>>(), share.receiver, params)
+            }
+            None => false,
+        };
+        if !valid {
+            self.disqualified.insert(share.sender);
+        } else if share.receiver == self.id {
+            self.shares.insert(share.sender, share.shares);
+        }
+        Ok(())
+    }
+
+    /// Dealers disqualified so far
+    pub fn disqualified(&self) -> Vec<usize> {
+        let mut disqualified = self.disqualified.iter().cloned().collect::<Vec<usize>>();
+        disqualified.sort();
+        disqualified
+    }
+
+    /// Compute the key share and the joint verkey once all complaints were broadcast and answered. Dealers which
+    /// did not broadcast commitments or did not answer a complaint are disqualified.
+    pub fn finish(self, params: &Params) -> Result<DkgOutput, PSError> {
+        let mut disqualified = self.disqualified;
+        for complaint in &self.complaints {
+            disqualified.insert(complaint.accused);
+        }
+        let qualified = (1..=self.config.total)
+            .filter(|i| !disqualified.contains(i) && self.commitments.contains_key(i))
+            .collect::<Vec<usize>>();
+        if qualified.len() < self.config.threshold {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Only {} qualified dealers but the threshold is {}",
+                    qualified.len(),
+                    self.config.threshold
+                ),
+            });
+        }
+
+        let secret_count = self.config.secret_count();
+        let mut shares = vec![FieldElement::zero(); secret_count];
+        let mut commitments = FeldmanCommitments::identity(secret_count, self.config.threshold);
+        for dealer in &qualified {
+            let dealer_shares = self.shares.get(dealer).ok_or_else(|| PSError::GeneralError {
+                msg: format!("No share from {}, a complaint should have been made", dealer),
+            })?;
+            for (j, s) in dealer_shares.iter().enumerate() {
+                shares[j] += s;
+            }
+            commitments.add_assign(&self.commitments[dealer]);
+        }
+
+        let verkey = commitments
+            .verkey()?
+            .with_metadata(KeyMetadata::new(self.config.scheme, self.config.count_messages, params));
+        let x = shares.remove(0);
+        Ok(DkgOutput {
+            share: SigkeyShare { id: self.id, x, y: shares },
+            verkey,
+            qualified,
+            commitments,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::keys::Sigkey;
+    use crate::threshold::{lagrange_coefficient, verify_share};
+
+    pub(crate) fn run_dkg(
+        config: DkgConfig,
+        params: &Params,
+        tamper: Option<(usize, usize)>,
+        answer_complaints: bool,
+    ) -> Vec<DkgOutput> {
+        let mut participants = vec![];
+        let mut commitment_msgs = vec![];
+        for id in 1..=config.total {
+            let (p, c) = Participant::new(id, config, params).unwrap();
+            participants.push(p);
+            commitment_msgs.push(c);
+        }
+        for p in participants.iter_mut() {
+            for c in &commitment_msgs {
+                if c.sender != p.id() {
+                    p.receive_commitments(c.clone()).unwrap();
+                }
+            }
+        }
+        let mut share_msgs = vec![];
+        for p in &participants {
+            share_msgs.append(&mut p.share_messages());
+        }
+        if let Some((sender, receiver)) = tamper {
+            for m in share_msgs.iter_mut() {
+                if m.sender == sender && m.receiver == receiver {
+                    m.shares[0] = FieldElement::random();
+                }
+            }
+        }
+        let mut complaints = vec![];
+        for m in share_msgs {
+            let receiver = m.receiver;
+            if let Some(c) = participants[receiver - 1].receive_share(m, params).unwrap() {
+                complaints.push(c);
+            }
+        }
+        let mut responses = vec![];
+        for c in &complaints {
+            for p in participants.iter_mut() {
+                if p.id() != c.complainer {
+                    if let Some(r) = p.receive_complaint(*c).unwrap() {
+                        responses.push(r);
+                    }
+                }
+            }
+        }
+        if answer_complaints {
+            for r in responses {
+                for p in participants.iter_mut() {
+                    p.receive_complaint_response(r.clone(), params).unwrap();
+                }
+            }
+        }
+        participants.into_iter().map(|p| p.finish(params).unwrap()).collect()
+    }
+
+    fn check_outputs(outputs: &[DkgOutput], config: DkgConfig, params: &Params) {
+        for o in outputs {
+            assert_eq!(o.verkey.to_bytes(config.scheme), outputs[0].verkey.to_bytes(config.scheme));
+            assert_eq!(o.qualified, outputs[0].qualified);
+            let share_vk = outputs[0].commitments.share_verkey(o.share.id).unwrap();
+            assert_eq!(share_vk.X_tilde, &params.g_tilde * &o.share.x);
+            assert!(verify_share(&o.share, &outputs[0].commitments, params));
+            assert!(outputs[0].commitments.is_for_verkey(&o.verkey));
+        }
+        // Any threshold shares give the signing key of the joint verkey
+        let signers = &outputs[1..=config.threshold];
+        let ids = signers.iter().map(|o| o.share.id).collect::<Vec<usize>>();
+        let mut x = FieldElement::zero();
+        let mut y = vec![FieldElement::zero(); config.secret_count() - 1];
+        for o in signers {
+            let l = lagrange_coefficient(&ids, o.share.id).unwrap();
+            x += &o.share.x * &l;
+            for (j, y_j) in o.share.y.iter().enumerate() {
+                y[j] += y_j * &l;
+            }
+        }
+        let sk = Sigkey { x, y, metadata: None };
+        assert!(sk.is_key_for(&outputs[0].verkey, params));
+    }
+
+    #[test]
+    fn test_dkg() {
+        let params = Params::new("test".as_bytes());
+        let config = DkgConfig::new(3, 5, Scheme::PS2016, 4).unwrap();
+        let outputs = run_dkg(config, &params, None, true);
+        assert_eq!(outputs[0].qualified, vec![1, 2, 3, 4, 5]);
+        check_outputs(&outputs, config, &params);
+
+        let config = DkgConfig::new(2, 3, Scheme::PS2018, 2).unwrap();
+        let outputs = run_dkg(config, &params, None, true);
+        assert_eq!(outputs[0].verkey.Y_tilde.len(), 3);
+        check_outputs(&outputs, config, &params);
+
+        assert!(DkgConfig::new(4, 3, Scheme::PS2016, 2).is_err());
+        assert!(DkgConfig::new(0, 3, Scheme::PS2016, 2).is_err());
+    }
+
+    #[test]
+    fn test_dkg_complaints() {
+        let params = Params::new("test".as_bytes());
+        let config = DkgConfig::new(3, 5, Scheme::PS2016, 3).unwrap();
+
+        // Dealer 2 sends a wrong share to 4 but reveals the correct one when accused so stays qualified
+        let outputs = run_dkg(config, &params, Some((2, 4)), true);
+        assert_eq!(outputs[0].qualified, vec![1, 2, 3, 4, 5]);
+        check_outputs(&outputs, config, &params);
+
+        // Dealer 2 does not answer the complaint and is disqualified by everyone
+        let outputs = run_dkg(config, &params, Some((2, 4)), false);
+        assert_eq!(outputs[0].qualified, vec![1, 3, 4, 5]);
+        check_outputs(&outputs, config, &params);
+
+        // A revealed share not matching the commitments disqualifies the dealer
+        let (mut p_1, _) = Participant::new(1, config, &params).unwrap();
+        let (mut p_2, c_2) = Participant::new(2, config, &params).unwrap();
+        p_1.receive_commitments(c_2).unwrap();
+        let complaint = Complaint {
+            complainer: 3,
+            accused: 2,
+        };
+        assert!(p_1.receive_complaint(complaint).unwrap().is_none());
+        let mut response = p_2.receive_complaint(complaint).unwrap().unwrap();
+        response.share.shares[1] = FieldElement::random();
+        p_1.receive_complaint_response(response, &params).unwrap();
+        assert_eq!(p_1.disqualified(), vec![2]);
+    }
+}
@@ -0,0 +1,107 @@
+// Binding a document to a credential by signing the hash of the document as one of the messages. During a proof of
+// knowledge of the signature, the prover does not send the message; the verifier, who holds the document (or gets
+// it out of band), computes the message by hashing the document and treats it as a revealed message. The proof
+// verifies only if the signed message is the hash of that document. Since the verifier can compute the message,
+// this hides nothing about the document from the verifier; the other messages remain hidden.
+
+use crate::errors::PSError;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for hashing documents to messages
+pub const DOCUMENT_HASH_DST: &[u8] = b"PS-SIG-DOCUMENT-HASH";
+
+/// Message at index `msg_idx` of the signature is the hash of a document
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DocumentBinding {
+    pub msg_idx: usize,
+}
+
+impl DocumentBinding {
+    pub fn new(msg_idx: usize) -> Self {
+        Self { msg_idx }
+    }
+
+    /// Hash the document to get the message to be signed
+    pub fn encode(document: &[u8]) -> FieldElement {
+        FieldElement::from_msg_hash(&[DOCUMENT_HASH_DST, document].concat())
+    }
+
+    /// Called by the prover before creating the proof of knowledge of signature. Checks that the message is
+    /// the hash of the document and marks the message as revealed. The message need not be sent to the verifier.
+    pub fn add_to_revealed_indices(
+        &self,
+        messages: &[FieldElement],
+        document: &[u8],
+        revealed_msg_indices: &mut HashSet<usize>,
+    ) -> Result<(), PSError> {
+        if self.msg_idx >= messages.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", self.msg_idx, messages.len()),
+            });
+        }
+        if messages[self.msg_idx] != Self::encode(document) {
+            return Err(PSError::GeneralError {
+                msg: format!("Message at index {} is not the hash of the document", self.msg_idx),
+            });
+        }
+        revealed_msg_indices.insert(self.msg_idx);
+        Ok(())
+    }
+
+    /// Called by the verifier before verifying the proof of knowledge of signature. Adds the hash of the
+    /// document to the revealed messages.
+    pub fn add_to_revealed_messages(
+        &self,
+        document: &[u8],
+        revealed_msgs: &mut HashMap<usize, FieldElement>,
+    ) {
+        revealed_msgs.insert(self.msg_idx, Self::encode(document));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_document_binding() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+
+        let document = vec![7u8; 10000];
+        let binding = DocumentBinding::new(2);
+        let mut msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        msgs[2] = DocumentBinding::encode(&document);
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        binding
+            .add_to_revealed_indices(msgs.as_slice(), &document, &mut revealed_msg_indices)
+            .unwrap();
+        // Prover cannot bind a different document
+        assert!(binding
+            .add_to_revealed_indices(msgs.as_slice(), "other".as_bytes(), &mut HashSet::new())
+            .is_err());
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_msg_indices, &vk, &params);
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+
+        let mut revealed_msgs = HashMap::new();
+        binding.add_to_revealed_messages(&document, &mut revealed_msgs);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+
+        // Verifier holding a different document rejects
+        let mut revealed_msgs = HashMap::new();
+        binding.add_to_revealed_messages("other".as_bytes(), &mut revealed_msgs);
+        assert!(!proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+    }
+}
@@ -0,0 +1,231 @@
+// Passphrase encrypted export of signing keys for backups. A key is derived from the passphrase with scrypt and the
+// DER encoding of the signing key (`Sigkey::to_der`) is encrypted with ChaCha20-Poly1305. The encoding is
+// version (1 byte) || scheme (1 byte) || message count (4 bytes big-endian) || scrypt log_n (1 byte) || scrypt r
+// (4 bytes big-endian) || scrypt p (4 bytes big-endian) || salt (16 bytes) || nonce (12 bytes) || ciphertext.
+// Everything before the ciphertext is authenticated as associated data so it can't be changed without making the
+// decryption fail. Enabled with the feature `encrypted_export`.
+
+use crate::errors::PSError;
+use crate::keys::{Scheme, Sigkey};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::RngCore;
+
+/// Version of the encoding of an encrypted signing key
+pub const ENCRYPTED_SIGKEY_VERSION: u8 = 1;
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const KEY_SIZE: usize = 32;
+const HEADER_SIZE: usize = 1 + 1 + 4 + 1 + 4 + 4 + SALT_SIZE + NONCE_SIZE;
+
+/// Parameters of scrypt, the cost is 2^log_n
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    /// Parameters recommended for interactive use
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+/// The unencrypted part of an encrypted signing key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSigkeyHeader {
+    pub scheme: Scheme,
+    pub message_count: u32,
+    pub kdf_params: KdfParams,
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8], kdf_params: &KdfParams) -> Result<[u8; KEY_SIZE], PSError> {
+    let params = scrypt::ScryptParams::new(kdf_params.log_n, kdf_params.r, kdf_params.p).map_err(|_| {
+        PSError::GeneralError {
+            msg: format!("Invalid scrypt parameters {:?}", kdf_params),
+        }
+    })?;
+    let mut key = [0u8; KEY_SIZE];
+    scrypt::scrypt(passphrase, salt, &params, &mut key).map_err(|_| PSError::GeneralError {
+        msg: String::from("scrypt failed"),
+    })?;
+    Ok(key)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&bytes[..4]);
+    u32::from_be_bytes(b)
+}
+
+impl EncryptedSigkeyHeader {
+    /// Parse the header of the output of `Sigkey::export_encrypted`, does not need the passphrase
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(PSError::IncorrectByteLength {
+                expected: HEADER_SIZE,
+                given: bytes.len(),
+            });
+        }
+        if bytes[0] != ENCRYPTED_SIGKEY_VERSION {
+            return Err(PSError::UnsupportedVersion { version: bytes[0] });
+        }
+        let scheme = Scheme::from_byte(bytes[1]).ok_or_else(|| PSError::InvalidBytes {
+            msg: format!("Unknown scheme {}", bytes[1]),
+        })?;
+        Ok(Self {
+            scheme,
+            message_count: read_u32(&bytes[2..]),
+            kdf_params: KdfParams {
+                log_n: bytes[6],
+                r: read_u32(&bytes[7..]),
+                p: read_u32(&bytes[11..]),
+            },
+        })
+    }
+}
+
+impl Sigkey {
+    /// Encrypt the key of `scheme` with a key derived from `passphrase` using default scrypt parameters
+    pub fn export_encrypted(&self, scheme: Scheme, passphrase: &[u8]) -> Result<Vec<u8>, PSError> {
+        self.export_encrypted_with_params(scheme, passphrase, KdfParams::default())
+    }
+
+    pub fn export_encrypted_with_params(
+        &self,
+        scheme: Scheme,
+        passphrase: &[u8],
+        kdf_params: KdfParams,
+    ) -> Result<Vec<u8>, PSError> {
+        let message_count = match scheme {
+            Scheme::PS2016 => self.y.len(),
+            Scheme::PS2018 => self.y.len().saturating_sub(1),
+        };
+        let mut salt = [0u8; SALT_SIZE];
+        let mut nonce = [0u8; NONCE_SIZE];
+        let mut rng = rand::thread_rng();
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let mut bytes = vec![ENCRYPTED_SIGKEY_VERSION, scheme.to_byte()];
+        bytes.extend_from_slice(&(message_count as u32).to_be_bytes());
+        bytes.push(kdf_params.log_n);
+        bytes.extend_from_slice(&kdf_params.r.to_be_bytes());
+        bytes.extend_from_slice(&kdf_params.p.to_be_bytes());
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+
+        let key = derive_key(passphrase, &salt, &kdf_params)?;
+        let cipher = ChaCha20Poly1305::new(GenericArray::clone_from_slice(&key));
+        let plaintext = self.to_der(scheme);
+        let ciphertext = cipher.encrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &bytes,
+            },
+        );
+        #[cfg(feature = "zeroize")]
+        {
+            let mut plaintext = plaintext;
+            zeroize::Zeroize::zeroize(&mut plaintext);
+        }
+        let mut ciphertext = ciphertext.map_err(|_| PSError::GeneralError {
+            msg: String::from("Encryption failed"),
+        })?;
+        bytes.append(&mut ciphertext);
+        Ok(bytes)
+    }
+
+    /// Decrypt the output of `export_encrypted`, returns the key and its scheme
+    pub fn import_encrypted(bytes: &[u8], passphrase: &[u8]) -> Result<(Self, Scheme), PSError> {
+        let header = EncryptedSigkeyHeader::from_bytes(bytes)?;
+        let salt = &bytes[HEADER_SIZE - SALT_SIZE - NONCE_SIZE..HEADER_SIZE - NONCE_SIZE];
+        let nonce = &bytes[HEADER_SIZE - NONCE_SIZE..HEADER_SIZE];
+        let key = derive_key(passphrase, salt, &header.kdf_params)?;
+        let cipher = ChaCha20Poly1305::new(GenericArray::clone_from_slice(&key));
+        let plaintext = cipher
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: &bytes[HEADER_SIZE..],
+                    aad: &bytes[..HEADER_SIZE],
+                },
+            )
+            .map_err(|_| PSError::DecryptionFailed)?;
+        let res = Sigkey::from_der(&plaintext);
+        #[cfg(feature = "zeroize")]
+        {
+            let mut plaintext = plaintext;
+            zeroize::Zeroize::zeroize(&mut plaintext);
+        }
+        let (sk, scheme) = res?;
+        // The header is authenticated so this only fails if the encrypting party created an inconsistent header
+        if scheme != header.scheme || scheme.key_size(header.message_count as usize) != sk.y.len() {
+            return Err(PSError::InvalidBytes {
+                msg: String::from("Header does not match the encrypted key"),
+            });
+        }
+        Ok((sk, scheme))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, keygen_2018, Params};
+
+    #[test]
+    fn test_encrypted_export() {
+        // Low cost to keep the test fast
+        let kdf_params = KdfParams {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        };
+        let params = Params::new("test".as_bytes());
+        let (sk, _) = keygen(5, &params);
+        let bytes = sk
+            .export_encrypted_with_params(Scheme::PS2016, b"passphrase", kdf_params)
+            .unwrap();
+        let header = EncryptedSigkeyHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(header.scheme, Scheme::PS2016);
+        assert_eq!(header.message_count, 5);
+        assert_eq!(header.kdf_params, kdf_params);
+
+        let (sk_1, scheme) = Sigkey::import_encrypted(&bytes, b"passphrase").unwrap();
+        assert_eq!(scheme, Scheme::PS2016);
+        assert_eq!(sk_1.x, sk.x);
+        assert_eq!(sk_1.y, sk.y);
+
+        match Sigkey::import_encrypted(&bytes, b"wrong passphrase") {
+            Err(PSError::DecryptionFailed) => (),
+            _ => panic!("Expected DecryptionFailed"),
+        }
+        // Header is authenticated
+        let mut tampered = bytes.clone();
+        tampered[5] ^= 1;
+        assert!(Sigkey::import_encrypted(&tampered, b"passphrase").is_err());
+        let mut tampered = bytes.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(Sigkey::import_encrypted(&tampered, b"passphrase").is_err());
+
+        let (sk, _) = keygen_2018(3, &params);
+        let bytes = sk
+            .export_encrypted_with_params(Scheme::PS2018, b"passphrase", kdf_params)
+            .unwrap();
+        assert_eq!(EncryptedSigkeyHeader::from_bytes(&bytes).unwrap().message_count, 3);
+        let (sk_1, scheme) = Sigkey::import_encrypted(&bytes, b"passphrase").unwrap();
+        assert_eq!(scheme, Scheme::PS2018);
+        assert_eq!(sk_1.y, sk.y);
+    }
+}
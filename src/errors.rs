@@ -1,3 +1,4 @@
+use amcl_wrapper::errors::SerzDeserzError;
 use failure::Error;
 
 #[derive(Debug, Fail)]
@@ -24,6 +25,245 @@ pub enum PSError {
     )]
     IncompatibleSigsForAggregation,
 
+    #[fail(display = "Credential with the given serial has been revoked")]
+    RevokedCredential,
+
+    #[fail(display = "Invalid state transition: {:?}", msg)]
+    InvalidStateTransition { msg: String },
+
+    #[fail(display = "Session has expired")]
+    SessionExpired,
+
+    #[fail(display = "Expected {} bytes but given {} bytes", expected, given)]
+    IncorrectByteLength { expected: usize, given: usize },
+
+    #[fail(display = "Invalid bytes: {:?}", msg)]
+    InvalidBytes { msg: String },
+
+    #[fail(display = "Invalid group element: {:?}", msg)]
+    InvalidGroupElement { msg: String },
+
+    #[fail(display = "Unsupported version {} of the encoding", version)]
+    UnsupportedVersion { version: u8 },
+
+    #[fail(display = "Decryption failed, wrong passphrase or corrupted data")]
+    DecryptionFailed,
+
+    #[fail(display = "Created by a {} build but this is a {} build", given, expected)]
+    GroupMismatch { expected: String, given: String },
+
+    #[fail(display = "Invalid verkey: {:?}", msg)]
+    InvalidVerkey { msg: String },
+
+    #[fail(display = "Key not compatible: {:?}", msg)]
+    IncompatibleKey { msg: String },
+
+    #[fail(display = "Blinding and hidden messages do not open the commitment")]
+    WrongBlinding,
+
+    #[fail(display = "Issuer returned an invalid signature on the commitment")]
+    InvalidIssuedSignature,
+
+    #[fail(display = "Credential expired at {} but the time is {}", expiry, now)]
+    CredentialExpired { expiry: u64, now: u64 },
+
+    #[fail(display = "At least {} messages required but given {}", minimum, given)]
+    TooFewMessages { minimum: usize, given: usize },
+
     #[fail(display = "Error with message {:?}", msg)]
     GeneralError { msg: String },
 }
+
+/// Reason a signature did not verify, returned by `verify_strict` of the signatures of both schemes
+#[derive(Debug, Fail)]
+pub enum VerificationError {
+    #[fail(
+        display = "Verkey valid for {} messages but given {} messages",
+        expected, given
+    )]
+    UnsupportedNoOfMessages { expected: usize, given: usize },
+
+    #[fail(display = "Key not compatible: {:?}", msg)]
+    IncompatibleKey { msg: String },
+
+    #[fail(display = "Signature has an identity element")]
+    IdentitySignature,
+
+    #[fail(display = "Pairing check failed")]
+    PairingCheckFailed,
+}
+
+impl From<PSError> for VerificationError {
+    /// Errors of checking the verkey's metadata
+    fn from(e: PSError) -> Self {
+        match e {
+            PSError::UnsupportedNoOfMessages { expected, given } => {
+                VerificationError::UnsupportedNoOfMessages { expected, given }
+            }
+            PSError::IncompatibleKey { msg } => VerificationError::IncompatibleKey { msg },
+            e => VerificationError::IncompatibleKey { msg: e.to_string() },
+        }
+    }
+}
+
+impl From<VerificationError> for PSError {
+    fn from(e: VerificationError) -> Self {
+        match e {
+            VerificationError::UnsupportedNoOfMessages { expected, given } => {
+                PSError::UnsupportedNoOfMessages { expected, given }
+            }
+            VerificationError::IncompatibleKey { msg } => PSError::IncompatibleKey { msg },
+            e => PSError::GeneralError { msg: e.to_string() },
+        }
+    }
+}
+
+/// Stable numeric codes for the variants of `PSError`, for FFI consumers and logging. A code is never changed
+/// or reused once assigned, new variants get new codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u32)]
+pub enum PSErrorCode {
+    UnsupportedNoOfMessages = 1,
+    UnequalNoOfBasesExponents = 2,
+    IncompatibleVerkeysForAggregation = 3,
+    IncompatibleSigsForAggregation = 4,
+    RevokedCredential = 5,
+    InvalidStateTransition = 6,
+    SessionExpired = 7,
+    IncorrectByteLength = 8,
+    InvalidBytes = 9,
+    InvalidGroupElement = 10,
+    UnsupportedVersion = 11,
+    DecryptionFailed = 12,
+    GroupMismatch = 13,
+    InvalidVerkey = 14,
+    IncompatibleKey = 15,
+    WrongBlinding = 16,
+    InvalidIssuedSignature = 17,
+    CredentialExpired = 18,
+    TooFewMessages = 19,
+    GeneralError = 1000,
+}
+
+impl PSErrorCode {
+    pub fn as_u32(&self) -> u32 {
+        *self as u32
+    }
+
+    pub fn from_u32(code: u32) -> Option<Self> {
+        match code {
+            1 => Some(PSErrorCode::UnsupportedNoOfMessages),
+            2 => Some(PSErrorCode::UnequalNoOfBasesExponents),
+            3 => Some(PSErrorCode::IncompatibleVerkeysForAggregation),
+            4 => Some(PSErrorCode::IncompatibleSigsForAggregation),
+            5 => Some(PSErrorCode::RevokedCredential),
+            6 => Some(PSErrorCode::InvalidStateTransition),
+            7 => Some(PSErrorCode::SessionExpired),
+            8 => Some(PSErrorCode::IncorrectByteLength),
+            9 => Some(PSErrorCode::InvalidBytes),
+            10 => Some(PSErrorCode::InvalidGroupElement),
+            11 => Some(PSErrorCode::UnsupportedVersion),
+            12 => Some(PSErrorCode::DecryptionFailed),
+            13 => Some(PSErrorCode::GroupMismatch),
+            14 => Some(PSErrorCode::InvalidVerkey),
+            15 => Some(PSErrorCode::IncompatibleKey),
+            16 => Some(PSErrorCode::WrongBlinding),
+            17 => Some(PSErrorCode::InvalidIssuedSignature),
+            18 => Some(PSErrorCode::CredentialExpired),
+            19 => Some(PSErrorCode::TooFewMessages),
+            1000 => Some(PSErrorCode::GeneralError),
+            _ => None,
+        }
+    }
+}
+
+impl From<SerzDeserzError> for PSError {
+    fn from(e: SerzDeserzError) -> Self {
+        PSError::InvalidBytes {
+            msg: format!("{:?}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for PSError {
+    fn from(e: std::io::Error) -> Self {
+        PSError::GeneralError {
+            msg: format!("IO error: {}", e),
+        }
+    }
+}
+
+impl PSError {
+    /// Stable numeric code of the error
+    pub fn code(&self) -> PSErrorCode {
+        match self {
+            PSError::UnsupportedNoOfMessages { .. } => PSErrorCode::UnsupportedNoOfMessages,
+            PSError::UnequalNoOfBasesExponents { .. } => PSErrorCode::UnequalNoOfBasesExponents,
+            PSError::IncompatibleVerkeysForAggregation => {
+                PSErrorCode::IncompatibleVerkeysForAggregation
+            }
+            PSError::IncompatibleSigsForAggregation => PSErrorCode::IncompatibleSigsForAggregation,
+            PSError::RevokedCredential => PSErrorCode::RevokedCredential,
+            PSError::InvalidStateTransition { .. } => PSErrorCode::InvalidStateTransition,
+            PSError::SessionExpired => PSErrorCode::SessionExpired,
+            PSError::IncorrectByteLength { .. } => PSErrorCode::IncorrectByteLength,
+            PSError::InvalidBytes { .. } => PSErrorCode::InvalidBytes,
+            PSError::InvalidGroupElement { .. } => PSErrorCode::InvalidGroupElement,
+            PSError::UnsupportedVersion { .. } => PSErrorCode::UnsupportedVersion,
+            PSError::DecryptionFailed => PSErrorCode::DecryptionFailed,
+            PSError::GroupMismatch { .. } => PSErrorCode::GroupMismatch,
+            PSError::InvalidVerkey { .. } => PSErrorCode::InvalidVerkey,
+            PSError::IncompatibleKey { .. } => PSErrorCode::IncompatibleKey,
+            PSError::WrongBlinding => PSErrorCode::WrongBlinding,
+            PSError::InvalidIssuedSignature => PSErrorCode::InvalidIssuedSignature,
+            PSError::CredentialExpired { .. } => PSErrorCode::CredentialExpired,
+            PSError::TooFewMessages { .. } => PSErrorCode::TooFewMessages,
+            PSError::GeneralError { .. } => PSErrorCode::GeneralError,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let e = PSError::UnsupportedNoOfMessages {
+            expected: 2,
+            given: 3,
+        };
+        assert_eq!(e.code(), PSErrorCode::UnsupportedNoOfMessages);
+        assert_eq!(e.code().as_u32(), 1);
+        let e = PSError::GeneralError {
+            msg: String::from("test"),
+        };
+        assert_eq!(e.code().as_u32(), 1000);
+
+        for code in &[
+            PSErrorCode::UnsupportedNoOfMessages,
+            PSErrorCode::UnequalNoOfBasesExponents,
+            PSErrorCode::IncompatibleVerkeysForAggregation,
+            PSErrorCode::IncompatibleSigsForAggregation,
+            PSErrorCode::RevokedCredential,
+            PSErrorCode::InvalidStateTransition,
+            PSErrorCode::SessionExpired,
+            PSErrorCode::IncorrectByteLength,
+            PSErrorCode::InvalidBytes,
+            PSErrorCode::InvalidGroupElement,
+            PSErrorCode::UnsupportedVersion,
+            PSErrorCode::DecryptionFailed,
+            PSErrorCode::GroupMismatch,
+            PSErrorCode::InvalidVerkey,
+            PSErrorCode::IncompatibleKey,
+            PSErrorCode::WrongBlinding,
+            PSErrorCode::InvalidIssuedSignature,
+            PSErrorCode::CredentialExpired,
+            PSErrorCode::TooFewMessages,
+            PSErrorCode::GeneralError,
+        ] {
+            assert_eq!(PSErrorCode::from_u32(code.as_u32()), Some(*code));
+        }
+        assert_eq!(PSErrorCode::from_u32(0), None);
+    }
+}
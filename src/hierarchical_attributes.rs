@@ -0,0 +1,229 @@
+// Attributes organised in groups (like "name", "address", "education") under a Merkle tree, with one of the
+// signed messages being the hash of the root. Each group is a leaf of the tree containing the group's attributes
+// and a random salt, the salt prevents guessing a hidden group from its hash. During a proof of knowledge of the
+// signature, the message with the root is revealed and the holder discloses whole groups along with their
+// inclusion proofs, the other groups stay hidden. The other signed messages can be hidden or revealed as usual.
+// Since the root is revealed, proofs of knowledge of the same signature are linkable.
+
+use crate::errors::PSError;
+use crate::merkle::{MerkleHash, MerkleInclusionProof, MerkleTree};
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for hashing the root to the signed message
+pub const MERKLE_ROOT_HASH_DST: &[u8] = b"PS-SIG-MERKLE-ATTRIBUTES-ROOT";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttributeGroup {
+    pub salt: FieldElement,
+    pub attributes: Vec<FieldElement>,
+}
+
+/// All attribute groups, held by the holder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HierarchicalAttributes {
+    pub groups: Vec<AttributeGroup>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisclosedGroup {
+    pub group: AttributeGroup,
+    pub proof: MerkleInclusionProof,
+}
+
+/// Sent by the holder to the verifier along with the proof of knowledge of signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupDisclosure {
+    pub root: MerkleHash,
+    pub group_count: usize,
+    pub groups: Vec<DisclosedGroup>,
+}
+
+impl AttributeGroup {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.salt.to_bytes();
+        for a in &self.attributes {
+            bytes.append(&mut a.to_bytes());
+        }
+        bytes
+    }
+}
+
+impl HierarchicalAttributes {
+    /// Create groups with random salts
+    pub fn new(groups: Vec<Vec<FieldElement>>) -> Self {
+        Self {
+            groups: groups
+                .into_iter()
+                .map(|attributes| AttributeGroup {
+                    salt: FieldElement::random(),
+                    attributes,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn tree(&self) -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        for g in &self.groups {
+            tree.push(&g.to_bytes());
+        }
+        tree
+    }
+
+    pub fn root(&self) -> MerkleHash {
+        self.tree().root()
+    }
+
+    /// Message to be signed
+    pub fn to_message(&self) -> FieldElement {
+        root_to_message(&self.root())
+    }
+
+    /// Disclose the groups at `indices`
+    pub fn disclose(&self, indices: &HashSet<usize>) -> Result<GroupDisclosure, PSError> {
+        let tree = self.tree();
+        let mut indices = indices.iter().cloned().collect::<Vec<usize>>();
+        indices.sort();
+        let mut groups = Vec::with_capacity(indices.len());
+        for i in indices {
+            groups.push(DisclosedGroup {
+                group: self
+                    .groups
+                    .get(i)
+                    .ok_or_else(|| PSError::GeneralError {
+                        msg: format!("Index {} should be less than {}", i, self.groups.len()),
+                    })?
+                    .clone(),
+                proof: tree.inclusion_proof(i)?,
+            });
+        }
+        Ok(GroupDisclosure {
+            root: tree.root(),
+            group_count: self.groups.len(),
+            groups,
+        })
+    }
+
+    /// Called by the prover before creating the proof of knowledge of signature. Checks that the message at
+    /// `msg_idx` is for these groups and marks it as revealed.
+    pub fn add_to_revealed_indices(
+        &self,
+        messages: &[FieldElement],
+        msg_idx: usize,
+        revealed_msg_indices: &mut HashSet<usize>,
+    ) -> Result<(), PSError> {
+        if msg_idx >= messages.len() || messages[msg_idx] != self.to_message() {
+            return Err(PSError::GeneralError {
+                msg: format!("Message at index {} is not the root of the attribute groups", msg_idx),
+            });
+        }
+        revealed_msg_indices.insert(msg_idx);
+        Ok(())
+    }
+}
+
+impl GroupDisclosure {
+    /// Verify the inclusion of each disclosed group
+    pub fn verify(&self) -> bool {
+        let mut seen = HashSet::new();
+        self.groups.iter().all(|g| {
+            seen.insert(g.proof.leaf_index)
+                && g.proof.tree_size == self.group_count
+                && g.proof.verify(&g.group.to_bytes(), &self.root)
+        })
+    }
+
+    /// Disclosed groups by index
+    pub fn disclosed_groups(&self) -> HashMap<usize, &AttributeGroup> {
+        self.groups
+            .iter()
+            .map(|g| (g.proof.leaf_index, &g.group))
+            .collect()
+    }
+
+    /// Called by the verifier before verifying the proof of knowledge of signature. Verifies the disclosure and
+    /// adds the message with the root at `msg_idx` to the revealed messages.
+    pub fn add_to_revealed_messages(
+        &self,
+        msg_idx: usize,
+        revealed_msgs: &mut HashMap<usize, FieldElement>,
+    ) -> Result<(), PSError> {
+        if !self.verify() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Disclosed groups are not included in the tree"),
+            });
+        }
+        revealed_msgs.insert(msg_idx, root_to_message(&self.root));
+        Ok(())
+    }
+}
+
+fn root_to_message(root: &MerkleHash) -> FieldElement {
+    FieldElement::from_msg_hash(&[MERKLE_ROOT_HASH_DST, root].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+    use crate::pok_sig::PoKOfSignature;
+    use crate::signature::Signature;
+    use amcl_wrapper::field_elem::FieldElementVector;
+
+    #[test]
+    fn test_hierarchical_attributes() {
+        let count_msgs = 3;
+        let root_idx = 1;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+
+        // 5 groups of different sizes
+        let groups = (0..5)
+            .map(|i| FieldElementVector::random(i + 2).as_slice().to_vec())
+            .collect::<Vec<Vec<FieldElement>>>();
+        let attributes = HierarchicalAttributes::new(groups);
+        let mut msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        msgs[root_idx] = attributes.to_message();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        attributes
+            .add_to_revealed_indices(msgs.as_slice(), root_idx, &mut revealed_msg_indices)
+            .unwrap();
+        let disclosure = attributes
+            .disclose(&vec![1, 3].into_iter().collect())
+            .unwrap();
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        disclosure
+            .add_to_revealed_messages(root_idx, &mut revealed_msgs)
+            .unwrap();
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params);
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+        let disclosed = disclosure.disclosed_groups();
+        assert_eq!(disclosed.len(), 2);
+        assert_eq!(disclosed[&3].attributes, attributes.groups[3].attributes);
+
+        // Tampered group is rejected
+        let mut bad_disclosure = disclosure.clone();
+        bad_disclosure.groups[0].group.attributes[0] = FieldElement::random();
+        assert!(bad_disclosure
+            .add_to_revealed_messages(root_idx, &mut HashMap::new())
+            .is_err());
+
+        // Disclosure for different groups does not verify with the proof of knowledge
+        let other = HierarchicalAttributes::new(vec![vec![FieldElement::random()]; 5]);
+        let other_disclosure = other.disclose(&vec![1].into_iter().collect()).unwrap();
+        let mut revealed_msgs = HashMap::new();
+        other_disclosure
+            .add_to_revealed_messages(root_idx, &mut revealed_msgs)
+            .unwrap();
+        assert!(!proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+    }
+}
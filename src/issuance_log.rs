@@ -0,0 +1,244 @@
+// Append-only log kept by the issuer of the commitments it blind-signed, along with the period (like a day number)
+// of issuance. Only commitments are logged, never the messages, so the log does not reveal anything about the
+// recipients. The log is a Merkle tree whose root the issuer publishes periodically; auditors check that successive
+// roots are consistent (the log was only appended to), verify the number of credentials issued in a period and,
+// in case of a dispute, a holder can be shown (or show) that its commitment was logged.
+// Entries are appended in non-decreasing order of period so entries of a period are contiguous. A count proof shows
+// the first and last entry of the period along with the neighbouring entries of other periods. An auditor relies on
+// the issuer having kept the order; one holding the full log can check it.
+
+use crate::errors::PSError;
+use crate::merkle::{MerkleConsistencyProof, MerkleHash, MerkleInclusionProof, MerkleTree};
+use crate::SignatureGroup;
+use amcl_wrapper::group_elem::GroupElement;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuanceLogEntry {
+    pub period: u64,
+    pub commitment: SignatureGroup,
+}
+
+/// Entry along with the proof of its inclusion in the log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IncludedEntry {
+    pub entry: IssuanceLogEntry,
+    pub proof: MerkleInclusionProof,
+}
+
+/// Proof that the log of size `tree_size` has `end - start` entries for `period`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeriodCountProof {
+    pub period: u64,
+    pub tree_size: usize,
+    /// Index of the first entry of the period, or where it would be if there are no entries
+    pub start: usize,
+    /// 1 more than the index of the last entry of the period
+    pub end: usize,
+    pub first: Option<IncludedEntry>,
+    pub last: Option<IncludedEntry>,
+    /// Entry just before `start`, absent if `start` is 0
+    pub before: Option<IncludedEntry>,
+    /// Entry at `end`, absent if `end` is the size of the log
+    pub after: Option<IncludedEntry>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IssuanceLog {
+    entries: Vec<IssuanceLogEntry>,
+    tree: MerkleTree,
+}
+
+impl IssuanceLogEntry {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.period.to_be_bytes().to_vec();
+        bytes.append(&mut self.commitment.to_bytes());
+        bytes
+    }
+}
+
+impl IncludedEntry {
+    pub fn verify(&self, root: &MerkleHash, tree_size: usize) -> bool {
+        self.proof.tree_size == tree_size && self.proof.verify(&self.entry.to_bytes(), root)
+    }
+}
+
+impl IssuanceLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log the commitment signed in `period` and return the index of the entry. Period should not be less
+    /// than the period of the last entry.
+    pub fn append(&mut self, commitment: SignatureGroup, period: u64) -> Result<usize, PSError> {
+        if let Some(last) = self.entries.last() {
+            if last.period > period {
+                return Err(PSError::GeneralError {
+                    msg: format!("Period {} is less than the last logged period {}", period, last.period),
+                });
+            }
+        }
+        let entry = IssuanceLogEntry { period, commitment };
+        self.tree.push(&entry.to_bytes());
+        self.entries.push(entry);
+        Ok(self.entries.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Root to be published by the issuer
+    pub fn root(&self) -> MerkleHash {
+        self.tree.root()
+    }
+
+    /// Index of the entry for `commitment`, if logged
+    pub fn find(&self, commitment: &SignatureGroup) -> Option<usize> {
+        self.entries.iter().position(|e| e.commitment == *commitment)
+    }
+
+    pub fn inclusion_proof(&self, index: usize) -> Result<IncludedEntry, PSError> {
+        self.included_entry(index, self.len())
+    }
+
+    /// Prove to an auditor holding an older root that the log was only appended to since
+    pub fn consistency_proof(&self, old_size: usize) -> Result<MerkleConsistencyProof, PSError> {
+        self.tree.consistency_proof(old_size)
+    }
+
+    /// Prove the number of entries for `period`
+    pub fn period_count_proof(&self, period: u64) -> Result<PeriodCountProof, PSError> {
+        let tree_size = self.len();
+        let start = self.entries.iter().take_while(|e| e.period < period).count();
+        let end = start
+            + self.entries[start..]
+                .iter()
+                .take_while(|e| e.period == period)
+                .count();
+        let (first, last) = if end > start {
+            (
+                Some(self.included_entry(start, tree_size)?),
+                Some(self.included_entry(end - 1, tree_size)?),
+            )
+        } else {
+            (None, None)
+        };
+        let before = if start > 0 {
+            Some(self.included_entry(start - 1, tree_size)?)
+        } else {
+            None
+        };
+        let after = if end < tree_size {
+            Some(self.included_entry(end, tree_size)?)
+        } else {
+            None
+        };
+        Ok(PeriodCountProof {
+            period,
+            tree_size,
+            start,
+            end,
+            first,
+            last,
+            before,
+            after,
+        })
+    }
+
+    fn included_entry(&self, index: usize, tree_size: usize) -> Result<IncludedEntry, PSError> {
+        Ok(IncludedEntry {
+            entry: self.entries[index].clone(),
+            proof: self.tree.inclusion_proof_at(index, tree_size)?,
+        })
+    }
+}
+
+impl PeriodCountProof {
+    /// Verify the proof against the published root of the log of size `tree_size` and return the number of
+    /// entries for the period.
+    pub fn verify(&self, root: &MerkleHash, tree_size: usize) -> Result<usize, PSError> {
+        let err = |msg: &str| PSError::GeneralError {
+            msg: format!("Invalid period count proof: {}", msg),
+        };
+        if self.tree_size != tree_size || self.start > self.end || self.end > tree_size {
+            return Err(err("inconsistent indices"));
+        }
+        let check = |e: &Option<IncludedEntry>, index: usize, period_ok: &dyn Fn(u64) -> bool| match e {
+            Some(e) => e.verify(root, tree_size) && e.proof.leaf_index == index && period_ok(e.entry.period),
+            None => false,
+        };
+        if self.end > self.start {
+            if !check(&self.first, self.start, &|p| p == self.period)
+                || !check(&self.last, self.end - 1, &|p| p == self.period)
+            {
+                return Err(err("first or last entry invalid"));
+            }
+        } else if self.first.is_some() || self.last.is_some() {
+            return Err(err("unexpected entries"));
+        }
+        if self.start > 0 {
+            if !check(&self.before, self.start - 1, &|p| p < self.period) {
+                return Err(err("entry before the period invalid"));
+            }
+        } else if self.before.is_some() {
+            return Err(err("unexpected entry before the period"));
+        }
+        if self.end < tree_size {
+            if !check(&self.after, self.end, &|p| p > self.period) {
+                return Err(err("entry after the period invalid"));
+            }
+        } else if self.after.is_some() {
+            return Err(err("unexpected entry after the period"));
+        }
+        Ok(self.end - self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issuance_log() {
+        let mut log = IssuanceLog::new();
+        let mut commitments = vec![];
+        // 3 entries in period 1, none in 2, 4 in 3, 2 in 5
+        for (period, count) in &[(1u64, 3), (3, 4), (5, 2)] {
+            for _ in 0..*count {
+                let comm = SignatureGroup::random();
+                commitments.push(comm.clone());
+                log.append(comm, *period).unwrap();
+            }
+        }
+        assert!(log.append(SignatureGroup::random(), 4).is_err());
+
+        let root = log.root();
+        let size = log.len();
+        for (period, count) in &[(0u64, 0), (1, 3), (2, 0), (3, 4), (4, 0), (5, 2), (6, 0)] {
+            let proof = log.period_count_proof(*period).unwrap();
+            assert_eq!(proof.verify(&root, size).unwrap(), *count);
+        }
+
+        // Tampered count is rejected
+        let mut proof = log.period_count_proof(3).unwrap();
+        proof.end -= 1;
+        assert!(proof.verify(&root, size).is_err());
+
+        // Inclusion of a commitment
+        let idx = log.find(&commitments[4]).unwrap();
+        let included = log.inclusion_proof(idx).unwrap();
+        assert!(included.verify(&root, size));
+        assert_eq!(included.entry.commitment, commitments[4]);
+        assert!(log.find(&SignatureGroup::random()).is_none());
+
+        // Log is append only
+        let old_root = root;
+        log.append(SignatureGroup::random(), 6).unwrap();
+        let proof = log.consistency_proof(size).unwrap();
+        assert!(proof.verify(&old_root, &log.root()));
+    }
+}
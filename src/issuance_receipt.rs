@@ -0,0 +1,199 @@
+// Receipt returned by the holder to the issuer after unblinding a blind signature. The receipt shows that the
+// unblinded signature is valid over the messages in the commitment the issuer signed and the messages known to
+// the issuer, without revealing the committed messages or the signature. It consists of a proof of knowledge of
+// the signature revealing only the known messages and a proof of knowledge of the opening of the commitment. Both
+// proofs use the same challenge and the same blindings for the committed messages so their responses are equal.
+
+use crate::blind_signature::{BlindingKey, ProofSignatureGroup};
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{PoKOfSignatureAndCommitment, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuanceReceipt {
+    /// Proof of knowledge of the unblinded signature
+    pub pok_sig: PoKOfSignatureProof,
+    /// Proof of knowledge of the opening of the commitment used in blind signing
+    pub pok_commitment: ProofSignatureGroup,
+}
+
+impl IssuanceReceipt {
+    /// Called by the holder after unblinding signature `sig` over `messages`. The messages at `known_indices`
+    /// were known to the issuer and the rest were committed with `blinding` as the blinding. `nonce` should
+    /// be chosen by the issuer.
+    pub fn new(
+        sig: &Signature,
+        messages: Vec<FieldElement>,
+        known_indices: &HashSet<usize>,
+        blinding: &FieldElement,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        let committed = Self::committed_indices(messages.len(), known_indices, blinding_key)?
+            .into_iter()
+            .map(|i| (i, blinding_key.Y[i].clone()))
+            .collect::<Vec<_>>();
+        let pok = PoKOfSignatureAndCommitment::init(
+            sig,
+            vk,
+            params,
+            messages,
+            known_indices.clone(),
+            &committed,
+            &[(params.g.clone(), blinding.clone())],
+        )?;
+
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        let (pok_sig, pok_commitment) = pok.gen_proof(&challenge)?;
+        Ok(Self {
+            pok_sig,
+            pok_commitment,
+        })
+    }
+
+    /// Called by the issuer with the commitment it signed and the messages it knew.
+    pub fn verify(
+        &self,
+        commitment: &SignatureGroup,
+        known_messages: HashMap<usize, FieldElement>,
+        vk: &Verkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let known_indices = known_messages.keys().cloned().collect::<HashSet<usize>>();
+        let committed = Self::committed_indices(vk.Y_tilde.len(), &known_indices, blinding_key)?;
+
+        let mut bases = Vec::<SignatureGroup>::with_capacity(committed.len() + 1);
+        for i in &committed {
+            bases.push(blinding_key.Y[*i].clone());
+        }
+        bases.push(params.g.clone());
+
+        let mut chal_bytes = self.pok_sig.get_bytes_for_challenge_with_commitment(
+            known_indices,
+            vk,
+            params,
+            &bases,
+            &self.pok_commitment,
+            commitment,
+        );
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        // Committed messages are exactly the hidden messages of the signature
+        self.pok_sig.verify_with_commitment(
+            vk,
+            params,
+            known_messages,
+            &self.pok_commitment,
+            &bases,
+            commitment,
+            &committed,
+            &challenge,
+        )
+    }
+
+    /// Indices of committed messages in increasing order
+    fn committed_indices(
+        msg_count: usize,
+        known_indices: &HashSet<usize>,
+        blinding_key: &BlindingKey,
+    ) -> Result<Vec<usize>, PSError> {
+        if msg_count != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: msg_count,
+            });
+        }
+        for idx in known_indices {
+            if *idx >= msg_count {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", idx, msg_count),
+                });
+            }
+        }
+        Ok((0..msg_count).filter(|i| !known_indices.contains(i)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blind_signature::BlindSignature;
+    use crate::keys::keygen;
+    use amcl_wrapper::group_elem::GroupElement;
+
+    #[test]
+    fn test_issuance_receipt() {
+        let count_msgs = 6;
+        let count_blinded_msgs = 2;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+
+        let mut comm = SignatureGroup::new();
+        for i in 0..count_blinded_msgs {
+            comm += (&blinding_key.Y[i] * &msgs[i]);
+        }
+        comm += (&params.g * &blinding);
+
+        let sig_blinded = BlindSignature::new(
+            &comm,
+            &msgs.as_slice()[count_blinded_msgs..count_msgs],
+            &sk,
+            &blinding_key,
+            &params,
+        )
+        .unwrap();
+        let sig = BlindSignature::unblind(&sig_blinded, &blinding);
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        let known_indices = (count_blinded_msgs..count_msgs).collect::<HashSet<usize>>();
+        let known_msgs = known_indices
+            .iter()
+            .map(|i| (*i, msgs[*i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        let nonce = FieldElement::random().to_bytes();
+
+        let receipt = IssuanceReceipt::new(
+            &sig,
+            msgs.clone(),
+            &known_indices,
+            &blinding,
+            &vk,
+            &blinding_key,
+            &params,
+            &nonce,
+        )
+        .unwrap();
+        assert!(receipt
+            .verify(&comm, known_msgs.clone(), &vk, &blinding_key, &params, &nonce)
+            .unwrap());
+
+        // Receipt does not verify for a different commitment
+        let other_comm = (&blinding_key.Y[0] * &FieldElement::random()) + (&params.g * &blinding);
+        assert!(!receipt
+            .verify(&other_comm, known_msgs.clone(), &vk, &blinding_key, &params, &nonce)
+            .unwrap());
+
+        // Or different known messages
+        let mut wrong_known_msgs = known_msgs;
+        wrong_known_msgs.insert(count_msgs - 1, FieldElement::random());
+        assert!(!receipt
+            .verify(&comm, wrong_known_msgs, &vk, &blinding_key, &params, &nonce)
+            .unwrap());
+    }
+}
@@ -0,0 +1,362 @@
+// Issuer-hiding proof of knowledge of a signature (2016 scheme): the holder proves that it has a signature under one
+// of the verkeys of a set approved by the verifier without revealing which one. The signature is randomized to
+// (sigma_1', sigma_2') as in `PoKOfSignature` and the holder sends K = X_tilde * Y_tilde_1^m_1 * ... * g_tilde^t for
+// its issuer's verkey, so that e(sigma_1', K) == e(sigma_2', g_tilde). For each verkey i of the set, the statement is
+// J_i = K / (X_tilde_i * Y_tilde_i_j^m_j of the revealed messages) = Y_tilde_i_k^m_k of the hidden messages * g_tilde^t
+// and the holder proves knowledge of the opening of one of the J_i with an OR composition of Schnorr proofs
+// (Cramer-Damgard-Schoenmakers): the branches of the other verkeys are simulated with chosen challenges and responses
+// and the challenges of all branches sum to the Fiat-Shamir challenge. Verification costs 1 pairing check and one
+// multi-exponentiation per verkey and the proof has 1 group element, 1 challenge and 1 + no of hidden messages
+// responses per verkey, e.g. for 32 verkeys with 10 messages about 12KB.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::PoKOfSignature;
+use crate::signature::Signature;
+use crate::{ate_2_pairing, VerkeyGroup, VerkeyGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for the challenge of `IssuerHidingProof`
+pub const ISSUER_HIDING_DST: &[u8] = b"PS-SIG-ISSUER-HIDING";
+
+/// bases[0]^responses[0] * bases[1]^responses[1] * ... * statement^challenge, the commitment of a Schnorr proof of
+/// knowledge of the opening of `statement` with `responses` for `challenge`. Used by the verifier to check a branch
+/// of an OR proof and by the prover to simulate one.
+pub(crate) fn recompute_commitment(
+    bases: &[VerkeyGroup],
+    responses: &[FieldElement],
+    statement: &VerkeyGroup,
+    challenge: &FieldElement,
+) -> Result<VerkeyGroup, PSError> {
+    if bases.len() != responses.len() {
+        return Err(PSError::UnequalNoOfBasesExponents {
+            bases: bases.len(),
+            exponents: responses.len(),
+        });
+    }
+    let mut points = VerkeyGroupVec::from(bases);
+    let mut scalars = FieldElementVector::with_capacity(responses.len() + 1);
+    for r in responses {
+        scalars.push(r.clone());
+    }
+    points.push(statement.clone());
+    scalars.push(challenge.clone());
+    Ok(points.multi_scalar_mul_var_time(&scalars).unwrap())
+}
+
+/// Bases of the hidden messages, g_tilde followed by Y_tilde of the hidden messages, and the statement J of a branch
+/// of the 2016 scheme for `vk` with `K = J * X_tilde * Y_tilde_j^m_j of the revealed messages`
+pub(crate) fn branch_statement(
+    vk: &Verkey,
+    K: &VerkeyGroup,
+    revealed_msgs: &HashMap<usize, FieldElement>,
+    params: &Params,
+) -> (Vec<VerkeyGroup>, VerkeyGroup) {
+    let mut bases = vec![params.g_tilde.clone()];
+    let mut J = K - &vk.X_tilde;
+    for i in 0..vk.Y_tilde.len() {
+        match revealed_msgs.get(&i) {
+            Some(m) => J = &J - &(&vk.Y_tilde[i] * m),
+            None => bases.push(vk.Y_tilde[i].clone()),
+        }
+    }
+    (bases, J)
+}
+
+/// A branch of an OR proof: the commitment, challenge and responses
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrBranch {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub commitment: VerkeyGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    pub responses: Vec<FieldElement>,
+}
+
+impl OrBranch {
+    /// Simulated branch for `statement` with a random challenge and random responses
+    pub(crate) fn simulate(bases: &[VerkeyGroup], statement: &VerkeyGroup) -> Result<Self, PSError> {
+        let challenge = FieldElement::random();
+        let responses = (0..bases.len()).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let commitment = recompute_commitment(bases, &responses, statement, &challenge)?;
+        Ok(Self {
+            commitment,
+            challenge,
+            responses,
+        })
+    }
+
+    /// Whether the branch is a valid proof for `statement` with its challenge
+    pub(crate) fn verify(&self, bases: &[VerkeyGroup], statement: &VerkeyGroup) -> Result<bool, PSError> {
+        Ok(recompute_commitment(bases, &self.responses, statement, &self.challenge)? == self.commitment)
+    }
+}
+
+/// Prover's side of the branch it knows the opening for
+pub(crate) struct RealBranch {
+    blindings: Vec<FieldElement>,
+    secrets: Vec<FieldElement>,
+    pub(crate) commitment: VerkeyGroup,
+}
+
+impl RealBranch {
+    pub(crate) fn new(bases: &[VerkeyGroup], secrets: Vec<FieldElement>) -> Self {
+        let blindings = (0..bases.len()).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let mut commitment = VerkeyGroup::identity();
+        for (b, r) in bases.iter().zip(blindings.iter()) {
+            commitment += b * r;
+        }
+        Self {
+            blindings,
+            secrets,
+            commitment,
+        }
+    }
+
+    /// Complete the branch with the challenge left after the simulated branches, `challenge` minus the sum of
+    /// their challenges
+    pub(crate) fn finish(self, challenge: &FieldElement, simulated: &[OrBranch]) -> OrBranch {
+        let mut c = challenge.clone();
+        for s in simulated {
+            c = &c - &s.challenge;
+        }
+        let responses = self
+            .blindings
+            .iter()
+            .zip(self.secrets.iter())
+            .map(|(r, m)| r - &(&c * m))
+            .collect();
+        OrBranch {
+            commitment: self.commitment,
+            challenge: c,
+            responses,
+        }
+    }
+}
+
+/// Whether the challenges of the branches sum to `challenge`
+pub(crate) fn challenges_sum_to(branches: &[OrBranch], challenge: &FieldElement) -> bool {
+    let mut sum = FieldElement::zero();
+    for b in branches {
+        sum += &b.challenge;
+    }
+    sum == *challenge
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IssuerHidingProof {
+    pub sig: Signature,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub K: VerkeyGroup,
+    /// One branch per verkey of the set, in the order of the set
+    pub branches: Vec<OrBranch>,
+}
+
+impl IssuerHidingProof {
+    /// Prove knowledge of `sig` on `messages` under `verkeys[signer_index]` hiding `signer_index`, revealing the
+    /// messages at `revealed_msg_indices`. All verkeys must be for the same no of messages. `nonce` is the verifier's
+    /// nonce for the challenge.
+    pub fn new(
+        sig: &Signature,
+        messages: &[FieldElement],
+        revealed_msg_indices: &HashSet<usize>,
+        verkeys: &[Verkey],
+        signer_index: usize,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        Self::check_verkeys(verkeys, messages.len())?;
+        if signer_index >= verkeys.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Signer index {} should be less than {}", signer_index, verkeys.len()),
+            });
+        }
+        PoKOfSignature::validate_revealed_indices(messages, revealed_msg_indices)?;
+        let revealed_msgs = revealed_msg_indices
+            .iter()
+            .map(|i| (*i, messages[*i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+
+        let (t, sig_prime) = PoKOfSignature::transform_sig_with_rng(sig, &mut rand::thread_rng());
+        let vk = &verkeys[signer_index];
+        let mut K = &vk.X_tilde + &(&params.g_tilde * &t);
+        for (y, m) in vk.Y_tilde.iter().zip(messages.iter()) {
+            K += y * m;
+        }
+        let mut secrets = vec![t];
+        for (i, m) in messages.iter().enumerate() {
+            if !revealed_msg_indices.contains(&i) {
+                secrets.push(m.clone());
+            }
+        }
+
+        let mut real = None;
+        let mut simulated = vec![];
+        let mut commitments = vec![];
+        for (i, vk) in verkeys.iter().enumerate() {
+            let (bases, J) = branch_statement(vk, &K, &revealed_msgs, params);
+            if i == signer_index {
+                let r = RealBranch::new(&bases, secrets.clone());
+                commitments.push(r.commitment.clone());
+                real = Some(r);
+            } else {
+                let s = OrBranch::simulate(&bases, &J)?;
+                commitments.push(s.commitment.clone());
+                simulated.push(s);
+            }
+        }
+        let challenge = Self::compute_challenge(&sig_prime, &K, &commitments, &revealed_msgs, verkeys, params, nonce);
+        let real = real.unwrap().finish(&challenge, &simulated);
+        let mut simulated = simulated.into_iter();
+        let branches = (0..verkeys.len())
+            .map(|i| {
+                if i == signer_index {
+                    real.clone()
+                } else {
+                    simulated.next().unwrap()
+                }
+            })
+            .collect();
+        Ok(Self {
+            sig: sig_prime,
+            K,
+            branches,
+        })
+    }
+
+    /// Verify the proof for the set `verkeys` and the revealed messages with the verifier's `nonce`
+    pub fn verify(
+        &self,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        verkeys: &[Verkey],
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        if verkeys.is_empty() || self.branches.len() != verkeys.len() || self.sig.is_identity() {
+            return Ok(false);
+        }
+        Self::check_verkeys(verkeys, verkeys[0].Y_tilde.len())?;
+        if let Some(i) = revealed_msgs.keys().find(|i| **i >= verkeys[0].Y_tilde.len()) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, verkeys[0].Y_tilde.len()),
+            });
+        }
+        // e(sigma_1', K) == e(sigma_2', g_tilde)
+        if !ate_2_pairing(&self.sig.sigma_1, &self.K, &(-&self.sig.sigma_2), &params.g_tilde).is_one() {
+            return Ok(false);
+        }
+        let commitments = self.branches.iter().map(|b| b.commitment.clone()).collect::<Vec<_>>();
+        let challenge = Self::compute_challenge(&self.sig, &self.K, &commitments, revealed_msgs, verkeys, params, nonce);
+        if !challenges_sum_to(&self.branches, &challenge) {
+            return Ok(false);
+        }
+        for (branch, vk) in self.branches.iter().zip(verkeys.iter()) {
+            let (bases, J) = branch_statement(vk, &self.K, revealed_msgs, params);
+            if !branch.verify(&bases, &J)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn check_verkeys(verkeys: &[Verkey], msg_count: usize) -> Result<(), PSError> {
+        if verkeys.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Set of verkeys should not be empty"),
+            });
+        }
+        for vk in verkeys {
+            if vk.Y_tilde.len() != msg_count {
+                return Err(PSError::UnsupportedNoOfMessages {
+                    expected: msg_count,
+                    given: vk.Y_tilde.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn compute_challenge(
+        sig: &Signature,
+        K: &VerkeyGroup,
+        commitments: &[VerkeyGroup],
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        verkeys: &[Verkey],
+        params: &Params,
+        nonce: &[u8],
+    ) -> FieldElement {
+        let mut bytes = ISSUER_HIDING_DST.to_vec();
+        bytes.append(&mut params.g_tilde.to_bytes());
+        for vk in verkeys {
+            bytes.append(&mut vk.X_tilde.to_bytes());
+            for y in &vk.Y_tilde {
+                bytes.append(&mut y.to_bytes());
+            }
+        }
+        let mut indices = revealed_msgs.keys().cloned().collect::<Vec<usize>>();
+        indices.sort();
+        for i in indices {
+            bytes.extend_from_slice(&(i as u64).to_be_bytes());
+            bytes.append(&mut revealed_msgs[&i].to_bytes());
+        }
+        bytes.append(&mut sig.to_bytes());
+        bytes.append(&mut K.to_bytes());
+        for c in commitments {
+            bytes.append(&mut c.to_bytes());
+        }
+        bytes.extend_from_slice(&(nonce.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_issuer_hiding_proof() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let keys = (0..5).map(|_| keygen(count_msgs, &params)).collect::<Vec<_>>();
+        let verkeys = keys.iter().map(|(_, vk)| vk.clone()).collect::<Vec<_>>();
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let mut revealed = HashSet::new();
+        revealed.insert(1);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        let nonce = b"verifier nonce";
+
+        for signer in 0..verkeys.len() {
+            let sig = Signature::new(msgs.as_slice(), &keys[signer].0, &params).unwrap();
+            let proof = IssuerHidingProof::new(&sig, &msgs, &revealed, &verkeys, signer, &params, nonce).unwrap();
+            assert_eq!(proof.branches.len(), verkeys.len());
+            assert!(proof.verify(&revealed_msgs, &verkeys, &params, nonce).unwrap());
+
+            // Other nonce, revealed message or set of verkeys
+            assert!(!proof.verify(&revealed_msgs, &verkeys, &params, b"other nonce").unwrap());
+            let mut wrong_msgs = revealed_msgs.clone();
+            wrong_msgs.insert(1, FieldElement::random());
+            assert!(!proof.verify(&wrong_msgs, &verkeys, &params, nonce).unwrap());
+            let mut other_verkeys = verkeys.clone();
+            other_verkeys.swap(0, 1);
+            assert!(!proof.verify(&revealed_msgs, &other_verkeys, &params, nonce).unwrap());
+        }
+
+        // A signature under a verkey not in the set can't be proven
+        let (other_sk, _) = keygen(count_msgs, &params);
+        let sig = Signature::new(msgs.as_slice(), &other_sk, &params).unwrap();
+        let proof = IssuerHidingProof::new(&sig, &msgs, &revealed, &verkeys, 2, &params, nonce).unwrap();
+        assert!(!proof.verify(&revealed_msgs, &verkeys, &params, nonce).unwrap());
+
+        // Verkeys for a different no of messages
+        let (_, short_vk) = keygen(count_msgs - 1, &params);
+        let mut mixed = verkeys.clone();
+        mixed.push(short_vk);
+        let sig = Signature::new(msgs.as_slice(), &keys[0].0, &params).unwrap();
+        assert!(IssuerHidingProof::new(&sig, &msgs, &revealed, &mixed, 0, &params, nonce).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+// Attestation that a verkey corresponds to an escrowed signing key, for key ceremonies and backup verification.
+// When the signing key is escrowed, a `SigkeyCommitment` with Pedersen commitments g^s * h^r to x and each y_i is
+// recorded with the escrow, the openings r are kept with the signing key. Later the holder of the signing key and the
+// openings creates a `KeyConsistencyProof` that the exponents committed to are the exponents of the published verkey,
+// without revealing them. The auditor chooses `context` (like a nonce and the audit date) so that an old proof can't
+// be replayed. This is more than the proof of possession (`PoPProof`) which only shows that someone knows the signing
+// key of the verkey, not that it is the escrowed key.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::{SignatureGroup, VerkeyGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Domain separation tag for the second generator of the commitments
+pub const KEY_ESCROW_H_DST: &[u8] = b"PS-SIG-KEY-ESCROW-H";
+
+/// Domain separation tag for the challenge of the consistency proof
+pub const KEY_CONSISTENCY_CHALLENGE_DST: &[u8] = b"PS-SIG-KEY-CONSISTENCY";
+
+/// Commitments to x followed by each y_i of a signing key, recorded with the escrowed key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SigkeyCommitment {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub commitments: Vec<SignatureGroup>,
+}
+
+/// Randomness of each commitment of `SigkeyCommitment`, kept secret with the signing key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SigkeyCommitmentOpening {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub randomness: FieldElementVector,
+}
+
+/// Proof that the exponents committed in a `SigkeyCommitment` are the exponents of a verkey. The responses are
+/// for x followed by each y_i.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyConsistencyProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub secret_responses: FieldElementVector,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub randomness_responses: FieldElementVector,
+}
+
+/// Second generator of the commitments, nobody knows its discrete log to the base g
+fn commitment_h(params: &Params) -> SignatureGroup {
+    let mut bytes = KEY_ESCROW_H_DST.to_vec();
+    bytes.append(&mut params.g.to_bytes());
+    SignatureGroup::from_msg_hash(&bytes)
+}
+
+fn key_secrets(sigkey: &Sigkey) -> Vec<&FieldElement> {
+    vec![&sigkey.x].into_iter().chain(sigkey.y.iter()).collect()
+}
+
+fn key_elements(verkey: &Verkey) -> Vec<&VerkeyGroup> {
+    vec![&verkey.X_tilde].into_iter().chain(verkey.Y_tilde.iter()).collect()
+}
+
+impl SigkeyCommitment {
+    /// Called when escrowing `sigkey`. The opening should be stored with the signing key.
+    pub fn new(sigkey: &Sigkey, params: &Params) -> (Self, SigkeyCommitmentOpening) {
+        let h = commitment_h(params);
+        let randomness = FieldElementVector::random(sigkey.y.len() + 1);
+        let commitments = key_secrets(sigkey)
+            .into_iter()
+            .zip(randomness.iter())
+            .map(|(s, r)| (&params.g * s) + (&h * r))
+            .collect();
+        (Self { commitments }, SigkeyCommitmentOpening { randomness })
+    }
+
+    /// Whether this commits to `sigkey` with `opening`, for the party holding the escrowed key
+    pub fn is_opened_by(&self, sigkey: &Sigkey, opening: &SigkeyCommitmentOpening, params: &Params) -> bool {
+        if self.commitments.len() != sigkey.y.len() + 1 || opening.randomness.len() != self.commitments.len() {
+            return false;
+        }
+        let h = commitment_h(params);
+        key_secrets(sigkey)
+            .into_iter()
+            .zip(opening.randomness.iter())
+            .zip(self.commitments.iter())
+            .all(|((s, r), c)| (&params.g * s) + (&h * r) == *c)
+    }
+}
+
+impl KeyConsistencyProof {
+    /// Called by the holder of the escrowed signing key and the opening of its commitment
+    pub fn new(
+        sigkey: &Sigkey,
+        opening: &SigkeyCommitmentOpening,
+        commitment: &SigkeyCommitment,
+        verkey: &Verkey,
+        params: &Params,
+        context: &[u8],
+    ) -> Result<Self, PSError> {
+        if !sigkey.is_key_for(verkey, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signing key does not correspond to the verkey"),
+            });
+        }
+        if !commitment.is_opened_by(sigkey, opening, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Commitment is not to the signing key"),
+            });
+        }
+        let h = commitment_h(params);
+        let count = sigkey.y.len() + 1;
+        let secret_blindings = FieldElementVector::random(count);
+        let randomness_blindings = FieldElementVector::random(count);
+        let mut t_commitments = Vec::with_capacity(count);
+        let mut t_verkey = Vec::with_capacity(count);
+        for (b_s, b_r) in secret_blindings.iter().zip(randomness_blindings.iter()) {
+            t_commitments.push((&params.g * b_s) + (&h * b_r));
+            t_verkey.push(&params.g_tilde * b_s);
+        }
+        let challenge = Self::challenge(commitment, verkey, &t_commitments, &t_verkey, params, context);
+
+        let mut secret_responses = FieldElementVector::with_capacity(count);
+        let mut randomness_responses = FieldElementVector::with_capacity(count);
+        for (i, s) in key_secrets(sigkey).into_iter().enumerate() {
+            secret_responses.push(&secret_blindings[i] - (&challenge * s));
+            randomness_responses.push(&randomness_blindings[i] - (&challenge * &opening.randomness[i]));
+        }
+        Ok(Self {
+            challenge,
+            secret_responses,
+            randomness_responses,
+        })
+    }
+
+    /// Called by the auditor with the commitment recorded at escrow and the published verkey
+    pub fn verify(&self, commitment: &SigkeyCommitment, verkey: &Verkey, params: &Params, context: &[u8]) -> bool {
+        let count = verkey.Y_tilde.len() + 1;
+        if commitment.commitments.len() != count
+            || self.secret_responses.len() != count
+            || self.randomness_responses.len() != count
+        {
+            return false;
+        }
+        let h = commitment_h(params);
+        let mut t_commitments = Vec::with_capacity(count);
+        let mut t_verkey = Vec::with_capacity(count);
+        for (i, pk) in key_elements(verkey).into_iter().enumerate() {
+            let z_s = &self.secret_responses[i];
+            let z_r = &self.randomness_responses[i];
+            // g^z_s * h^z_r * commitment^challenge and g_tilde^z_s * verkey element^challenge
+            t_commitments.push((&params.g * z_s) + (&h * z_r) + (&commitment.commitments[i] * &self.challenge));
+            t_verkey.push((&params.g_tilde * z_s) + (pk * &self.challenge));
+        }
+        Self::challenge(commitment, verkey, &t_commitments, &t_verkey, params, context) == self.challenge
+    }
+
+    /// Hash of the generators, the commitments, the verkey, `context` and the proof commitments
+    fn challenge(
+        commitment: &SigkeyCommitment,
+        verkey: &Verkey,
+        t_commitments: &[SignatureGroup],
+        t_verkey: &[VerkeyGroup],
+        params: &Params,
+        context: &[u8],
+    ) -> FieldElement {
+        let mut bytes = KEY_CONSISTENCY_CHALLENGE_DST.to_vec();
+        bytes.append(&mut params.g.to_bytes());
+        bytes.append(&mut params.g_tilde.to_bytes());
+        for c in &commitment.commitments {
+            bytes.append(&mut c.to_bytes());
+        }
+        for e in key_elements(verkey) {
+            bytes.append(&mut e.to_bytes());
+        }
+        bytes.extend_from_slice(&(context.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(context);
+        for t in t_commitments {
+            bytes.append(&mut t.to_bytes());
+        }
+        for t in t_verkey {
+            bytes.append(&mut t.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, keygen_2018};
+
+    #[test]
+    fn test_key_consistency_proof() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let (commitment, opening) = SigkeyCommitment::new(&sk, &params);
+        assert!(commitment.is_opened_by(&sk, &opening, &params));
+
+        let proof = KeyConsistencyProof::new(&sk, &opening, &commitment, &vk, &params, b"audit 1").unwrap();
+        assert!(proof.verify(&commitment, &vk, &params, b"audit 1"));
+        // Proof is bound to the context
+        assert!(!proof.verify(&commitment, &vk, &params, b"audit 2"));
+
+        // Different verkey or commitment to a different key fails
+        let (sk_1, vk_1) = keygen(4, &params);
+        assert!(!proof.verify(&commitment, &vk_1, &params, b"audit 1"));
+        let (commitment_1, opening_1) = SigkeyCommitment::new(&sk_1, &params);
+        assert!(!proof.verify(&commitment_1, &vk, &params, b"audit 1"));
+        assert!(!commitment_1.is_opened_by(&sk, &opening, &params));
+
+        // Can't prove with a key not matching the verkey or the commitment
+        assert!(KeyConsistencyProof::new(&sk_1, &opening_1, &commitment_1, &vk, &params, b"audit 1").is_err());
+        assert!(KeyConsistencyProof::new(&sk, &opening_1, &commitment_1, &vk, &params, b"audit 1").is_err());
+
+        let (sk, vk) = keygen_2018(3, &params);
+        let (commitment, opening) = SigkeyCommitment::new(&sk, &params);
+        let proof = KeyConsistencyProof::new(&sk, &opening, &commitment, &vk, &params, b"audit").unwrap();
+        assert!(proof.verify(&commitment, &vk, &params, b"audit"));
+    }
+}
@@ -0,0 +1,240 @@
+// Rotation of the issuer's keys to independently generated new keys. Unlike re-randomization (`key_update`), the
+// new signing key is unrelated to the old one so rotation can be used after a compromise, but credentials under the
+// old key have to be re-issued. To let verifiers and wallets trust the new verkey, the issuer publishes a
+// `KeyRotation` with a proof linking the keys: a Schnorr proof of knowledge of the signing keys of both the old and
+// the new verkey whose challenge is over both verkeys and the overlap window. So it is a Schnorr signature by the old
+// key over the new verkey which also proves possession of the new key. A PS signature by the old key is not used
+// since it would be a valid credential over the hashed message.
+// During the overlap window both keys are accepted, `verify_during_overlap` tries the keys valid at a given time.
+// Times are seconds since the Unix epoch, chosen by the caller.
+
+use crate::errors::PSError;
+use crate::keys::{Keypair, Params, Scheme, Sigkey, Verkey};
+use crate::VerkeyGroup;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Domain separation tag for the challenge of the proof linking the old and new keys
+pub const KEY_ROTATION_CHALLENGE_DST: &[u8] = b"PS-SIG-KEY-ROTATION";
+
+/// Proof of knowledge of the signing keys of the old and new verkey. The responses are for x followed by each y_i.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyLinkProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub old_key_responses: FieldElementVector,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub new_key_responses: FieldElementVector,
+}
+
+/// Published by the issuer when rotating keys. The new key is valid from `new_valid_from` and the old key until
+/// `old_valid_until`, credentials are accepted under both keys in between.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub scheme: Scheme,
+    pub old_verkey: Verkey,
+    pub new_verkey: Verkey,
+    pub new_valid_from: u64,
+    pub old_valid_until: u64,
+    pub proof: KeyLinkProof,
+}
+
+fn key_secrets(sigkey: &Sigkey) -> Vec<&FieldElement> {
+    vec![&sigkey.x].into_iter().chain(sigkey.y.iter()).collect()
+}
+
+fn key_elements(verkey: &Verkey) -> Vec<&VerkeyGroup> {
+    vec![&verkey.X_tilde].into_iter().chain(verkey.Y_tilde.iter()).collect()
+}
+
+impl KeyRotation {
+    /// Called by the issuer. Generates new keys of the same scheme and message count as `old` and the rotation
+    /// to publish.
+    pub fn rotate(
+        old: &Keypair,
+        new_valid_from: u64,
+        old_valid_until: u64,
+        params: &Params,
+    ) -> Result<(Keypair, Self), PSError> {
+        let new = Keypair::new(old.scheme, old.messages_supported(), params);
+        let rotation = Self::new(old, &new, new_valid_from, old_valid_until, params)?;
+        Ok((new, rotation))
+    }
+
+    /// Called by the issuer when the new keys have already been generated
+    pub fn new(
+        old: &Keypair,
+        new: &Keypair,
+        new_valid_from: u64,
+        old_valid_until: u64,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        if old.scheme != new.scheme {
+            return Err(PSError::GeneralError {
+                msg: String::from("Old and new keys should be of the same scheme"),
+            });
+        }
+        if new_valid_from > old_valid_until {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "New key valid from {} is after old key valid until {}, there is no overlap",
+                    new_valid_from, old_valid_until
+                ),
+            });
+        }
+        if !old.sigkey.is_key_for(&old.verkey, params) || !new.sigkey.is_key_for(&new.verkey, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signing key does not correspond to the verkey"),
+            });
+        }
+
+        let old_blindings = FieldElementVector::random(old.sigkey.y.len() + 1);
+        let new_blindings = FieldElementVector::random(new.sigkey.y.len() + 1);
+        let mut rotation = Self {
+            scheme: old.scheme,
+            old_verkey: old.verkey.clone(),
+            new_verkey: new.verkey.clone(),
+            new_valid_from,
+            old_valid_until,
+            proof: KeyLinkProof {
+                challenge: FieldElement::zero(),
+                old_key_responses: FieldElementVector::new(0),
+                new_key_responses: FieldElementVector::new(0),
+            },
+        };
+        let commitments = old_blindings
+            .iter()
+            .chain(new_blindings.iter())
+            .map(|b| &params.g_tilde * b)
+            .collect::<Vec<VerkeyGroup>>();
+        let challenge = rotation.challenge(&commitments, params);
+
+        let responses = |blindings: &FieldElementVector, sigkey: &Sigkey| {
+            let mut responses = FieldElementVector::with_capacity(blindings.len());
+            for (b, s) in blindings.iter().zip(key_secrets(sigkey)) {
+                responses.push(b - (&challenge * s));
+            }
+            responses
+        };
+        rotation.proof.old_key_responses = responses(&old_blindings, &old.sigkey);
+        rotation.proof.new_key_responses = responses(&new_blindings, &new.sigkey);
+        rotation.proof.challenge = challenge;
+        Ok(rotation)
+    }
+
+    /// Called by verifiers and wallets to check that the new verkey was published by the holder of the old key
+    pub fn verify(&self, params: &Params) -> bool {
+        if self.new_valid_from > self.old_valid_until
+            || self.proof.old_key_responses.len() != self.old_verkey.Y_tilde.len() + 1
+            || self.proof.new_key_responses.len() != self.new_verkey.Y_tilde.len() + 1
+        {
+            return false;
+        }
+        // Commitment is g_tilde^response * public_key^challenge
+        let commitments = key_elements(&self.old_verkey)
+            .into_iter()
+            .chain(key_elements(&self.new_verkey))
+            .zip(
+                self.proof
+                    .old_key_responses
+                    .iter()
+                    .chain(self.proof.new_key_responses.iter()),
+            )
+            .map(|(pk, r)| (&params.g_tilde * r) + (pk * &self.proof.challenge))
+            .collect::<Vec<VerkeyGroup>>();
+        self.challenge(&commitments, params) == self.proof.challenge
+    }
+
+    /// Verkeys accepted at time `now`, the old key until `old_valid_until` and the new key from
+    /// `new_valid_from`
+    pub fn accepted_verkeys(&self, now: u64) -> Vec<&Verkey> {
+        let mut verkeys = vec![];
+        if now >= self.new_valid_from {
+            verkeys.push(&self.new_verkey);
+        }
+        if now <= self.old_valid_until {
+            verkeys.push(&self.old_verkey);
+        }
+        verkeys
+    }
+
+    /// Whether `verify` succeeds with any verkey accepted at time `now`. `verify` checks a signature of either
+    /// scheme or a proof of knowledge of signature against the given verkey. Errors from `verify` are
+    /// returned only if it does not succeed with any key.
+    pub fn verify_during_overlap<F>(&self, now: u64, verify: F) -> Result<bool, PSError>
+    where
+        F: Fn(&Verkey) -> Result<bool, PSError>,
+    {
+        let mut error = None;
+        for vk in self.accepted_verkeys(now) {
+            match verify(vk) {
+                Ok(true) => return Ok(true),
+                Ok(false) => (),
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(false),
+        }
+    }
+
+    /// Hash of g_tilde, the scheme, both verkeys, the window and the commitments
+    fn challenge(&self, commitments: &[VerkeyGroup], params: &Params) -> FieldElement {
+        let mut bytes = KEY_ROTATION_CHALLENGE_DST.to_vec();
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut self.old_verkey.to_bytes(self.scheme));
+        bytes.append(&mut self.new_verkey.to_bytes(self.scheme));
+        bytes.extend_from_slice(&self.new_valid_from.to_be_bytes());
+        bytes.extend_from_slice(&self.old_valid_until.to_be_bytes());
+        for c in commitments {
+            bytes.append(&mut c.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::Signature;
+
+    #[test]
+    fn test_key_rotation() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let old = Keypair::new_2016(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let old_sig = Signature::new(&msgs, &old.sigkey, &params).unwrap();
+
+        let (new, rotation) = KeyRotation::rotate(&old, 100, 200, &params).unwrap();
+        assert_eq!(new.messages_supported(), count_msgs);
+        assert!(rotation.verify(&params));
+        let new_sig = Signature::new(&msgs, &new.sigkey, &params).unwrap();
+
+        // Tampering with the keys or the window fails verification
+        let mut tampered = rotation.clone();
+        tampered.old_valid_until = 300;
+        assert!(!tampered.verify(&params));
+        let mut tampered = rotation.clone();
+        tampered.new_verkey = Keypair::new_2016(count_msgs, &params).verkey;
+        assert!(!tampered.verify(&params));
+
+        let verify_old = |vk: &Verkey| old_sig.verify(msgs.clone(), vk, &params);
+        let verify_new = |vk: &Verkey| new_sig.verify(msgs.clone(), vk, &params);
+        assert_eq!(rotation.accepted_verkeys(50).len(), 1);
+        assert!(rotation.verify_during_overlap(50, verify_old).unwrap());
+        assert!(!rotation.verify_during_overlap(50, verify_new).unwrap());
+        assert_eq!(rotation.accepted_verkeys(150).len(), 2);
+        assert!(rotation.verify_during_overlap(150, verify_old).unwrap());
+        assert!(rotation.verify_during_overlap(150, verify_new).unwrap());
+        assert!(!rotation.verify_during_overlap(250, verify_old).unwrap());
+        assert!(rotation.verify_during_overlap(250, verify_new).unwrap());
+
+        // No overlap and mismatched schemes are rejected
+        assert!(KeyRotation::rotate(&old, 200, 100, &params).is_err());
+        let new_2018 = Keypair::new_2018(count_msgs, &params);
+        assert!(KeyRotation::new(&old, &new_2018, 100, 200, &params).is_err());
+    }
+}
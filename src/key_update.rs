@@ -0,0 +1,126 @@
+// Re-randomization of the issuer's keys with a public key-update token so that holders can update their signatures
+// locally. The new signing key is (r*x, r*y_1, r*y_2, ...) for a random r, so the new verkey is
+// (X_tilde^r, Y_tilde_1^r, Y_tilde_2^r, ...) and a signature (sigma_1, sigma_2) under the old key becomes
+// (sigma_1, sigma_2^r) under the new key. The token is r. Works for signatures of both the 2016 and 2018 scheme.
+// Caveat: the token lets anyone convert any signature valid under the old key, so re-randomization is only for
+// routine key hygiene and must not be used to recover from compromise of the old signing key; in that case the new
+// key must be generated independently and the credentials re-issued. Also, the old and new verkeys are publicly
+// linkable through the token.
+
+use crate::blind_signature::BlindingKey;
+use crate::errors::PSError;
+use crate::keys::{Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::signature_2018::Signature as Signature18;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Published by the issuer after re-randomizing its keys
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyUpdateToken {
+    pub r: FieldElement,
+}
+
+impl KeyUpdateToken {
+    /// Called by the issuer. Returns the new signing key, the new verkey and the token to publish.
+    pub fn rerandomize(sigkey: &Sigkey, vk: &Verkey) -> (Sigkey, Verkey, Self) {
+        let token = Self {
+            r: FieldElement::random(),
+        };
+        let sigkey = Sigkey {
+            x: &sigkey.x * &token.r,
+            y: sigkey.y.iter().map(|y| y * &token.r).collect(),
+            metadata: sigkey.metadata.clone(),
+        };
+        (sigkey, token.update_verkey(vk), token)
+    }
+
+    pub fn update_verkey(&self, vk: &Verkey) -> Verkey {
+        Verkey {
+            X_tilde: &vk.X_tilde * &self.r,
+            Y_tilde: vk.Y_tilde.iter().map(|y| y * &self.r).collect(),
+            metadata: vk.metadata.clone(),
+        }
+    }
+
+    pub fn update_blinding_key(&self, blinding_key: &BlindingKey) -> BlindingKey {
+        BlindingKey {
+            X: &blinding_key.X * &self.r,
+            Y: blinding_key.Y.iter().map(|y| y * &self.r).collect(),
+        }
+    }
+
+    /// Called by a holder to check that the token converts the old verkey to the new verkey
+    pub fn verify(&self, old_vk: &Verkey, new_vk: &Verkey) -> Result<bool, PSError> {
+        if old_vk.Y_tilde.len() != new_vk.Y_tilde.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: old_vk.Y_tilde.len(),
+                given: new_vk.Y_tilde.len(),
+            });
+        }
+        if self.r.is_zero() {
+            return Ok(false);
+        }
+        let updated = self.update_verkey(old_vk);
+        Ok(updated.X_tilde == new_vk.X_tilde && updated.Y_tilde == new_vk.Y_tilde)
+    }
+
+    /// Update a signature valid under the old key to be valid under the new key
+    pub fn update_signature(&self, sig: &Signature) -> Signature {
+        Signature {
+            sigma_1: sig.sigma_1.clone(),
+            sigma_2: &sig.sigma_2 * &self.r,
+        }
+    }
+
+    pub fn update_signature_2018(&self, sig: &Signature18) -> Signature18 {
+        Signature18 {
+            m_prime: sig.m_prime.clone(),
+            sig: self.update_signature(&sig.sig),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+
+    #[test]
+    fn test_key_update() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let (new_sk, new_vk, token) = KeyUpdateToken::rerandomize(&sk, &vk);
+        assert!(token.verify(&vk, &new_vk).unwrap());
+        let (_, other_vk) = keygen(count_msgs, &params);
+        assert!(!token.verify(&vk, &other_vk).unwrap());
+        let (_, larger_vk) = keygen(count_msgs + 1, &params);
+        match token.verify(&vk, &larger_vk) {
+            Err(PSError::UnsupportedNoOfMessages { expected, given }) => {
+                assert_eq!(expected, count_msgs);
+                assert_eq!(given, count_msgs + 1);
+            }
+            _ => panic!("expected UnsupportedNoOfMessages"),
+        }
+
+        assert!(!sig.verify(msgs.clone(), &new_vk, &params).unwrap());
+        let new_sig = token.update_signature(&sig);
+        assert!(new_sig.verify(msgs.clone(), &new_vk, &params).unwrap());
+
+        // New key signs as usual
+        let sig = Signature::new(msgs.as_slice(), &new_sk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &new_vk, &params).unwrap());
+
+        // 2018 scheme
+        let (sk, vk) = keygen(count_msgs + 1, &params);
+        let sig = Signature18::new(msgs.as_slice(), &sk).unwrap();
+        let (_, new_vk, token) = KeyUpdateToken::rerandomize(&sk, &vk);
+        assert!(!sig.verify(msgs.clone(), &new_vk, &params).unwrap());
+        let new_sig = token.update_signature_2018(&sig);
+        assert!(new_sig.verify(msgs, &new_vk, &params).unwrap());
+    }
+}
@@ -1,56 +1,1378 @@
-use amcl_wrapper::field_elem::FieldElement;
-use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 
+use crate::der::{self, DerReader};
 use crate::errors::PSError;
-use crate::{VerkeyGroup, SignatureGroup};
+use crate::validation::{deserialize_validated, Validate};
+use crate::wire::{self, GroupMarker};
+use crate::multibase::{self, Base};
+use crate::xof::ElementReader;
+use crate::{VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec, CURVE_ID, VERKEY_GROUP_SIZE};
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sigkey {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub x: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
     pub y: Vec<FieldElement>,
+    /// Scheme, message count and params the key was generated for, see `KeyMetadata`
+    #[serde(default)]
+    pub metadata: Option<KeyMetadata>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Overwrite a secret field element with zero. With feature `zeroize` the write is volatile so it is not
+/// optimized away, without the feature it is a no-op.
+#[inline]
+pub(crate) fn wipe(elem: &mut FieldElement) {
+    #[cfg(feature = "zeroize")]
+    {
+        // `FieldElement` does not own heap memory so overwriting it in place wipes it, the old value needs no drop
+        unsafe { std::ptr::write_volatile(elem, FieldElement::zero()) };
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+    }
+    #[cfg(not(feature = "zeroize"))]
+    let _ = elem;
+}
+
+/// With feature `zeroize`, the key is wiped when dropped. Clones are separate copies which are wiped when
+/// they are dropped.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Sigkey {
+    fn zeroize(&mut self) {
+        wipe(&mut self.x);
+        for y in self.y.iter_mut() {
+            wipe(y);
+        }
+        self.y.clear();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Sigkey {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for Sigkey {}
+
+/// Deserializing validates the key like `Validate::validate` so a key with an identity element or an element not in the
+/// prime order subgroup is rejected with `PSError::InvalidGroupElement`
+#[derive(Clone, Debug, Serialize)]
 pub struct Verkey {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub X_tilde: VerkeyGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
     pub Y_tilde: Vec<VerkeyGroup>,
+    /// Scheme, message count and params the key was generated for, see `KeyMetadata`
+    #[serde(default)]
+    pub metadata: Option<KeyMetadata>,
+}
+
+/// Fields of `Verkey` as deserialized, before validation
+#[derive(Deserialize)]
+pub(crate) struct VerkeyFields {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    X_tilde: VerkeyGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    Y_tilde: Vec<VerkeyGroup>,
+    #[serde(default)]
+    metadata: Option<KeyMetadata>,
+}
+
+impl VerkeyFields {
+    /// The verkey without validating it, for types holding a verkey which can be the identity, like the aggregate of
+    /// no signers, and which validate it themselves
+    pub(crate) fn into_verkey_unchecked(self) -> Verkey {
+        Verkey {
+            X_tilde: self.X_tilde,
+            Y_tilde: self.Y_tilde,
+            metadata: self.metadata,
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Verkey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_validated(deserializer, VerkeyFields::into_verkey_unchecked)
+    }
+}
+
+/// Scheme, message count and params a key was generated for. Keys from `keygen`, `keygen_2018` and `Keypair` have
+/// it and signing and verification with both schemes check it, so a key of one scheme used with the other scheme
+/// or with different params fails with `PSError::IncompatibleKey` instead of a length mismatch or a failed
+/// verification. Keys parsed from bytes or created from their elements have none and are not checked, use
+/// `with_metadata` to add it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    pub scheme: Scheme,
+    /// No of messages, for the 2018 scheme this excludes m'
+    pub message_count: usize,
+    /// `Params::hash` of the params of the verkey
+    pub params_hash: Vec<u8>,
+}
+
+impl KeyMetadata {
+    pub fn new(scheme: Scheme, message_count: usize, params: &Params) -> Self {
+        Self {
+            scheme,
+            message_count,
+            params_hash: params.hash(),
+        }
+    }
+
+    /// Fails if the key is not for `scheme` and `message_count` messages or, when given, `params`
+    pub fn check(&self, scheme: Scheme, message_count: usize, params: Option<&Params>) -> Result<(), PSError> {
+        if self.scheme != scheme {
+            return Err(PSError::IncompatibleKey {
+                msg: format!("Key is for scheme {:?} but used with scheme {:?}", self.scheme, scheme),
+            });
+        }
+        if self.message_count != message_count {
+            return Err(PSError::IncompatibleKey {
+                msg: format!(
+                    "Key is for {} messages but used with {} messages",
+                    self.message_count, message_count
+                ),
+            });
+        }
+        if let Some(params) = params {
+            if self.params_hash != params.hash() {
+                return Err(PSError::IncompatibleKey {
+                    msg: String::from("Key was generated with different params"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn with_message_count(&self, message_count: usize) -> Self {
+        Self {
+            message_count,
+            ..self.clone()
+        }
+    }
 }
 
 // Parameters generated by random oracle.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Params {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub g: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub g_tilde: VerkeyGroup,
+    /// Optional generators for commitments to messages, see `Params::new_with_commitment_generators`
+    #[serde(default)]
+    pub commitment_gens: Option<CommitmentGenerators>,
+}
+
+/// Version of the derivation of params from a label, part of the hashed input
+pub const PARAMS_VERSION: u8 = 1;
+
+/// Domain separation tag for hashing to g
+pub const PARAMS_G_DST: &[u8] = b"PS-SIG-G";
+
+/// Domain separation tag for hashing to g_tilde
+pub const PARAMS_G_TILDE_DST: &[u8] = b"PS-SIG-G~";
+
+/// generator_dst || version || length of dst (4 bytes big-endian) || dst || length of label || label
+fn params_hash_input(generator_dst: &[u8], dst: &[u8], label: &[u8]) -> Vec<u8> {
+    let mut bytes = generator_dst.to_vec();
+    bytes.push(PARAMS_VERSION);
+    bytes.extend_from_slice(&(dst.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(dst);
+    bytes.extend_from_slice(&(label.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(label);
+    bytes
 }
 
 impl Params {
-    /// Generate g1, g2. These are shared by signer and all users.
+    /// Generate g1, g2. These are shared by signer and all users. Same as `new_with_dst` with an empty `dst`.
+    /// Panics if a generator is invalid which happens with negligible probability, use `new_with_dst` to get
+    /// an error instead.
     pub fn new(label: &[u8]) -> Self {
-        let g = SignatureGroup::from_msg_hash(&[label, " : g".as_bytes()].concat());
-        let g_tilde = VerkeyGroup::from_msg_hash(&[label, " : g_tilde".as_bytes()].concat());
-        Self { g, g_tilde }
+        Self::new_with_dst(&[], label).expect("Label should not hash to an invalid generator")
+    }
+
+    /// Generate g and g_tilde by hashing `label` with `dst`, a domain separation tag of the application, and a
+    /// separate tag for each generator (`PARAMS_G_DST`, `PARAMS_G_TILDE_DST`) and `PARAMS_VERSION`. Params
+    /// generated independently from the same `dst` and `label` are identical. Fails if a generator is identity
+    /// or not in the prime order subgroup.
+    pub fn new_with_dst(dst: &[u8], label: &[u8]) -> Result<Self, PSError> {
+        let g = SignatureGroup::from_msg_hash(&params_hash_input(PARAMS_G_DST, dst, label));
+        let g_tilde = VerkeyGroup::from_msg_hash(&params_hash_input(PARAMS_G_TILDE_DST, dst, label));
+        let params = Self {
+            g,
+            g_tilde,
+            commitment_gens: None,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Hash of g and g_tilde identifying the params, used in `KeyMetadata`. The commitment generators are not
+    /// included since keys do not depend on them.
+    pub fn hash(&self) -> Vec<u8> {
+        let mut hasher = Sha3_256::new();
+        hasher.input(&self.g.to_bytes());
+        hasher.input(&self.g_tilde.to_bytes());
+        hasher.result().to_vec()
+    }
+
+    /// Whether g and g_tilde are the ones generated by `new_with_dst` from `dst` and `label`, to check params
+    /// received from others
+    pub fn is_generated_from(&self, dst: &[u8], label: &[u8]) -> bool {
+        match Self::new_with_dst(dst, label) {
+            Ok(params) => params.g == self.g && params.g_tilde == self.g_tilde,
+            Err(_) => false,
+        }
+    }
+
+    /// Same as `new` but also generates commitment generators for `count_messages` messages from `label`
+    pub fn new_with_commitment_generators(label: &[u8], count_messages: usize) -> Self {
+        let mut params = Self::new(label);
+        params.commitment_gens = Some(CommitmentGenerators::new(label, count_messages));
+        params
+    }
+}
+
+/// Independent generators of the signature group for Pedersen commitments to messages, h_0 for the blinding and
+/// h_1, h_2, ... h_n, one per message, so commitments to attributes are computed the same way by all issuers
+/// and verifiers using the same params. They are hashed from a label so nobody knows the discrete log of one
+/// with respect to another. These are not for commitments in blind signing which must use the issuer's
+/// `BlindingKey`, they are for protocols over the attributes outside the signature, like proving that the
+/// attributes of a credential are the ones committed in another proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommitmentGenerators {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub h_0: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub h: Vec<SignatureGroup>,
+}
+
+impl CommitmentGenerators {
+    pub fn new(label: &[u8], count_messages: usize) -> Self {
+        let mut reader = ElementReader::new(&[label, " : commitment generators".as_bytes()].concat());
+        let h_0 = reader.next_element::<SignatureGroup>();
+        let h = (0..count_messages).map(|_| reader.next_element::<SignatureGroup>()).collect();
+        Self { h_0, h }
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.h.len()
+    }
+
+    /// h_0^blinding * h_i^m_i for each message m_i at index i in `messages`
+    pub fn commit(
+        &self,
+        messages: &HashMap<usize, FieldElement>,
+        blinding: &FieldElement,
+    ) -> Result<SignatureGroup, PSError> {
+        let mut bases = SignatureGroupVec::with_capacity(messages.len() + 1);
+        let mut exponents = FieldElementVector::with_capacity(messages.len() + 1);
+        bases.push(self.h_0.clone());
+        exponents.push(blinding.clone());
+        for (i, m) in messages {
+            if *i >= self.msg_count() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", i, self.msg_count()),
+                });
+            }
+            bases.push(self.h[*i].clone());
+            exponents.push(m.clone());
+        }
+        Ok(bases.multi_scalar_mul_const_time(&exponents).unwrap())
+    }
+}
+
+/// Verkey with precomputation done for verification. When the same verkey is used to verify many
+/// signatures or proofs of knowledge of signatures, prepare it once and use the `*_with_prepared_verkey`
+/// verification methods. For proofs of knowledge, the sets of revealed messages the verifier expects (like those of
+/// its presentation policy) can be prepared with `prepare_revealed` so the bases of the proof and of the revealed
+/// messages are not collected from the verkey for each proof. The multi-exponentiation of the revealed messages
+/// depends on their values so it is still done for each proof.
+#[derive(Clone, Debug)]
+pub struct PreparedVerkey {
+    pub vk: Verkey,
+    pub g_tilde: VerkeyGroup,
+    /// Inverse of g_tilde so that the signature need not be inverted during the pairing check
+    pub neg_g_tilde: VerkeyGroup,
+    /// Byte representation of g_tilde and each element of Y_tilde, used in challenge computation
+    pub(crate) g_tilde_bytes: Vec<u8>,
+    pub(crate) Y_tilde_bytes: Vec<Vec<u8>>,
+    /// Precomputation for each prepared set of revealed indices, keyed by the sorted indices
+    pub(crate) revealed_sets: HashMap<Vec<usize>, PreparedRevealedSet>,
+    /// `Params::hash` of the params the verkey was prepared with, checked against the verkey's metadata
+    pub(crate) params_hash: Vec<u8>,
+}
+
+/// Precomputation of `PreparedVerkey` for proofs of knowledge revealing a fixed set of messages
+#[derive(Clone, Debug)]
+pub(crate) struct PreparedRevealedSet {
+    /// g_tilde followed by Y_tilde of the hidden messages, the bases of the proof of knowledge
+    pub(crate) hidden_bases: Vec<VerkeyGroup>,
+    /// Bytes of `hidden_bases` as in the challenge
+    pub(crate) hidden_bases_bytes: Vec<u8>,
+    /// Y_tilde of the revealed messages in the order of the sorted indices
+    pub(crate) revealed_bases: VerkeyGroupVec,
+}
+
+impl PreparedVerkey {
+    pub fn new(vk: Verkey, params: &Params) -> Self {
+        let neg_g_tilde = params.g_tilde.negation();
+        let g_tilde_bytes = params.g_tilde.to_bytes();
+        let Y_tilde_bytes = vk.Y_tilde.iter().map(|y| y.to_bytes()).collect();
+        Self {
+            vk,
+            g_tilde: params.g_tilde.clone(),
+            neg_g_tilde,
+            g_tilde_bytes,
+            Y_tilde_bytes,
+            revealed_sets: HashMap::new(),
+            params_hash: params.hash(),
+        }
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.vk.Y_tilde.len()
+    }
+
+    /// Fails if the verkey has metadata which is not for the 2016 scheme, `message_count` messages and the params
+    /// the verkey was prepared with, the same check as `Signature::verify` does
+    pub(crate) fn check_metadata(&self, message_count: usize) -> Result<(), PSError> {
+        if let Some(metadata) = &self.vk.metadata {
+            metadata.check(Scheme::PS2016, message_count, None)?;
+            if metadata.params_hash != self.params_hash {
+                return Err(PSError::IncompatibleKey {
+                    msg: String::from("Key was generated with different params"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Precompute for proofs of knowledge revealing the messages at `revealed_msg_indices`. Fails if an index is not
+    /// less than the no of messages.
+    pub fn prepare_revealed(&mut self, revealed_msg_indices: &HashSet<usize>) -> Result<(), PSError> {
+        let indices = self.sorted_revealed_indices(revealed_msg_indices)?;
+        let set = self.compute_revealed_set(&indices);
+        self.revealed_sets.insert(indices, set);
+        Ok(())
+    }
+
+    /// The revealed indices sorted. Fails if an index is not less than the no of messages.
+    pub(crate) fn sorted_revealed_indices(&self, revealed_msg_indices: &HashSet<usize>) -> Result<Vec<usize>, PSError> {
+        let mut indices = revealed_msg_indices.iter().cloned().collect::<Vec<usize>>();
+        indices.sort_unstable();
+        if let Some(i) = indices.iter().find(|i| **i >= self.msg_count()) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, self.msg_count()),
+            });
+        }
+        Ok(indices)
+    }
+
+    /// The prepared set for the sorted revealed `indices` or, if not prepared, one computed now
+    pub(crate) fn revealed_set(&self, indices: &[usize]) -> std::borrow::Cow<'_, PreparedRevealedSet> {
+        match self.revealed_sets.get(indices) {
+            Some(set) => std::borrow::Cow::Borrowed(set),
+            None => std::borrow::Cow::Owned(self.compute_revealed_set(indices)),
+        }
+    }
+
+    fn compute_revealed_set(&self, indices: &[usize]) -> PreparedRevealedSet {
+        // +1 for `t`
+        let mut hidden_bases = Vec::with_capacity(self.msg_count() - indices.len() + 1);
+        let mut hidden_bases_bytes = self.g_tilde_bytes.clone();
+        hidden_bases.push(self.g_tilde.clone());
+        let mut revealed_bases = VerkeyGroupVec::with_capacity(indices.len());
+        for (i, y) in self.vk.Y_tilde.iter().enumerate() {
+            if indices.binary_search(&i).is_ok() {
+                revealed_bases.push(y.clone());
+            } else {
+                hidden_bases.push(y.clone());
+                hidden_bases_bytes.extend_from_slice(&self.Y_tilde_bytes[i]);
+            }
+        }
+        PreparedRevealedSet {
+            hidden_bases,
+            hidden_bases_bytes,
+            revealed_bases,
+        }
+    }
+}
+
+/// Window of `FixedBaseTable`, the scalar is processed 4 bits at a time
+const FIXED_BASE_WINDOW: usize = 4;
+
+/// Multiples of a base b for each digit of base 16 and position i of a scalar so that a scalar multiplication needs
+/// only an addition per position and no doublings. Row i has b * ((j + 1) * 16^i) for each digit j so that no entry is
+/// the identity, and b * (1 + 16 + 16^2 + ...) is subtracted from the sum. Every entry of a row is read and the one for
+/// the digit is selected with a mask and an addition is done for each position whatever the digit, so the memory
+/// accesses and the group operations do not depend on the scalar and it can be used with secret scalars.
+#[derive(Clone, Debug)]
+pub(crate) struct FixedBaseTable {
+    /// rows[i][j] = bytes of b * ((j + 1) * 16^i) for j in 0..16, the least significant position first
+    rows: Vec<Vec<Vec<u8>>>,
+    /// b * (1 + 16 + 16^2 + ...) for all positions
+    offset: SignatureGroup,
+}
+
+impl FixedBaseTable {
+    pub(crate) fn new(base: &SignatureGroup) -> Self {
+        let positions = FieldElement::zero().to_bytes().len() * 8 / FIXED_BASE_WINDOW;
+        let digits = 1 << FIXED_BASE_WINDOW;
+        let mut rows = Vec::with_capacity(positions);
+        let mut offset = SignatureGroup::identity();
+        // b * 16^i
+        let mut b = base.clone();
+        for _ in 0..positions {
+            let mut row = Vec::with_capacity(digits);
+            let mut entry = b.clone();
+            for _ in 0..digits {
+                row.push(entry.to_bytes());
+                entry += &b;
+            }
+            offset += &b;
+            // entry is b * 17 * 16^i so b * 16^(i+1) for the next position is entry - b
+            b = &entry - &b;
+            rows.push(row);
+        }
+        Self { rows, offset }
+    }
+
+    /// base * scalar in constant time
+    pub(crate) fn mul(&self, scalar: &FieldElement) -> SignatureGroup {
+        #[cfg_attr(not(feature = "zeroize"), allow(unused_mut))]
+        let mut bytes = scalar.to_bytes();
+        let mut selected = vec![0u8; self.rows[0][0].len()];
+        let mut res = self.offset.negation();
+        // Big endian bytes, each byte having 2 digits
+        for (k, byte) in bytes.iter().rev().enumerate() {
+            for (i, digit) in [(2 * k, byte & 0x0f), (2 * k + 1, byte >> 4)].iter() {
+                for s in selected.iter_mut() {
+                    *s = 0;
+                }
+                for (j, entry) in self.rows[*i].iter().enumerate() {
+                    // 0xff if j is the digit, 0 otherwise
+                    let mask = (((j as u8 ^ digit) as u16).wrapping_sub(1) >> 8) as u8;
+                    for (s, e) in selected.iter_mut().zip(entry.iter()) {
+                        *s |= e & mask;
+                    }
+                }
+                res += &SignatureGroup::from_bytes(&selected).unwrap();
+            }
+        }
+        #[cfg(feature = "zeroize")]
+        {
+            use zeroize::Zeroize;
+            bytes.zeroize();
+            selected.zeroize();
+        }
+        res
+    }
+}
+
+/// Signing key with a table of multiples of `params.g` (`FixedBaseTable`) so that signing, where both elements of
+/// the signature are powers of g, needs additions instead of scalar multiplications. For an issuer creating many
+/// signatures with the same key, prepare it once and use `Signature::new_with_prepared_sigkey` and
+/// `BlindSignature::new_with_prepared_sigkey`. The whole row of the table is scanned for each digit of the exponent
+/// so neither the time taken nor the memory accessed depends on u or the secret exponent.
+#[derive(Clone, Debug)]
+pub struct PreparedSigkey {
+    pub sigkey: Sigkey,
+    pub(crate) g: SignatureGroup,
+    pub(crate) g_table: FixedBaseTable,
+}
+
+impl PreparedSigkey {
+    pub fn new(sigkey: Sigkey, params: &Params) -> Self {
+        Self {
+            sigkey,
+            g: params.g.clone(),
+            g_table: FixedBaseTable::new(&params.g),
+        }
+    }
+
+    /// Fails if the key was prepared with different params
+    pub(crate) fn check_params(&self, params: &Params) -> Result<(), PSError> {
+        if self.g != params.g {
+            return Err(PSError::IncompatibleKey {
+                msg: String::from("Key was prepared with different params"),
+            });
+        }
+        Ok(())
+    }
+
+    /// x + y_i*m_i + ... for the `messages` at the given indices
+    pub(crate) fn signing_exponent(&self, messages: &[(usize, &FieldElement)]) -> Result<FieldElement, PSError> {
+        signing_exponent_with_secrets(&self.sigkey.x, &self.sigkey.y, messages)
+    }
+}
+
+impl Signer for PreparedSigkey {
+    fn key_size(&self) -> usize {
+        self.sigkey.y.len()
+    }
+
+    fn sign_exponent(
+        &self,
+        h: &SignatureGroup,
+        messages: &[(usize, &FieldElement)],
+    ) -> Result<SignatureGroup, PSError> {
+        self.sigkey.sign_exponent(h, messages)
+    }
+
+    fn metadata(&self) -> Option<&KeyMetadata> {
+        self.sigkey.metadata.as_ref()
+    }
+}
+
+/// The signature scheme a key, signature or proof belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scheme {
+    /// Scheme from CT-RSA 2016 paper
+    PS2016,
+    /// Scheme from CT-RSA 2018 paper
+    PS2018,
+}
+
+impl Scheme {
+    /// Single byte tag identifying the scheme
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            Scheme::PS2016 => 1,
+            Scheme::PS2018 => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Scheme::PS2016),
+            2 => Some(Scheme::PS2018),
+            _ => None,
+        }
+    }
+
+    /// No of elements in `y` of the signing key (or `Y_tilde` of verkey) for `count_messages` messages
+    pub fn key_size(&self, count_messages: usize) -> usize {
+        match self {
+            Scheme::PS2016 => count_messages,
+            Scheme::PS2018 => count_messages + 1,
+        }
+    }
+
+    /// No of messages supported by a key with `key_size` elements in `y` (or `Y_tilde`), inverse of `key_size`
+    pub fn message_count(&self, key_size: usize) -> usize {
+        match self {
+            Scheme::PS2016 => key_size,
+            Scheme::PS2018 => key_size.saturating_sub(1),
+        }
+    }
+}
+
+/// Performs the operations needing the secret exponents of a signing key so signing does not need the `Sigkey` in
+/// memory, an implementation can forward to an HSM, secure enclave or remote KMS. Signing of both schemes and
+/// blind signing take a `Signer`, `Sigkey` is the in-memory implementation.
+pub trait Signer {
+    /// No of elements in `y` of the key
+    fn key_size(&self) -> usize;
+
+    /// h^(x + y_i*m_i + y_j*m_j + ...) for each (i, m_i) in `messages`. Fails if an index is not less than
+    /// `key_size` or is repeated.
+    fn sign_exponent(
+        &self,
+        h: &SignatureGroup,
+        messages: &[(usize, &FieldElement)],
+    ) -> Result<SignatureGroup, PSError>;
+
+    /// Metadata of the key, checked when signing. None if the key has no metadata.
+    fn metadata(&self) -> Option<&KeyMetadata> {
+        None
+    }
+}
+
+/// So a `&dyn Signer` can be passed where a `Signer` is expected
+impl<'a, T: Signer + ?Sized> Signer for &'a T {
+    fn key_size(&self) -> usize {
+        (**self).key_size()
+    }
+
+    fn sign_exponent(
+        &self,
+        h: &SignatureGroup,
+        messages: &[(usize, &FieldElement)],
+    ) -> Result<SignatureGroup, PSError> {
+        (**self).sign_exponent(h, messages)
+    }
+
+    fn metadata(&self) -> Option<&KeyMetadata> {
+        (**self).metadata()
+    }
+}
+
+impl Signer for Sigkey {
+    fn key_size(&self) -> usize {
+        self.y.len()
+    }
+
+    fn sign_exponent(
+        &self,
+        h: &SignatureGroup,
+        messages: &[(usize, &FieldElement)],
+    ) -> Result<SignatureGroup, PSError> {
+        sign_exponent_with_secrets(&self.x, &self.y, h, messages)
+    }
+
+    fn metadata(&self) -> Option<&KeyMetadata> {
+        self.metadata.as_ref()
+    }
+}
+
+/// `Signer::sign_exponent` for a key or a key share with secrets `x` and `y`
+pub(crate) fn sign_exponent_with_secrets(
+    x: &FieldElement,
+    y: &[FieldElement],
+    h: &SignatureGroup,
+    messages: &[(usize, &FieldElement)],
+) -> Result<SignatureGroup, PSError> {
+    let mut exp = signing_exponent_with_secrets(x, y, messages)?;
+    let res = h * &exp;
+    wipe(&mut exp);
+    Ok(res)
+}
+
+/// x + y_i*m_i + ... for the `messages` at the given indices, the caller should wipe it after use
+pub(crate) fn signing_exponent_with_secrets(
+    x: &FieldElement,
+    y: &[FieldElement],
+    messages: &[(usize, &FieldElement)],
+) -> Result<FieldElement, PSError> {
+    for (j, (i, _)) in messages.iter().enumerate() {
+        if *i >= y.len() || messages[..j].iter().any(|(k, _)| k == i) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} is repeated or not less than {}", i, y.len()),
+            });
+        }
+    }
+    let mut exp = x.clone();
+    for (i, m) in messages {
+        let mut term = &y[*i] * *m;
+        exp += &term;
+        wipe(&mut term);
+    }
+    Ok(exp)
+}
+
+/// Domain separation tag for the fingerprint of a verkey
+pub const VERKEY_FINGERPRINT_DST: &[u8] = b"PS-SIG-VERKEY-FINGERPRINT";
+
+/// Version of the DER encoding of keys
+pub const KEY_DER_VERSION: u64 = 1;
+
+/*
+Keys are exported in DER as
+PSKey ::= SEQUENCE {
+    version      INTEGER,               -- KEY_DER_VERSION
+    scheme       INTEGER,               -- Scheme::to_byte
+    curve        UTF8String,            -- CURVE_ID
+    messageCount INTEGER,               -- no of messages that can be signed
+    first        OCTET STRING,          -- x or X_tilde
+    rest         SEQUENCE OF OCTET STRING   -- y or Y_tilde
+}
+*/
+fn key_to_der(scheme: Scheme, first: Vec<u8>, rest: Vec<Vec<u8>>) -> Vec<u8> {
+    let count_messages = match scheme {
+        Scheme::PS2016 => rest.len(),
+        Scheme::PS2018 => rest.len().saturating_sub(1),
+    };
+    let mut contents = vec![];
+    der::write_uint(KEY_DER_VERSION, &mut contents);
+    der::write_uint(scheme.to_byte() as u64, &mut contents);
+    der::write_utf8_string(CURVE_ID, &mut contents);
+    der::write_uint(count_messages as u64, &mut contents);
+    der::write_octet_string(&first, &mut contents);
+    let mut rest_contents = vec![];
+    for r in rest {
+        der::write_octet_string(&r, &mut rest_contents);
+    }
+    der::write_sequence(&rest_contents, &mut contents);
+    let mut out = vec![];
+    der::write_sequence(&contents, &mut out);
+    out
+}
+
+/// Keys of a build with the other signature group are rejected with `PSError::GroupMismatch` unless `any_group`
+fn key_from_der(bytes: &[u8], any_group: bool) -> Result<(Scheme, &[u8], Vec<&[u8]>), PSError> {
+    let mut outer = DerReader::new(bytes);
+    let mut reader = outer.read_sequence()?;
+    outer.finish()?;
+    let version = reader.read_uint()?;
+    if version != KEY_DER_VERSION {
+        return Err(PSError::InvalidBytes {
+            msg: format!("Unsupported key version {}", version),
+        });
+    }
+    let scheme_byte = reader.read_uint()?;
+    let scheme = Scheme::from_byte(scheme_byte as u8)
+        .filter(|_| scheme_byte <= u8::max_value() as u64)
+        .ok_or_else(|| PSError::InvalidBytes {
+            msg: format!("Unknown scheme {}", scheme_byte),
+        })?;
+    let curve = reader.read_utf8_string()?;
+    match GroupMarker::from_curve_id(curve) {
+        Some(group) if !any_group => wire::check_group(group)?,
+        Some(_) => (),
+        None => {
+            return Err(PSError::InvalidBytes {
+                msg: format!("Key is for curve {} but expected {}", curve, CURVE_ID),
+            })
+        }
+    }
+    let count_messages = reader.read_uint()?;
+    let first = reader.read_octet_string()?;
+    let mut rest_reader = reader.read_sequence()?;
+    let mut rest = vec![];
+    while !rest_reader.is_empty() {
+        rest.push(rest_reader.read_octet_string()?);
+    }
+    reader.finish()?;
+    // A count more than the elements present can't be valid and might overflow `key_size`
+    if count_messages > rest.len() as u64 {
+        return Err(PSError::InvalidBytes {
+            msg: format!("Message count {} but the key has {} elements", count_messages, rest.len()),
+        });
+    }
+    let count_messages = count_messages as usize;
+    if rest.len() != scheme.key_size(count_messages) {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: scheme.key_size(count_messages),
+            given: rest.len(),
+        });
+    }
+    Ok((scheme, first, rest))
+}
+
+/// Elements of a key of `scheme` at `indices` followed, for the 2018 scheme, by the element for m'
+fn restrict_elements<T: Clone>(elements: &[T], scheme: Scheme, indices: &[usize]) -> Result<Vec<T>, PSError> {
+    let count = scheme.message_count(elements.len());
+    let mut restricted = Vec::with_capacity(scheme.key_size(indices.len()));
+    for (j, i) in indices.iter().enumerate() {
+        if *i >= count {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, count),
+            });
+        }
+        if indices[..j].contains(i) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} is repeated", i),
+            });
+        }
+        restricted.push(elements[*i].clone());
+    }
+    restricted.extend_from_slice(&elements[count..]);
+    Ok(restricted)
+}
+
+/// Domain separation tag for deriving sigma_1 (and m' for the 2018 scheme) from the signing key, messages and
+/// context in `new_deterministic_keyed` of both schemes
+pub const KEYED_DETERMINISTIC_SIGNING_DST: &[u8] = b"PS-SIG-KEYED-DETERMINISTIC-SIGNING";
+
+impl Sigkey {
+    /// Bytes hashed to derive the randomness of keyed deterministic signing, which only the signer can compute,
+    /// the caller should wipe them after hashing
+    pub(crate) fn deterministic_signing_input(
+        &self,
+        scheme: Scheme,
+        messages: &[FieldElement],
+        context: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = KEYED_DETERMINISTIC_SIGNING_DST.to_vec();
+        bytes.push(scheme.to_byte());
+        bytes.append(&mut self.x.to_bytes());
+        for y in &self.y {
+            bytes.append(&mut y.to_bytes());
+        }
+        bytes.extend_from_slice(&(context.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(context);
+        for m in messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes
+    }
+
+    /// DER encoding of the key for `scheme`
+    pub fn to_der(&self, scheme: Scheme) -> Vec<u8> {
+        key_to_der(
+            scheme,
+            self.x.to_bytes(),
+            self.y.iter().map(|y| y.to_bytes()).collect(),
+        )
+    }
+
+    /// Parse the output of `to_der`, returns the key and its scheme. Since the signing key does not depend on the
+    /// group of signatures, keys exported by a build with the other group are accepted, the verkey for this build can
+    /// then be created from it.
+    pub fn from_der(bytes: &[u8]) -> Result<(Self, Scheme), PSError> {
+        let (scheme, first, rest) = key_from_der(bytes, true)?;
+        let x = FieldElement::from_bytes(first)?;
+        let mut y = Vec::with_capacity(rest.len());
+        for r in rest {
+            y.push(FieldElement::from_bytes(r)?);
+        }
+        Ok((Self { x, y, metadata: None }, scheme))
+    }
+
+    /// Key with `metadata`, for keys parsed from bytes or created from their elements
+    pub fn with_metadata(mut self, metadata: KeyMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Verkey of this key, so only the signing key and params need to be stored. The same for both schemes,
+    /// a key from `keygen_2018` gives the verkey from `keygen_2018`.
+    pub fn verkey(&self, params: &Params) -> Verkey {
+        Verkey::from_sigkey(self, params)
+    }
+
+    /// Whether `verkey` is the verkey of this key, to cross-check keys stored separately
+    pub fn is_key_for(&self, verkey: &Verkey, params: &Params) -> bool {
+        verkey.Y_tilde.len() == self.y.len()
+            && verkey.X_tilde == &params.g_tilde * &self.x
+            && self
+                .y
+                .iter()
+                .zip(verkey.Y_tilde.iter())
+                .all(|(y, Y)| *Y == &params.g_tilde * y)
+    }
+
+    /// Key of `scheme` supporting `additional` more messages, the new elements of `y` are random. The existing
+    /// elements are kept so signatures created with this key can be verified with `Verkey::truncate` of the
+    /// extended verkey. Use `Verkey::extend` to get the extended verkey.
+    pub fn extend(&self, scheme: Scheme, additional: usize) -> Self {
+        let new_y = (0..additional).map(|_| FieldElement::random()).collect();
+        self.extend_with(scheme, new_y)
+    }
+
+    /// Same as `extend` but the new elements of `y` are derived from `seed` so extending the same key with
+    /// the same seed gives the same key
+    pub fn extend_with_seed(&self, scheme: Scheme, additional: usize, seed: &[u8]) -> Self {
+        let mut reader = ElementReader::new(&[seed, " : key extension".as_bytes()].concat());
+        let new_y = (0..additional).map(|_| reader.next_element::<FieldElement>()).collect();
+        self.extend_with(scheme, new_y)
+    }
+
+    /// Key of `scheme` for only the messages at `indices` of this key, in the order of `indices`. See
+    /// `Verkey::restrict`.
+    pub fn restrict(&self, scheme: Scheme, indices: &[usize]) -> Result<Self, PSError> {
+        Ok(Self {
+            x: self.x.clone(),
+            y: restrict_elements(&self.y, scheme, indices)?,
+            metadata: self.metadata.as_ref().map(|m| m.with_message_count(indices.len())),
+        })
+    }
+
+    fn extend_with(&self, scheme: Scheme, new_y: Vec<FieldElement>) -> Self {
+        let mut y = self.y.clone();
+        // Insert after the elements for the messages, for the 2018 scheme this keeps the element for m' last
+        let end = scheme.message_count(y.len());
+        let additional = new_y.len();
+        y.splice(end..end, new_y);
+        Self {
+            x: self.x.clone(),
+            y,
+            metadata: self
+                .metadata
+                .as_ref()
+                .map(|m| m.with_message_count(m.message_count + additional)),
+        }
+    }
+}
+
+impl Verkey {
+    /// Byte representation of the key for `scheme`, the header (see `wire`) followed by X_tilde and each element
+    /// of Y_tilde
+    pub fn to_bytes(&self, scheme: Scheme) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        // Writing to a Vec does not fail
+        self.write_bytes(scheme, &mut bytes).unwrap();
+        bytes
+    }
+
+    /// Same as `to_bytes` but writes to `writer`
+    pub fn write_bytes<W: Write>(&self, scheme: Scheme, writer: &mut W) -> Result<(), PSError> {
+        wire::write_header(scheme, writer)?;
+        writer.write_all(&self.X_tilde.to_bytes())?;
+        for y in &self.Y_tilde {
+            writer.write_all(&y.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Same as `to_bytes` but writes to the start of `buf`, which should have at least `serialized_len` bytes.
+    /// Returns the no of bytes written.
+    pub fn write_into(&self, scheme: Scheme, buf: &mut [u8]) -> Result<usize, PSError> {
+        wire::write_into(self.serialized_len(), buf, |w| self.write_bytes(scheme, w))
+    }
+
+    /// Size in bytes of the output of `to_bytes`
+    pub fn serialized_len(&self) -> usize {
+        Self::serialized_size(self.Y_tilde.len())
+    }
+
+    /// Parse the output of `to_bytes`, returns the key and its scheme. The group elements are validated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, Scheme), PSError> {
+        let (scheme, rest) = wire::read_header(bytes)?;
+        if rest.is_empty() || rest.len() % VERKEY_GROUP_SIZE != 0 {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE + (rest.len() / VERKEY_GROUP_SIZE + 1) * VERKEY_GROUP_SIZE,
+                given: bytes.len(),
+            });
+        }
+        let mut chunks = rest.chunks(VERKEY_GROUP_SIZE);
+        let X_tilde = VerkeyGroup::from_bytes(chunks.next().unwrap())?;
+        let mut Y_tilde = vec![];
+        for c in chunks {
+            Y_tilde.push(VerkeyGroup::from_bytes(c)?);
+        }
+        let vk = Self {
+            X_tilde,
+            Y_tilde,
+            metadata: None,
+        };
+        vk.validate()?;
+        Ok((vk, scheme))
+    }
+
+    /// Size in bytes of the output of `to_bytes` for a key with `Y_tilde_count` elements in Y_tilde, see
+    /// `Scheme::key_size`
+    pub fn serialized_size(Y_tilde_count: usize) -> usize {
+        wire::HEADER_SIZE + (Y_tilde_count + 1) * VERKEY_GROUP_SIZE
+    }
+
+    /// Multibase (base58btc) encoding of `to_bytes`. There is no `Display` for verkeys since the same key type is
+    /// used by both schemes and the encoding includes the scheme.
+    pub fn to_multibase(&self, scheme: Scheme) -> String {
+        multibase::encode(Base::Base58Btc, &self.to_bytes(scheme))
+    }
+
+    /// Parse multibase (base58btc or base64url) encoding of `to_bytes`, returns the key and its scheme
+    pub fn from_multibase(s: &str) -> Result<(Self, Scheme), PSError> {
+        Self::from_bytes(&multibase::decode(s)?.1)
+    }
+
+    /// DER encoding of the key for `scheme`
+    pub fn to_der(&self, scheme: Scheme) -> Vec<u8> {
+        key_to_der(
+            scheme,
+            self.X_tilde.to_bytes(),
+            self.Y_tilde.iter().map(|y| y.to_bytes()).collect(),
+        )
+    }
+
+    /// Parse the output of `to_der`, returns the key and its scheme. The group elements are validated.
+    pub fn from_der(bytes: &[u8]) -> Result<(Self, Scheme), PSError> {
+        let (scheme, first, rest) = key_from_der(bytes, false)?;
+        let X_tilde = VerkeyGroup::from_bytes(first)?;
+        let mut Y_tilde = Vec::with_capacity(rest.len());
+        for r in rest {
+            Y_tilde.push(VerkeyGroup::from_bytes(r)?);
+        }
+        let vk = Self {
+            X_tilde,
+            Y_tilde,
+            metadata: None,
+        };
+        vk.validate()?;
+        Ok((vk, scheme))
+    }
+
+    /// Verkey corresponding to the signing key, used to recreate the verkey for this build from a signing key
+    /// exported by a build with the other signature group
+    pub fn from_sigkey(sigkey: &Sigkey, params: &Params) -> Self {
+        Self {
+            X_tilde: &params.g_tilde * &sigkey.x,
+            Y_tilde: sigkey.y.iter().map(|y| &params.g_tilde * y).collect(),
+            metadata: sigkey.metadata.as_ref().map(|m| KeyMetadata {
+                params_hash: params.hash(),
+                ..m.clone()
+            }),
+        }
+    }
+
+    /// Verkey of `sigkey` which should be the signing key of this verkey extended with `Sigkey::extend`.
+    /// Returns an error if `sigkey` is not an extension of the signing key of this verkey.
+    pub fn extend(&self, scheme: Scheme, sigkey: &Sigkey, params: &Params) -> Result<Self, PSError> {
+        let extended = Self::from_sigkey(sigkey, params);
+        if !extended.is_extension_of(self, scheme) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signing key is not an extension of the verkey's signing key"),
+            });
+        }
+        Ok(extended)
+    }
+
+    /// Whether this verkey is `other` extended to support more messages, i.e. every signature verifiable with
+    /// `other` is verifiable with `truncate` of this verkey to the message count of `other`
+    pub fn is_extension_of(&self, other: &Verkey, scheme: Scheme) -> bool {
+        if self.X_tilde != other.X_tilde || self.Y_tilde.len() < other.Y_tilde.len() {
+            return false;
+        }
+        let other_end = scheme.message_count(other.Y_tilde.len());
+        let end = scheme.message_count(self.Y_tilde.len());
+        self.Y_tilde[..other_end] == other.Y_tilde[..other_end]
+            && self.Y_tilde[end..] == other.Y_tilde[other_end..]
+    }
+
+    /// Verkey for only the first `count_messages` messages of this verkey of `scheme`. Signatures created
+    /// before the key was extended (`Sigkey::extend`) to more messages verify with it.
+    pub fn truncate(&self, scheme: Scheme, count_messages: usize) -> Result<Self, PSError> {
+        let supported = scheme.message_count(self.Y_tilde.len());
+        if count_messages > supported {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: supported,
+                given: count_messages,
+            });
+        }
+        self.restrict(scheme, &(0..count_messages).collect::<Vec<usize>>())
+    }
+
+    /// Verkey of `scheme` for only the messages at `indices` of this verkey, in the order of `indices`, so one
+    /// issuer key can serve credential schemas with fewer messages. It verifies signatures over
+    /// `indices.len()` messages created with `Sigkey::restrict` of the signing key using the same `indices`,
+    /// and signatures created with the signing key before it was extended when `indices` are its message
+    /// indices. It does not verify signatures created with the unrestricted signing key. Since all restrictions
+    /// share X_tilde, a signature created with one restriction is a valid signature with another restriction
+    /// for messages placed at the corresponding positions, schemas sharing a key should fix a schema identifier
+    /// in one of the messages.
+    pub fn restrict(&self, scheme: Scheme, indices: &[usize]) -> Result<Self, PSError> {
+        Ok(Self {
+            X_tilde: self.X_tilde.clone(),
+            Y_tilde: restrict_elements(&self.Y_tilde, scheme, indices)?,
+            metadata: self.metadata.as_ref().map(|m| m.with_message_count(indices.len())),
+        })
+    }
+
+    /// Key with `metadata`, for keys parsed from bytes or created from their elements
+    pub fn with_metadata(mut self, metadata: KeyMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Verify the proof of possession of the signing key of this verkey
+    pub fn verify_pop(&self, proof: &PoPProof, params: &Params) -> bool {
+        if proof.responses.len() != self.Y_tilde.len() + 1 {
+            return false;
+        }
+        // Commitment is g_tilde^response * public_key^challenge
+        let commitments = vec![&self.X_tilde]
+            .into_iter()
+            .chain(self.Y_tilde.iter())
+            .zip(proof.responses.iter())
+            .map(|(pk, r)| (&params.g_tilde * r) + (pk * &proof.challenge))
+            .collect::<Vec<VerkeyGroup>>();
+        PoPProof::challenge(self, &commitments, params) == proof.challenge
+    }
+
+    /// Stable identifier of the key, the SHA3-256 hash of its DER encoding (which includes the scheme and
+    /// the curve). Does not depend on serde.
+    pub fn fingerprint(&self, scheme: Scheme) -> [u8; 32] {
+        let mut hasher = Sha3_256::new();
+        hasher.input(VERKEY_FINGERPRINT_DST);
+        hasher.input(&self.to_der(scheme));
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(hasher.result().as_slice());
+        fingerprint
+    }
+}
+
+/// Domain separation tag for the challenge of the proof of possession of a signing key
+pub const POP_CHALLENGE_DST: &[u8] = b"PS-SIG-PROOF-OF-POSSESSION";
+
+/// Proof of possession of the signing key of a verkey. A non-interactive Schnorr proof of knowledge of x and each
+/// y_i in X_tilde = g_tilde^x and Y_tilde_i = g_tilde^y_i. It has the challenge and the responses, the verifier
+/// recomputes the commitments from them so no pairing is needed for verification. Check it with
+/// `Verkey::verify_pop` before accepting a verkey into an aggregated verkey.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoPProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub challenge: FieldElement,
+    /// Response for x followed by responses for each y_i
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
+    pub responses: FieldElementVector,
+}
+
+impl PoPProof {
+    /// Prove possession of `sigkey`, the signing key of `verkey`
+    pub fn new(sigkey: &Sigkey, verkey: &Verkey, params: &Params) -> Result<Self, PSError> {
+        if !sigkey.is_key_for(verkey, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signing key does not correspond to the verkey"),
+            });
+        }
+        let secrets = vec![&sigkey.x].into_iter().chain(sigkey.y.iter());
+        let blindings = FieldElementVector::random(sigkey.y.len() + 1);
+        let commitments = blindings
+            .iter()
+            .map(|b| &params.g_tilde * b)
+            .collect::<Vec<VerkeyGroup>>();
+        let challenge = Self::challenge(verkey, &commitments, params);
+        let mut responses = FieldElementVector::with_capacity(blindings.len());
+        for (b, s) in blindings.iter().zip(secrets) {
+            responses.push(b - (&challenge * s));
+        }
+        Ok(Self {
+            challenge,
+            responses,
+        })
+    }
+
+    /// Hash of g_tilde, X_tilde, each Y_tilde_i and each commitment
+    fn challenge(verkey: &Verkey, commitments: &[VerkeyGroup], params: &Params) -> FieldElement {
+        let mut bytes = POP_CHALLENGE_DST.to_vec();
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut verkey.X_tilde.to_bytes());
+        for y in &verkey.Y_tilde {
+            bytes.append(&mut y.to_bytes());
+        }
+        for c in commitments {
+            bytes.append(&mut c.to_bytes());
+        }
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Domain separation tag for hierarchical derivation of keys from a master secret
+pub const KEY_DERIVATION_DST: &[u8] = b"PS-SIG-KEY-DERIVATION";
+
+/// Minimum size in bytes of a master secret
+pub const MASTER_SECRET_MIN_SIZE: usize = 32;
+
+/// Secret from which signing keys are derived at indexed paths, like hardened derivation of BIP32. Each index of
+/// the path derives a child secret by hashing the parent secret and the index, so a service can derive a key per
+/// tenant or credential schema (like `[tenant, schema]`) and only store the master secret. A child secret or key
+/// does not reveal its parent or siblings. There is no derivation of child verkeys from a parent verkey, the
+/// verkey is computed from the derived signing key, see `derive_keypair`.
+#[derive(Clone)]
+pub struct MasterSecret {
+    secret: Vec<u8>,
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for MasterSecret {
+    fn zeroize(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.secret);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for MasterSecret {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for MasterSecret {}
+
+impl MasterSecret {
+    /// `secret` should be at least `MASTER_SECRET_MIN_SIZE` bytes of high entropy
+    pub fn new(secret: &[u8]) -> Result<Self, PSError> {
+        if secret.len() < MASTER_SECRET_MIN_SIZE {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Master secret should be at least {} bytes but given {} bytes",
+                    MASTER_SECRET_MIN_SIZE,
+                    secret.len()
+                ),
+            });
+        }
+        Ok(Self {
+            secret: secret.to_vec(),
+        })
+    }
+
+    pub fn random() -> Self {
+        let mut secret = vec![0u8; MASTER_SECRET_MIN_SIZE];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self { secret }
+    }
+
+    /// Secret of the child at `index`
+    pub fn derive_child(&self, index: u32) -> Self {
+        let mut hasher = Sha3_256::new();
+        hasher.input(KEY_DERIVATION_DST);
+        hasher.input(b" : child");
+        hasher.input(&self.secret);
+        hasher.input(&index.to_be_bytes());
+        Self {
+            secret: hasher.result().to_vec(),
+        }
+    }
+
+    /// Secret at `path`, each index derives a child of the previous secret
+    pub fn derive_path(&self, path: &[u32]) -> Self {
+        path.iter().fold(self.clone(), |secret, i| secret.derive_child(*i))
+    }
+
+    /// Signing key of `scheme` for `count_messages` messages at `path`. The scheme and message count are part of
+    /// the derivation so keys of different schemes or sizes at the same path are unrelated.
+    pub fn derive_sigkey(&self, path: &[u32], scheme: Scheme, count_messages: usize) -> Sigkey {
+        let node = self.derive_path(path);
+        let mut bytes = KEY_DERIVATION_DST.to_vec();
+        bytes.extend_from_slice(b" : key");
+        bytes.extend_from_slice(&node.secret);
+        bytes.push(scheme.to_byte());
+        bytes.extend_from_slice(&(count_messages as u32).to_be_bytes());
+        let mut reader = ElementReader::new(&bytes);
+        let x = reader.next_element::<FieldElement>();
+        let y = (0..scheme.key_size(count_messages))
+            .map(|_| reader.next_element::<FieldElement>())
+            .collect();
+        Sigkey { x, y, metadata: None }
+    }
+
+    /// Signing key and verkey of `scheme` for `count_messages` messages at `path`
+    pub fn derive_keypair(&self, path: &[u32], scheme: Scheme, count_messages: usize, params: &Params) -> Keypair {
+        let sigkey = self
+            .derive_sigkey(path, scheme, count_messages)
+            .with_metadata(KeyMetadata::new(scheme, count_messages, params));
+        let verkey = sigkey.verkey(params);
+        Keypair {
+            sigkey,
+            verkey,
+            scheme,
+        }
     }
 }
 
 /// Generate signing and verification keys for scheme from 2016 paper
 pub fn keygen(count_messages: usize, params: &Params) -> (Sigkey, Verkey) {
-    // TODO: Take PRNG as argument
-    let x = FieldElement::random();
+    keygen_with_rng(count_messages, params, &mut rand::thread_rng())
+}
+
+/// Same as `keygen` but the signing key is generated using `rng`
+pub fn keygen_with_rng<R: RngCore + CryptoRng>(
+    count_messages: usize,
+    params: &Params,
+    rng: &mut R,
+) -> (Sigkey, Verkey) {
+    let x = FieldElement::random_using_rng(rng);
     let X_tilde = &params.g_tilde * &x;
     let mut y = vec![];
     let mut Y_tilde = vec![];
     for _ in 0..count_messages {
-        let y_i = FieldElement::random();
+        let y_i = FieldElement::random_using_rng(rng);
         Y_tilde.push(&params.g_tilde * &y_i);
         y.push(y_i);
     }
-    (Sigkey { x, y }, Verkey { X_tilde, Y_tilde })
+    let metadata = KeyMetadata::new(Scheme::PS2016, count_messages, params);
+    (
+        Sigkey {
+            x,
+            y,
+            metadata: Some(metadata.clone()),
+        },
+        Verkey {
+            X_tilde,
+            Y_tilde,
+            metadata: Some(metadata),
+        },
+    )
 }
 
 /// Generate signing and verification keys for scheme from 2018 paper. The signing and verification
 /// keys will have 1 extra element for m'
 pub fn keygen_2018(count_messages: usize, params: &Params) -> (Sigkey, Verkey) {
-    keygen(count_messages + 1, params)
+    keygen_2018_with_rng(count_messages, params, &mut rand::thread_rng())
+}
+
+/// Same as `keygen_2018` but the signing key is generated using `rng`
+pub fn keygen_2018_with_rng<R: RngCore + CryptoRng>(
+    count_messages: usize,
+    params: &Params,
+    rng: &mut R,
+) -> (Sigkey, Verkey) {
+    let (sk, vk) = keygen_with_rng(count_messages + 1, params, rng);
+    let metadata = KeyMetadata::new(Scheme::PS2018, count_messages, params);
+    (sk.with_metadata(metadata.clone()), vk.with_metadata(metadata))
+}
+
+/// Signing and verification keys together with the scheme they were generated for
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Keypair {
+    pub sigkey: Sigkey,
+    pub verkey: Verkey,
+    pub scheme: Scheme,
+}
+
+impl Keypair {
+    /// Generate keys of `scheme` for `count_messages` messages
+    pub fn new(scheme: Scheme, count_messages: usize, params: &Params) -> Self {
+        Self::new_with_rng(scheme, count_messages, params, &mut rand::thread_rng())
+    }
+
+    /// Same as `new` but the signing key is generated using `rng`
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
+        scheme: Scheme,
+        count_messages: usize,
+        params: &Params,
+        rng: &mut R,
+    ) -> Self {
+        let (sigkey, verkey) = match scheme {
+            Scheme::PS2016 => keygen_with_rng(count_messages, params, rng),
+            Scheme::PS2018 => keygen_2018_with_rng(count_messages, params, rng),
+        };
+        Self {
+            sigkey,
+            verkey,
+            scheme,
+        }
+    }
+
+    /// Keys for `signature::Signature` and `pok_sig`
+    pub fn new_2016(count_messages: usize, params: &Params) -> Self {
+        Self::new(Scheme::PS2016, count_messages, params)
+    }
+
+    /// Keys for `signature_2018::Signature` and `pok_sig_2018`
+    pub fn new_2018(count_messages: usize, params: &Params) -> Self {
+        Self::new(Scheme::PS2018, count_messages, params)
+    }
+
+    /// No of messages that can be signed with these keys. For the 2018 scheme this excludes m'.
+    pub fn messages_supported(&self) -> usize {
+        self.scheme.message_count(self.verkey.Y_tilde.len())
+    }
+
+    /// Keys supporting `additional` more messages, see `Sigkey::extend`
+    pub fn extend(&self, additional: usize, params: &Params) -> Self {
+        let sigkey = self.sigkey.extend(self.scheme, additional);
+        let verkey = Verkey::from_sigkey(&sigkey, params);
+        Self {
+            sigkey,
+            verkey,
+            scheme: self.scheme,
+        }
+    }
+
+    /// Keys for only the messages at `indices`, see `Verkey::restrict`
+    pub fn restrict(&self, indices: &[usize]) -> Result<Self, PSError> {
+        Ok(Self {
+            sigkey: self.sigkey.restrict(self.scheme, indices)?,
+            verkey: self.verkey.restrict(self.scheme, indices)?,
+            scheme: self.scheme,
+        })
+    }
+
+    pub fn into_keys(self) -> (Sigkey, Verkey) {
+        (self.sigkey, self.verkey)
+    }
 }
 
 #[cfg(test)]
@@ -68,6 +1390,32 @@ mod tests {
         assert_eq!(vk.Y_tilde.len(), count_msgs);
     }
 
+    #[test]
+    fn test_fixed_base_table() {
+        let params = Params::new("test".as_bytes());
+        let table = FixedBaseTable::new(&params.g);
+        assert!(table.mul(&FieldElement::zero()).is_identity());
+        assert_eq!(table.mul(&FieldElement::one()), params.g);
+        assert_eq!(table.mul(&(&FieldElement::zero() - &FieldElement::one())), params.g.negation());
+        for _ in 0..10 {
+            let s = FieldElement::random();
+            assert_eq!(table.mul(&s), &params.g * &s);
+        }
+
+        let n = 100;
+        let scalars = (0..n).map(|_| FieldElement::random()).collect::<Vec<_>>();
+        let start = Instant::now();
+        for s in &scalars {
+            let _ = &params.g * s;
+        }
+        println!("Time for {} scalar multiplications {:?}", n, start.elapsed());
+        let start = Instant::now();
+        for s in &scalars {
+            table.mul(s);
+        }
+        println!("Time for {} scalar multiplications with the table {:?}", n, start.elapsed());
+    }
+
     #[test]
     fn test_keygen_2018() {
         let count_msgs = 5;
@@ -76,4 +1424,420 @@ mod tests {
         assert_eq!(sk.y.len(), count_msgs+1);
         assert_eq!(vk.Y_tilde.len(), count_msgs+1);
     }
+
+    #[test]
+    fn test_keypair() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let keypair = Keypair::new_2016(count_msgs, &params);
+        assert_eq!(keypair.scheme, Scheme::PS2016);
+        assert_eq!(keypair.messages_supported(), count_msgs);
+        assert_eq!(keypair.sigkey.y.len(), count_msgs);
+
+        let keypair = Keypair::new_2018(count_msgs, &params);
+        assert_eq!(keypair.scheme, Scheme::PS2018);
+        assert_eq!(keypair.messages_supported(), count_msgs);
+        assert_eq!(keypair.verkey.Y_tilde.len(), count_msgs + 1);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = crate::signature_2018::Signature::new(&msgs, &keypair.sigkey).unwrap();
+        assert!(sig.verify(msgs, &keypair.verkey, &params).unwrap());
+
+        let json = serde_json::to_string(&keypair).unwrap();
+        let keypair_1: Keypair = serde_json::from_str(&json).unwrap();
+        assert_eq!(keypair_1.scheme, Scheme::PS2018);
+        assert_eq!(keypair_1.verkey.Y_tilde, keypair.verkey.Y_tilde);
+        let (sk, _) = keypair_1.into_keys();
+        assert_eq!(sk.x, keypair.sigkey.x);
+    }
+
+    #[test]
+    fn test_key_metadata() {
+        use crate::signature::Signature;
+        use crate::signature_2018::Signature as Signature18;
+
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (sk_18, vk_18) = keygen_2018(count_msgs, &params);
+        assert_eq!(sk.metadata, Some(KeyMetadata::new(Scheme::PS2016, count_msgs, &params)));
+        assert_eq!(vk_18.metadata, Some(KeyMetadata::new(Scheme::PS2018, count_msgs, &params)));
+
+        // A 2018 key used with the 2016 API fails with a descriptive error and vice versa
+        let mut msgs_1 = msgs.clone();
+        msgs_1.push(FieldElement::random());
+        match Signature::new(&msgs_1, &sk_18, &params) {
+            Err(PSError::IncompatibleKey { .. }) => (),
+            _ => panic!("Expected IncompatibleKey"),
+        }
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        match Signature18::new(&msgs[..count_msgs - 1], &sk) {
+            Err(PSError::IncompatibleKey { .. }) => (),
+            _ => panic!("Expected IncompatibleKey"),
+        }
+        let sig_18 = Signature18::new(&msgs, &sk_18).unwrap();
+        match sig.verify(msgs_1.clone(), &vk_18, &params) {
+            Err(PSError::IncompatibleKey { .. }) => (),
+            _ => panic!("Expected IncompatibleKey"),
+        }
+        assert!(sig_18.verify(msgs.clone(), &vk_18, &params).unwrap());
+
+        // Different params
+        let other_params = Params::new("other".as_bytes());
+        assert!(Signature::new(&msgs, &sk, &other_params).is_err());
+        assert!(sig.verify(msgs.clone(), &vk, &other_params).is_err());
+
+        // Keys without metadata are not checked
+        let (vk_1, scheme) = Verkey::from_bytes(&vk.to_bytes(Scheme::PS2016)).unwrap();
+        assert!(vk_1.metadata.is_none());
+        assert!(sig.verify(msgs.clone(), &vk_1, &params).unwrap());
+        let vk_1 = vk_1.with_metadata(KeyMetadata::new(scheme, count_msgs, &params));
+        assert!(sig.verify(msgs.clone(), &vk_1, &params).unwrap());
+
+        // Derived keys have updated message counts
+        assert_eq!(vk.truncate(Scheme::PS2016, 2).unwrap().metadata.unwrap().message_count, 2);
+        assert_eq!(sk_18.extend(Scheme::PS2018, 2).metadata.as_ref().unwrap().message_count, count_msgs + 2);
+        assert_eq!(sk.verkey(&params).metadata, vk.metadata);
+    }
+
+    #[test]
+    fn test_key_extension() {
+        let params = Params::new("test".as_bytes());
+        let msgs = (0..5).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+
+        let (sk, vk) = keygen(3, &params);
+        let sig = crate::signature::Signature::new(&msgs[..3], &sk, &params).unwrap();
+        let sk_ext = sk.extend(Scheme::PS2016, 2);
+        assert_eq!(sk_ext.y[..3], sk.y[..]);
+        let vk_ext = vk.extend(Scheme::PS2016, &sk_ext, &params).unwrap();
+        assert!(vk_ext.is_extension_of(&vk, Scheme::PS2016));
+        assert!(!vk.is_extension_of(&vk_ext, Scheme::PS2016));
+        let sig_ext = crate::signature::Signature::new(&msgs, &sk_ext, &params).unwrap();
+        assert!(sig_ext.verify(msgs.clone(), &vk_ext, &params).unwrap());
+        // Old signature verifies with the truncated verkey
+        let vk_trunc = vk_ext.truncate(Scheme::PS2016, 3).unwrap();
+        assert_eq!(vk_trunc.Y_tilde, vk.Y_tilde);
+        assert!(sig.verify(msgs[..3].to_vec(), &vk_trunc, &params).unwrap());
+        assert!(vk_ext.truncate(Scheme::PS2016, 6).is_err());
+        // Signing key of a different verkey
+        let (sk_other, _) = keygen(5, &params);
+        assert!(vk.extend(Scheme::PS2016, &sk_other, &params).is_err());
+
+        // Same seed gives the same extension
+        assert_eq!(
+            sk.extend_with_seed(Scheme::PS2016, 2, b"seed").y,
+            sk.extend_with_seed(Scheme::PS2016, 2, b"seed").y
+        );
+
+        // For the 2018 scheme the element for m' stays last
+        let keypair = Keypair::new_2018(3, &params);
+        let sig = crate::signature_2018::Signature::new(&msgs[..3], &keypair.sigkey).unwrap();
+        let keypair_ext = keypair.extend(2, &params);
+        assert_eq!(keypair_ext.messages_supported(), 5);
+        assert_eq!(keypair_ext.sigkey.y[5], keypair.sigkey.y[3]);
+        assert!(keypair_ext.verkey.is_extension_of(&keypair.verkey, Scheme::PS2018));
+        let sig_ext = crate::signature_2018::Signature::new(&msgs, &keypair_ext.sigkey).unwrap();
+        assert!(sig_ext.verify(msgs.clone(), &keypair_ext.verkey, &params).unwrap());
+        let vk_trunc = keypair_ext.verkey.truncate(Scheme::PS2018, 3).unwrap();
+        assert!(sig.verify(msgs[..3].to_vec(), &vk_trunc, &params).unwrap());
+    }
+
+    #[test]
+    fn test_key_restriction() {
+        let params = Params::new("test".as_bytes());
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let indices = vec![4, 1, 2];
+
+        for scheme in vec![Scheme::PS2016, Scheme::PS2018] {
+            let keypair = Keypair::new(scheme, 6, &params);
+            let restricted = keypair.restrict(&indices).unwrap();
+            assert_eq!(restricted.messages_supported(), 3);
+            assert_eq!(restricted.verkey.Y_tilde[0], keypair.verkey.Y_tilde[4]);
+            if scheme == Scheme::PS2018 {
+                // Element for m' is kept
+                assert_eq!(restricted.verkey.Y_tilde.last(), keypair.verkey.Y_tilde.last());
+            }
+            let valid = match scheme {
+                Scheme::PS2016 => crate::signature::Signature::new(&msgs, &restricted.sigkey, &params)
+                    .unwrap()
+                    .verify(msgs.clone(), &restricted.verkey, &params)
+                    .unwrap(),
+                Scheme::PS2018 => crate::signature_2018::Signature::new(&msgs, &restricted.sigkey)
+                    .unwrap()
+                    .verify(msgs.clone(), &restricted.verkey, &params)
+                    .unwrap(),
+            };
+            assert!(valid);
+
+            assert!(keypair.restrict(&[6]).is_err());
+            assert!(keypair.restrict(&[1, 1]).is_err());
+            assert_eq!(
+                keypair.verkey.truncate(scheme, 2).unwrap().Y_tilde,
+                keypair.verkey.restrict(scheme, &[0, 1]).unwrap().Y_tilde
+            );
+        }
+    }
+
+    #[test]
+    fn test_verkey_from_sigkey() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let vk_1 = sk.verkey(&params);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+        assert!(sk.is_key_for(&vk, &params));
+
+        let (sk, vk) = keygen_2018(4, &params);
+        assert_eq!(sk.verkey(&params).Y_tilde, vk.Y_tilde);
+        assert!(sk.is_key_for(&vk, &params));
+
+        let (_, vk_other) = keygen(4, &params);
+        assert!(!sk.is_key_for(&vk_other, &params));
+        assert!(!sk.is_key_for(&vk.truncate(Scheme::PS2018, 3).unwrap(), &params));
+    }
+
+    #[test]
+    fn test_pop() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let proof = PoPProof::new(&sk, &vk, &params).unwrap();
+        assert!(vk.verify_pop(&proof, &params));
+
+        let (sk_other, vk_other) = keygen(4, &params);
+        assert!(!vk_other.verify_pop(&proof, &params));
+        assert!(PoPProof::new(&sk_other, &vk, &params).is_err());
+
+        let mut tampered = proof.clone();
+        tampered.challenge = FieldElement::random();
+        assert!(!vk.verify_pop(&tampered, &params));
+        let (_, vk_short) = keygen(3, &params);
+        assert!(!vk_short.verify_pop(&proof, &params));
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        let params = Params::new("test".as_bytes());
+        assert!(MasterSecret::new(&[1u8; 31]).is_err());
+        let master = MasterSecret::new(&[1u8; 32]).unwrap();
+
+        let keypair = master.derive_keypair(&[1, 2], Scheme::PS2016, 3, &params);
+        assert_eq!(keypair.messages_supported(), 3);
+        assert!(keypair.sigkey.is_key_for(&keypair.verkey, &params));
+        // Deterministic and same as deriving from the intermediate secret
+        let sk = master.derive_sigkey(&[1, 2], Scheme::PS2016, 3);
+        assert_eq!(sk.x, keypair.sigkey.x);
+        let sk = master.derive_child(1).derive_sigkey(&[2], Scheme::PS2016, 3);
+        assert_eq!(sk.y, keypair.sigkey.y);
+
+        // Different paths, schemes and sizes give different keys
+        assert_ne!(master.derive_sigkey(&[1, 3], Scheme::PS2016, 3).x, sk.x);
+        assert_ne!(master.derive_sigkey(&[2, 1], Scheme::PS2016, 3).x, sk.x);
+        assert_ne!(master.derive_sigkey(&[1, 2], Scheme::PS2016, 4).x, sk.x);
+        let sk_18 = master.derive_sigkey(&[1, 2], Scheme::PS2018, 3);
+        assert_ne!(sk_18.x, sk.x);
+        assert_eq!(sk_18.y.len(), 4);
+        assert_ne!(MasterSecret::random().derive_sigkey(&[1, 2], Scheme::PS2016, 3).x, sk.x);
+    }
+
+    #[test]
+    fn test_commitment_generators() {
+        let params = Params::new("test".as_bytes());
+        assert!(params.commitment_gens.is_none());
+        let params = Params::new_with_commitment_generators("test".as_bytes(), 3);
+        let gens = params.commitment_gens.clone().unwrap();
+        assert_eq!(gens.msg_count(), 3);
+        // Same label gives same generators
+        assert_eq!(CommitmentGenerators::new("test".as_bytes(), 3).h, gens.h);
+        assert_ne!(gens.h[0], gens.h[1]);
+        assert_ne!(gens.h_0, gens.h[0]);
+
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let blinding = FieldElement::random();
+        let mut messages = HashMap::new();
+        messages.insert(0, msgs[0].clone());
+        messages.insert(2, msgs[2].clone());
+        let comm = gens.commit(&messages, &blinding).unwrap();
+        assert_eq!(comm, &gens.h_0 * &blinding + &gens.h[0] * &msgs[0] + &gens.h[2] * &msgs[2]);
+        messages.insert(3, msgs[1].clone());
+        assert!(gens.commit(&messages, &blinding).is_err());
+
+        let json = serde_json::to_string(&params).unwrap();
+        let params_1: Params = serde_json::from_str(&json).unwrap();
+        assert_eq!(params_1.commitment_gens.unwrap().h, gens.h);
+    }
+
+    #[test]
+    fn test_params() {
+        let params = Params::new("test".as_bytes());
+        let params_1 = Params::new("test".as_bytes());
+        assert_eq!(params.g.to_bytes(), params_1.g.to_bytes());
+        assert_eq!(params.g_tilde.to_bytes(), params_1.g_tilde.to_bytes());
+        assert!(params.is_generated_from(&[], "test".as_bytes()));
+        assert!(!params.is_generated_from(&[], "test1".as_bytes()));
+        assert!(!params.is_generated_from("app".as_bytes(), "test".as_bytes()));
+
+        let params_2 = Params::new_with_dst("app".as_bytes(), "test".as_bytes()).unwrap();
+        assert_ne!(params_2.g, params.g);
+        assert_ne!(params_2.g_tilde, params.g_tilde);
+        // Lengths are part of the hashed input so moving bytes between dst and label changes the params
+        let params_3 = Params::new_with_dst("ap".as_bytes(), "ptest".as_bytes()).unwrap();
+        assert_ne!(params_3.g, params_2.g);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_sigkey() {
+        use zeroize::Zeroize;
+        let params = Params::new("test".as_bytes());
+        let (mut sk, vk) = keygen(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = crate::signature::Signature::new(&msgs, &sk, &params).unwrap();
+        assert!(sig.verify(msgs, &vk, &params).unwrap());
+        sk.zeroize();
+        assert!(sk.x.is_zero());
+        assert!(sk.y.is_empty());
+
+        let mut elem = FieldElement::random();
+        wipe(&mut elem);
+        assert!(elem.is_zero());
+    }
+
+    #[test]
+    fn test_keys_der() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+
+        let (sk_1, scheme) = Sigkey::from_der(&sk.to_der(Scheme::PS2016)).unwrap();
+        assert_eq!(scheme, Scheme::PS2016);
+        assert_eq!(sk_1.x, sk.x);
+        assert_eq!(sk_1.y, sk.y);
+        let (vk_1, scheme) = Verkey::from_der(&vk.to_der(Scheme::PS2016)).unwrap();
+        assert_eq!(scheme, Scheme::PS2016);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let (sk_1, scheme) = Sigkey::from_der(&sk.to_der(Scheme::PS2018)).unwrap();
+        assert_eq!(scheme, Scheme::PS2018);
+        assert_eq!(sk_1.y, sk.y);
+        let (_, scheme) = Verkey::from_der(&vk.to_der(Scheme::PS2018)).unwrap();
+        assert_eq!(scheme, Scheme::PS2018);
+
+        // Trailing bytes and wrong key type are rejected
+        let mut der = vk.to_der(Scheme::PS2018);
+        der.push(0);
+        assert!(Verkey::from_der(&der).is_err());
+        assert!(Verkey::from_der(&sk.to_der(Scheme::PS2018)).is_err());
+    }
+
+    #[test]
+    fn test_keys_der_malformed_message_count() {
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen_2018(3, &params);
+        let der_with_count = |count: u64| {
+            let mut contents = vec![];
+            der::write_uint(KEY_DER_VERSION, &mut contents);
+            der::write_uint(Scheme::PS2018.to_byte() as u64, &mut contents);
+            der::write_utf8_string(CURVE_ID, &mut contents);
+            der::write_uint(count, &mut contents);
+            der::write_octet_string(&vk.X_tilde.to_bytes(), &mut contents);
+            let mut rest_contents = vec![];
+            for Y in &vk.Y_tilde {
+                der::write_octet_string(&Y.to_bytes(), &mut rest_contents);
+            }
+            der::write_sequence(&rest_contents, &mut contents);
+            let mut out = vec![];
+            der::write_sequence(&contents, &mut out);
+            out
+        };
+        assert!(Verkey::from_der(&der_with_count(3)).is_ok());
+        for count in &[u64::max_value(), u64::max_value() - 1, 5] {
+            match Verkey::from_der(&der_with_count(*count)) {
+                Err(PSError::InvalidBytes { .. }) => (),
+                _ => panic!("Expected InvalidBytes for message count {}", count),
+            }
+        }
+        match Verkey::from_der(&der_with_count(4)) {
+            Err(PSError::UnsupportedNoOfMessages { .. }) => (),
+            _ => panic!("Expected UnsupportedNoOfMessages"),
+        }
+    }
+
+    #[test]
+    fn test_keys_der_other_group() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params);
+        let other_group = match wire::GROUP_MARKER {
+            GroupMarker::SignatureG1 => GroupMarker::SignatureG2,
+            GroupMarker::SignatureG2 => GroupMarker::SignatureG1,
+        };
+        // Curve ids of both groups have the same length so replacing keeps the DER valid
+        let replace_curve = |der: Vec<u8>| {
+            let pos = der
+                .windows(CURVE_ID.len())
+                .position(|w| w == CURVE_ID.as_bytes())
+                .unwrap();
+            let mut der = der;
+            der[pos..pos + CURVE_ID.len()].copy_from_slice(other_group.curve_id().as_bytes());
+            der
+        };
+
+        // Signing key from the other build is accepted and the verkey can be recreated
+        let (sk_1, _) = Sigkey::from_der(&replace_curve(sk.to_der(Scheme::PS2016))).unwrap();
+        let vk_1 = Verkey::from_sigkey(&sk_1, &params);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+
+        match Verkey::from_der(&replace_curve(vk.to_der(Scheme::PS2016))) {
+            Err(PSError::GroupMismatch { .. }) => (),
+            _ => panic!("Expected GroupMismatch"),
+        }
+        let mut bytes = vk.to_bytes(Scheme::PS2016);
+        bytes[2] = other_group.to_byte();
+        match Verkey::from_bytes(&bytes) {
+            Err(PSError::GroupMismatch { .. }) => (),
+            _ => panic!("Expected GroupMismatch"),
+        }
+    }
+
+    #[test]
+    fn test_verkey_to_string() {
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(5, &params);
+        assert_eq!(vk.to_bytes(Scheme::PS2016).len(), Verkey::serialized_size(5));
+        let mut buf = vec![0u8; vk.serialized_len()];
+        vk.write_into(Scheme::PS2018, &mut buf).unwrap();
+        assert_eq!(buf, vk.to_bytes(Scheme::PS2018));
+        let s = vk.to_multibase(Scheme::PS2016);
+        assert!(s.starts_with('z'));
+        let (vk_1, scheme) = Verkey::from_multibase(&s).unwrap();
+        assert_eq!(scheme, Scheme::PS2016);
+        assert_eq!(vk_1.X_tilde, vk.X_tilde);
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+        let (vk_2, scheme) =
+            Verkey::from_multibase(&multibase::encode(Base::Base64Url, &vk.to_bytes(Scheme::PS2018))).unwrap();
+        assert_eq!(scheme, Scheme::PS2018);
+        assert_eq!(vk_2.Y_tilde, vk.Y_tilde);
+        assert!(Verkey::from_multibase(&s[..s.len() - 1]).is_err());
+
+        let mut wrong_version = vk.to_bytes(Scheme::PS2016);
+        wrong_version[0] = wire::WIRE_FORMAT_VERSION + 1;
+        match Verkey::from_bytes(&wrong_version) {
+            Err(PSError::UnsupportedVersion { .. }) => (),
+            _ => panic!("Expected UnsupportedVersion"),
+        }
+    }
+
+    #[test]
+    fn test_verkey_fingerprint() {
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(5, &params);
+        let (_, other_vk) = keygen(5, &params);
+        assert_eq!(vk.fingerprint(Scheme::PS2016), vk.clone().fingerprint(Scheme::PS2016));
+        assert_ne!(vk.fingerprint(Scheme::PS2016), other_vk.fingerprint(Scheme::PS2016));
+        // Same key elements used with a different scheme have a different fingerprint
+        assert_ne!(vk.fingerprint(Scheme::PS2016), vk.fingerprint(Scheme::PS2018));
+    }
 }
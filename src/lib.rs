@@ -25,6 +25,19 @@ pub fn ate_2_pairing(
 ) -> GT {
     GT::ate_2_pairing(g2, g1, h2, h1)
 }
+/// Size in bytes of the byte representation of an element of `SignatureGroup`
+#[cfg(feature = "SignatureG2")]
+pub const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
+/// Size in bytes of the byte representation of an element of `VerkeyGroup`
+#[cfg(feature = "SignatureG2")]
+pub const VERKEY_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
+/// Identifier of the curve and the group of signatures, used in exported keys
+#[cfg(feature = "SignatureG2")]
+pub const CURVE_ID: &str = "BLS12-381/SignatureG2";
+#[cfg(feature = "SignatureG2")]
+pub fn ate_multi_pairing(elems: Vec<(&SignatureGroup, &VerkeyGroup)>) -> GT {
+    GT::ate_multi_pairing(elems.into_iter().map(|(s, v)| (v, s)).collect())
+}
 
 #[cfg(feature = "SignatureG1")]
 pub type SignatureGroup = amcl_wrapper::group_elem_g1::G1;
@@ -43,18 +56,34 @@ pub fn ate_2_pairing(
 ) -> GT {
     GT::ate_2_pairing(g1, g2, h1, h2)
 }
+#[cfg(feature = "SignatureG1")]
+pub const SIGNATURE_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG1_SIZE;
+#[cfg(feature = "SignatureG1")]
+pub const VERKEY_GROUP_SIZE: usize = amcl_wrapper::constants::GroupG2_SIZE;
+#[cfg(feature = "SignatureG1")]
+pub const CURVE_ID: &str = "BLS12-381/SignatureG1";
+#[cfg(feature = "SignatureG1")]
+pub fn ate_multi_pairing(elems: Vec<(&SignatureGroup, &VerkeyGroup)>) -> GT {
+    GT::ate_multi_pairing(elems)
+}
+
+/// Size in bytes of the byte representation of a field element
+pub const FIELD_ELEMENT_SIZE: usize = amcl_wrapper::constants::FieldElement_SIZE;
 
 extern crate rand;
 #[macro_use]
 extern crate failure;
 
 extern crate serde;
+extern crate sha3;
 #[macro_use]
 extern crate serde_derive;
 
 pub mod errors;
 #[macro_use]
 pub mod pok_vc;
+#[macro_use]
+pub mod xof;
 pub mod keys;
 pub mod pok_sig;
 pub mod signature;
@@ -62,3 +91,51 @@ pub mod blind_signature;
 pub mod multi_signature;
 pub mod signature_2018;
 pub mod pok_sig_2018;
+pub mod credential_update;
+pub mod verifiable_encryption;
+pub mod sps;
+pub mod presentation;
+pub mod issuance_receipt;
+pub mod document_binding;
+pub mod split_signing;
+pub mod partitioned_signing;
+pub mod lifecycle;
+pub mod key_update;
+pub mod vrf;
+pub mod vector_commitment;
+pub mod merkle;
+pub mod issuance_log;
+pub mod hierarchical_attributes;
+pub mod validation;
+pub mod der;
+pub mod multibase;
+pub mod views;
+pub mod wire;
+pub mod key_rotation;
+pub mod key_escrow;
+pub mod threshold;
+pub mod dkg;
+pub mod coconut;
+pub mod validity;
+pub mod linear_relations;
+pub mod issuer_hiding;
+pub mod or_composition;
+pub mod pseudonym;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "serde_hex")]
+pub mod serde_hex;
+#[cfg(feature = "encrypted_export")]
+pub mod encrypted_export;
+#[cfg(feature = "transcript")]
+pub mod transcript;
+#[cfg(feature = "range_proof")]
+pub mod range_proof;
+#[cfg(feature = "range_proof")]
+pub mod non_membership;
+#[cfg(feature = "range_proof")]
+pub mod predicates;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "test_vectors")]
+pub mod test_vectors;
@@ -0,0 +1,464 @@
+// State machines for the lifecycle of a credential (signature from the 2016 scheme) so that integrators do not need
+// to assemble the primitives themselves. Issuance: issuer sends an offer with the messages known to it, holder sends
+// a request with a commitment to the remaining messages and a proof of knowledge of its opening, issuer blind-signs
+// the commitment and holder unblinds, verifies and stores the credential. Presentation: verifier sends a presentation
+// request with the indices to be revealed, holder responds with a proof of knowledge of the signature, verifier
+// verifies it and optionally checks the revealed serial against a revocation list.
+// Each session is bound to a fresh nonce chosen by the issuer or verifier and each party's state is consumed by a
+// transition so a message cannot be replayed in another session or processed twice in the same session. Offers and
+// presentation requests carry an expiry time; the current time is passed by the caller (in any unit, seconds since
+// the UNIX epoch is recommended) so that the library does not depend on a clock. All states are serializable so that
+// they can be persisted between messages.
+
+use crate::blind_signature::{BlindSignature, BlindingKey, ProofSignatureGroup};
+use crate::credential_update::RevocationList;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{get_bytes_for_commitment_challenge, PoKOfCommitment, PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Sent by the issuer to the holder. The holder commits to the messages at indices not in `known_messages`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialOffer {
+    pub nonce: Vec<u8>,
+    pub known_messages: HashMap<usize, FieldElement>,
+    pub expires_at: u64,
+}
+
+/// Sent by the holder to the issuer in response to a `CredentialOffer`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialRequest {
+    pub nonce: Vec<u8>,
+    /// Commitment to the messages not known to the issuer
+    pub commitment: SignatureGroup,
+    /// Proof of knowledge of the opening of `commitment`
+    pub proof: ProofSignatureGroup,
+}
+
+/// Sent by the issuer to the holder in response to a `CredentialRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialResponse {
+    pub nonce: Vec<u8>,
+    pub blind_signature: Signature,
+}
+
+/// Credential stored by the holder after issuance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredCredential {
+    pub sig: Signature,
+    pub messages: Vec<FieldElement>,
+}
+
+/// Sent by the verifier to the holder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PresentationRequest {
+    pub nonce: Vec<u8>,
+    pub revealed_msg_indices: HashSet<usize>,
+    pub expires_at: u64,
+}
+
+/// Sent by the holder to the verifier in response to a `PresentationRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialPresentation {
+    pub nonce: Vec<u8>,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+    pub proof: PoKOfSignatureProof,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum IssuerSession {
+    Offered(CredentialOffer),
+    Issued { nonce: Vec<u8> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum HolderSession {
+    Requested {
+        offer: CredentialOffer,
+        messages: Vec<FieldElement>,
+        blinding: FieldElement,
+    },
+    Stored(StoredCredential),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum VerifierSession {
+    Requested(PresentationRequest),
+    Verified {
+        nonce: Vec<u8>,
+        revealed_msgs: HashMap<usize, FieldElement>,
+    },
+}
+
+impl IssuerSession {
+    /// Start an issuance session. At least one message should be left for the holder to commit to.
+    pub fn offer(known_messages: HashMap<usize, FieldElement>, expires_at: u64) -> (Self, CredentialOffer) {
+        let offer = CredentialOffer {
+            nonce: new_nonce(),
+            known_messages,
+            expires_at,
+        };
+        (IssuerSession::Offered(offer.clone()), offer)
+    }
+
+    /// Verify the holder's request and blind-sign its commitment
+    pub fn issue(
+        self,
+        request: &CredentialRequest,
+        now: u64,
+        sigkey: &Sigkey,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<(Self, CredentialResponse), PSError> {
+        let offer = match self {
+            IssuerSession::Offered(offer) => offer,
+            IssuerSession::Issued { .. } => {
+                return Err(PSError::InvalidStateTransition {
+                    msg: String::from("Credential already issued in this session"),
+                })
+            }
+        };
+        check_expiry(offer.expires_at, now)?;
+        check_nonce(&offer.nonce, &request.nonce)?;
+
+        let bases = commitment_bases(&offer.known_messages, blinding_key, params)?;
+        let mut chal_bytes = get_bytes_for_commitment_challenge(&bases, &request.proof, &request.commitment);
+        chal_bytes.extend_from_slice(&offer.nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        if !request
+            .proof
+            .verify(bases.as_slice(), &request.commitment, &challenge)?
+        {
+            return Err(PSError::GeneralError {
+                msg: String::from("Proof of knowledge of committed messages failed"),
+            });
+        }
+
+        let blind_signature = BlindSignature::new_with_known_messages_at_indices(
+            &request.commitment,
+            &offer.known_messages,
+            sigkey,
+            blinding_key,
+            params,
+        )?;
+        Ok((
+            IssuerSession::Issued {
+                nonce: offer.nonce.clone(),
+            },
+            CredentialResponse {
+                nonce: offer.nonce,
+                blind_signature,
+            },
+        ))
+    }
+}
+
+impl HolderSession {
+    /// Respond to an offer. `messages` are all the messages of the credential including the ones known to the
+    /// issuer, which must match the offer.
+    pub fn request(
+        offer: CredentialOffer,
+        messages: Vec<FieldElement>,
+        now: u64,
+        blinding_key: &BlindingKey,
+        params: &Params,
+    ) -> Result<(Self, CredentialRequest), PSError> {
+        check_expiry(offer.expires_at, now)?;
+        if messages.len() != blinding_key.msg_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: blinding_key.msg_count(),
+                given: messages.len(),
+            });
+        }
+        for (i, m) in &offer.known_messages {
+            if *i >= messages.len() || messages[*i] != *m {
+                return Err(PSError::GeneralError {
+                    msg: format!("Message at index {} does not match the offer", i),
+                });
+            }
+        }
+
+        let bases = commitment_bases(&offer.known_messages, blinding_key, params)?;
+        let blinding = FieldElement::random();
+        let mut exponents = committed_indices(&offer.known_messages, messages.len())
+            .iter()
+            .map(|i| messages[*i].clone())
+            .collect::<Vec<FieldElement>>();
+        exponents.push(blinding.clone());
+        let pok = PoKOfCommitment::init(&bases, exponents, &vec![None; bases.len()])?;
+        let commitment = pok.commitment().clone();
+
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.extend_from_slice(&offer.nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok.gen_proof(&challenge)?;
+        let request = CredentialRequest {
+            nonce: offer.nonce.clone(),
+            commitment,
+            proof,
+        };
+        Ok((
+            HolderSession::Requested {
+                offer,
+                messages,
+                blinding,
+            },
+            request,
+        ))
+    }
+
+    /// Unblind and verify the issued signature and store the credential
+    pub fn store(self, response: &CredentialResponse, vk: &Verkey, params: &Params) -> Result<Self, PSError> {
+        let (offer, messages, blinding) = match self {
+            HolderSession::Requested {
+                offer,
+                messages,
+                blinding,
+            } => (offer, messages, blinding),
+            HolderSession::Stored(_) => {
+                return Err(PSError::InvalidStateTransition {
+                    msg: String::from("Credential already stored"),
+                })
+            }
+        };
+        check_nonce(&offer.nonce, &response.nonce)?;
+        let sig = BlindSignature::unblind(&response.blind_signature, &blinding);
+        if !sig.verify(messages.clone(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Issued signature is invalid"),
+            });
+        }
+        Ok(HolderSession::Stored(StoredCredential { sig, messages }))
+    }
+
+    pub fn credential(&self) -> Option<&StoredCredential> {
+        match self {
+            HolderSession::Stored(cred) => Some(cred),
+            _ => None,
+        }
+    }
+}
+
+impl StoredCredential {
+    /// Respond to a presentation request
+    pub fn present(
+        &self,
+        request: &PresentationRequest,
+        now: u64,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<CredentialPresentation, PSError> {
+        check_expiry(request.expires_at, now)?;
+        let pok = PoKOfSignature::init(
+            &self.sig,
+            vk,
+            params,
+            self.messages.clone(),
+            None,
+            request.revealed_msg_indices.clone(),
+        )?;
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.extend_from_slice(&request.nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let revealed_msgs = request
+            .revealed_msg_indices
+            .iter()
+            .map(|i| (*i, self.messages[*i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        Ok(CredentialPresentation {
+            nonce: request.nonce.clone(),
+            revealed_msgs,
+            proof: pok.gen_proof(&challenge)?,
+        })
+    }
+}
+
+impl VerifierSession {
+    /// Start a presentation session
+    pub fn request(revealed_msg_indices: HashSet<usize>, expires_at: u64) -> (Self, PresentationRequest) {
+        let request = PresentationRequest {
+            nonce: new_nonce(),
+            revealed_msg_indices,
+            expires_at,
+        };
+        (VerifierSession::Requested(request.clone()), request)
+    }
+
+    /// Verify the presentation. If `revocation` is given as the index of the serial and the revocation list, the
+    /// serial must be revealed and not revoked.
+    pub fn verify(
+        self,
+        presentation: &CredentialPresentation,
+        now: u64,
+        vk: &Verkey,
+        params: &Params,
+        revocation: Option<(usize, &RevocationList)>,
+    ) -> Result<Self, PSError> {
+        let request = match self {
+            VerifierSession::Requested(request) => request,
+            VerifierSession::Verified { .. } => {
+                return Err(PSError::InvalidStateTransition {
+                    msg: String::from("Presentation already verified in this session"),
+                })
+            }
+        };
+        check_expiry(request.expires_at, now)?;
+        check_nonce(&request.nonce, &presentation.nonce)?;
+        let revealed_indices = presentation
+            .revealed_msgs
+            .keys()
+            .cloned()
+            .collect::<HashSet<usize>>();
+        if revealed_indices != request.revealed_msg_indices {
+            return Err(PSError::GeneralError {
+                msg: String::from("Revealed messages do not match the request"),
+            });
+        }
+
+        let mut chal_bytes = presentation
+            .proof
+            .get_bytes_for_challenge(revealed_indices, vk, params);
+        chal_bytes.extend_from_slice(&request.nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        if !presentation
+            .proof
+            .verify(vk, params, presentation.revealed_msgs.clone(), &challenge)?
+        {
+            return Err(PSError::GeneralError {
+                msg: String::from("Proof of knowledge of signature failed"),
+            });
+        }
+
+        if let Some((serial_idx, list)) = revocation {
+            match presentation.revealed_msgs.get(&serial_idx) {
+                Some(serial) if list.is_revoked(serial) => return Err(PSError::RevokedCredential),
+                Some(_) => (),
+                None => {
+                    return Err(PSError::GeneralError {
+                        msg: format!("Serial at index {} not revealed", serial_idx),
+                    })
+                }
+            }
+        }
+        Ok(VerifierSession::Verified {
+            nonce: request.nonce,
+            revealed_msgs: presentation.revealed_msgs.clone(),
+        })
+    }
+}
+
+fn new_nonce() -> Vec<u8> {
+    FieldElement::random().to_bytes()
+}
+
+fn check_expiry(expires_at: u64, now: u64) -> Result<(), PSError> {
+    if now > expires_at {
+        return Err(PSError::SessionExpired);
+    }
+    Ok(())
+}
+
+fn check_nonce(expected: &[u8], given: &[u8]) -> Result<(), PSError> {
+    if expected != given {
+        return Err(PSError::InvalidStateTransition {
+            msg: String::from("Nonce does not match the session"),
+        });
+    }
+    Ok(())
+}
+
+/// Indices of messages the holder commits to, in increasing order
+fn committed_indices(known_messages: &HashMap<usize, FieldElement>, msg_count: usize) -> Vec<usize> {
+    (0..msg_count)
+        .filter(|i| !known_messages.contains_key(i))
+        .collect()
+}
+
+/// Bases of the commitment, the `Y` of each committed message followed by `g`
+fn commitment_bases(
+    known_messages: &HashMap<usize, FieldElement>,
+    blinding_key: &BlindingKey,
+    params: &Params,
+) -> Result<Vec<SignatureGroup>, PSError> {
+    let committed = committed_indices(known_messages, blinding_key.msg_count());
+    if committed.is_empty() || committed.len() + known_messages.len() != blinding_key.msg_count() {
+        return Err(PSError::GeneralError {
+            msg: String::from("Offer should leave at least 1 message to be committed by the holder"),
+        });
+    }
+    let mut bases = committed
+        .iter()
+        .map(|i| blinding_key.Y[*i].clone())
+        .collect::<Vec<SignatureGroup>>();
+    bases.push(params.g.clone());
+    Ok(bases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_lifecycle() {
+        let count_msgs = 5;
+        let serial_idx = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let blinding_key = BlindingKey::new(&sk, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+
+        // Issuer knows the last 2 messages, including the serial
+        let known_msgs = (3..count_msgs)
+            .map(|i| (i, msgs[i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        let (issuer, offer) = IssuerSession::offer(known_msgs, 100);
+
+        // Holder cannot respond after the offer expires
+        assert!(HolderSession::request(offer.clone(), msgs.clone(), 101, &blinding_key, &params).is_err());
+        let (holder, request) = HolderSession::request(offer, msgs.clone(), 10, &blinding_key, &params).unwrap();
+
+        let (issuer, response) = issuer.issue(&request, 20, &sk, &blinding_key, &params).unwrap();
+        // Request cannot be replayed
+        assert!(issuer.issue(&request, 20, &sk, &blinding_key, &params).is_err());
+
+        let holder = holder.store(&response, &vk, &params).unwrap();
+        let cred = holder.credential().unwrap();
+        assert_eq!(cred.messages, msgs);
+
+        let mut revealed = HashSet::new();
+        revealed.insert(1);
+        revealed.insert(serial_idx);
+        let mut revocation_list = RevocationList::new();
+
+        let (verifier, pres_request) = VerifierSession::request(revealed.clone(), 200);
+        let presentation = cred.present(&pres_request, 150, &vk, &params).unwrap();
+        let verified = verifier
+            .clone()
+            .verify(&presentation, 160, &vk, &params, Some((serial_idx, &revocation_list)))
+            .unwrap();
+        // Presentation cannot be replayed in the same session
+        assert!(verified
+            .verify(&presentation, 160, &vk, &params, None)
+            .is_err());
+        // Or in a different session
+        let (other_verifier, _) = VerifierSession::request(revealed.clone(), 200);
+        assert!(other_verifier
+            .verify(&presentation, 160, &vk, &params, None)
+            .is_err());
+        // Verifier rejects after the request expires
+        assert!(verifier
+            .clone()
+            .verify(&presentation, 201, &vk, &params, None)
+            .is_err());
+
+        revocation_list.revoke(&msgs[serial_idx]);
+        match verifier.verify(&presentation, 160, &vk, &params, Some((serial_idx, &revocation_list))) {
+            Err(PSError::RevokedCredential) => (),
+            _ => panic!("Revoked credential should be rejected"),
+        }
+    }
+}
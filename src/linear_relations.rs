@@ -0,0 +1,214 @@
+// Proofs of linear relations over hidden messages, a_1*m_i + a_2*m_j + ... = c with public coefficients and constant,
+// as part of the proof of knowledge of a signature (2016 scheme). The response for a hidden message m_k is
+// s_k = r_k - challenge*m_k, so if the blindings satisfy a_1*r_i + a_2*r_j + ... = 0 then
+// a_1*s_i + a_2*s_j + ... = -challenge*c, which the verifier checks. The prover picks random blindings for all but the
+// last message of a relation and solves for the blinding of the last. The relations must be hashed into the challenge
+// on both sides, using `relations_to_bytes`, and the messages of different relations must be disjoint.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::HashSet;
+
+/// Label of the relations in the challenge bytes
+pub const LINEAR_RELATION_DST: &[u8] = b"PS-SIG-linear-relation";
+
+/// The relation sum of `coefficient * m_index` over `terms` equals `constant`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LinearRelation {
+    pub terms: Vec<(usize, FieldElement)>,
+    pub constant: FieldElement,
+}
+
+impl LinearRelation {
+    /// Fails if there are no terms, an index is repeated or a coefficient is 0
+    pub fn new(terms: Vec<(usize, FieldElement)>, constant: FieldElement) -> Result<Self, PSError> {
+        if terms.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Relation should have at least 1 term"),
+            });
+        }
+        let mut indices = HashSet::new();
+        for (i, a) in &terms {
+            if !indices.insert(*i) {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} repeated in relation", i),
+                });
+            }
+            if a.is_zero() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Coefficient for index {} is 0", i),
+                });
+            }
+        }
+        Ok(Self { terms, constant })
+    }
+
+    /// Whether `messages` satisfy the relation
+    pub fn is_satisfied(&self, messages: &[FieldElement]) -> bool {
+        let mut sum = FieldElement::zero();
+        for (i, a) in &self.terms {
+            match messages.get(*i) {
+                Some(m) => sum += a * m,
+                None => return false,
+            }
+        }
+        sum == self.constant
+    }
+
+    /// No of terms as 4 byte big-endian, then each index as 4 byte big-endian followed by its coefficient, then the
+    /// constant
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&(self.terms.len() as u32).to_be_bytes());
+        for (i, a) in &self.terms {
+            bytes.extend_from_slice(&(*i as u32).to_be_bytes());
+            bytes.append(&mut a.to_bytes());
+        }
+        bytes.append(&mut self.constant.to_bytes());
+        bytes
+    }
+}
+
+/// Bytes of the relations to append to the challenge bytes of the proof by both the prover and the verifier
+pub fn relations_to_bytes(relations: &[LinearRelation]) -> Vec<u8> {
+    let mut bytes = LINEAR_RELATION_DST.to_vec();
+    bytes.extend_from_slice(&(relations.len() as u32).to_be_bytes());
+    for r in relations {
+        bytes.append(&mut r.to_bytes());
+    }
+    bytes
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and proving that the hidden
+/// messages satisfy `relations`. Fails if a relation involves a revealed message or is not satisfied by `messages`
+/// or if 2 relations share a message.
+pub fn init_pok_with_relations(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    relations: &[LinearRelation],
+) -> Result<PoKOfSignature, PSError> {
+    let mut blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let mut constrained = HashSet::new();
+    for relation in relations {
+        if !relation.is_satisfied(&messages) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Messages do not satisfy the relation"),
+            });
+        }
+        let mut positions = vec![];
+        for (i, _) in &relation.terms {
+            if !constrained.insert(*i) {
+                return Err(PSError::GeneralError {
+                    msg: format!("Message at index {} is in more than 1 relation", i),
+                });
+            }
+            positions.push(hidden_message_position(*i, &revealed_msg_indices)?);
+        }
+        // Blinding of the last term is -(a_1*r_1 + ... + a_{n-1}*r_{n-1}) / a_n
+        let n = relation.terms.len() - 1;
+        let mut sum = FieldElement::zero();
+        for k in 0..n {
+            sum += &relation.terms[k].1 * &blindings[positions[k]];
+        }
+        blindings[positions[n]] = &FieldElement::zero() - &(&sum * &relation.terms[n].1.inverse());
+    }
+    PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)
+}
+
+/// Check that the responses of the hidden messages satisfy `relations` for `challenge`. The proof itself must be
+/// verified separately with the same challenge.
+pub fn verify_relations(
+    proof: &PoKOfSignatureProof,
+    revealed_msg_indices: &HashSet<usize>,
+    relations: &[LinearRelation],
+    challenge: &FieldElement,
+) -> Result<bool, PSError> {
+    for relation in relations {
+        // a_1*s_1 + ... + a_n*s_n + challenge*c == 0
+        let mut sum = challenge * &relation.constant;
+        for (i, a) in &relation.terms {
+            sum += a * &proof.get_resp_for_hidden_message(*i, revealed_msg_indices)?;
+        }
+        if !sum.is_zero() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pok_with_linear_relations() {
+        // Amounts at 1, 2 and 3 sum to the invoice total, 2*m_4 - m_5 = 7, the total at 0 is revealed
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(6, &params);
+        let amounts = [120u64, 35, 845];
+        let total = FieldElement::from(amounts.iter().sum::<u64>());
+        let m_5 = FieldElement::random();
+        let m_4 = &(&m_5 + &FieldElement::from(7u64)) * &FieldElement::from(2u64).inverse();
+        let msgs = vec![
+            total.clone(),
+            FieldElement::from(amounts[0]),
+            FieldElement::from(amounts[1]),
+            FieldElement::from(amounts[2]),
+            m_4,
+            m_5,
+        ];
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let one = FieldElement::one();
+        let minus_one = &FieldElement::zero() - &one;
+        let relations = vec![
+            LinearRelation::new(vec![(1, one.clone()), (2, one.clone()), (3, one.clone())], total.clone()).unwrap(),
+            LinearRelation::new(vec![(4, FieldElement::from(2u64)), (5, minus_one.clone())], FieldElement::from(7u64))
+                .unwrap(),
+        ];
+        let mut revealed = HashSet::new();
+        revealed.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, total.clone());
+
+        let pok = init_pok_with_relations(&sig, &vk, &params, msgs.clone(), revealed.clone(), &relations).unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.append(&mut relations_to_bytes(&relations));
+        let proof = pok.gen_proof(&FieldElement::from_msg_hash(&chal_bytes)).unwrap();
+
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+        chal_bytes.append(&mut relations_to_bytes(&relations));
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+        assert!(verify_relations(&proof, &revealed, &relations, &challenge).unwrap());
+
+        // A different total
+        let wrong = vec![LinearRelation::new(
+            vec![(1, one.clone()), (2, one.clone()), (3, one.clone())],
+            &total + &one,
+        )
+        .unwrap()];
+        assert!(!verify_relations(&proof, &revealed, &wrong, &challenge).unwrap());
+
+        // Relations not satisfied, over a revealed message or sharing a message are rejected
+        assert!(init_pok_with_relations(&sig, &vk, &params, msgs.clone(), revealed.clone(), &wrong).is_err());
+        let over_revealed =
+            vec![LinearRelation::new(vec![(0, one.clone()), (1, minus_one)], &total - &msgs[1]).unwrap()];
+        assert!(init_pok_with_relations(&sig, &vk, &params, msgs.clone(), revealed.clone(), &over_revealed).is_err());
+        let overlapping = vec![relations[0].clone(), relations[0].clone()];
+        assert!(init_pok_with_relations(&sig, &vk, &params, msgs, revealed, &overlapping).is_err());
+
+        assert!(LinearRelation::new(vec![], total.clone()).is_err());
+        assert!(LinearRelation::new(vec![(1, one.clone()), (1, one.clone())], total.clone()).is_err());
+        assert!(LinearRelation::new(vec![(1, FieldElement::zero())], total).is_err());
+    }
+}
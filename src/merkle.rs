@@ -0,0 +1,304 @@
+// Append-only Merkle tree with inclusion and consistency proofs. The tree shape and proofs follow RFC 6962
+// (Certificate Transparency) with SHA3-256 as the hash: a tree over n leaves is split into a left subtree over the
+// largest power of 2 less than n leaves and a right subtree over the rest. Leaves and internal nodes are hashed with
+// different prefixes so that a leaf cannot be passed off as an internal node.
+
+use crate::errors::PSError;
+use sha3::{Digest, Sha3_256};
+
+pub const MERKLE_HASH_SIZE: usize = 32;
+
+pub type MerkleHash = [u8; MERKLE_HASH_SIZE];
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+pub fn hash_leaf(data: &[u8]) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.input(&[LEAF_PREFIX]);
+    hasher.input(data);
+    to_merkle_hash(hasher)
+}
+
+pub fn hash_node(left: &MerkleHash, right: &MerkleHash) -> MerkleHash {
+    let mut hasher = Sha3_256::new();
+    hasher.input(&[NODE_PREFIX]);
+    hasher.input(left);
+    hasher.input(right);
+    to_merkle_hash(hasher)
+}
+
+fn to_merkle_hash(hasher: Sha3_256) -> MerkleHash {
+    let mut hash = [0u8; MERKLE_HASH_SIZE];
+    hash.copy_from_slice(hasher.result().as_slice());
+    hash
+}
+
+/// Largest power of 2 less than `n`, `n` should be greater than 1
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MerkleTree {
+    leaves: Vec<MerkleHash>,
+}
+
+/// Proof that a leaf is at `leaf_index` in the tree of size `tree_size`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleInclusionProof {
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    pub path: Vec<MerkleHash>,
+}
+
+/// Proof that the tree of size `old_size` is a prefix of the tree of size `new_size`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MerkleConsistencyProof {
+    pub old_size: usize,
+    pub new_size: usize,
+    pub path: Vec<MerkleHash>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a leaf and return its index
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        self.leaves.push(hash_leaf(data));
+        self.leaves.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Root of the current tree
+    pub fn root(&self) -> MerkleHash {
+        Self::subtree_root(&self.leaves)
+    }
+
+    /// Root of the tree when it had `size` leaves
+    pub fn root_at(&self, size: usize) -> Result<MerkleHash, PSError> {
+        self.check_size(size)?;
+        Ok(Self::subtree_root(&self.leaves[..size]))
+    }
+
+    pub fn inclusion_proof(&self, leaf_index: usize) -> Result<MerkleInclusionProof, PSError> {
+        self.inclusion_proof_at(leaf_index, self.len())
+    }
+
+    /// Inclusion proof in the tree when it had `tree_size` leaves
+    pub fn inclusion_proof_at(&self, leaf_index: usize, tree_size: usize) -> Result<MerkleInclusionProof, PSError> {
+        self.check_size(tree_size)?;
+        if leaf_index >= tree_size {
+            return Err(PSError::GeneralError {
+                msg: format!("Leaf index {} should be less than {}", leaf_index, tree_size),
+            });
+        }
+        let mut path = vec![];
+        Self::build_inclusion_path(leaf_index, &self.leaves[..tree_size], &mut path);
+        Ok(MerkleInclusionProof {
+            leaf_index,
+            tree_size,
+            path,
+        })
+    }
+
+    /// Consistency proof between the tree of size `old_size` and the current tree
+    pub fn consistency_proof(&self, old_size: usize) -> Result<MerkleConsistencyProof, PSError> {
+        self.check_size(old_size)?;
+        let mut path = vec![];
+        if old_size > 0 {
+            Self::build_consistency_path(old_size, &self.leaves, true, &mut path);
+        }
+        Ok(MerkleConsistencyProof {
+            old_size,
+            new_size: self.len(),
+            path,
+        })
+    }
+
+    fn check_size(&self, size: usize) -> Result<(), PSError> {
+        if size > self.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Tree size {} should not be more than {}", size, self.len()),
+            });
+        }
+        Ok(())
+    }
+
+    fn subtree_root(leaves: &[MerkleHash]) -> MerkleHash {
+        match leaves.len() {
+            0 => to_merkle_hash(Sha3_256::new()),
+            1 => leaves[0],
+            n => {
+                let k = split_point(n);
+                hash_node(&Self::subtree_root(&leaves[..k]), &Self::subtree_root(&leaves[k..]))
+            }
+        }
+    }
+
+    fn build_inclusion_path(m: usize, leaves: &[MerkleHash], path: &mut Vec<MerkleHash>) {
+        let n = leaves.len();
+        if n <= 1 {
+            return;
+        }
+        let k = split_point(n);
+        if m < k {
+            Self::build_inclusion_path(m, &leaves[..k], path);
+            path.push(Self::subtree_root(&leaves[k..]));
+        } else {
+            Self::build_inclusion_path(m - k, &leaves[k..], path);
+            path.push(Self::subtree_root(&leaves[..k]));
+        }
+    }
+
+    fn build_consistency_path(m: usize, leaves: &[MerkleHash], complete: bool, path: &mut Vec<MerkleHash>) {
+        let n = leaves.len();
+        if m == n {
+            if !complete {
+                path.push(Self::subtree_root(leaves));
+            }
+            return;
+        }
+        let k = split_point(n);
+        if m <= k {
+            Self::build_consistency_path(m, &leaves[..k], complete, path);
+            path.push(Self::subtree_root(&leaves[k..]));
+        } else {
+            Self::build_consistency_path(m - k, &leaves[k..], false, path);
+            path.push(Self::subtree_root(&leaves[..k]));
+        }
+    }
+}
+
+impl MerkleInclusionProof {
+    /// Verify that `data` is the leaf at `leaf_index` in the tree with root `root`
+    pub fn verify(&self, data: &[u8], root: &MerkleHash) -> bool {
+        self.verify_leaf_hash(&hash_leaf(data), root)
+    }
+
+    pub fn verify_leaf_hash(&self, leaf_hash: &MerkleHash, root: &MerkleHash) -> bool {
+        if self.leaf_index >= self.tree_size {
+            return false;
+        }
+        let mut f_n = self.leaf_index;
+        let mut s_n = self.tree_size - 1;
+        let mut r = *leaf_hash;
+        for p in &self.path {
+            if s_n == 0 {
+                return false;
+            }
+            if f_n & 1 == 1 || f_n == s_n {
+                r = hash_node(p, &r);
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            } else {
+                r = hash_node(&r, p);
+            }
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        s_n == 0 && r == *root
+    }
+}
+
+impl MerkleConsistencyProof {
+    /// Verify that the tree with root `old_root` is a prefix of the tree with root `new_root`
+    pub fn verify(&self, old_root: &MerkleHash, new_root: &MerkleHash) -> bool {
+        if self.old_size > self.new_size {
+            return false;
+        }
+        if self.old_size == self.new_size {
+            return self.path.is_empty() && old_root == new_root;
+        }
+        if self.old_size == 0 {
+            // Empty tree is a prefix of every tree
+            return self.path.is_empty();
+        }
+        let mut path = self.path.clone();
+        if self.old_size.is_power_of_two() {
+            path.insert(0, *old_root);
+        }
+        if path.is_empty() {
+            return false;
+        }
+        let mut f_n = self.old_size - 1;
+        let mut s_n = self.new_size - 1;
+        while f_n & 1 == 1 {
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        let mut f_r = path[0];
+        let mut s_r = path[0];
+        for c in &path[1..] {
+            if s_n == 0 {
+                return false;
+            }
+            if f_n & 1 == 1 || f_n == s_n {
+                f_r = hash_node(c, &f_r);
+                s_r = hash_node(c, &s_r);
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            } else {
+                s_r = hash_node(&s_r, c);
+            }
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        s_n == 0 && f_r == *old_root && s_r == *new_root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_inclusion_proof() {
+        let mut tree = MerkleTree::new();
+        for i in 0..20u64 {
+            tree.push(&i.to_be_bytes());
+            let root = tree.root();
+            for j in 0..=i {
+                let proof = tree.inclusion_proof(j as usize).unwrap();
+                assert!(proof.verify(&j.to_be_bytes(), &root));
+                assert!(!proof.verify(&(j + 1).to_be_bytes(), &root));
+            }
+        }
+        assert!(tree.inclusion_proof(20).is_err());
+    }
+
+    #[test]
+    fn test_merkle_consistency_proof() {
+        let mut tree = MerkleTree::new();
+        for i in 0..20u64 {
+            tree.push(&i.to_be_bytes());
+        }
+        let new_root = tree.root();
+        for old_size in 0..=20 {
+            let old_root = tree.root_at(old_size).unwrap();
+            let proof = tree.consistency_proof(old_size).unwrap();
+            assert!(proof.verify(&old_root, &new_root));
+            if old_size > 0 && old_size < 20 {
+                let other_root = tree.root_at(old_size - 1).unwrap();
+                assert!(!proof.verify(&other_root, &new_root));
+            }
+        }
+    }
+}
@@ -1,16 +1,23 @@
-use crate::keys::{Verkey, Params};
+use crate::keys::{Verkey, VerkeyFields, Params, PoPProof, Scheme};
 use crate::{VerkeyGroup, SignatureGroup};
 use crate::amcl_wrapper::group_elem::GroupElement;
 use crate::signature::Signature;
 use crate::signature_2018::Signature as Signature18;
 use amcl_wrapper::field_elem::FieldElement;
 use crate::errors::PSError;
+use crate::validation::{deserialize_validated, Validate};
+use std::collections::BTreeSet;
 
 /// PS multi-signatures using the same idea as BLS multi-signatures.
 
-// TODO: Add Proof of possesion of private key. Can be a verifiable signature on a standard message or
-// can be a proof of knowledge of secret key. Proof of knowledge should be better since it will not involve any pairing during verification.
-// But is signature sufficient for elements of both group?
+// Aggregation is only secure if each signer has proven possession of its signing key (`keys::PoPProof`) otherwise a
+// rogue signer can choose its verkey as a function of others' verkeys. `AggregatedVerkeyFast::from_verkeys_with_pop`
+// checks the proofs, as does `SignerRegistry` which only registers verkeys with a valid proof and
+// `AggregatedVerkeyFast::from_registry` which aggregates only registered verkeys.
+// `AggregatedVerkey` and `MultiSignature` don't need the proofs. Like in MuSig and the BLS multi-signatures of Boneh,
+// Drijvers and Neven, each verkey and signature is multiplied by a coefficient obtained by hashing the verkey with
+// the set of all verkeys so a rogue signer can't choose its verkey to cancel others. This costs a scalar
+// multiplication per verkey and per signature when aggregating.
 
 pub struct AggregatedVerkeyFast {}
 
@@ -25,6 +32,13 @@ impl AggregatedVerkeyFast {
         if !ver_keys.iter().all(|vk| vk.Y_tilde.len() == y_len) {
             return Err(PSError::IncompatibleVerkeysForAggregation)
         }
+        // Metadata is kept only if it is the same for all keys
+        let metadata = ver_keys[0].metadata.clone();
+        let metadata = if ver_keys.iter().all(|vk| vk.metadata == metadata) {
+            metadata
+        } else {
+            None
+        };
         let mut X_tilde = VerkeyGroup::new();
         let mut Y_tilde = (0..y_len).map(|_| VerkeyGroup::new()).collect::<Vec<VerkeyGroup>>();
         for vk in ver_keys {
@@ -33,7 +47,376 @@ impl AggregatedVerkeyFast {
                 Y_tilde[i] += &vk.Y_tilde[i];
             }
         }
-        Ok(Verkey {X_tilde, Y_tilde})
+        Ok(Verkey {X_tilde, Y_tilde, metadata})
+    }
+
+    /// Same as `from_verkeys` but first verifies the proof of possession of the signing key of each verkey
+    pub fn from_verkeys_with_pop(ver_keys: Vec<(&Verkey, &PoPProof)>, params: &Params) -> Result<Verkey, PSError> {
+        for (i, (vk, pop)) in ver_keys.iter().enumerate() {
+            if !vk.verify_pop(pop, params) {
+                return Err(PSError::GeneralError {
+                    msg: format!("Invalid proof of possession for verkey at index {}", i),
+                });
+            }
+        }
+        Self::from_verkeys(ver_keys.into_iter().map(|(vk, _)| vk).collect())
+    }
+
+    /// Aggregate the verkeys registered at `indices` in `registry`, fails if any index is not registered
+    pub fn from_registry(registry: &SignerRegistry, indices: &[usize]) -> Result<Verkey, PSError> {
+        let mut ver_keys = Vec::with_capacity(indices.len());
+        for (j, i) in indices.iter().enumerate() {
+            if indices[..j].contains(i) {
+                return Err(PSError::GeneralError {
+                    msg: format!("Signer index {} is repeated", i),
+                });
+            }
+            ver_keys.push(registry.get(*i).ok_or_else(|| PSError::GeneralError {
+                msg: format!("No verkey registered at index {}", i),
+            })?);
+        }
+        Self::from_verkeys(ver_keys)
+    }
+}
+
+/// Verkeys of signers, each registered only with a valid proof of possession of its signing key so aggregating
+/// them is safe against rogue keys
+#[derive(Clone, Debug, Default)]
+pub struct SignerRegistry {
+    verkeys: Vec<Verkey>,
+}
+
+impl SignerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `vk` after verifying `pop`, returns the index of the signer. Fails if the proof is invalid or the
+    /// verkey is already registered.
+    pub fn register(&mut self, vk: Verkey, pop: &PoPProof, params: &Params) -> Result<usize, PSError> {
+        if !vk.verify_pop(pop, params) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Invalid proof of possession"),
+            });
+        }
+        if self.verkeys.iter().any(|v| v.X_tilde == vk.X_tilde && v.Y_tilde == vk.Y_tilde) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Verkey already registered"),
+            });
+        }
+        self.verkeys.push(vk);
+        Ok(self.verkeys.len() - 1)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Verkey> {
+        self.verkeys.get(index)
+    }
+
+    /// Registered verkeys in order of registration, e.g. the registry of `MultiSigWithParticipants::verify`
+    pub fn verkeys(&self) -> &[Verkey] {
+        &self.verkeys
+    }
+
+    pub fn len(&self) -> usize {
+        self.verkeys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verkeys.is_empty()
+    }
+}
+
+/// Aggregate of the verkeys of a changing set of signers, like `AggregatedVerkeyFast`, updated as signers are
+/// added or removed rather than aggregated again. It is a plain sum like `AggregatedVerkeyFast` and not the
+/// rogue-key resistant `AggregatedVerkey` (whose coefficients change with every change of the set) so only keys whose
+/// possession has been proven (`keys::PoPProof`) should be added. The fingerprints of the added keys are kept so a key
+/// that was not added can't be removed and a key can't be added twice.
+#[derive(Clone, Debug, Serialize)]
+pub struct IncrementalAggregatedVerkey {
+    verkey: Verkey,
+    signers: BTreeSet<[u8; 32]>,
+}
+
+/// Fields of `IncrementalAggregatedVerkey` as deserialized, before validation
+#[derive(Deserialize)]
+struct IncrementalAggregatedVerkeyFields {
+    verkey: VerkeyFields,
+    signers: BTreeSet<[u8; 32]>,
+}
+
+impl<'de> serde::Deserialize<'de> for IncrementalAggregatedVerkey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_validated(deserializer, |f: IncrementalAggregatedVerkeyFields| IncrementalAggregatedVerkey {
+            verkey: f.verkey.into_verkey_unchecked(),
+            signers: f.signers,
+        })
+    }
+}
+
+impl Validate for IncrementalAggregatedVerkey {
+    /// The aggregate of no signers is the identity, else it is validated like a verkey
+    fn validate(&self) -> Result<(), PSError> {
+        if !self.signers.is_empty() {
+            return self.verkey.validate();
+        }
+        if self.verkey.X_tilde.is_identity() && self.verkey.Y_tilde.iter().all(|y| y.is_identity()) {
+            Ok(())
+        } else {
+            Err(PSError::InvalidVerkey {
+                msg: String::from("Aggregate of no signers should be the identity"),
+            })
+        }
+    }
+}
+
+impl IncrementalAggregatedVerkey {
+    /// Aggregate of no signers for keys of size `key_size`, i.e. `Y_tilde` of `key_size` elements
+    pub fn new(key_size: usize) -> Self {
+        Self {
+            verkey: Verkey {
+                X_tilde: VerkeyGroup::identity(),
+                Y_tilde: vec![VerkeyGroup::identity(); key_size],
+                metadata: None,
+            },
+            signers: BTreeSet::new(),
+        }
+    }
+
+    pub fn from_verkeys(ver_keys: Vec<&Verkey>) -> Result<Self, PSError> {
+        let key_size = match ver_keys.first() {
+            Some(vk) => vk.Y_tilde.len(),
+            None => {
+                return Err(PSError::GeneralError {
+                    msg: String::from("Provide at least one verkey"),
+                })
+            }
+        };
+        let mut avk = Self::new(key_size);
+        for vk in ver_keys {
+            avk.add_signer(vk)?;
+        }
+        Ok(avk)
+    }
+
+    /// Fails if the key is not of the same size as the others or was already added
+    pub fn add_signer(&mut self, vk: &Verkey) -> Result<(), PSError> {
+        self.check_compat(vk)?;
+        if !self.signers.insert(Self::signer_id(vk)) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signer was already added"),
+            });
+        }
+        self.verkey.X_tilde += &vk.X_tilde;
+        for (y, vk_y) in self.verkey.Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+            *y += vk_y;
+        }
+        self.update_metadata(vk);
+        Ok(())
+    }
+
+    /// Fails if the key was not added
+    pub fn remove_signer(&mut self, vk: &Verkey) -> Result<(), PSError> {
+        self.check_compat(vk)?;
+        if !self.signers.remove(&Self::signer_id(vk)) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signer was not added"),
+            });
+        }
+        self.verkey.X_tilde += &vk.X_tilde.negation();
+        for (y, vk_y) in self.verkey.Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+            *y += &vk_y.negation();
+        }
+        Ok(())
+    }
+
+    /// The aggregated verkey to verify multi-signatures of the current signers
+    pub fn verkey(&self) -> &Verkey {
+        &self.verkey
+    }
+
+    pub fn signer_count(&self) -> usize {
+        self.signers.len()
+    }
+
+    pub fn contains(&self, vk: &Verkey) -> bool {
+        self.signers.contains(&Self::signer_id(vk))
+    }
+
+    /// Fingerprint of the key elements only so that the metadata of a key does not change its identity
+    fn signer_id(vk: &Verkey) -> [u8; 32] {
+        Verkey {
+            X_tilde: vk.X_tilde.clone(),
+            Y_tilde: vk.Y_tilde.clone(),
+            metadata: None,
+        }
+        .fingerprint(Scheme::PS2016)
+    }
+
+    fn check_compat(&self, vk: &Verkey) -> Result<(), PSError> {
+        if vk.Y_tilde.len() != self.verkey.Y_tilde.len() {
+            return Err(PSError::IncompatibleVerkeysForAggregation)
+        }
+        Ok(())
+    }
+
+    /// Metadata is kept only if it is the same for all keys added. Removing keys does not restore it.
+    fn update_metadata(&mut self, vk: &Verkey) {
+        if self.signers.len() == 1 {
+            self.verkey.metadata = vk.metadata.clone();
+        } else if self.verkey.metadata != vk.metadata {
+            self.verkey.metadata = None;
+        }
+    }
+}
+
+/// Multi-signature (2016 scheme) with the signers that contributed to it, as a bitmap of indices into a registry
+/// of verkeys known to the verifier. Bit i, i.e. bit `i % 8` of byte `i / 8`, is set if the signer at index i
+/// signed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiSigWithParticipants {
+    pub sig: Signature,
+    pub participants: Vec<u8>,
+}
+
+/// Same as `MultiSigWithParticipants` but for the 2018 scheme
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiSigWithParticipants2018 {
+    pub sig: Signature18,
+    pub participants: Vec<u8>,
+}
+
+/// Bitmap of `indices` for a registry of `registry_size` signers, fails if an index is repeated or out of range
+fn participants_bitmap(indices: &[usize], registry_size: usize) -> Result<Vec<u8>, PSError> {
+    let mut bitmap = vec![0u8; (registry_size + 7) / 8];
+    for i in indices {
+        if *i >= registry_size || (bitmap[i / 8] >> (i % 8)) & 1 == 1 {
+            return Err(PSError::GeneralError {
+                msg: format!("Signer index {} is repeated or not less than {}", i, registry_size),
+            });
+        }
+        bitmap[i / 8] |= 1 << (i % 8);
+    }
+    Ok(bitmap)
+}
+
+fn bitmap_indices(bitmap: &[u8]) -> Vec<usize> {
+    (0..bitmap.len() * 8).filter(|i| (bitmap[i / 8] >> (i % 8)) & 1 == 1).collect()
+}
+
+/// Verkeys of `registry` at the indices set in `bitmap`
+fn participant_verkeys<'a>(bitmap: &[u8], registry: &'a [Verkey]) -> Result<Vec<&'a Verkey>, PSError> {
+    if bitmap.len() != (registry.len() + 7) / 8 {
+        return Err(PSError::GeneralError {
+            msg: format!("Bitmap of {} bytes for a registry of {} signers", bitmap.len(), registry.len()),
+        });
+    }
+    bitmap_indices(bitmap)
+        .into_iter()
+        .map(|i| {
+            registry.get(i).ok_or_else(|| PSError::GeneralError {
+                msg: format!("Signer index {} not in the registry", i),
+            })
+        })
+        .collect()
+}
+
+impl MultiSigWithParticipants {
+    /// Aggregate the signatures of the signers at the given indices of a registry of `registry_size` signers
+    pub fn new(sigs: Vec<(usize, &Signature)>, registry_size: usize) -> Result<Self, PSError> {
+        let indices = sigs.iter().map(|(i, _)| *i).collect::<Vec<usize>>();
+        let participants = participants_bitmap(&indices, registry_size)?;
+        let sig = MultiSignatureFast::from_sigs(sigs.into_iter().map(|(_, s)| s).collect())?;
+        Ok(Self { sig, participants })
+    }
+
+    /// Indices of the signers in the registry
+    pub fn participant_indices(&self) -> Vec<usize> {
+        bitmap_indices(&self.participants)
+    }
+
+    /// Verify with the aggregate of the verkeys of the participants in `registry`. The registry should only have
+    /// verkeys whose possession was proven.
+    pub fn verify(&self, messages: Vec<FieldElement>, registry: &[Verkey], params: &Params) -> Result<bool, PSError> {
+        MultiSignatureFast::verify(&self.sig, messages, participant_verkeys(&self.participants, registry)?, params)
+    }
+}
+
+impl MultiSigWithParticipants2018 {
+    /// Aggregate the signatures of the signers at the given indices of a registry of `registry_size` signers
+    pub fn new(sigs: Vec<(usize, &Signature18)>, registry_size: usize) -> Result<Self, PSError> {
+        let indices = sigs.iter().map(|(i, _)| *i).collect::<Vec<usize>>();
+        let participants = participants_bitmap(&indices, registry_size)?;
+        let sig = MultiSignatureFast::from_sigs_2018(sigs.into_iter().map(|(_, s)| s).collect())?;
+        Ok(Self { sig, participants })
+    }
+
+    /// Indices of the signers in the registry
+    pub fn participant_indices(&self) -> Vec<usize> {
+        bitmap_indices(&self.participants)
+    }
+
+    /// Same as `MultiSigWithParticipants::verify`
+    pub fn verify(&self, messages: Vec<FieldElement>, registry: &[Verkey], params: &Params) -> Result<bool, PSError> {
+        MultiSignatureFast::verify_2018(&self.sig, messages, participant_verkeys(&self.participants, registry)?, params)
+    }
+}
+
+/// Domain separation tag for the coefficient of each signer in `AggregatedVerkey` and `MultiSignature`
+pub const MULTI_SIG_COEFFICIENT_DST: &[u8] = b"PS-SIG-MULTI-SIG-COEFFICIENT";
+
+pub struct AggregatedVerkey {}
+
+impl AggregatedVerkey {
+    /// Coefficient of each verkey, the hash of the verkey and the set of all verkeys. The set does not depend on the
+    /// order of `ver_keys`.
+    pub fn coefficients(ver_keys: &[&Verkey]) -> Vec<FieldElement> {
+        let mut encodings = ver_keys.iter().map(|vk| Self::verkey_bytes(vk)).collect::<Vec<Vec<u8>>>();
+        let coefficient_prefix = |vk_bytes: &[u8]| {
+            let mut bytes = MULTI_SIG_COEFFICIENT_DST.to_vec();
+            bytes.extend_from_slice(&(vk_bytes.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(vk_bytes);
+            bytes
+        };
+        let prefixes = encodings.iter().map(|e| coefficient_prefix(e)).collect::<Vec<Vec<u8>>>();
+        encodings.sort();
+        let mut set_bytes = vec![];
+        for e in encodings {
+            set_bytes.extend_from_slice(&(e.len() as u64).to_be_bytes());
+            set_bytes.extend_from_slice(&e);
+        }
+        prefixes
+            .into_iter()
+            .map(|mut bytes| {
+                bytes.extend_from_slice(&set_bytes);
+                FieldElement::from_msg_hash(&bytes)
+            })
+            .collect()
+    }
+
+    /// Aggregate verkey, the sum of the verkeys multiplied by their coefficient
+    pub fn from_verkeys(ver_keys: Vec<&Verkey>) -> Result<Verkey, PSError> {
+        let coefficients = Self::coefficients(&ver_keys);
+        // Checks for empty and incompatible keys and computes metadata
+        let mut avk = AggregatedVerkeyFast::from_verkeys(ver_keys.clone())?;
+        avk.X_tilde = VerkeyGroup::identity();
+        for y in avk.Y_tilde.iter_mut() {
+            *y = VerkeyGroup::identity();
+        }
+        for (vk, c) in ver_keys.into_iter().zip(coefficients.iter()) {
+            avk.X_tilde += &vk.X_tilde * c;
+            for (y, vk_y) in avk.Y_tilde.iter_mut().zip(vk.Y_tilde.iter()) {
+                *y += vk_y * c;
+            }
+        }
+        Ok(avk)
+    }
+
+    fn verkey_bytes(vk: &Verkey) -> Vec<u8> {
+        let mut bytes = vk.X_tilde.to_bytes();
+        for y in &vk.Y_tilde {
+            bytes.append(&mut y.to_bytes());
+        }
+        bytes
     }
 }
 
@@ -96,6 +479,157 @@ impl MultiSignatureFast {
     // an aggregated verkey should be created once and then used for each signature verification
 }
 
+/// Aggregates signatures like `MultiSignatureFast` as they arrive instead of all at once. All signatures should
+/// be of the same scheme and over the same sigma_1 (and m' for the 2018 scheme), which is checked on each addition.
+#[derive(Clone, Debug)]
+pub struct Aggregator {
+    scheme: Option<Scheme>,
+    sigma_1: Option<SignatureGroup>,
+    m_prime: Option<FieldElement>,
+    sigma_2: SignatureGroup,
+    count: usize,
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aggregator {
+    pub fn new() -> Self {
+        Self {
+            scheme: None,
+            sigma_1: None,
+            m_prime: None,
+            sigma_2: SignatureGroup::identity(),
+            count: 0,
+        }
+    }
+
+    /// Add a signature from signature scheme defined in 2016 paper, CT-RSA 2016
+    pub fn add(&mut self, sig: &Signature) -> Result<(), PSError> {
+        self.check_and_set_scheme(Scheme::PS2016)?;
+        self.add_sig(sig)
+    }
+
+    /// Add a signature from signature scheme defined in 2018 paper, CT-RSA 2018
+    pub fn add_2018(&mut self, sig: &Signature18) -> Result<(), PSError> {
+        self.check_and_set_scheme(Scheme::PS2018)?;
+        match &self.m_prime {
+            Some(m_prime) if *m_prime != sig.m_prime => return Err(PSError::IncompatibleSigsForAggregation),
+            Some(_) => (),
+            None => self.m_prime = Some(sig.m_prime.clone()),
+        }
+        self.add_sig(&sig.sig)
+    }
+
+    /// No of signatures added
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The multi-signature of the added signatures from signature scheme defined in 2016 paper, CT-RSA 2016
+    pub fn finalize(self) -> Result<Signature, PSError> {
+        self.check_finalize(Scheme::PS2016)?;
+        Ok(Signature { sigma_1: self.sigma_1.unwrap(), sigma_2: self.sigma_2 })
+    }
+
+    /// The multi-signature of the added signatures from signature scheme defined in 2018 paper, CT-RSA 2018
+    pub fn finalize_2018(self) -> Result<Signature18, PSError> {
+        self.check_finalize(Scheme::PS2018)?;
+        Ok(Signature18 {
+            m_prime: self.m_prime.unwrap(),
+            sig: Signature { sigma_1: self.sigma_1.unwrap(), sigma_2: self.sigma_2 },
+        })
+    }
+
+    fn check_and_set_scheme(&mut self, scheme: Scheme) -> Result<(), PSError> {
+        match self.scheme {
+            Some(s) if s != scheme => Err(PSError::IncompatibleSigsForAggregation),
+            _ => {
+                self.scheme = Some(scheme);
+                Ok(())
+            }
+        }
+    }
+
+    fn add_sig(&mut self, sig: &Signature) -> Result<(), PSError> {
+        match &self.sigma_1 {
+            Some(sigma_1) if *sigma_1 != sig.sigma_1 => return Err(PSError::IncompatibleSigsForAggregation),
+            Some(_) => (),
+            None => self.sigma_1 = Some(sig.sigma_1.clone()),
+        }
+        self.sigma_2 += &sig.sigma_2;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn check_finalize(&self, scheme: Scheme) -> Result<(), PSError> {
+        if self.count == 0 {
+            return Err(PSError::GeneralError {
+                msg: String::from("Provide at least one signature"),
+            });
+        }
+        if self.scheme != Some(scheme) {
+            return Err(PSError::IncompatibleSigsForAggregation)
+        }
+        Ok(())
+    }
+}
+
+/// Multi-signatures secure without proofs of possession, see `AggregatedVerkey`
+pub struct MultiSignature {}
+
+impl MultiSignature {
+    /// Create a multi-signature from signature scheme defined in 2016 paper, CT-RSA 2016. Each signature is given
+    /// with its signer's verkey and all signatures should be over the same sigma_1.
+    pub fn from_sigs(sigs: Vec<(&Signature, &Verkey)>) -> Result<Signature, PSError> {
+        if sigs.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Provide at least one signature"),
+            });
+        }
+        let sigma_1 = &sigs[0].0.sigma_1;
+        if !sigs.iter().all(|(sig, _)| sig.sigma_1 == *sigma_1) {
+            return Err(PSError::IncompatibleSigsForAggregation)
+        }
+        let coefficients = AggregatedVerkey::coefficients(&sigs.iter().map(|(_, vk)| *vk).collect::<Vec<&Verkey>>());
+        let mut sigma_2 = SignatureGroup::identity();
+        for ((s, _), c) in sigs.iter().zip(coefficients.iter()) {
+            sigma_2 += &s.sigma_2 * c;
+        }
+        Ok(Signature { sigma_1: sigma_1.clone(), sigma_2 })
+    }
+
+    /// Create a multi-signature from signature scheme defined in 2018 paper, CT-RSA 2018
+    pub fn from_sigs_2018(sigs: Vec<(&Signature18, &Verkey)>) -> Result<Signature18, PSError> {
+        if sigs.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Provide at least one signature"),
+            });
+        }
+        let m_prime = sigs[0].0.m_prime.clone();
+        if !sigs.iter().all(|(sig, _)| sig.m_prime == m_prime) {
+            return Err(PSError::IncompatibleSigsForAggregation)
+        }
+        let sig = Self::from_sigs(sigs.into_iter().map(|(s, vk)| (&s.sig, vk)).collect())?;
+        Ok(Signature18 {m_prime, sig})
+    }
+
+    /// For verifying a multi-signature from signature scheme defined in 2016 paper, CT-RSA 2016
+    pub fn verify(sig: &Signature, messages: Vec<FieldElement>, ver_keys: Vec<&Verkey>, params: &Params) -> Result<bool, PSError> {
+        let avk = AggregatedVerkey::from_verkeys(ver_keys)?;
+        sig.verify(messages, &avk, params)
+    }
+
+    /// For verifying a multi-signature from signature scheme defined in 2018 paper, CT-RSA 2018
+    pub fn verify_2018(sig: &Signature18, messages: Vec<FieldElement>, ver_keys: Vec<&Verkey>, params: &Params) -> Result<bool, PSError> {
+        let avk = AggregatedVerkey::from_verkeys(ver_keys)?;
+        sig.verify(messages, &avk, params)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,5 +678,230 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_aggregate_verkeys_with_pop() {
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(3, &params);
+        let (sk_2, vk_2) = keygen(3, &params);
+        let pop_1 = PoPProof::new(&sk_1, &vk_1, &params).unwrap();
+        let pop_2 = PoPProof::new(&sk_2, &vk_2, &params).unwrap();
+
+        let avk = AggregatedVerkeyFast::from_verkeys_with_pop(vec![(&vk_1, &pop_1), (&vk_2, &pop_2)], &params).unwrap();
+        let avk_1 = AggregatedVerkeyFast::from_verkeys(vec![&vk_1, &vk_2]).unwrap();
+        assert_eq!(avk.X_tilde, avk_1.X_tilde);
+        assert!(AggregatedVerkeyFast::from_verkeys_with_pop(vec![(&vk_1, &pop_1), (&vk_2, &pop_1)], &params).is_err());
+    }
+
+    #[test]
+    fn test_multi_signature_without_pop() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk_1, vk_1) = keygen(count_msgs, &params);
+        let (sk_2, vk_2) = keygen(count_msgs, &params);
+        let (sk_3, vk_3) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+
+        let sig_1 = Signature::new_deterministic(msgs.as_slice(), &sk_1).unwrap();
+        let sig_2 = Signature::new_deterministic(msgs.as_slice(), &sk_2).unwrap();
+        let sig_3 = Signature::new_deterministic(msgs.as_slice(), &sk_3).unwrap();
+        let multi_sig = MultiSignature::from_sigs(vec![(&sig_1, &vk_1), (&sig_2, &vk_2), (&sig_3, &vk_3)]).unwrap();
+        assert!(MultiSignature::verify(&multi_sig, msgs.clone(), vec![&vk_1, &vk_2, &vk_3], &params).unwrap());
+        // Order of the verkeys does not matter
+        assert!(MultiSignature::verify(&multi_sig, msgs.clone(), vec![&vk_3, &vk_1, &vk_2], &params).unwrap());
+        // Missing signer or signature not matching its verkey
+        assert!(!MultiSignature::verify(&multi_sig, msgs.clone(), vec![&vk_1, &vk_2], &params).unwrap());
+        let multi_sig = MultiSignature::from_sigs(vec![(&sig_2, &vk_1), (&sig_1, &vk_2), (&sig_3, &vk_3)]).unwrap();
+        assert!(!MultiSignature::verify(&multi_sig, msgs.clone(), vec![&vk_1, &vk_2, &vk_3], &params).unwrap());
+
+        // A rogue verkey vk' - vk_1 makes the fast aggregate of vk_1 and the rogue key vk' whose signing key the
+        // attacker knows, but not the aggregate with coefficients
+        let (sk_r, vk_r) = keygen(count_msgs, &params);
+        let mut rogue = vk_r.clone();
+        rogue.X_tilde = &vk_r.X_tilde - &vk_1.X_tilde;
+        for i in 0..count_msgs {
+            rogue.Y_tilde[i] = &vk_r.Y_tilde[i] - &vk_1.Y_tilde[i];
+        }
+        let forged = Signature::new_deterministic(msgs.as_slice(), &sk_r).unwrap();
+        assert!(MultiSignatureFast::verify(&forged, msgs.clone(), vec![&vk_1, &rogue], &params).unwrap());
+        assert!(!MultiSignature::verify(&forged, msgs.clone(), vec![&vk_1, &rogue], &params).unwrap());
+
+        let (sk_1, vk_1) = keygen_2018(count_msgs, &params);
+        let (sk_2, vk_2) = keygen_2018(count_msgs, &params);
+        let sig_1 = Signature18::new_deterministic(msgs.as_slice(), &sk_1).unwrap();
+        let sig_2 = Signature18::new_deterministic(msgs.as_slice(), &sk_2).unwrap();
+        let multi_sig = MultiSignature::from_sigs_2018(vec![(&sig_1, &vk_1), (&sig_2, &vk_2)]).unwrap();
+        assert!(MultiSignature::verify_2018(&multi_sig, msgs, vec![&vk_2, &vk_1], &params).unwrap());
+    }
+
+    #[test]
+    fn test_incremental_aggregation() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let keys = (0..3).map(|_| keygen(count_msgs, &params)).collect::<Vec<_>>();
+        let sigs = keys
+            .iter()
+            .map(|(sk, _)| Signature::new_deterministic(msgs.as_slice(), sk).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut aggregator = Aggregator::new();
+        assert!(aggregator.clone().finalize().is_err());
+        for sig in &sigs {
+            aggregator.add(sig).unwrap();
+        }
+        assert_eq!(aggregator.count(), 3);
+        // Signature over a different sigma_1 and of the other scheme are rejected and not added
+        let other = Signature::new(msgs.as_slice(), &keys[0].0, &params).unwrap();
+        assert!(aggregator.add(&other).is_err());
+        let (sk_18, _) = keygen_2018(count_msgs, &params);
+        let sig_18 = Signature18::new_deterministic(msgs.as_slice(), &sk_18).unwrap();
+        assert!(aggregator.add_2018(&sig_18).is_err());
+        assert_eq!(aggregator.count(), 3);
+        assert!(aggregator.clone().finalize_2018().is_err());
+
+        let multi_sig = aggregator.finalize().unwrap();
+        let multi_sig_1 = MultiSignatureFast::from_sigs(sigs.iter().collect()).unwrap();
+        assert_eq!(multi_sig.sigma_2, multi_sig_1.sigma_2);
+        let vks = keys.iter().map(|(_, vk)| vk).collect::<Vec<_>>();
+        assert!(MultiSignatureFast::verify(&multi_sig, msgs.clone(), vks, &params).unwrap());
+
+        let keys = (0..2).map(|_| keygen_2018(count_msgs, &params)).collect::<Vec<_>>();
+        let mut aggregator = Aggregator::new();
+        for (sk, _) in &keys {
+            aggregator
+                .add_2018(&Signature18::new_deterministic(msgs.as_slice(), sk).unwrap())
+                .unwrap();
+        }
+        // Different m'
+        let sig = Signature18::new_with_deterministic_m(&msgs[..], &keys[0].0).unwrap();
+        assert!(aggregator.add_2018(&sig).is_err());
+        let multi_sig = aggregator.finalize_2018().unwrap();
+        let vks = keys.iter().map(|(_, vk)| vk).collect::<Vec<_>>();
+        assert!(MultiSignatureFast::verify_2018(&multi_sig, msgs, vks, &params).unwrap());
+    }
+
+    #[test]
+    fn test_incremental_aggregated_verkey_add_remove() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let keys = (0..4).map(|_| keygen(count_msgs, &params)).collect::<Vec<_>>();
+        let sigs = keys
+            .iter()
+            .map(|(sk, _)| Signature::new_deterministic(msgs.as_slice(), sk).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut avk = IncrementalAggregatedVerkey::new(count_msgs);
+        let json = serde_json::to_string(&avk).unwrap();
+        assert_eq!(serde_json::from_str::<IncrementalAggregatedVerkey>(&json).unwrap().signer_count(), 0);
+        for (_, vk) in &keys[..3] {
+            avk.add_signer(vk).unwrap();
+        }
+        assert_eq!(avk.signer_count(), 3);
+        let multi_sig = MultiSignatureFast::from_sigs(sigs[..3].iter().collect()).unwrap();
+        assert!(multi_sig.verify(msgs.clone(), avk.verkey(), &params).unwrap());
+
+        // Signer 1 leaves and signer 3 joins
+        avk.remove_signer(&keys[1].1).unwrap();
+        avk.add_signer(&keys[3].1).unwrap();
+        let multi_sig = MultiSignatureFast::from_sigs(vec![&sigs[0], &sigs[2], &sigs[3]]).unwrap();
+        assert!(multi_sig.verify(msgs.clone(), avk.verkey(), &params).unwrap());
+        let avk_1 = IncrementalAggregatedVerkey::from_verkeys(vec![&keys[0].1, &keys[2].1, &keys[3].1]).unwrap();
+        assert_eq!(avk.verkey().X_tilde, avk_1.verkey().X_tilde);
+
+        let json = serde_json::to_string(&avk).unwrap();
+        let avk_2: IncrementalAggregatedVerkey = serde_json::from_str(&json).unwrap();
+        assert_eq!(avk_2.signer_count(), 3);
+        assert!(multi_sig.verify(msgs, avk_2.verkey(), &params).unwrap());
+
+        let (_, vk) = keygen(count_msgs + 1, &params);
+        assert!(avk.add_signer(&vk).is_err());
+        assert!(IncrementalAggregatedVerkey::new(count_msgs).remove_signer(&keys[0].1).is_err());
+
+        // Removing a signer that was never added or was already removed fails and leaves the aggregate unchanged
+        assert!(avk.contains(&keys[3].1) && !avk.contains(&keys[1].1));
+        let before = avk.verkey().X_tilde.clone();
+        assert!(avk.remove_signer(&keys[1].1).is_err());
+        let (_, stranger) = keygen(count_msgs, &params);
+        assert!(avk.remove_signer(&stranger).is_err());
+        assert_eq!(avk.verkey().X_tilde, before);
+        assert_eq!(avk.signer_count(), 3);
+        // A signer can't be added twice
+        assert!(avk.add_signer(&keys[0].1).is_err());
+        assert!(IncrementalAggregatedVerkey::from_verkeys(vec![&keys[0].1, &keys[0].1]).is_err());
+        assert!(IncrementalAggregatedVerkey::from_verkeys(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_multi_sig_with_participants() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 2;
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let keys = (0..10).map(|_| keygen(count_msgs, &params)).collect::<Vec<_>>();
+        let registry = keys.iter().map(|(_, vk)| vk.clone()).collect::<Vec<_>>();
+        let sigs = keys
+            .iter()
+            .map(|(sk, _)| Signature::new_deterministic(msgs.as_slice(), sk).unwrap())
+            .collect::<Vec<_>>();
+
+        let multi_sig =
+            MultiSigWithParticipants::new(vec![(9, &sigs[9]), (2, &sigs[2]), (4, &sigs[4])], registry.len()).unwrap();
+        assert_eq!(multi_sig.participants.len(), 2);
+        assert_eq!(multi_sig.participant_indices(), vec![2, 4, 9]);
+        assert!(multi_sig.verify(msgs.clone(), &registry, &params).unwrap());
+
+        // Claiming a different signer fails
+        let mut wrong = multi_sig.clone();
+        wrong.participants = participants_bitmap(&[2, 4, 8], registry.len()).unwrap();
+        assert!(!wrong.verify(msgs.clone(), &registry, &params).unwrap());
+        assert!(multi_sig.verify(msgs.clone(), &registry[..8], &params).is_err());
+
+        assert!(MultiSigWithParticipants::new(vec![(2, &sigs[2]), (2, &sigs[2])], registry.len()).is_err());
+        assert!(MultiSigWithParticipants::new(vec![(10, &sigs[2])], registry.len()).is_err());
+
+        let keys = (0..3).map(|_| keygen_2018(count_msgs, &params)).collect::<Vec<_>>();
+        let registry = keys.iter().map(|(_, vk)| vk.clone()).collect::<Vec<_>>();
+        let sig_0 = Signature18::new_deterministic(msgs.as_slice(), &keys[0].0).unwrap();
+        let sig_2 = Signature18::new_deterministic(msgs.as_slice(), &keys[2].0).unwrap();
+        let multi_sig = MultiSigWithParticipants2018::new(vec![(0, &sig_0), (2, &sig_2)], registry.len()).unwrap();
+        assert_eq!(multi_sig.participant_indices(), vec![0, 2]);
+        assert!(multi_sig.verify(msgs, &registry, &params).unwrap());
+    }
+
+    #[test]
+    fn test_signer_registry() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let keys = (0..3).map(|_| keygen(count_msgs, &params)).collect::<Vec<_>>();
+
+        let mut registry = SignerRegistry::new();
+        for (i, (sk, vk)) in keys.iter().enumerate() {
+            let pop = PoPProof::new(sk, vk, &params).unwrap();
+            assert_eq!(registry.register(vk.clone(), &pop, &params).unwrap(), i);
+        }
+        assert_eq!(registry.len(), 3);
+
+        // Invalid proof or already registered key
+        let (_, vk) = keygen(count_msgs, &params);
+        let pop_0 = PoPProof::new(&keys[0].0, &keys[0].1, &params).unwrap();
+        assert!(registry.register(vk, &pop_0, &params).is_err());
+        assert!(registry.register(keys[0].1.clone(), &pop_0, &params).is_err());
+        assert_eq!(registry.len(), 3);
+
+        let sigs = keys
+            .iter()
+            .map(|(sk, _)| Signature::new_deterministic(msgs.as_slice(), sk).unwrap())
+            .collect::<Vec<_>>();
+        let multi_sig = MultiSignatureFast::from_sigs(vec![&sigs[0], &sigs[2]]).unwrap();
+        let avk = AggregatedVerkeyFast::from_registry(&registry, &[0, 2]).unwrap();
+        assert!(multi_sig.verify(msgs.clone(), &avk, &params).unwrap());
+        assert!(AggregatedVerkeyFast::from_registry(&registry, &[0, 3]).is_err());
+        assert!(AggregatedVerkeyFast::from_registry(&registry, &[0, 0]).is_err());
+
+        let multi_sig = MultiSigWithParticipants::new(vec![(0, &sigs[0]), (2, &sigs[2])], registry.len()).unwrap();
+        assert!(multi_sig.verify(msgs, registry.verkeys(), &params).unwrap());
+    }
+
     // TODO: For aggregating blind signature, a Coconut like approach is needed.
 }
\ No newline at end of file
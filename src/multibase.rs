@@ -0,0 +1,166 @@
+// Multibase (https://github.com/multiformats/multibase) string encoding of bytes, supporting base58btc (prefix 'z')
+// and base64url without padding (prefix 'u'). Used for the `Display` and `FromStr` implementations of verkeys and
+// signatures, `Display` uses base58btc.
+
+use crate::errors::PSError;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE64URL_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Base {
+    Base58Btc,
+    Base64Url,
+}
+
+impl Base {
+    pub fn prefix(&self) -> char {
+        match self {
+            Base::Base58Btc => 'z',
+            Base::Base64Url => 'u',
+        }
+    }
+}
+
+pub fn encode(base: Base, bytes: &[u8]) -> String {
+    let mut s = String::new();
+    s.push(base.prefix());
+    match base {
+        Base::Base58Btc => s.push_str(&base58_encode(bytes)),
+        Base::Base64Url => s.push_str(&base64url_encode(bytes)),
+    }
+    s
+}
+
+pub fn decode(s: &str) -> Result<(Base, Vec<u8>), PSError> {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some('z') => Ok((Base::Base58Btc, base58_decode(chars.as_str())?)),
+        Some('u') => Ok((Base::Base64Url, base64url_decode(chars.as_str())?)),
+        _ => Err(PSError::InvalidBytes {
+            msg: String::from("Unsupported multibase prefix"),
+        }),
+    }
+}
+
+fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    // Digits in base 58, least significant first
+    let mut digits: Vec<u8> = vec![];
+    for b in &bytes[zeros..] {
+        let mut carry = *b as u32;
+        for d in digits.iter_mut() {
+            carry += (*d as u32) << 8;
+            *d = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut s = String::with_capacity(zeros + digits.len());
+    for _ in 0..zeros {
+        s.push('1');
+    }
+    for d in digits.iter().rev() {
+        s.push(BASE58_ALPHABET[*d as usize] as char);
+    }
+    s
+}
+
+fn base58_decode(s: &str) -> Result<Vec<u8>, PSError> {
+    let zeros = s.bytes().take_while(|c| *c == b'1').count();
+    // Bytes, least significant first
+    let mut bytes: Vec<u8> = vec![];
+    for c in s.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| PSError::InvalidBytes {
+                msg: format!("Invalid base58 character {:?}", c as char),
+            })? as u32;
+        for b in bytes.iter_mut() {
+            carry += (*b as u32) * 58;
+            *b = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+fn base64url_encode(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() * 4 + 2) / 3);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.len();
+        let v = ((chunk[0] as u32) << 16)
+            | ((*chunk.get(1).unwrap_or(&0) as u32) << 8)
+            | (*chunk.get(2).unwrap_or(&0) as u32);
+        for i in 0..(n + 1) {
+            s.push(BASE64URL_ALPHABET[((v >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    s
+}
+
+fn base64url_decode(s: &str) -> Result<Vec<u8>, PSError> {
+    let err = |msg: &str| PSError::InvalidBytes {
+        msg: format!("Invalid base64url: {}", msg),
+    };
+    let s = s.as_bytes();
+    if s.len() % 4 == 1 {
+        return Err(err("invalid length"));
+    }
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.chunks(4) {
+        let mut v = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let d = BASE64URL_ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or_else(|| err("invalid character"))? as u32;
+            v |= d << (18 - 6 * i);
+        }
+        let n = chunk.len() - 1;
+        // Unused bits should be 0 so that the encoding is unique
+        if v & ((1 << (24 - 8 * n)) - 1) != 0 {
+            return Err(err("non-zero trailing bits"));
+        }
+        for i in 0..n {
+            out.push((v >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multibase() {
+        // Test vectors from the multibase spec
+        let bytes = "Decentralize everything!!".as_bytes();
+        assert_eq!(encode(Base::Base58Btc, bytes), "zUXE7GvtEk8XTXs1GF8HSGbVA9FCX9SEBPe");
+        assert_eq!(encode(Base::Base64Url, bytes), "uRGVjZW50cmFsaXplIGV2ZXJ5dGhpbmchIQ");
+        assert_eq!(encode(Base::Base58Btc, &[0, 0, 1]), "z112");
+
+        for len in 0..20 {
+            let bytes = (0..len).map(|i| (i * 37 % 256) as u8).collect::<Vec<u8>>();
+            for base in &[Base::Base58Btc, Base::Base64Url] {
+                let s = encode(*base, &bytes);
+                assert_eq!(decode(&s).unwrap(), (*base, bytes.clone()));
+            }
+        }
+        assert!(decode("f00").is_err());
+        assert!(decode("z0").is_err());
+        assert!(decode("uA").is_err());
+        assert!(decode("uAB").is_err());
+    }
+}
@@ -0,0 +1,298 @@
+// Proofs that a hidden message (like a credential serial) is not in a public blocklist. The values of the blocklist
+// are less than 2^n and the blocklist authority, with a key for 3 messages used for nothing else, signs each gap
+// (epoch, a, b) between consecutive values of the sorted blocklist, with -1 below the smallest and 2^n above the
+// largest. To prove that m is not in the blocklist, the holder proves knowledge of the signature of the gap with
+// a < m < b revealing only the epoch, and proves with range proofs (`range_proof`) that m - a - 1 and b - m - 1 are in
+// [0, 2^n). The blindings of these differences are the differences of the blindings of m, a and b in the proofs of
+// knowledge, so the verifier derives their responses from the responses for m, a and b and knows the ranged values
+// are for the signed m. Verification costs the same for any size of blocklist, the verifier only needs the authority's
+// verkey and the current epoch; the epoch is incremented on each update so gaps of older blocklists are rejected.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::range_proof::{message_bits, RangeProof, RangeProofCommitted};
+use crate::signature::Signature;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// A gap (a, b) of the blocklist with the authority's signature on (epoch, a, b)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedGap {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub lower: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub upper: FieldElement,
+    pub signature: Signature,
+}
+
+/// Blocklist of values less than 2^`bits` with the signed gaps between them, published by the authority
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedBlocklist {
+    pub epoch: u64,
+    pub bits: usize,
+    pub gaps: Vec<SignedGap>,
+}
+
+impl SignedBlocklist {
+    /// Sort the values and sign the gaps between them with `sigkey`, a key for 3 messages. Fails if a value is not
+    /// less than 2^`bits`.
+    pub fn new(
+        values: &[FieldElement],
+        bits: usize,
+        epoch: u64,
+        sigkey: &Sigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        for v in values {
+            message_bits(v, bits)?;
+        }
+        // Big-endian bytes of the same length sort as the values
+        let mut values = values.iter().map(|v| (v.to_bytes(), v.clone())).collect::<Vec<_>>();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values.dedup_by(|a, b| a.0 == b.0);
+
+        let mut bounds = vec![&FieldElement::zero() - &FieldElement::one()];
+        bounds.extend(values.into_iter().map(|(_, v)| v));
+        bounds.push(power_of_2(bits));
+        let epoch_msg = FieldElement::from(epoch);
+        let mut gaps = Vec::with_capacity(bounds.len() - 1);
+        for w in bounds.windows(2) {
+            let signature = Signature::new(&[epoch_msg.clone(), w[0].clone(), w[1].clone()], sigkey, params)?;
+            gaps.push(SignedGap {
+                lower: w[0].clone(),
+                upper: w[1].clone(),
+                signature,
+            });
+        }
+        Ok(Self { epoch, bits, gaps })
+    }
+
+    /// The gap containing `value`, failing if `value` is in the blocklist or not less than 2^`bits`
+    pub fn gap_for(&self, value: &FieldElement) -> Result<&SignedGap, PSError> {
+        message_bits(value, self.bits)?;
+        let v = value.to_bytes();
+        // The lower bound of the first gap is -1 which is less than any value
+        let idx = self.gaps[1..].iter().take_while(|g| g.lower.to_bytes() < v).count();
+        let gap = &self.gaps[idx];
+        if gap.upper.to_bytes() <= v {
+            return Err(PSError::GeneralError {
+                msg: String::from("Value is in the blocklist"),
+            });
+        }
+        Ok(gap)
+    }
+}
+
+fn power_of_2(bits: usize) -> FieldElement {
+    let mut p = FieldElement::one();
+    for _ in 0..bits {
+        p = &p + &p;
+    }
+    p
+}
+
+fn revealed_epoch() -> HashSet<usize> {
+    let mut revealed = HashSet::new();
+    revealed.insert(0);
+    revealed
+}
+
+/// Prover's state of the proof of non-membership
+#[derive(Clone, Debug)]
+pub struct NonMembershipCommitted {
+    pub gap_pok: PoKOfSignature,
+    pub lower: RangeProofCommitted,
+    pub upper: RangeProofCommitted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NonMembershipProof {
+    pub gap_proof: PoKOfSignatureProof,
+    pub lower: RangeProof,
+    pub upper: RangeProof,
+}
+
+impl NonMembershipCommitted {
+    /// Bytes to append to the challenge bytes of the proof of knowledge of the credential
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.gap_pok.to_bytes();
+        bytes.append(&mut self.lower.to_bytes());
+        bytes.append(&mut self.upper.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<NonMembershipProof, PSError> {
+        Ok(NonMembershipProof {
+            gap_proof: self.gap_pok.gen_proof(challenge)?,
+            lower: self.lower.gen_proof(challenge),
+            upper: self.upper.gen_proof(challenge),
+        })
+    }
+}
+
+impl NonMembershipProof {
+    /// Same bytes as `NonMembershipCommitted::to_bytes` for the verifier's challenge
+    pub fn get_bytes_for_challenge(&self, blocklist_vk: &Verkey, params: &Params) -> Vec<u8> {
+        let mut bytes = self.gap_proof.get_bytes_for_challenge(revealed_epoch(), blocklist_vk, params);
+        bytes.append(&mut self.lower.get_bytes_for_challenge());
+        bytes.append(&mut self.upper.get_bytes_for_challenge());
+        bytes
+    }
+
+    /// Verify that the hidden message at `msg_idx` of `pok_proof` is not in the blocklist of `epoch` with values less
+    /// than 2^`bits`. `pok_proof` must be verified with the same challenge, computed over it and this proof.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify(
+        &self,
+        pok_proof: &PoKOfSignatureProof,
+        msg_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+        epoch: u64,
+        bits: usize,
+        blocklist_vk: &Verkey,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, FieldElement::from(epoch));
+        if !self.gap_proof.verify(blocklist_vk, params, revealed_msgs, challenge)? {
+            return Ok(false);
+        }
+        let s_m = pok_proof.get_resp_for_hidden_message(msg_idx, revealed_msg_indices)?;
+        let s_a = self.gap_proof.get_resp_for_hidden_message(1, &revealed_epoch())?;
+        let s_b = self.gap_proof.get_resp_for_hidden_message(2, &revealed_epoch())?;
+        // Responses for m - a - 1 and b - m - 1
+        let s_lower = &(&s_m - &s_a) + challenge;
+        let s_upper = &(&s_b - &s_m) + challenge;
+        Ok(self.lower.verify_with_response(&s_lower, bits, params, challenge)
+            && self.upper.verify_with_response(&s_upper, bits, params, challenge))
+    }
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and a proof that the hidden
+/// message at `msg_idx` is not in `blocklist`. The challenge is computed over the bytes of the proof of knowledge
+/// followed by those of the proof of non-membership.
+#[allow(clippy::too_many_arguments)]
+pub fn init_pok_with_non_membership(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    msg_idx: usize,
+    blocklist: &SignedBlocklist,
+    blocklist_vk: &Verkey,
+) -> Result<(PoKOfSignature, NonMembershipCommitted), PSError> {
+    if msg_idx >= messages.len() {
+        return Err(PSError::GeneralError {
+            msg: format!("Index {} should be less than {}", msg_idx, messages.len()),
+        });
+    }
+    let m = &messages[msg_idx];
+    let gap = blocklist.gap_for(m)?;
+    let blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let r_m = &blindings[hidden_message_position(msg_idx, &revealed_msg_indices)?];
+    let r_a = FieldElement::random();
+    let r_b = FieldElement::random();
+
+    let one = FieldElement::one();
+    let lower = RangeProofCommitted::new(
+        &(&(m - &gap.lower) - &one),
+        blocklist.bits,
+        &(r_m - &r_a),
+        params,
+    )?;
+    let upper = RangeProofCommitted::new(
+        &(&(&gap.upper - m) - &one),
+        blocklist.bits,
+        &(&r_b - r_m),
+        params,
+    )?;
+    let gap_pok = PoKOfSignature::init(
+        &gap.signature,
+        blocklist_vk,
+        params,
+        vec![FieldElement::from(blocklist.epoch), gap.lower.clone(), gap.upper.clone()],
+        Some(&[r_a, r_b][..]),
+        revealed_epoch(),
+    )?;
+    let pok = PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)?;
+    Ok((pok, NonMembershipCommitted { gap_pok, lower, upper }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_non_membership() {
+        let params = Params::new("test".as_bytes());
+        let bits = 64;
+        let (blocklist_sk, blocklist_vk) = keygen(3, &params);
+        let blocked = [9u64, 1000, 77, 1_000_000, 77]
+            .iter()
+            .map(|v| FieldElement::from(*v))
+            .collect::<Vec<_>>();
+        let blocklist = SignedBlocklist::new(&blocked, bits, 5, &blocklist_sk, &params).unwrap();
+        // Duplicates are removed
+        assert_eq!(blocklist.gaps.len(), 5);
+        for gap in &blocklist.gaps {
+            let msgs = vec![FieldElement::from(5u64), gap.lower.clone(), gap.upper.clone()];
+            assert!(gap.signature.verify(msgs, &blocklist_vk, &params).unwrap());
+        }
+        for v in &blocked {
+            assert!(blocklist.gap_for(v).is_err());
+        }
+
+        let (sk, vk) = keygen(3, &params);
+        let mut revealed = HashSet::new();
+        revealed.insert(0);
+        // Serials in the first, an inner and the last gap
+        for serial in [0u64, 78, 999_999, 5_000_000].iter() {
+            let msgs = vec![FieldElement::random(), FieldElement::from(*serial), FieldElement::random()];
+            let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+            let mut revealed_msgs = HashMap::new();
+            revealed_msgs.insert(0, msgs[0].clone());
+
+            let (pok, committed) = init_pok_with_non_membership(
+                &sig, &vk, &params, msgs.clone(), revealed.clone(), 1, &blocklist, &blocklist_vk,
+            )
+            .unwrap();
+            let mut chal_bytes = pok.to_bytes();
+            chal_bytes.append(&mut committed.to_bytes());
+            let challenge = FieldElement::from_msg_hash(&chal_bytes);
+            let proof = pok.gen_proof(&challenge).unwrap();
+            let nm_proof = committed.gen_proof(&challenge).unwrap();
+
+            let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+            chal_bytes.append(&mut nm_proof.get_bytes_for_challenge(&blocklist_vk, &params));
+            let challenge = FieldElement::from_msg_hash(&chal_bytes);
+            assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+            assert!(nm_proof
+                .verify(&proof, 1, &revealed, 5, bits, &blocklist_vk, &params, &challenge)
+                .unwrap());
+            // Another message, an older epoch or another no of bits
+            assert!(!nm_proof
+                .verify(&proof, 2, &revealed, 5, bits, &blocklist_vk, &params, &challenge)
+                .unwrap());
+            assert!(!nm_proof
+                .verify(&proof, 1, &revealed, 4, bits, &blocklist_vk, &params, &challenge)
+                .unwrap());
+            assert!(!nm_proof
+                .verify(&proof, 1, &revealed, 5, 32, &blocklist_vk, &params, &challenge)
+                .unwrap());
+        }
+
+        // A blocked serial can't be proven
+        let msgs = vec![FieldElement::random(), FieldElement::from(77u64), FieldElement::random()];
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(init_pok_with_non_membership(
+            &sig, &vk, &params, msgs, revealed, 1, &blocklist, &blocklist_vk
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// Proof of possession of one of several credentials (2016 scheme), like a driver's licence or a passport, without
+// revealing which one or any of its messages. For each alternative verkey the proof has a randomized signature
+// (sigma_1', sigma_2'), K with e(sigma_1', K) == e(sigma_2', g_tilde) and a branch of an OR composition of Schnorr
+// proofs of knowledge of the opening of J = K / X_tilde over g_tilde and Y_tilde, as in `issuer_hiding`. For the
+// credential the holder has, these are computed as in `PoKOfSignature`. For the others the holder picks a random
+// sigma_1' and e and sets sigma_2' = sigma_1'^e and K = g_tilde^e, which satisfy the pairing check and are distributed
+// like a randomized signature and K, and simulates the branch. The credentials can be of different types with
+// different no of messages.
+
+use crate::errors::PSError;
+use crate::issuer_hiding::{branch_statement, challenges_sum_to, OrBranch, RealBranch};
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::PoKOfSignature;
+use crate::signature::Signature;
+use crate::{ate_2_pairing, SignatureGroup, VerkeyGroup};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::HashMap;
+
+/// Domain separation tag for the challenge of `OrProofOfCredentials`
+pub const OR_CREDENTIALS_DST: &[u8] = b"PS-SIG-OR-CREDENTIALS";
+
+/// The part of the proof for one alternative
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CredentialBranch {
+    pub sig: Signature,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub K: VerkeyGroup,
+    pub branch: OrBranch,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrProofOfCredentials {
+    /// One per alternative verkey, in the order of the alternatives
+    pub branches: Vec<CredentialBranch>,
+}
+
+impl OrProofOfCredentials {
+    /// Prove knowledge of `sig` on `messages` under `verkeys[index]` or of a signature under any other verkey of
+    /// `verkeys`, hiding `index` and all messages. `nonce` is the verifier's nonce for the challenge.
+    pub fn new(
+        sig: &Signature,
+        messages: &[FieldElement],
+        verkeys: &[Verkey],
+        index: usize,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<Self, PSError> {
+        if index >= verkeys.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", index, verkeys.len()),
+            });
+        }
+        Signature::check_verkey_and_messages_compat(messages, &verkeys[index])?;
+        let no_revealed = HashMap::new();
+
+        let mut real = None;
+        let mut parts = Vec::with_capacity(verkeys.len());
+        let mut simulated = vec![];
+        for (i, vk) in verkeys.iter().enumerate() {
+            if i == index {
+                let (t, sig_prime) = PoKOfSignature::transform_sig_with_rng(sig, &mut rand::thread_rng());
+                let mut K = &vk.X_tilde + &(&params.g_tilde * &t);
+                for (y, m) in vk.Y_tilde.iter().zip(messages.iter()) {
+                    K += y * m;
+                }
+                let (bases, _) = branch_statement(vk, &K, &no_revealed, params);
+                let mut secrets = vec![t];
+                secrets.extend_from_slice(messages);
+                let r = RealBranch::new(&bases, secrets);
+                parts.push((sig_prime, K, r.commitment.clone()));
+                real = Some(r);
+            } else {
+                let sigma_1 = SignatureGroup::random();
+                let e = FieldElement::random();
+                let sig_prime = Signature {
+                    sigma_2: &sigma_1 * &e,
+                    sigma_1,
+                };
+                let K = &params.g_tilde * &e;
+                let (bases, J) = branch_statement(vk, &K, &no_revealed, params);
+                let s = OrBranch::simulate(&bases, &J)?;
+                parts.push((sig_prime, K, s.commitment.clone()));
+                simulated.push(s);
+            }
+        }
+        let challenge = Self::compute_challenge(
+            parts.iter().map(|(s, k, c)| (s, k, c)),
+            verkeys,
+            params,
+            nonce,
+        );
+        let real = real.unwrap().finish(&challenge, &simulated);
+        let mut simulated = simulated.into_iter();
+        let branches = parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (sig, K, _))| CredentialBranch {
+                sig,
+                K,
+                branch: if i == index {
+                    real.clone()
+                } else {
+                    simulated.next().unwrap()
+                },
+            })
+            .collect();
+        Ok(Self { branches })
+    }
+
+    /// Verify that the prover has a signature under one of `verkeys` with the verifier's `nonce`
+    pub fn verify(&self, verkeys: &[Verkey], params: &Params, nonce: &[u8]) -> Result<bool, PSError> {
+        if verkeys.is_empty() || self.branches.len() != verkeys.len() {
+            return Ok(false);
+        }
+        let challenge = Self::compute_challenge(
+            self.branches.iter().map(|b| (&b.sig, &b.K, &b.branch.commitment)),
+            verkeys,
+            params,
+            nonce,
+        );
+        let or_branches = self.branches.iter().map(|b| b.branch.clone()).collect::<Vec<_>>();
+        if !challenges_sum_to(&or_branches, &challenge) {
+            return Ok(false);
+        }
+        let no_revealed = HashMap::new();
+        for (b, vk) in self.branches.iter().zip(verkeys.iter()) {
+            if b.sig.is_identity() {
+                return Ok(false);
+            }
+            // e(sigma_1', K) == e(sigma_2', g_tilde)
+            if !ate_2_pairing(&b.sig.sigma_1, &b.K, &(-&b.sig.sigma_2), &params.g_tilde).is_one() {
+                return Ok(false);
+            }
+            let (bases, J) = branch_statement(vk, &b.K, &no_revealed, params);
+            if !b.branch.verify(&bases, &J)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn compute_challenge<'a, I: Iterator<Item = (&'a Signature, &'a VerkeyGroup, &'a VerkeyGroup)>>(
+        parts: I,
+        verkeys: &[Verkey],
+        params: &Params,
+        nonce: &[u8],
+    ) -> FieldElement {
+        let mut bytes = OR_CREDENTIALS_DST.to_vec();
+        bytes.append(&mut params.g_tilde.to_bytes());
+        for vk in verkeys {
+            bytes.extend_from_slice(&(vk.Y_tilde.len() as u64).to_be_bytes());
+            bytes.append(&mut vk.X_tilde.to_bytes());
+            for y in &vk.Y_tilde {
+                bytes.append(&mut y.to_bytes());
+            }
+        }
+        for (sig, K, commitment) in parts {
+            bytes.append(&mut sig.to_bytes());
+            bytes.append(&mut K.to_bytes());
+            bytes.append(&mut commitment.to_bytes());
+        }
+        bytes.extend_from_slice(&(nonce.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(nonce);
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_or_proof_of_credentials() {
+        // A driver's licence with 5 messages or a passport with 3
+        let params = Params::new("test".as_bytes());
+        let (licence_sk, licence_vk) = keygen(5, &params);
+        let (passport_sk, passport_vk) = keygen(3, &params);
+        let verkeys = vec![licence_vk, passport_vk];
+        let nonce = b"verifier nonce";
+
+        let licence_msgs = (0..5).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let licence = Signature::new(licence_msgs.as_slice(), &licence_sk, &params).unwrap();
+        let passport_msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let passport = Signature::new(passport_msgs.as_slice(), &passport_sk, &params).unwrap();
+
+        for (sig, msgs, index) in vec![(&licence, &licence_msgs, 0), (&passport, &passport_msgs, 1)] {
+            let proof = OrProofOfCredentials::new(sig, msgs, &verkeys, index, &params, nonce).unwrap();
+            assert!(proof.verify(&verkeys, &params, nonce).unwrap());
+            assert!(!proof.verify(&verkeys, &params, b"other nonce").unwrap());
+            let swapped = vec![verkeys[1].clone(), verkeys[0].clone()];
+            assert!(!proof.verify(&swapped, &params, nonce).unwrap());
+            assert!(!proof.verify(&verkeys[..1], &params, nonce).unwrap());
+        }
+
+        // A credential of neither type
+        let (other_sk, _) = keygen(5, &params);
+        let other = Signature::new(licence_msgs.as_slice(), &other_sk, &params).unwrap();
+        let proof = OrProofOfCredentials::new(&other, &licence_msgs, &verkeys, 0, &params, nonce).unwrap();
+        assert!(!proof.verify(&verkeys, &params, nonce).unwrap());
+        // Messages not matching the verkey
+        assert!(OrProofOfCredentials::new(&licence, &licence_msgs, &verkeys, 1, &params, nonce).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+// Signing (2016 scheme) by several co-signers each responsible for a disjoint block of the messages, e.g. an issuer
+// signing the identity attributes and an authority signing the qualification attributes of the same credential.
+// Co-signer j has its own key (x_j, y_j_1, y_j_2, ...) for the messages at offsets o_j to o_j + n_j - 1 and the
+// combined verkey has X_tilde = X_tilde_1 * X_tilde_2 * ... and the Y_tilde of each co-signer at its offsets, which
+// is the verkey of the key (x_1 + x_2 + ..., y of each co-signer). All co-signers sign over the same sigma_1 whose
+// discrete log is unknown to them, derived by hashing all messages, and the product of the sigma_2 of each block is
+// a signature verifiable with the combined verkey.
+
+use crate::errors::PSError;
+use crate::keys::{Scheme, Signer, Verkey};
+use crate::signature::Signature;
+use crate::{SignatureGroup, VerkeyGroup};
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Signature of a co-signer over its block of messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockSignature {
+    /// Index of the first message of the block
+    pub offset: usize,
+    /// No of messages in the block
+    pub count: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub sigma_2: SignatureGroup,
+}
+
+/// Check that the blocks given as (offset, count) are disjoint and cover the messages 0 to `total - 1`
+fn check_blocks(blocks: &[(usize, usize)], total: usize) -> Result<(), PSError> {
+    let mut sorted = blocks.to_vec();
+    sorted.sort();
+    let mut next = 0;
+    for (offset, count) in sorted {
+        if offset != next || count == 0 {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Block at offset {} of {} messages overlaps or leaves a gap, expected offset {}",
+                    offset, count, next
+                ),
+            });
+        }
+        next = offset + count;
+    }
+    if next != total {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: total,
+            given: next,
+        });
+    }
+    Ok(())
+}
+
+/// The sigma_1 all co-signers sign over, the hash of all messages like in `Signature::new_deterministic`
+pub fn shared_sigma_1(messages: &[FieldElement]) -> SignatureGroup {
+    Signature::generate_sigma_1_from_messages(messages)
+}
+
+/// Verkey of the combined key from the verkey of each co-signer and the offset of its block. Fails unless the
+/// blocks are disjoint and cover all `total` messages.
+pub fn combine_verkeys(blocks: Vec<(usize, &Verkey)>, total: usize) -> Result<Verkey, PSError> {
+    check_blocks(
+        &blocks.iter().map(|(o, vk)| (*o, vk.Y_tilde.len())).collect::<Vec<_>>(),
+        total,
+    )?;
+    let mut X_tilde = VerkeyGroup::identity();
+    let mut Y_tilde = vec![VerkeyGroup::identity(); total];
+    for (offset, vk) in blocks {
+        X_tilde += &vk.X_tilde;
+        for (i, y) in vk.Y_tilde.iter().enumerate() {
+            Y_tilde[offset + i] = y.clone();
+        }
+    }
+    Ok(Verkey {
+        X_tilde,
+        Y_tilde,
+        metadata: None,
+    })
+}
+
+/// Called by a co-signer to sign `messages`, all messages of the signature, at `offset` to `offset + key_size - 1`
+/// with its key
+pub fn sign_block<S: Signer>(messages: &[FieldElement], offset: usize, sigkey: &S) -> Result<BlockSignature, PSError> {
+    let count = sigkey.key_size();
+    if offset + count > messages.len() {
+        return Err(PSError::UnsupportedNoOfMessages {
+            expected: offset + count,
+            given: messages.len(),
+        });
+    }
+    let block = &messages[offset..offset + count];
+    if let Some(metadata) = sigkey.metadata() {
+        metadata.check(Scheme::PS2016, block.len(), None)?;
+    }
+    let sigma_1 = shared_sigma_1(messages);
+    let sigma_2 = Signature::sign_with_given_sigma_1(block, sigkey, 0, &sigma_1)?;
+    Ok(BlockSignature { offset, count, sigma_2 })
+}
+
+/// Combine the signatures of the blocks of `messages`. Fails unless the blocks are disjoint and cover all messages.
+pub fn combine_blocks(messages: &[FieldElement], blocks: &[BlockSignature]) -> Result<Signature, PSError> {
+    check_blocks(
+        &blocks.iter().map(|b| (b.offset, b.count)).collect::<Vec<_>>(),
+        messages.len(),
+    )?;
+    let mut sigma_2 = SignatureGroup::identity();
+    for b in blocks {
+        sigma_2 += &b.sigma_2;
+    }
+    Ok(Signature {
+        sigma_1: shared_sigma_1(messages),
+        sigma_2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, Params};
+
+    #[test]
+    fn test_partitioned_signing() {
+        let params = Params::new("test".as_bytes());
+        let msgs = (0..6).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        // Co-signers for messages 0-2, 3 and 4-5
+        let (sk_1, vk_1) = keygen(3, &params);
+        let (sk_2, vk_2) = keygen(1, &params);
+        let (sk_3, vk_3) = keygen(2, &params);
+        let vk = combine_verkeys(vec![(4, &vk_3), (0, &vk_1), (3, &vk_2)], msgs.len()).unwrap();
+
+        let blocks = vec![
+            sign_block(&msgs, 0, &sk_1).unwrap(),
+            sign_block(&msgs, 3, &sk_2).unwrap(),
+            sign_block(&msgs, 4, &sk_3).unwrap(),
+        ];
+        let sig = combine_blocks(&msgs, &blocks).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Missing block, overlapping blocks or block past the messages
+        assert!(combine_blocks(&msgs, &blocks[..2]).is_err());
+        let mut overlapping = blocks.clone();
+        overlapping[1] = sign_block(&msgs, 2, &sk_2).unwrap();
+        assert!(combine_blocks(&msgs, &overlapping).is_err());
+        assert!(sign_block(&msgs, 5, &sk_3).is_err());
+        assert!(combine_verkeys(vec![(0, &vk_1), (3, &vk_3)], msgs.len()).is_err());
+
+        // Block signed at the wrong offset gives an invalid signature
+        let mut wrong = blocks.clone();
+        wrong[0] = sign_block(&msgs[1..], 0, &sk_1).unwrap();
+        wrong[0].offset = 0;
+        let sig = combine_blocks(&msgs, &wrong).unwrap();
+        assert!(!sig.verify(msgs, &vk, &params).unwrap());
+    }
+}
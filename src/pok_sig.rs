@@ -1,25 +1,27 @@
 // Proof of knowledge of signature for signature from 2016 paper, CT-RSA 2016 (eprint 2015/525), section 6.2
 
 use crate::errors::PSError;
-use crate::keys::{Params, Verkey};
+use crate::keys::{Params, PreparedVerkey, Scheme, Verkey};
 use crate::signature::Signature;
-use crate::blind_signature::{BlindingKey, BlindSignature};
-use crate::{ate_2_pairing, VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec};
+use crate::validation::{deserialize_validated, Validate};
+use crate::wire;
+use crate::blind_signature::{
+    BlindingKey, BlindSignature, ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+};
+use crate::{
+    ate_2_pairing, ate_multi_pairing, VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec, FIELD_ELEMENT_SIZE,
+    SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE,
+};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 use amcl_wrapper::group_elem_g1::{G1Vector, G1};
 use amcl_wrapper::group_elem_g2::{G2Vector, G2};
+use rand::{CryptoRng, RngCore};
 use std::collections::{HashMap, HashSet};
+use std::io::Write;
 
-// Implement proof of knowledge of committed values in a vector commitment for `SignatureGroup`
-
-impl_PoK_VC!(
-    ProverCommittingOtherGroup,
-    ProverCommittedOtherGroup,
-    ProofOtherGroup,
-    VerkeyGroup,
-    VerkeyGroupVec
-);
+// Proof of knowledge of committed values in a vector commitment for `VerkeyGroup`
+pub use crate::pok_vc::{ProofOtherGroup, ProverCommittedOtherGroup, ProverCommittingOtherGroup};
 
 /*
 As [Short Randomizable signatures](https://eprint.iacr.org/2015/525), section 6.2 describes, for proving knowledge of a signature, the signature sigma is first randomized and also
@@ -33,6 +35,69 @@ The verifier will then check the pairing e(sigma_prime_1, J'*X_tilde) == e(sigma
 To reveal some of the messages from the signature but not all, in above protocol, construct J to be of the hidden values only, the verifier will
 then add the revealed values (raised to the respective generators) to get a final J which will then be used in the pairing check.
 */
+
+/// Label at the start of the challenge bytes of version `ChallengeVersion::V1`
+pub const POK_SIG_CHALLENGE_DST: &[u8] = b"PS-SIG-PoKOfSignature-challenge";
+
+/// Construction of the bytes hashed for the challenge. `Legacy` concatenates the randomized signature, J, the
+/// generators of the hidden messages and the commitment as `to_bytes` and `get_bytes_for_challenge` always did, and
+/// is kept so proofs created before `V1` can still be verified. `V1` starts with `POK_SIG_CHALLENGE_DST`, the version
+/// and the scheme tag and prefixes each element and the list of generators with its length as 4 byte big-endian, so
+/// the bytes can't collide with those of another protocol, scheme or number of hidden messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChallengeVersion {
+    Legacy,
+    V1,
+}
+
+impl Default for ChallengeVersion {
+    fn default() -> Self {
+        ChallengeVersion::Legacy
+    }
+}
+
+impl ChallengeVersion {
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            ChallengeVersion::Legacy => 0,
+            ChallengeVersion::V1 => 1,
+        }
+    }
+}
+
+fn append_with_length(bytes: &mut Vec<u8>, mut b: Vec<u8>) {
+    bytes.extend_from_slice(&(b.len() as u32).to_be_bytes());
+    bytes.append(&mut b);
+}
+
+/// Challenge bytes of version `V1` for a proof for a signature of `scheme` with `bases`, g_tilde followed by the
+/// elements of Y_tilde of the hidden messages
+pub(crate) fn challenge_bytes_v1(
+    scheme: Scheme,
+    sig: &Signature,
+    J: &VerkeyGroup,
+    bases: Vec<Vec<u8>>,
+    commitment: &VerkeyGroup,
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    append_with_length(&mut bytes, POK_SIG_CHALLENGE_DST.to_vec());
+    bytes.push(ChallengeVersion::V1.to_byte());
+    bytes.push(scheme.to_byte());
+    append_with_length(&mut bytes, sig.to_bytes());
+    append_with_length(&mut bytes, J.to_bytes());
+    bytes.extend_from_slice(&(bases.len() as u32).to_be_bytes());
+    for b in bases {
+        append_with_length(&mut bytes, b);
+    }
+    append_with_length(&mut bytes, commitment.to_bytes());
+    bytes
+}
+
+/// State of the prover between `init` and `gen_proof`. It can be serialized with serde to suspend the protocol after
+/// `init` (e.g. to get the user's approval of the challenge) and deserialized to call `gen_proof` later, in another
+/// thread or process. The state contains the messages and blindings in the clear so the serialized state must be
+/// encrypted at rest with an AEAD under a key only the wallet has, and deleted once `gen_proof` is called. A state
+/// must never be resumed twice: 2 proofs from the same state with different challenges reveal all the hidden messages.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoKOfSignature {
     pub secrets: FieldElementVector,
@@ -41,13 +106,35 @@ pub struct PoKOfSignature {
     pub pok_vc: ProverCommittedOtherGroup,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Deserializing rejects a proof with an identity element or an element not in the prime order subgroup with
+/// `PSError::InvalidGroupElement`
+#[derive(Clone, Debug, Serialize)]
 pub struct PoKOfSignatureProof {
     pub sig: Signature,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub J: VerkeyGroup,
     pub proof_vc: ProofOtherGroup,
 }
 
+/// Fields of `PoKOfSignatureProof` as deserialized, before validation
+#[derive(Deserialize)]
+struct PoKOfSignatureProofFields {
+    sig: Signature,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    J: VerkeyGroup,
+    proof_vc: ProofOtherGroup,
+}
+
+impl<'de> serde::Deserialize<'de> for PoKOfSignatureProof {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_validated(deserializer, |f: PoKOfSignatureProofFields| PoKOfSignatureProof {
+            sig: f.sig,
+            J: f.J,
+            proof_vc: f.proof_vc,
+        })
+    }
+}
+
 impl PoKOfSignature {
     /// Section 6.2 of paper
     pub fn init(
@@ -57,15 +144,36 @@ impl PoKOfSignature {
         messages: Vec<FieldElement>,
         blindings: Option<&[FieldElement]>,
         revealed_msg_indices: HashSet<usize>,
+    ) -> Result<Self, PSError> {
+        Self::init_with_rng(sig, vk, params, messages, blindings, revealed_msg_indices, &mut rand::thread_rng())
+    }
+
+    /// Same as `init` but the randomizers of the signature and the blindings not given are generated using `rng`
+    pub fn init_with_rng<R: RngCore + CryptoRng>(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        blindings: Option<&[FieldElement]>,
+        revealed_msg_indices: HashSet<usize>,
+        rng: &mut R,
     ) -> Result<Self, PSError> {
         Signature::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
         Self::validate_revealed_indices(messages.as_slice(), &revealed_msg_indices)?;
 
         let blindings = Self::get_blindings(blindings, messages.as_slice(), &revealed_msg_indices)?;
+        let blindings = Self::fill_blindings_with_rng(blindings, rng);
 
-        let (t, sigma_prime) = Self::transform_sig(sig);
+        let (t, sigma_prime) = Self::transform_sig_with_rng(sig, rng);
 
-        let (exponents, J, committed) = Self::commit_for_pok(messages, blindings, &revealed_msg_indices, t, vk, params);
+        let (exponents, J, committed) = Self::commit_for_pok(
+            messages,
+            blindings.iter().map(Some).collect(),
+            &revealed_msg_indices,
+            t,
+            vk,
+            params,
+        );
 
         Ok(Self {
             secrets: exponents,
@@ -84,6 +192,25 @@ impl PoKOfSignature {
         bytes
     }
 
+    /// Bytes of the public elements for challenge computation with the given construction, `Legacy` being the same
+    /// as `to_bytes`
+    pub fn to_bytes_for_challenge(&self, version: ChallengeVersion) -> Vec<u8> {
+        self.to_bytes_for_challenge_with_scheme(version, Scheme::PS2016)
+    }
+
+    pub(crate) fn to_bytes_for_challenge_with_scheme(&self, version: ChallengeVersion, scheme: Scheme) -> Vec<u8> {
+        match version {
+            ChallengeVersion::Legacy => self.to_bytes(),
+            ChallengeVersion::V1 => challenge_bytes_v1(
+                scheme,
+                &self.sig,
+                &self.J,
+                self.pok_vc.bases().iter().map(|b| b.to_bytes()).collect(),
+                self.pok_vc.commitment(),
+            ),
+        }
+    }
+
     pub fn gen_proof(self, challenge: &FieldElement) -> Result<PoKOfSignatureProof, PSError> {
         let proof_vc = self.pok_vc.gen_proof(challenge, self.secrets.as_slice())?;
         Ok(PoKOfSignatureProof {
@@ -130,19 +257,35 @@ impl PoKOfSignature {
         Ok(blindings)
     }
 
+    /// Blindings with the ones not given generated using `rng`
+    pub(crate) fn fill_blindings_with_rng<R: RngCore + CryptoRng>(
+        blindings: Vec<Option<&FieldElement>>,
+        rng: &mut R,
+    ) -> Vec<FieldElement> {
+        blindings
+            .into_iter()
+            .map(|b| match b {
+                Some(b) => b.clone(),
+                None => FieldElement::random_using_rng(rng),
+            })
+            .collect()
+    }
+
     /// Transform signature to an aggregate signature on (messages, t)
-    pub(crate) fn transform_sig(sig: &Signature) -> (FieldElement, Signature) {
-        let r = FieldElement::random();
-        let t = FieldElement::random();
+    pub(crate) fn transform_sig_with_rng<R: RngCore + CryptoRng>(
+        sig: &Signature,
+        rng: &mut R,
+    ) -> (FieldElement, Signature) {
+        let r = FieldElement::random_using_rng(rng);
+        let t = FieldElement::random_using_rng(rng);
+        let sigma_prime = Self::transform_sig_with_randomness(sig, &r, &t);
+        (t, sigma_prime)
+    }
 
+    /// Same as `transform_sig` with given randomizer `r` and `t`
+    pub(crate) fn transform_sig_with_randomness(sig: &Signature, r: &FieldElement, t: &FieldElement) -> Signature {
         // Transform signature to an aggregate signature on (messages, t)
-        let sigma_prime_1 = &sig.sigma_1 * &r;
-        let sigma_prime_2 = (&sig.sigma_2 + (&sig.sigma_1 * &t)) * &r;
-
-        (t, Signature {
-            sigma_1: sigma_prime_1,
-            sigma_2: sigma_prime_2,
-        })
+        sig.randomize_with(r, t)
     }
 
     pub(crate) fn commit_for_pok(messages: Vec<FieldElement>, mut blindings: Vec<Option<&FieldElement>>, revealed_msg_indices: &HashSet<usize>,
@@ -202,6 +345,158 @@ impl PoKOfSignatureProof {
         bytes
     }
 
+    /// Same as `get_bytes_for_challenge` with the given construction, the verifier must use the same version as the
+    /// prover used with `PoKOfSignature::to_bytes_for_challenge`
+    pub fn get_bytes_for_challenge_versioned(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        version: ChallengeVersion,
+    ) -> Vec<u8> {
+        self.get_bytes_for_challenge_with_scheme(revealed_msg_indices, vk, params, version, Scheme::PS2016)
+    }
+
+    pub(crate) fn get_bytes_for_challenge_with_scheme(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        version: ChallengeVersion,
+        scheme: Scheme,
+    ) -> Vec<u8> {
+        match version {
+            ChallengeVersion::Legacy => self.get_bytes_for_challenge(revealed_msg_indices, vk, params),
+            ChallengeVersion::V1 => {
+                let mut bases = vec![params.g_tilde.to_bytes()];
+                for i in 0..vk.Y_tilde.len() {
+                    if !revealed_msg_indices.contains(&i) {
+                        bases.push(vk.Y_tilde[i].to_bytes());
+                    }
+                }
+                challenge_bytes_v1(scheme, &self.sig, &self.J, bases, &self.proof_vc.commitment)
+            }
+        }
+    }
+
+    /// Same as `get_bytes_for_challenge` but uses the byte representations cached in the prepared verkey
+    pub fn get_bytes_for_challenge_with_prepared_verkey(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        pvk: &PreparedVerkey,
+    ) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.sig.to_bytes());
+        bytes.append(&mut self.J.to_bytes());
+        match pvk.sorted_revealed_indices(&revealed_msg_indices) {
+            Ok(indices) => bytes.extend_from_slice(&pvk.revealed_set(&indices).hidden_bases_bytes),
+            // Indices out of range are skipped as in `get_bytes_for_challenge`, `verify_with_prepared_verkey` fails
+            Err(_) => {
+                bytes.extend_from_slice(&pvk.g_tilde_bytes);
+                for i in 0..pvk.Y_tilde_bytes.len() {
+                    if revealed_msg_indices.contains(&i) {
+                        continue;
+                    }
+                    bytes.extend_from_slice(&pvk.Y_tilde_bytes[i]);
+                }
+            }
+        }
+        bytes.append(&mut self.proof_vc.commitment.to_bytes());
+        bytes
+    }
+
+    /// Binary encoding of the proof: the header (see `wire`), randomized signature, J, commitment of the proof of
+    /// knowledge of the committed values in J, no of responses as 4 byte big-endian and the responses. The header
+    /// always has scheme `PS2016` since proofs for signatures of both schemes are the same.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        // Writing to a Vec does not fail
+        self.write_bytes(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Same as `to_bytes` but writes to `writer`
+    pub fn write_bytes<W: Write>(&self, writer: &mut W) -> Result<(), PSError> {
+        wire::write_header(Scheme::PS2016, writer)?;
+        self.sig.write_bytes_without_header(writer)?;
+        writer.write_all(&self.J.to_bytes())?;
+        writer.write_all(&self.proof_vc.commitment.to_bytes())?;
+        writer.write_all(&(self.proof_vc.responses.len() as u32).to_be_bytes())?;
+        for r in self.proof_vc.responses.iter() {
+            writer.write_all(&r.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Same as `to_bytes` but writes to the start of `buf`, which should have at least `serialized_len` bytes.
+    /// Returns the no of bytes written.
+    pub fn write_into(&self, buf: &mut [u8]) -> Result<usize, PSError> {
+        wire::write_into(self.serialized_len(), buf, |w| self.write_bytes(w))
+    }
+
+    /// Size in bytes of the output of `to_bytes`
+    pub fn serialized_len(&self) -> usize {
+        // The responses are for the hidden messages and `t`
+        Self::size_hint(self.proof_vc.responses.len() - 1, 0)
+    }
+
+    /// Size in bytes of the output of `to_bytes` for a proof of a signature over `msg_count` messages of which
+    /// `revealed_count` are revealed. Does not need the proof so payloads can be budgeted before creating it.
+    pub fn size_hint(msg_count: usize, revealed_count: usize) -> usize {
+        // +1 for the response of `t`
+        let responses = msg_count - revealed_count + 1;
+        wire::HEADER_SIZE
+            + 2 * SIGNATURE_GROUP_SIZE
+            + 2 * VERKEY_GROUP_SIZE
+            + 4
+            + responses * FIELD_ELEMENT_SIZE
+    }
+
+    /// Parse a proof from the output of `to_bytes`. The group elements are validated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        wire::read_header_for_scheme(bytes, Scheme::PS2016)?;
+        let header_size = wire::HEADER_SIZE + 2 * SIGNATURE_GROUP_SIZE + 2 * VERKEY_GROUP_SIZE + 4;
+        if bytes.len() < header_size {
+            return Err(PSError::IncorrectByteLength {
+                expected: header_size,
+                given: bytes.len(),
+            });
+        }
+        let mut offset = wire::HEADER_SIZE;
+        let sig = Signature::from_bytes_without_header(&bytes[offset..offset + 2 * SIGNATURE_GROUP_SIZE])?;
+        offset += 2 * SIGNATURE_GROUP_SIZE;
+        let J = VerkeyGroup::from_bytes(&bytes[offset..offset + VERKEY_GROUP_SIZE])?;
+        offset += VERKEY_GROUP_SIZE;
+        let commitment = VerkeyGroup::from_bytes(&bytes[offset..offset + VERKEY_GROUP_SIZE])?;
+        offset += VERKEY_GROUP_SIZE;
+        let mut count_bytes = [0u8; 4];
+        count_bytes.copy_from_slice(&bytes[offset..offset + 4]);
+        let count = u32::from_be_bytes(count_bytes) as usize;
+        offset += 4;
+
+        // Compare without multiplying to avoid overflow with a large count
+        if (bytes.len() - offset) / FIELD_ELEMENT_SIZE != count || (bytes.len() - offset) % FIELD_ELEMENT_SIZE != 0 {
+            return Err(PSError::IncorrectByteLength {
+                expected: offset + count.saturating_mul(FIELD_ELEMENT_SIZE),
+                given: bytes.len(),
+            });
+        }
+        let mut responses = FieldElementVector::with_capacity(count);
+        for chunk in bytes[offset..].chunks(FIELD_ELEMENT_SIZE) {
+            responses.push(FieldElement::from_bytes(chunk)?);
+        }
+        let proof = Self {
+            sig,
+            J,
+            proof_vc: ProofOtherGroup {
+                commitment,
+                responses,
+            },
+        };
+        proof.validate()?;
+        Ok(proof)
+    }
+
     /// Get the response from post-challenge phase of the Sigma protocol for the given message index `msg_idx`.
     /// Used when comparing message equality
     pub fn get_resp_for_message(&self, msg_idx: usize) -> Result<FieldElement, PSError> {
@@ -219,6 +514,16 @@ impl PoKOfSignatureProof {
         Ok(self.proof_vc.responses[1 + msg_idx].clone())
     }
 
+    /// Response for the message at index `msg_idx` of the signed messages, `get_resp_for_message` takes the index
+    /// among the hidden messages. Fails if the message at `msg_idx` is in `revealed_msg_indices`.
+    pub fn get_resp_for_hidden_message(
+        &self,
+        msg_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+    ) -> Result<FieldElement, PSError> {
+        self.get_resp_for_message(hidden_message_position(msg_idx, revealed_msg_indices)?)
+    }
+
     pub fn verify(
         &self,
         vk: &Verkey,
@@ -268,22 +573,591 @@ impl PoKOfSignatureProof {
         );
         Ok(res.is_one())
     }
+
+    /// Verify the proof using a verkey on which precomputation has been done. The bases are taken from the prepared
+    /// set of the revealed indices if `PreparedVerkey::prepare_revealed` was called for them. Fails if a revealed index
+    /// is not less than the no of messages.
+    pub fn verify_with_prepared_verkey(
+        &self,
+        pvk: &PreparedVerkey,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        pvk.check_metadata(pvk.msg_count())?;
+        let indices = pvk.sorted_revealed_indices(&revealed_msgs.keys().cloned().collect())?;
+        if self.sig.is_identity() {
+            return Ok(false);
+        }
+        let set = pvk.revealed_set(&indices);
+        if !self.proof_vc.verify(&set.hidden_bases, &self.J, challenge)? {
+            return Ok(false);
+        }
+
+        let mut J = &self.J + &pvk.vk.X_tilde;
+        if !indices.is_empty() {
+            let mut e = FieldElementVector::with_capacity(indices.len());
+            for i in &indices {
+                e.push(revealed_msgs[i].clone());
+            }
+            J += set.revealed_bases.multi_scalar_mul_var_time(&e).unwrap();
+        }
+        // e(sigma_prime_1, J*X_tilde) * e(sigma_prime_2, -g_tilde) == 1
+        let res = ate_2_pairing(&self.sig.sigma_1, &J, &self.sig.sigma_2, &pvk.neg_g_tilde);
+        Ok(res.is_one())
+    }
+}
+
+/// Position of the message at `msg_idx` among the hidden messages, which is the index of its blinding and, in the
+/// proof, of its response. Fails if the message is revealed.
+pub fn hidden_message_position(msg_idx: usize, revealed_msg_indices: &HashSet<usize>) -> Result<usize, PSError> {
+    if revealed_msg_indices.contains(&msg_idx) {
+        return Err(PSError::GeneralError {
+            msg: format!("Message at index {} is revealed", msg_idx),
+        });
+    }
+    Ok(msg_idx - revealed_msg_indices.iter().filter(|i| **i < msg_idx).count())
+}
+
+impl PoKOfSignature {
+    /// Start proofs of knowledge of 2 signatures, possibly under different verkeys, proving that the hidden message at
+    /// index i of the first equals the hidden message at index j of the second for each `(i, j)` in `equal_indices`
+    /// without revealing it. The same blinding is used for both messages of a pair so their responses are equal for a
+    /// common challenge, which must be computed over both proofs. The verifier checks both proofs with that challenge
+    /// and the equality with `PoKOfSignatureProof::verify_equal_messages`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_with_equal_messages(
+        sig_1: &Signature,
+        vk_1: &Verkey,
+        messages_1: Vec<FieldElement>,
+        revealed_msg_indices_1: HashSet<usize>,
+        sig_2: &Signature,
+        vk_2: &Verkey,
+        messages_2: Vec<FieldElement>,
+        revealed_msg_indices_2: HashSet<usize>,
+        equal_indices: &[(usize, usize)],
+        params: &Params,
+    ) -> Result<(Self, Self), PSError> {
+        Self::init_with_equal_messages_with_rng(
+            sig_1,
+            vk_1,
+            messages_1,
+            revealed_msg_indices_1,
+            sig_2,
+            vk_2,
+            messages_2,
+            revealed_msg_indices_2,
+            equal_indices,
+            params,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Same as `init_with_equal_messages` but the randomizers of the signatures and the blindings are generated
+    /// using `rng`
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_with_equal_messages_with_rng<R: RngCore + CryptoRng>(
+        sig_1: &Signature,
+        vk_1: &Verkey,
+        messages_1: Vec<FieldElement>,
+        revealed_msg_indices_1: HashSet<usize>,
+        sig_2: &Signature,
+        vk_2: &Verkey,
+        messages_2: Vec<FieldElement>,
+        revealed_msg_indices_2: HashSet<usize>,
+        equal_indices: &[(usize, usize)],
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<(Self, Self), PSError> {
+        Self::validate_revealed_indices(messages_1.as_slice(), &revealed_msg_indices_1)?;
+        Self::validate_revealed_indices(messages_2.as_slice(), &revealed_msg_indices_2)?;
+        let mut blindings_1 = (0..messages_1.len() - revealed_msg_indices_1.len())
+            .map(|_| FieldElement::random_using_rng(rng))
+            .collect::<Vec<FieldElement>>();
+        let mut blindings_2 = (0..messages_2.len() - revealed_msg_indices_2.len())
+            .map(|_| FieldElement::random_using_rng(rng))
+            .collect::<Vec<FieldElement>>();
+        for (i, j) in equal_indices {
+            if *i >= messages_1.len() || *j >= messages_2.len() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Indices ({}, {}) out of range", i, j),
+                });
+            }
+            if messages_1[*i] != messages_2[*j] {
+                return Err(PSError::GeneralError {
+                    msg: format!("Message at index {} of first is not equal to message at index {} of second", i, j),
+                });
+            }
+            let blinding = FieldElement::random_using_rng(rng);
+            blindings_1[hidden_message_position(*i, &revealed_msg_indices_1)?] = blinding.clone();
+            blindings_2[hidden_message_position(*j, &revealed_msg_indices_2)?] = blinding;
+        }
+        let pok_1 = Self::init_with_rng(
+            sig_1,
+            vk_1,
+            params,
+            messages_1,
+            Some(blindings_1.as_slice()),
+            revealed_msg_indices_1,
+            rng,
+        )?;
+        let pok_2 = Self::init_with_rng(
+            sig_2,
+            vk_2,
+            params,
+            messages_2,
+            Some(blindings_2.as_slice()),
+            revealed_msg_indices_2,
+            rng,
+        )?;
+        Ok((pok_1, pok_2))
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Check that the responses for the hidden messages at each `(i, j)` of `equal_indices`, i in the first proof and
+    /// j in the second, are equal. The proofs must be created with `PoKOfSignature::init_with_equal_messages` and
+    /// verified with a common challenge, else equal responses prove nothing.
+    pub fn verify_equal_messages(
+        proof_1: &Self,
+        revealed_msg_indices_1: &HashSet<usize>,
+        proof_2: &Self,
+        revealed_msg_indices_2: &HashSet<usize>,
+        equal_indices: &[(usize, usize)],
+    ) -> Result<bool, PSError> {
+        for (i, j) in equal_indices {
+            let resp_1 = proof_1.get_resp_for_hidden_message(*i, revealed_msg_indices_1)?;
+            let resp_2 = proof_2.get_resp_for_hidden_message(*j, revealed_msg_indices_2)?;
+            if resp_1 != resp_2 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Prover's state of a proof of knowledge of the opening of a commitment in the signature group over `bases` to
+/// `exponents`. A blinding can be given for an exponent so that its response is equal to the response with the same
+/// blinding in another proof with the same challenge, as in `PoKOfSignatureAndCommitment`.
+pub struct PoKOfCommitment {
+    committed: ProverCommittedSignatureGroup,
+    commitment: SignatureGroup,
+    exponents: FieldElementVector,
+}
+
+impl PoKOfCommitment {
+    /// `blindings` has the blinding of each exponent, a random blinding is used for `None`
+    pub fn init(
+        bases: &[SignatureGroup],
+        exponents: Vec<FieldElement>,
+        blindings: &[Option<&FieldElement>],
+    ) -> Result<Self, PSError> {
+        if bases.len() != exponents.len() || bases.len() != blindings.len() {
+            return Err(PSError::UnequalNoOfBasesExponents {
+                bases: bases.len(),
+                exponents: exponents.len(),
+            });
+        }
+        let mut committing = ProverCommittingSignatureGroup::new();
+        for (b, r) in bases.iter().zip(blindings.iter()) {
+            committing.commit(b, *r);
+        }
+        let mut exponent_vec = FieldElementVector::with_capacity(exponents.len());
+        for e in exponents {
+            exponent_vec.push(e);
+        }
+        let commitment = SignatureGroupVec::from(bases)
+            .multi_scalar_mul_const_time(&exponent_vec)
+            .unwrap();
+        Ok(Self {
+            committed: committing.finish(),
+            commitment,
+            exponents: exponent_vec,
+        })
+    }
+
+    pub fn commitment(&self) -> &SignatureGroup {
+        &self.commitment
+    }
+
+    /// Bytes of the bases, the random commitment and the commitment for the challenge
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.committed.to_bytes();
+        bytes.append(&mut self.commitment.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<ProofSignatureGroup, PSError> {
+        self.committed.gen_proof(challenge, self.exponents.as_slice())
+    }
+}
+
+/// Bytes of a proof of knowledge of the opening of `commitment` over `bases` for the challenge, the same as
+/// `PoKOfCommitment::to_bytes` of the prover
+pub fn get_bytes_for_commitment_challenge(
+    bases: &[SignatureGroup],
+    proof: &ProofSignatureGroup,
+    commitment: &SignatureGroup,
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    for b in bases {
+        bytes.append(&mut b.to_bytes());
+    }
+    bytes.append(&mut proof.commitment.to_bytes());
+    bytes.append(&mut commitment.to_bytes());
+    bytes
+}
+
+/// Prover's state of a proof of knowledge of a signature and of the opening of a commitment in the signature group to
+/// some of its hidden messages followed by other values, like the blinding of the commitment. Both proofs use the same
+/// challenge and the same blinding for each committed message, so the responses for it are equal in both proofs,
+/// which shows that the committed messages are the signed ones. The verifier checks both proofs and the responses with
+/// `PoKOfSignatureProof::verify_with_commitment`.
+pub struct PoKOfSignatureAndCommitment {
+    pub pok_sig: PoKOfSignature,
+    pub pok_commitment: PoKOfCommitment,
+}
+
+impl PoKOfSignatureAndCommitment {
+    /// Start the proofs for `sig` over `messages` revealing the messages at `revealed_msg_indices`. The commitment is
+    /// to the hidden message at index i with base b for each `(i, b)` of `committed_msgs`, in that order, and then to
+    /// each exponent e with base b for each `(b, e)` of `others`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        revealed_msg_indices: HashSet<usize>,
+        committed_msgs: &[(usize, SignatureGroup)],
+        others: &[(SignatureGroup, FieldElement)],
+    ) -> Result<Self, PSError> {
+        for i in revealed_msg_indices.iter().chain(committed_msgs.iter().map(|(i, _)| i)) {
+            if *i >= messages.len() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", i, messages.len()),
+                });
+            }
+        }
+        let mut bases = Vec::with_capacity(committed_msgs.len() + others.len());
+        let mut exponents = Vec::with_capacity(committed_msgs.len() + others.len());
+        let mut positions = Vec::with_capacity(committed_msgs.len());
+        for (i, b) in committed_msgs {
+            positions.push(hidden_message_position(*i, &revealed_msg_indices)?);
+            bases.push(b.clone());
+            exponents.push(messages[*i].clone());
+        }
+        for (b, e) in others {
+            bases.push(b.clone());
+            exponents.push(e.clone());
+        }
+
+        let blindings = FieldElementVector::random(messages.len() - revealed_msg_indices.len());
+        let mut commitment_blindings = positions
+            .iter()
+            .map(|j| Some(&blindings[*j]))
+            .collect::<Vec<Option<&FieldElement>>>();
+        commitment_blindings.resize(bases.len(), None);
+        let pok_commitment = PoKOfCommitment::init(&bases, exponents, &commitment_blindings)?;
+        let pok_sig = PoKOfSignature::init(
+            sig,
+            vk,
+            params,
+            messages,
+            Some(blindings.as_slice()),
+            revealed_msg_indices,
+        )?;
+        Ok(Self { pok_sig, pok_commitment })
+    }
+
+    pub fn commitment(&self) -> &SignatureGroup {
+        self.pok_commitment.commitment()
+    }
+
+    /// Bytes of both proofs for the challenge
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.pok_sig.to_bytes();
+        bytes.append(&mut self.pok_commitment.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> Result<(PoKOfSignatureProof, ProofSignatureGroup), PSError> {
+        Ok((
+            self.pok_sig.gen_proof(challenge)?,
+            self.pok_commitment.gen_proof(challenge)?,
+        ))
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Bytes of the proofs of a `PoKOfSignatureAndCommitment` for the challenge, the same as its `to_bytes`
+    pub fn get_bytes_for_challenge_with_commitment(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        bases: &[SignatureGroup],
+        pok_commitment: &ProofSignatureGroup,
+        commitment: &SignatureGroup,
+    ) -> Vec<u8> {
+        let mut bytes = self.get_bytes_for_challenge(revealed_msg_indices, vk, params);
+        bytes.append(&mut get_bytes_for_commitment_challenge(bases, pok_commitment, commitment));
+        bytes
+    }
+
+    /// Verify the proofs of a `PoKOfSignatureAndCommitment`, this proof and `pok_commitment`, the proof of knowledge
+    /// of the opening of `commitment` over `bases`, where the first bases are for the hidden messages at
+    /// `committed_indices`, and check that the responses for those messages are equal in both proofs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_with_commitment(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        pok_commitment: &ProofSignatureGroup,
+        bases: &[SignatureGroup],
+        commitment: &SignatureGroup,
+        committed_indices: &[usize],
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if committed_indices.len() > bases.len() {
+            return Err(PSError::UnequalNoOfBasesExponents {
+                bases: bases.len(),
+                exponents: committed_indices.len(),
+            });
+        }
+        let revealed_msg_indices = revealed_msgs.keys().cloned().collect::<HashSet<usize>>();
+        if !self.verify(vk, params, revealed_msgs, challenge)? {
+            return Ok(false);
+        }
+        if !pok_commitment.verify(bases, commitment, challenge)? {
+            return Ok(false);
+        }
+        for (j, i) in committed_indices.iter().enumerate() {
+            if self.get_resp_for_hidden_message(*i, &revealed_msg_indices)? != pok_commitment.responses[j] {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A proof of knowledge of a signature with the verkey, the revealed messages and the challenge the verifier expects,
+/// for `PoKOfSignatureProof::batch_verify`
+#[derive(Clone, Debug)]
+pub struct ProofWithContext<'a> {
+    pub proof: &'a PoKOfSignatureProof,
+    pub vk: &'a Verkey,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+    pub challenge: FieldElement,
+}
+
+impl<'a> ProofWithContext<'a> {
+    /// The signature is not the identity, the revealed indices are in range and there is a response for `t` and each
+    /// hidden message. `verify` returns an error or false for proofs failing this.
+    fn is_well_formed(&self) -> bool {
+        let msg_count = self.vk.Y_tilde.len();
+        !self.proof.sig.is_identity()
+            && self.revealed_msgs.keys().all(|i| *i < msg_count)
+            && self.proof.proof_vc.responses.len() == msg_count - self.revealed_msgs.len() + 1
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Verify many proofs, possibly under different verkeys and with different challenges, like a verifier receiving
+    /// many presentations. Returns the indices of the invalid proofs in `proofs_with_context`, empty if all are valid
+    /// (except with negligible probability). For proofs i with random r_i and rho_i, the Schnorr checks
+    /// g_tilde^s_i_0 * Y_tilde_i_1^s_i_1 * ... * J_i^c_i == T_i are combined into one multi-exponentiation
+    /// prod(g_tilde^s_i_0 * Y_tilde_i_1^s_i_1 * ... * J_i^c_i * T_i^-1)^rho_i == 1 and the pairing checks into one
+    /// multi-pairing e(sigma_1_1^r_1, J_1') * e(sigma_1_2^r_2, J_2') * ... == e(sigma_2_1^r_1 * sigma_2_2^r_2 * ..., g_tilde)
+    /// where J_i' is J_i * X_tilde_i with the revealed messages. If the combined check fails, the proofs are split in
+    /// halves which are checked again till the invalid proofs are found, so a few invalid proofs among many cost a few
+    /// more checks rather than verifying each proof.
+    pub fn batch_verify(proofs_with_context: &[ProofWithContext], params: &Params) -> Vec<usize> {
+        let mut invalid = vec![];
+        let mut well_formed = vec![];
+        for (i, p) in proofs_with_context.iter().enumerate() {
+            if p.is_well_formed() {
+                well_formed.push(i);
+            } else {
+                invalid.push(i);
+            }
+        }
+        Self::find_invalid(proofs_with_context, &well_formed, params, &mut invalid);
+        invalid.sort_unstable();
+        invalid
+    }
+
+    fn find_invalid(
+        proofs_with_context: &[ProofWithContext],
+        indices: &[usize],
+        params: &Params,
+        invalid: &mut Vec<usize>,
+    ) {
+        if indices.is_empty() || Self::batch_check(proofs_with_context, indices, params) {
+            return;
+        }
+        if indices.len() == 1 {
+            invalid.push(indices[0]);
+            return;
+        }
+        let (left, right) = indices.split_at(indices.len() / 2);
+        Self::find_invalid(proofs_with_context, left, params, invalid);
+        Self::find_invalid(proofs_with_context, right, params, invalid);
+    }
+
+    /// The combined check of `batch_verify` for the well formed proofs at `indices`
+    fn batch_check(proofs_with_context: &[ProofWithContext], indices: &[usize], params: &Params) -> bool {
+        let rho = FieldElementVector::random(indices.len());
+        let r = FieldElementVector::random(indices.len());
+
+        // g_tilde is common to all proofs so its exponents are summed
+        let mut g_tilde_exp = FieldElement::zero();
+        let mut points = VerkeyGroupVec::with_capacity(indices.len() * 3);
+        let mut scalars = FieldElementVector::with_capacity(indices.len() * 3);
+        let mut sigma_1s = Vec::with_capacity(indices.len());
+        let mut Js = Vec::with_capacity(indices.len());
+        let mut sigma_2s = SignatureGroupVec::with_capacity(indices.len());
+        for ((rho_i, r_i), idx) in rho.iter().zip(r.iter()).zip(indices.iter()) {
+            let p = &proofs_with_context[*idx];
+            let responses = &p.proof.proof_vc.responses;
+            g_tilde_exp += rho_i * &responses[0];
+            let mut k = 1;
+            let mut J = &p.proof.J + &p.vk.X_tilde;
+            for (i, Y) in p.vk.Y_tilde.iter().enumerate() {
+                match p.revealed_msgs.get(&i) {
+                    Some(m) => J += Y * m,
+                    None => {
+                        points.push(Y.clone());
+                        scalars.push(rho_i * &responses[k]);
+                        k += 1;
+                    }
+                }
+            }
+            points.push(p.proof.J.clone());
+            scalars.push(rho_i * &p.challenge);
+            points.push(p.proof.proof_vc.commitment.clone());
+            scalars.push(&FieldElement::zero() - rho_i);
+
+            sigma_1s.push(&p.proof.sig.sigma_1 * r_i);
+            Js.push(J);
+            sigma_2s.push(p.proof.sig.sigma_2.clone());
+        }
+        points.push(params.g_tilde.clone());
+        scalars.push(g_tilde_exp);
+        if !points.multi_scalar_mul_var_time(&scalars).unwrap().is_identity() {
+            return false;
+        }
+
+        let neg_sigma_2 = sigma_2s.multi_scalar_mul_var_time(&r).unwrap().negation();
+        let mut pairs = vec![(&neg_sigma_2, &params.g_tilde)];
+        for (s, J) in sigma_1s.iter().zip(Js.iter()) {
+            pairs.push((s, J));
+        }
+        ate_multi_pairing(pairs).is_one()
+    }
+}
+
+/// Label of the verifier's nonce and the presentation context in the challenge bytes of `BoundPoKOfSignature`
+pub const PRESENTATION_DST: &[u8] = b"PS-SIG-PoKOfSignature-presentation";
+
+fn challenge_with_presentation(mut bytes: Vec<u8>, nonce: &[u8], context: &[u8]) -> FieldElement {
+    append_with_length(&mut bytes, PRESENTATION_DST.to_vec());
+    append_with_length(&mut bytes, nonce.to_vec());
+    append_with_length(&mut bytes, context.to_vec());
+    FieldElement::from_msg_hash(&bytes)
+}
+
+/// Proof of knowledge of a signature bound to a nonce chosen by the verifier and a context string (like the verifier's
+/// identity or the purpose of the presentation) so the proof can't be replayed to the same or another verifier. The
+/// challenge is computed over the `ChallengeVersion::V1` bytes followed by `PRESENTATION_DST`, the nonce and the
+/// context, each prefixed with its length. The verifier checks with `PoKOfSignatureProof::verify_with_nonce` using
+/// the nonce it sent and its context, the proof is rejected if either differs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoundPoKOfSignature {
+    pub pok: PoKOfSignature,
+    pub nonce: Vec<u8>,
+    pub context: Vec<u8>,
+}
+
+impl BoundPoKOfSignature {
+    /// Same as `PoKOfSignature::init` with the verifier's nonce and the context of the presentation
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        blindings: Option<&[FieldElement]>,
+        revealed_msg_indices: HashSet<usize>,
+        nonce: &[u8],
+        context: &[u8],
+    ) -> Result<Self, PSError> {
+        let pok = PoKOfSignature::init(sig, vk, params, messages, blindings, revealed_msg_indices)?;
+        Ok(Self {
+            pok,
+            nonce: nonce.to_vec(),
+            context: context.to_vec(),
+        })
+    }
+
+    /// Challenge over the public elements, the nonce and the context
+    pub fn challenge(&self) -> FieldElement {
+        challenge_with_presentation(
+            self.pok.to_bytes_for_challenge(ChallengeVersion::V1),
+            &self.nonce,
+            &self.context,
+        )
+    }
+
+    pub fn gen_proof(self) -> Result<PoKOfSignatureProof, PSError> {
+        let challenge = self.challenge();
+        self.pok.gen_proof(&challenge)
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Challenge of a proof created with `BoundPoKOfSignature` for the given nonce and context
+    pub fn challenge_with_nonce(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        nonce: &[u8],
+        context: &[u8],
+    ) -> FieldElement {
+        challenge_with_presentation(
+            self.get_bytes_for_challenge_versioned(revealed_msg_indices, vk, params, ChallengeVersion::V1),
+            nonce,
+            context,
+        )
+    }
+
+    /// Verify a proof created with `BoundPoKOfSignature` for the nonce sent by the verifier and its context. Returns
+    /// false if the prover used a different nonce or context.
+    pub fn verify_with_nonce(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        nonce: &[u8],
+        context: &[u8],
+    ) -> Result<bool, PSError> {
+        let challenge =
+            self.challenge_with_nonce(revealed_msgs.keys().cloned().collect(), vk, params, nonce, context);
+        self.verify(vk, params, revealed_msgs, &challenge)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     // For benchmarking
-    use crate::keys::keygen;
+    use crate::keys::{keygen, KeyMetadata};
     use std::time::{Duration, Instant};
 
-    impl_PoK_VC!(
-        ProverCommittingSignatureGroup,
-        ProverCommittedSignatureGroup,
-        ProofSignatureGroup,
-        SignatureGroup,
-        SignatureGroupVec
-    );
+    use crate::pok_vc::{
+        ProofSignatureGroup, ProverCommittedSignatureGroup, ProverCommittingSignatureGroup,
+    };
 
     #[test]
     fn test_PoK_VC_SignatureGroup() {
@@ -357,6 +1231,52 @@ mod tests {
             .unwrap());
     }
 
+    #[test]
+    fn test_PoK_sig_with_rng() {
+        // Same seed gives the same keys, signature and proof
+        use crate::keys::keygen_with_rng;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        let prove = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (sk, vk) = keygen_with_rng(count_msgs, &params, &mut rng);
+            let sig = Signature::new_with_rng(msgs.as_slice(), &sk, &params, &mut rng).unwrap();
+            let pok = PoKOfSignature::init_with_rng(
+                &sig,
+                &vk,
+                &params,
+                msgs.clone(),
+                None,
+                revealed_msg_indices.clone(),
+                &mut rng,
+            )
+            .unwrap();
+            let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+            (vk, pok.gen_proof(&chal).unwrap())
+        };
+
+        let (vk, proof) = prove(1);
+        let (vk_1, proof_1) = prove(1);
+        let (vk_2, proof_2) = prove(2);
+        assert_eq!(vk.to_bytes(Scheme::PS2016), vk_1.to_bytes(Scheme::PS2016));
+        assert_eq!(proof.to_bytes(), proof_1.to_bytes());
+        assert_ne!(vk.to_bytes(Scheme::PS2016), vk_2.to_bytes(Scheme::PS2016));
+        assert_ne!(proof.to_bytes(), proof_2.to_bytes());
+
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_msg_indices, &vk, &params);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        assert!(proof
+            .verify(&vk, &params, revealed_msgs, &FieldElement::from_msg_hash(&chal_bytes))
+            .unwrap());
+    }
+
     #[test]
     fn test_PoK_sig_reveal_messages() {
         let count_msgs = 10;
@@ -525,6 +1445,451 @@ mod tests {
         assert!(proof_2.verify(&vk, &params, HashMap::new(), &chal_verifier).unwrap());
     }
 
+    #[test]
+    fn test_PoK_sig_versioned_challenge() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        revealed_msg_indices.insert(3);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        revealed_msgs.insert(3, msgs[3].clone());
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices.clone()).unwrap();
+        assert_eq!(pok.to_bytes_for_challenge(ChallengeVersion::Legacy), pok.to_bytes());
+        let prover_bytes = pok.to_bytes_for_challenge(ChallengeVersion::V1);
+        assert_ne!(prover_bytes, pok.to_bytes());
+        assert!(prover_bytes.starts_with(&(POK_SIG_CHALLENGE_DST.len() as u32).to_be_bytes()));
+        let chal_prover = FieldElement::from_msg_hash(&prover_bytes);
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        assert_eq!(
+            proof.get_bytes_for_challenge_versioned(revealed_msg_indices.clone(), &vk, &params, ChallengeVersion::Legacy),
+            proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params)
+        );
+        let verifier_bytes =
+            proof.get_bytes_for_challenge_versioned(revealed_msg_indices.clone(), &vk, &params, ChallengeVersion::V1);
+        assert_eq!(prover_bytes, verifier_bytes);
+        let chal_verifier = FieldElement::from_msg_hash(&verifier_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs.clone(), &chal_verifier).unwrap());
+
+        // A verifier using the legacy construction gets a different challenge
+        let chal_legacy =
+            FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(revealed_msg_indices, &vk, &params));
+        assert!(!proof.verify(&vk, &params, revealed_msgs, &chal_legacy).unwrap());
+    }
+
+    #[test]
+    fn test_PoK_sig_bound_to_nonce() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        let nonce = FieldElement::random().to_bytes();
+        let context = b"verifier-1/login";
+        let pok =
+            BoundPoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices, &nonce, context).unwrap();
+        let proof = pok.gen_proof().unwrap();
+
+        assert!(proof.verify_with_nonce(&vk, &params, revealed_msgs.clone(), &nonce, context).unwrap());
+        // Replayed with another nonce or to another verifier
+        let other_nonce = FieldElement::random().to_bytes();
+        assert!(!proof.verify_with_nonce(&vk, &params, revealed_msgs.clone(), &other_nonce, context).unwrap());
+        assert!(!proof.verify_with_nonce(&vk, &params, revealed_msgs.clone(), &nonce, b"verifier-2/login").unwrap());
+        // The boundary between nonce and context is fixed by the length prefixes
+        let mut shifted_nonce = nonce.clone();
+        shifted_nonce.push(context[0]);
+        assert!(!proof.verify_with_nonce(&vk, &params, revealed_msgs, &shifted_nonce, &context[1..]).unwrap());
+    }
+
+    #[test]
+    fn test_PoK_sigs_with_equal_messages() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        // Message 1 of a credential under vk_1 equals message 3 of a credential under vk_2, message 0 of the first
+        // equals message 0 of the second, some other messages are revealed
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(4, &params);
+        let (sk_2, vk_2) = keygen(5, &params);
+        let ssn = FieldElement::random();
+        let name = FieldElement::random();
+        let msgs_1 = vec![name.clone(), ssn.clone(), FieldElement::random(), FieldElement::random()];
+        let msgs_2 = vec![name, FieldElement::random(), FieldElement::random(), ssn, FieldElement::random()];
+        let sig_1 = Signature::new(msgs_1.as_slice(), &sk_1, &params).unwrap();
+        let sig_2 = Signature::new(msgs_2.as_slice(), &sk_2, &params).unwrap();
+        let equal_indices = vec![(1, 3), (0, 0)];
+
+        let mut revealed_1 = HashSet::new();
+        revealed_1.insert(2);
+        let mut revealed_2 = HashSet::new();
+        revealed_2.insert(1);
+        revealed_2.insert(2);
+        let mut revealed_msgs_1 = HashMap::new();
+        revealed_msgs_1.insert(2, msgs_1[2].clone());
+        let mut revealed_msgs_2 = HashMap::new();
+        revealed_msgs_2.insert(1, msgs_2[1].clone());
+        revealed_msgs_2.insert(2, msgs_2[2].clone());
+
+        let (pok_1, pok_2) = PoKOfSignature::init_with_equal_messages(
+            &sig_1, &vk_1, msgs_1.clone(), revealed_1.clone(),
+            &sig_2, &vk_2, msgs_2.clone(), revealed_2.clone(),
+            &equal_indices, &params,
+        )
+        .unwrap();
+        let mut chal_bytes = pok_1.to_bytes();
+        chal_bytes.append(&mut pok_2.to_bytes());
+        let chal_prover = FieldElement::from_msg_hash(&chal_bytes);
+        let proof_1 = pok_1.gen_proof(&chal_prover).unwrap();
+        let proof_2 = pok_2.gen_proof(&chal_prover).unwrap();
+
+        let mut chal_bytes = proof_1.get_bytes_for_challenge(revealed_1.clone(), &vk_1, &params);
+        chal_bytes.append(&mut proof_2.get_bytes_for_challenge(revealed_2.clone(), &vk_2, &params));
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof_1.verify(&vk_1, &params, revealed_msgs_1, &chal_verifier).unwrap());
+        assert!(proof_2.verify(&vk_2, &params, revealed_msgs_2, &chal_verifier).unwrap());
+        assert!(PoKOfSignatureProof::verify_equal_messages(
+            &proof_1, &revealed_1, &proof_2, &revealed_2, &equal_indices
+        )
+        .unwrap());
+        // Messages not proven equal
+        assert!(!PoKOfSignatureProof::verify_equal_messages(
+            &proof_1, &revealed_1, &proof_2, &revealed_2, &[(3, 4)]
+        )
+        .unwrap());
+        // A revealed message can't be part of an equality
+        assert!(PoKOfSignatureProof::verify_equal_messages(
+            &proof_1, &revealed_1, &proof_2, &revealed_2, &[(2, 4)]
+        )
+        .is_err());
+
+        // Messages that are not equal
+        assert!(PoKOfSignature::init_with_equal_messages(
+            &sig_1, &vk_1, msgs_1.clone(), revealed_1.clone(),
+            &sig_2, &vk_2, msgs_2.clone(), revealed_2.clone(),
+            &[(2, 4)], &params,
+        )
+        .is_err());
+
+        // Out of range revealed indices are an error, not a panic
+        let mut out_of_range = revealed_1.clone();
+        out_of_range.insert(10);
+        assert!(PoKOfSignature::init_with_equal_messages(
+            &sig_1, &vk_1, msgs_1.clone(), out_of_range.clone(),
+            &sig_2, &vk_2, msgs_2.clone(), revealed_2.clone(),
+            &equal_indices, &params,
+        )
+        .is_err());
+        assert!(PoKOfSignature::init_with_equal_messages(
+            &sig_2, &vk_2, msgs_2.clone(), revealed_2.clone(),
+            &sig_1, &vk_1, msgs_1.clone(), out_of_range,
+            &[(3, 1), (0, 0)], &params,
+        )
+        .is_err());
+
+        // Same seed gives the same proofs
+        let prove = |seed: u64| {
+            let (pok_1, pok_2) = PoKOfSignature::init_with_equal_messages_with_rng(
+                &sig_1, &vk_1, msgs_1.clone(), revealed_1.clone(),
+                &sig_2, &vk_2, msgs_2.clone(), revealed_2.clone(),
+                &equal_indices, &params, &mut StdRng::seed_from_u64(seed),
+            )
+            .unwrap();
+            (pok_1.to_bytes(), pok_2.to_bytes())
+        };
+        assert_eq!(prove(1), prove(1));
+        assert_ne!(prove(1), prove(2));
+    }
+
+    #[test]
+    fn test_PoK_sig_and_commitment() {
+        // Messages at 1 and 3 committed with bases h_1, h_3 and blinding with g, message at 0 revealed
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let h = (0..count_msgs).map(|_| SignatureGroup::random()).collect::<Vec<SignatureGroup>>();
+        let blinding = FieldElement::random();
+        let committed_indices = vec![1, 3];
+        let committed = committed_indices.iter().map(|i| (*i, h[*i].clone())).collect::<Vec<_>>();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        let pok = PoKOfSignatureAndCommitment::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            revealed_msg_indices.clone(),
+            &committed,
+            &[(params.g.clone(), blinding.clone())],
+        )
+        .unwrap();
+        let commitment = pok.commitment().clone();
+        assert_eq!(commitment, (&h[1] * &msgs[1]) + (&h[3] * &msgs[3]) + (&params.g * &blinding));
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let (proof, pok_commitment) = pok.gen_proof(&challenge).unwrap();
+
+        let bases = vec![h[1].clone(), h[3].clone(), params.g.clone()];
+        let chal_bytes = proof.get_bytes_for_challenge_with_commitment(
+            revealed_msg_indices.clone(),
+            &vk,
+            &params,
+            &bases,
+            &pok_commitment,
+            &commitment,
+        );
+        assert_eq!(FieldElement::from_msg_hash(&chal_bytes), challenge);
+        assert!(proof
+            .verify_with_commitment(&vk, &params, revealed_msgs.clone(), &pok_commitment, &bases, &commitment, &committed_indices, &challenge)
+            .unwrap());
+        // The commitment is not to the messages at 2 and 3
+        assert!(!proof
+            .verify_with_commitment(&vk, &params, revealed_msgs.clone(), &pok_commitment, &bases, &commitment, &[2, 3], &challenge)
+            .unwrap());
+
+        // A commitment to other values with the same bases fails the equality of responses
+        let other = PoKOfCommitment::init(&bases, vec![FieldElement::random(), msgs[3].clone(), blinding.clone()], &[None, None, None])
+            .unwrap();
+        let other_commitment = other.commitment().clone();
+        let other_proof = other.gen_proof(&challenge).unwrap();
+        assert!(other_proof.verify(&bases, &other_commitment, &challenge).unwrap());
+        assert!(!proof
+            .verify_with_commitment(&vk, &params, revealed_msgs, &other_proof, &bases, &other_commitment, &committed_indices, &challenge)
+            .unwrap());
+
+        // A revealed message can't be committed
+        assert!(PoKOfSignatureAndCommitment::init(&sig, &vk, &params, msgs.clone(), revealed_msg_indices, &[(0, h[0].clone())], &[]).is_err());
+        assert!(PoKOfSignatureAndCommitment::init(&sig, &vk, &params, msgs, HashSet::new(), &[(count_msgs, h[0].clone())], &[]).is_err());
+    }
+
+    #[test]
+    fn test_PoK_sig_resp_for_hidden_message() {
+        let count_msgs = 6;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        revealed_msg_indices.insert(4);
+        // Blindings of the hidden messages 0, 2, 3 and 5
+        let blindings = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), Some(blindings.as_slice()), revealed_msg_indices.clone()).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+
+        for (pos, idx) in [0, 2, 3, 5].iter().enumerate() {
+            let resp = proof.get_resp_for_hidden_message(*idx, &revealed_msg_indices).unwrap();
+            assert_eq!(resp, proof.get_resp_for_message(pos).unwrap());
+            assert_eq!(resp, &blindings[pos] - &(&challenge * &msgs[*idx]));
+        }
+        assert!(proof.get_resp_for_hidden_message(1, &revealed_msg_indices).is_err());
+        assert!(proof.get_resp_for_hidden_message(4, &revealed_msg_indices).is_err());
+        assert!(proof.get_resp_for_hidden_message(count_msgs, &revealed_msg_indices).is_err());
+    }
+
+    #[test]
+    fn test_PoK_sig_batch_verify() {
+        // 30 proofs under 3 verkeys with different messages revealed
+        let params = Params::new("test".as_bytes());
+        let keys = (3..6).map(|n| keygen(n, &params)).collect::<Vec<_>>();
+        let mut proofs = vec![];
+        let mut contexts = vec![];
+        for i in 0..30 {
+            let (sk, vk) = &keys[i % 3];
+            let msgs = (0..vk.Y_tilde.len()).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+            let sig = Signature::new(msgs.as_slice(), sk, &params).unwrap();
+            let mut revealed_msg_indices = HashSet::new();
+            revealed_msg_indices.insert(i % vk.Y_tilde.len());
+            let pok = PoKOfSignature::init(&sig, vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+            let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+            proofs.push(pok.gen_proof(&challenge).unwrap());
+            let revealed_msgs = revealed_msg_indices.iter().map(|i| (*i, msgs[*i].clone())).collect::<HashMap<usize, FieldElement>>();
+            contexts.push((vk, revealed_msgs, challenge));
+        }
+        let mut batch = proofs
+            .iter()
+            .zip(contexts.iter())
+            .map(|(proof, (vk, revealed_msgs, challenge))| ProofWithContext {
+                proof,
+                vk,
+                revealed_msgs: revealed_msgs.clone(),
+                challenge: challenge.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        assert!(PoKOfSignatureProof::batch_verify(&batch, &params).is_empty());
+        println!("Time to verify {} proofs in a batch {:?}", batch.len(), start.elapsed());
+        let start = Instant::now();
+        for p in &batch {
+            assert!(p.proof.verify(p.vk, &params, p.revealed_msgs.clone(), &p.challenge).unwrap());
+        }
+        println!("Time to verify {} proofs individually {:?}", batch.len(), start.elapsed());
+        assert!(PoKOfSignatureProof::batch_verify(&[], &params).is_empty());
+
+        // A wrong challenge, a wrong revealed message, a wrong verkey and a revealed index out of range are found
+        batch[4].challenge = FieldElement::random();
+        let idx = *batch[11].revealed_msgs.keys().next().unwrap();
+        batch[11].revealed_msgs.insert(idx, FieldElement::random());
+        batch[17].vk = &keys[0].1;
+        batch[25].revealed_msgs.insert(10, FieldElement::random());
+        assert_eq!(PoKOfSignatureProof::batch_verify(&batch, &params), vec![4, 11, 17, 25]);
+        assert_eq!(PoKOfSignatureProof::batch_verify(&batch[4..5], &params), vec![0]);
+    }
+
+    #[test]
+    fn test_PoK_sig_with_prepared_verkey() {
+        let count_msgs = 10;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let pvk = PreparedVerkey::new(vk.clone(), &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(3);
+        revealed_msg_indices.insert(7);
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(i.clone(), msgs[*i].clone());
+        }
+        let chal_bytes = proof.get_bytes_for_challenge_with_prepared_verkey(revealed_msg_indices.clone(), &pvk);
+        assert_eq!(chal_bytes, proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params));
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify_with_prepared_verkey(&pvk, revealed_msgs.clone(), &chal_verifier).unwrap());
+
+        // Same with the revealed set prepared
+        let mut prepared = pvk.clone();
+        prepared.prepare_revealed(&revealed_msg_indices).unwrap();
+        assert_eq!(chal_bytes, proof.get_bytes_for_challenge_with_prepared_verkey(revealed_msg_indices.clone(), &prepared));
+        assert!(proof.verify_with_prepared_verkey(&prepared, revealed_msgs.clone(), &chal_verifier).unwrap());
+
+        // Reveal wrong message
+        revealed_msgs.insert(3, FieldElement::random());
+        assert!(!proof.verify_with_prepared_verkey(&pvk, revealed_msgs.clone(), &chal_verifier).unwrap());
+        assert!(!proof.verify_with_prepared_verkey(&prepared, revealed_msgs.clone(), &chal_verifier).unwrap());
+
+        // Revealed indices out of range are an error and not a panic
+        let mut out_of_range = HashSet::new();
+        out_of_range.insert(count_msgs);
+        assert!(prepared.prepare_revealed(&out_of_range).is_err());
+        revealed_msgs.insert(count_msgs, FieldElement::random());
+        assert!(proof.verify_with_prepared_verkey(&pvk, revealed_msgs, &chal_verifier).is_err());
+        let too_many = (0..count_msgs + 2).map(|i| (i, FieldElement::random())).collect::<HashMap<usize, FieldElement>>();
+        assert!(proof.verify_with_prepared_verkey(&pvk, too_many, &chal_verifier).is_err());
+
+        // A verkey with metadata of another scheme or prepared with other params is rejected
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+        let vk_2018 = vk.clone().with_metadata(KeyMetadata::new(Scheme::PS2018, count_msgs, &params));
+        let pvk_2018 = PreparedVerkey::new(vk_2018, &params);
+        assert!(proof.verify_with_prepared_verkey(&pvk_2018, revealed_msgs.clone(), &chal_verifier).is_err());
+        let pvk_other_params = PreparedVerkey::new(vk.clone(), &Params::new("other".as_bytes()));
+        assert!(proof.verify_with_prepared_verkey(&pvk_other_params, revealed_msgs, &chal_verifier).is_err());
+    }
+
+    #[test]
+    fn test_PoK_sig_suspend_and_resume() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+
+        // Suspend after commitment and resume in another thread
+        let state = serde_json::to_string(&pok).unwrap();
+        drop(pok);
+        let proof = std::thread::spawn(move || {
+            let pok: PoKOfSignature = serde_json::from_str(&state).unwrap();
+            pok.gen_proof(&chal_prover).unwrap()
+        })
+        .join()
+        .unwrap();
+
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(2, msgs[2].clone());
+        let chal_verifier =
+            FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(revealed_msg_indices, &vk, &params));
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+    }
+
+    #[test]
+    fn test_PoK_sig_proof_to_and_from_bytes() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        let bytes = proof.to_bytes();
+        assert_eq!(
+            bytes.len(),
+            wire::HEADER_SIZE + 2 * SIGNATURE_GROUP_SIZE + 2 * VERKEY_GROUP_SIZE + 4 + count_msgs * FIELD_ELEMENT_SIZE
+        );
+        assert_eq!(bytes.len(), PoKOfSignatureProof::size_hint(count_msgs, revealed_msg_indices.len()));
+        assert_eq!(bytes.len(), proof.serialized_len());
+        let mut buf = vec![];
+        proof.write_bytes(&mut buf).unwrap();
+        assert_eq!(buf, bytes);
+        let proof_1 = PoKOfSignatureProof::from_bytes(&bytes).unwrap();
+        assert_eq!(proof_1.to_bytes(), bytes);
+
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        let chal_bytes = proof_1.get_bytes_for_challenge(revealed_msg_indices, &vk, &params);
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof_1.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+
+        // Truncated bytes, extra bytes and unknown version are rejected
+        assert!(PoKOfSignatureProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(PoKOfSignatureProof::from_bytes(&extra).is_err());
+        let mut wrong_version = bytes;
+        wrong_version[0] = wire::WIRE_FORMAT_VERSION + 1;
+        match PoKOfSignatureProof::from_bytes(&wrong_version) {
+            Err(PSError::UnsupportedVersion { .. }) => (),
+            _ => panic!("Expected UnsupportedVersion"),
+        }
+    }
+
     #[test]
     fn timing_pok_signature() {
         // Measure time to prove knowledge of signatures, both generation and verification of proof
@@ -1,39 +1,110 @@
 // Proof of knowledge of signature for signature defined in 2018 paper, CT-RSA 2018 (eprint 2017/1197).
 
-use crate::pok_sig::{PoKOfSignature as PoKOfSignature16, PoKOfSignatureProof};
+use crate::pok_sig::{ChallengeVersion, PoKOfSignature as PoKOfSignature16, PoKOfSignatureProof as PoKOfSignatureProof16};
 use amcl_wrapper::field_elem::FieldElement;
 use crate::signature_2018::Signature;
-use crate::keys::{Verkey, Params};
+use crate::keys::{Verkey, Params, Scheme};
 use crate::errors::PSError;
-use std::collections::HashSet;
+use rand::{CryptoRng, RngCore};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoKOfSignature(pub PoKOfSignature16);
 
+/// Proof of knowledge of a signature of the 2018 scheme, the proof of the 2016 scheme where m' is an additional
+/// hidden message after the messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKOfSignatureProof(pub PoKOfSignatureProof16);
+
 /// Most of the protocol is same as followed for the 2016 scheme
 impl PoKOfSignature {
     pub fn init(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        blindings: Option<&[FieldElement]>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<Self, PSError> {
+        Self::init_with_rng(sig, vk, params, messages, blindings, revealed_msg_indices, &mut rand::thread_rng())
+    }
+
+    /// Same as `init` but the randomizers of the signature and the blindings not given are generated using `rng`
+    pub fn init_with_rng<R: RngCore + CryptoRng>(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        blindings: Option<&[FieldElement]>,
+        revealed_msg_indices: HashSet<usize>,
+        rng: &mut R,
+    ) -> Result<Self, PSError> {
+        Self::init_with_m_prime_blinding_and_rng(sig, vk, params, messages, blindings, None, revealed_msg_indices, rng)
+    }
+
+    /// Same as `init` but with the blinding for m', which is otherwise random, so that equality of m' can be proven
+    /// across proofs or with another protocol using the same blinding. m' is always hidden.
+    pub fn init_with_m_prime_blinding(
+        sig: &Signature,
+        vk: &Verkey,
+        params: &Params,
+        messages: Vec<FieldElement>,
+        blindings: Option<&[FieldElement]>,
+        m_prime_blinding: Option<&FieldElement>,
+        revealed_msg_indices: HashSet<usize>,
+    ) -> Result<Self, PSError> {
+        Self::init_with_m_prime_blinding_and_rng(
+            sig,
+            vk,
+            params,
+            messages,
+            blindings,
+            m_prime_blinding,
+            revealed_msg_indices,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    /// Same as `init_with_m_prime_blinding` but the randomizers of the signature and the blindings not given are
+    /// generated using `rng`
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_with_m_prime_blinding_and_rng<R: RngCore + CryptoRng>(
         sig: &Signature,
         vk: &Verkey,
         params: &Params,
         mut messages: Vec<FieldElement>,
         blindings: Option<&[FieldElement]>,
+        m_prime_blinding: Option<&FieldElement>,
         revealed_msg_indices: HashSet<usize>,
+        rng: &mut R,
     ) -> Result<Self, PSError> {
         Signature::check_verkey_and_messages_compat(messages.as_slice(), vk)?;
 
         // m_prime should never be revealed
+        if revealed_msg_indices.contains(&messages.len()) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} is of m' which can't be revealed", messages.len()),
+            });
+        }
         PoKOfSignature16::validate_revealed_indices(messages.as_slice(), &revealed_msg_indices)?;
 
         let mut blindings = PoKOfSignature16::get_blindings(blindings, messages.as_slice(), &revealed_msg_indices)?;
 
-        let (t, sigma_prime) = PoKOfSignature16::transform_sig(&sig.sig);
-
         messages.push(sig.m_prime.clone());
-        // Choose blinding for m_prime randomly
-        blindings.push(None);
+        // Blinding for m_prime is chosen randomly if not given
+        blindings.push(m_prime_blinding);
+        let blindings = PoKOfSignature16::fill_blindings_with_rng(blindings, rng);
 
-        let (exponents, J, committed) = PoKOfSignature16::commit_for_pok(messages, blindings, &revealed_msg_indices, t, vk, params);
+        let (t, sigma_prime) = PoKOfSignature16::transform_sig_with_rng(&sig.sig, rng);
+
+        let (exponents, J, committed) = PoKOfSignature16::commit_for_pok(
+            messages,
+            blindings.iter().map(Some).collect(),
+            &revealed_msg_indices,
+            t,
+            vk,
+            params,
+        );
         Ok(Self(PoKOfSignature16 {
             secrets: exponents,
             sig: sigma_prime,
@@ -47,10 +118,80 @@ impl PoKOfSignature {
         self.0.to_bytes()
     }
 
-    /// The proof generation protocol is same as for the 2016 scheme the resulting proof is same as
-    /// the proof for the 2016 scheme and can be verified using its `verify method`
+    /// Same as `pok_sig::PoKOfSignature::to_bytes_for_challenge` with the scheme tag of the 2018 scheme in `V1`
+    pub fn to_bytes_for_challenge(&self, version: ChallengeVersion) -> Vec<u8> {
+        self.0.to_bytes_for_challenge_with_scheme(version, Scheme::PS2018)
+    }
+
+    /// The proof generation protocol is same as for the 2016 scheme, the resulting proof wraps the proof for
+    /// the 2016 scheme
     pub fn gen_proof(self, challenge: &FieldElement) -> Result<PoKOfSignatureProof, PSError> {
-        self.0.gen_proof(challenge)
+        Ok(PoKOfSignatureProof(self.0.gen_proof(challenge)?))
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Bytes of the public elements for the verifier's challenge. m' is never revealed so its index, the last
+    /// index of the verkey, is ignored if present in `revealed_msg_indices`.
+    pub fn get_bytes_for_challenge(
+        &self,
+        mut revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Vec<u8> {
+        revealed_msg_indices.remove(&Self::m_prime_index(vk));
+        self.0.get_bytes_for_challenge(revealed_msg_indices, vk, params)
+    }
+
+    /// Same as `get_bytes_for_challenge` with the given construction, the verifier must use the same version as the
+    /// prover used with `PoKOfSignature::to_bytes_for_challenge`
+    pub fn get_bytes_for_challenge_versioned(
+        &self,
+        mut revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        version: ChallengeVersion,
+    ) -> Vec<u8> {
+        revealed_msg_indices.remove(&Self::m_prime_index(vk));
+        self.0
+            .get_bytes_for_challenge_with_scheme(revealed_msg_indices, vk, params, version, Scheme::PS2018)
+    }
+
+    /// Verify the proof with the revealed messages. Fails if `revealed_msgs` includes the index of m'.
+    pub fn verify(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        let m_prime_index = Self::m_prime_index(vk);
+        if let Some(idx) = revealed_msgs.keys().find(|i| **i >= m_prime_index) {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}, m' can't be revealed", idx, m_prime_index),
+            });
+        }
+        self.0.verify(vk, params, revealed_msgs, challenge)
+    }
+
+    /// Response for the hidden message at `msg_idx` among the hidden messages, m' being the last hidden message
+    pub fn get_resp_for_message(&self, msg_idx: usize) -> Result<FieldElement, PSError> {
+        self.0.get_resp_for_message(msg_idx)
+    }
+
+    /// Same as `pok_sig::PoKOfSignatureProof::get_resp_for_hidden_message`, the response for m' is at the last
+    /// index of the verkey
+    pub fn get_resp_for_hidden_message(
+        &self,
+        msg_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+    ) -> Result<FieldElement, PSError> {
+        self.0.get_resp_for_hidden_message(msg_idx, revealed_msg_indices)
+    }
+
+    /// The last element of Y_tilde is for m'
+    fn m_prime_index(vk: &Verkey) -> usize {
+        vk.Y_tilde.len().saturating_sub(1)
     }
 }
 
@@ -59,7 +200,6 @@ mod tests {
     use super::*;
     // For benchmarking
     use crate::keys::keygen_2018;
-    use std::collections::HashMap;
     use amcl_wrapper::field_elem::FieldElementVector;
 
     #[test]
@@ -152,4 +292,106 @@ mod tests {
         revealed_msgs_1.insert(2, FieldElement::random());
         assert!(!proof.verify(&vk, &params, revealed_msgs_1.clone(), &chal_verifier).unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_PoK_sig_m_prime_not_revealed() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(1);
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal_prover).unwrap();
+
+        // Index of m' in the revealed indices does not change the challenge
+        let mut with_m_prime = revealed_msg_indices.clone();
+        with_m_prime.insert(count_msgs);
+        assert_eq!(
+            proof.get_bytes_for_challenge(with_m_prime, &vk, &params),
+            proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params)
+        );
+        let chal_verifier =
+            FieldElement::from_msg_hash(&proof.get_bytes_for_challenge(revealed_msg_indices.clone(), &vk, &params));
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        assert!(proof.verify(&vk, &params, revealed_msgs.clone(), &chal_verifier).unwrap());
+
+        // Revealing m' is rejected by both prover and verifier
+        revealed_msgs.insert(count_msgs, sig.m_prime.clone());
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal_verifier).is_err());
+        let mut reveal_m_prime = HashSet::new();
+        reveal_m_prime.insert(count_msgs);
+        assert!(PoKOfSignature::init(&sig, &vk, &params, msgs, None, reveal_m_prime).is_err());
+    }
+
+    #[test]
+    fn test_PoK_sig_with_m_prime_blinding() {
+        // Same m' in 2 proofs shown by using the same blinding for it, the responses for m' are then equal
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+        let m_prime_blinding = FieldElement::random();
+
+        let mut proofs = vec![];
+        for _ in 0..2 {
+            let pok = PoKOfSignature::init_with_m_prime_blinding(
+                &sig, &vk, &params, msgs.clone(), None, Some(&m_prime_blinding), HashSet::new(),
+            )
+            .unwrap();
+            proofs.push(pok);
+        }
+        // A common challenge over both proofs
+        let mut chal_bytes = proofs[0].to_bytes();
+        chal_bytes.append(&mut proofs[1].to_bytes());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proofs = proofs
+            .into_iter()
+            .map(|p| p.gen_proof(&challenge).unwrap())
+            .collect::<Vec<_>>();
+        for p in &proofs {
+            assert!(p.verify(&vk, &params, HashMap::new(), &challenge).unwrap());
+        }
+        assert_eq!(
+            proofs[0].get_resp_for_message(count_msgs).unwrap(),
+            proofs[1].get_resp_for_message(count_msgs).unwrap()
+        );
+
+        let mut reveal_m_prime = HashSet::new();
+        reveal_m_prime.insert(count_msgs);
+        assert!(PoKOfSignature::init_with_m_prime_blinding(
+            &sig, &vk, &params, msgs, None, Some(&m_prime_blinding), reveal_m_prime,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_PoK_sig_versioned_challenge() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(2);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(2, msgs[2].clone());
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs, None, revealed_msg_indices.clone()).unwrap();
+        let prover_bytes = pok.to_bytes_for_challenge(ChallengeVersion::V1);
+        // The scheme tag differs from the 2016 scheme for the same proof
+        assert_ne!(prover_bytes, pok.0.to_bytes_for_challenge(ChallengeVersion::V1));
+        let proof = pok.gen_proof(&FieldElement::from_msg_hash(&prover_bytes)).unwrap();
+
+        let verifier_bytes =
+            proof.get_bytes_for_challenge_versioned(revealed_msg_indices, &vk, &params, ChallengeVersion::V1);
+        assert_eq!(prover_bytes, verifier_bytes);
+        let chal_verifier = FieldElement::from_msg_hash(&verifier_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+    }
+}
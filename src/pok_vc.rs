@@ -10,6 +10,50 @@
 // During response generation `ProverCommitted` is consumed to create `Proof` object containing the commitments and responses.
 // `Proof` can then be verified by the verifier.
 
+use crate::errors::PSError;
+use crate::{SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+/// Commitment phase of the proof of knowledge of committed values where the commitment is in group `G`
+pub trait ProverCommittingVC<G>: Sized {
+    type Committed: ProverCommittedVC<G>;
+
+    fn new() -> Self;
+
+    /// generate a new random blinding if None provided
+    fn commit(&mut self, gen: &G, blinding: Option<&FieldElement>) -> usize;
+
+    fn finish(self) -> Self::Committed;
+
+    fn get_index(&self, idx: usize) -> Result<(&G, &FieldElement), PSError>;
+}
+
+/// End of commitment phase of the proof of knowledge of committed values where the commitment is in group `G`
+pub trait ProverCommittedVC<G> {
+    type Proof: ProofVC<G>;
+
+    fn to_bytes(&self) -> Vec<u8>;
+
+    fn gen_challenge(&self, extra: Vec<u8>) -> FieldElement;
+
+    fn gen_proof(self, challenge: &FieldElement, secrets: &[FieldElement]) -> Result<Self::Proof, PSError>;
+}
+
+/// Proof of knowledge of committed values where the commitment is in group `G`
+pub trait ProofVC<G> {
+    fn verify(&self, bases: &[G], commitment: &G, challenge: &FieldElement) -> Result<bool, PSError>;
+}
+
+/// A group for which proof of knowledge of committed values is implemented. Protocols generic over the group
+/// of the commitment can use the associated types.
+pub trait PoKVCGroup {
+    type Element: GroupElement;
+    type ProverCommitting: ProverCommittingVC<Self::Element, Committed = Self::ProverCommitted>;
+    type ProverCommitted: ProverCommittedVC<Self::Element, Proof = Self::Proof>;
+    type Proof: ProofVC<Self::Element>;
+}
+
 /*pub struct ProverCommitting<'a, T: GroupElement> {
     gens: Vec<&'a T>,
     blindings: Vec<FieldElement>,
@@ -75,7 +119,9 @@ macro_rules! impl_PoK_VC {
 
         #[derive(Clone, Debug, Serialize, Deserialize)]
         pub struct $Proof {
+            #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
             pub commitment: $group_element,
+            #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::field_element_vector"))]
             pub responses: FieldElementVector,
         }
 
@@ -130,6 +176,15 @@ macro_rules! impl_PoK_VC {
         }
 
         impl $ProverCommitted {
+            /// Generators committed to, in the order of `commit`
+            pub fn bases(&self) -> &[$group_element] {
+                self.gens.as_slice()
+            }
+
+            pub fn commitment(&self) -> &$group_element {
+                &self.commitment
+            }
+
             pub fn to_bytes(&self) -> Vec<u8> {
                 let mut bytes = vec![];
                 for b in self.gens.as_slice() {
@@ -194,9 +249,94 @@ macro_rules! impl_PoK_VC {
                 Ok(pr.is_identity())
             }
         }
+
+        impl $crate::pok_vc::ProverCommittingVC<$group_element> for $ProverCommitting {
+            type Committed = $ProverCommitted;
+
+            fn new() -> Self {
+                $ProverCommitting::new()
+            }
+
+            fn commit(&mut self, gen: &$group_element, blinding: Option<&FieldElement>) -> usize {
+                $ProverCommitting::commit(self, gen, blinding)
+            }
+
+            fn finish(self) -> $ProverCommitted {
+                $ProverCommitting::finish(self)
+            }
+
+            fn get_index(&self, idx: usize) -> Result<(&$group_element, &FieldElement), PSError> {
+                $ProverCommitting::get_index(self, idx)
+            }
+        }
+
+        impl $crate::pok_vc::ProverCommittedVC<$group_element> for $ProverCommitted {
+            type Proof = $Proof;
+
+            fn to_bytes(&self) -> Vec<u8> {
+                $ProverCommitted::to_bytes(self)
+            }
+
+            fn gen_challenge(&self, extra: Vec<u8>) -> FieldElement {
+                $ProverCommitted::gen_challenge(self, extra)
+            }
+
+            fn gen_proof(self, challenge: &FieldElement, secrets: &[FieldElement]) -> Result<$Proof, PSError> {
+                $ProverCommitted::gen_proof(self, challenge, secrets)
+            }
+        }
+
+        impl $crate::pok_vc::ProofVC<$group_element> for $Proof {
+            fn verify(
+                &self,
+                bases: &[$group_element],
+                commitment: &$group_element,
+                challenge: &FieldElement,
+            ) -> Result<bool, PSError> {
+                $Proof::verify(self, bases, commitment, challenge)
+            }
+        }
     };
 }
 
+// Proof of knowledge of committed values in a vector commitment in `SignatureGroup`, used for proving knowledge
+// of committed messages when requesting a blind signature
+impl_PoK_VC!(
+    ProverCommittingSignatureGroup,
+    ProverCommittedSignatureGroup,
+    ProofSignatureGroup,
+    SignatureGroup,
+    SignatureGroupVec
+);
+
+// Proof of knowledge of committed values in a vector commitment in `VerkeyGroup`, used for proving knowledge
+// of messages in a proof of knowledge of signature
+impl_PoK_VC!(
+    ProverCommittingOtherGroup,
+    ProverCommittedOtherGroup,
+    ProofOtherGroup,
+    VerkeyGroup,
+    VerkeyGroupVec
+);
+
+pub struct SignatureGroupPoKVC {}
+
+impl PoKVCGroup for SignatureGroupPoKVC {
+    type Element = SignatureGroup;
+    type ProverCommitting = ProverCommittingSignatureGroup;
+    type ProverCommitted = ProverCommittedSignatureGroup;
+    type Proof = ProofSignatureGroup;
+}
+
+pub struct VerkeyGroupPoKVC {}
+
+impl PoKVCGroup for VerkeyGroupPoKVC {
+    type Element = VerkeyGroup;
+    type ProverCommitting = ProverCommittingOtherGroup;
+    type ProverCommitted = ProverCommittedOtherGroup;
+    type Proof = ProofOtherGroup;
+}
+
 #[cfg(test)]
 #[macro_export]
 macro_rules! test_PoK_VC {
@@ -280,4 +420,50 @@ pub(crate) mod tests {
             G2Vector
         );
     }
+
+    fn prove_and_verify<P: PoKVCGroup>(
+        bases: &[P::Element],
+        secrets: &[FieldElement],
+        commitment: &P::Element,
+    ) -> bool {
+        let mut committing = P::ProverCommitting::new();
+        for b in bases {
+            committing.commit(b, None);
+        }
+        let committed = committing.finish();
+        let challenge = committed.gen_challenge(vec![]);
+        let proof = committed.gen_proof(&challenge, secrets).unwrap();
+        proof.verify(bases, commitment, &challenge).unwrap()
+    }
+
+    #[test]
+    fn test_PoK_VC_generic_over_group() {
+        let n = 5;
+        let secrets = FieldElementVector::random(n);
+
+        let bases_1 = (0..n).map(|_| SignatureGroup::random()).collect::<Vec<SignatureGroup>>();
+        let commitment_1 = SignatureGroupVec::from(bases_1.as_slice())
+            .multi_scalar_mul_const_time(&secrets)
+            .unwrap();
+        assert!(prove_and_verify::<SignatureGroupPoKVC>(
+            bases_1.as_slice(),
+            secrets.as_slice(),
+            &commitment_1
+        ));
+        assert!(!prove_and_verify::<SignatureGroupPoKVC>(
+            bases_1.as_slice(),
+            secrets.as_slice(),
+            &SignatureGroup::random()
+        ));
+
+        let bases_2 = (0..n).map(|_| VerkeyGroup::random()).collect::<Vec<VerkeyGroup>>();
+        let commitment_2 = VerkeyGroupVec::from(bases_2.as_slice())
+            .multi_scalar_mul_const_time(&secrets)
+            .unwrap();
+        assert!(prove_and_verify::<VerkeyGroupPoKVC>(
+            bases_2.as_slice(),
+            secrets.as_slice(),
+            &commitment_2
+        ));
+    }
 }
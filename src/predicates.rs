@@ -0,0 +1,313 @@
+// Comparison predicates on hidden messages of a proof of knowledge of a signature (2016 scheme), built on the range
+// proofs of `range_proof` with n bits. m > bound is proven by m - bound - 1 being in [0, 2^n). m < bound is proven by
+// m and bound - 1 - m both being in [0, 2^n), the range proof of m prevents m from wrapping around the group order.
+// The bound must be less than 2^n. Each ranged value is a message plus a constant or its negation, its blinding is the
+// blinding of the message or its negation so the verifier derives its response from the response for the message.
+// The predicates are hashed into the challenge along with the range proofs.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::range_proof::{message_bits, RangeProof, RangeProofCommitted, MAX_RANGE_BITS};
+use crate::signature::Signature;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::HashSet;
+
+/// Domain separation tag of the predicate in the challenge bytes
+pub const PREDICATE_DST: &[u8] = b"PS-SIG-PREDICATE";
+
+/// The message at `index` is greater than `bound`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProveGreaterThan {
+    pub index: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub bound: FieldElement,
+}
+
+/// The message at `index` is less than `bound`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProveLessThan {
+    pub index: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub bound: FieldElement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Predicate {
+    GreaterThan(ProveGreaterThan),
+    LessThan(ProveLessThan),
+}
+
+impl From<ProveGreaterThan> for Predicate {
+    fn from(p: ProveGreaterThan) -> Self {
+        Predicate::GreaterThan(p)
+    }
+}
+
+impl From<ProveLessThan> for Predicate {
+    fn from(p: ProveLessThan) -> Self {
+        Predicate::LessThan(p)
+    }
+}
+
+impl Predicate {
+    pub fn index(&self) -> usize {
+        match self {
+            Predicate::GreaterThan(p) => p.index,
+            Predicate::LessThan(p) => p.index,
+        }
+    }
+
+    /// `PREDICATE_DST`, 0 for greater than and 1 for less than, the index as 4 byte big-endian and the bound
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (tag, index, bound) = match self {
+            Predicate::GreaterThan(p) => (0u8, p.index, &p.bound),
+            Predicate::LessThan(p) => (1u8, p.index, &p.bound),
+        };
+        let mut bytes = PREDICATE_DST.to_vec();
+        bytes.push(tag);
+        bytes.extend_from_slice(&(index as u32).to_be_bytes());
+        bytes.append(&mut bound.to_bytes());
+        bytes
+    }
+
+    /// The ranged values as (sign of the message, constant) so that each value is sign*m + constant
+    fn ranged_values(&self) -> Vec<(bool, FieldElement)> {
+        let one = FieldElement::one();
+        match self {
+            // m - bound - 1
+            Predicate::GreaterThan(p) => vec![(true, &FieldElement::zero() - &(&p.bound + &one))],
+            // m and bound - 1 - m
+            Predicate::LessThan(p) => vec![(true, FieldElement::zero()), (false, &p.bound - &one)],
+        }
+    }
+
+    fn check_bound(&self, bits: usize) -> Result<(), PSError> {
+        let bound = match self {
+            Predicate::GreaterThan(p) => &p.bound,
+            Predicate::LessThan(p) => &p.bound,
+        };
+        message_bits(bound, bits).map(|_| ())
+    }
+}
+
+/// Prover's state of the proof of a predicate
+#[derive(Clone, Debug)]
+pub struct PredicateCommitted {
+    pub predicate: Predicate,
+    pub range_proofs: Vec<RangeProofCommitted>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PredicateProof {
+    pub range_proofs: Vec<RangeProof>,
+}
+
+impl PredicateCommitted {
+    /// Bytes of the predicate and the range proofs to append to the challenge bytes of the proof of knowledge
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.predicate.to_bytes();
+        for r in &self.range_proofs {
+            bytes.append(&mut r.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> PredicateProof {
+        PredicateProof {
+            range_proofs: self.range_proofs.into_iter().map(|r| r.gen_proof(challenge)).collect(),
+        }
+    }
+}
+
+impl PredicateProof {
+    /// Same bytes as `PredicateCommitted::to_bytes` for the verifier's challenge
+    pub fn get_bytes_for_challenge(&self, predicate: &Predicate) -> Vec<u8> {
+        let mut bytes = predicate.to_bytes();
+        for r in &self.range_proofs {
+            bytes.append(&mut r.get_bytes_for_challenge());
+        }
+        bytes
+    }
+
+    /// Verify that the hidden message of `pok_proof` satisfies `predicate` with range proofs of `bits` bits.
+    /// `pok_proof` must be verified with the same challenge, computed over it and this proof.
+    pub fn verify(
+        &self,
+        predicate: &Predicate,
+        pok_proof: &PoKOfSignatureProof,
+        revealed_msg_indices: &HashSet<usize>,
+        bits: usize,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if bits > MAX_RANGE_BITS || predicate.check_bound(bits).is_err() {
+            return Ok(false);
+        }
+        let ranged_values = predicate.ranged_values();
+        if ranged_values.len() != self.range_proofs.len() {
+            return Ok(false);
+        }
+        let s_m = pok_proof.get_resp_for_hidden_message(predicate.index(), revealed_msg_indices)?;
+        let minus_s_m = &FieldElement::zero() - &s_m;
+        for ((positive, constant), r) in ranged_values.iter().zip(self.range_proofs.iter()) {
+            // Response for sign*m + constant is sign*s_m - challenge*constant
+            let s = if *positive { &s_m } else { &minus_s_m };
+            let response = s - &(challenge * constant);
+            if !r.verify_with_response(&response, bits, params, challenge) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and proofs of `predicates`
+/// over hidden messages with range proofs of `bits` bits. Fails if a predicate is not satisfied. The challenge is
+/// computed over the bytes of the proof of knowledge followed by those of each predicate.
+pub fn init_pok_with_predicates(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    predicates: &[Predicate],
+    bits: usize,
+) -> Result<(PoKOfSignature, Vec<PredicateCommitted>), PSError> {
+    let blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let mut committed = Vec::with_capacity(predicates.len());
+    for predicate in predicates {
+        predicate.check_bound(bits)?;
+        let index = predicate.index();
+        if index >= messages.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", index, messages.len()),
+            });
+        }
+        let r_m = &blindings[hidden_message_position(index, &revealed_msg_indices)?];
+        let minus_r_m = &FieldElement::zero() - r_m;
+        let minus_m = &FieldElement::zero() - &messages[index];
+        let mut range_proofs = vec![];
+        for (positive, constant) in predicate.ranged_values() {
+            let (m, r) = if positive {
+                (&messages[index], r_m)
+            } else {
+                (&minus_m, &minus_r_m)
+            };
+            let value = m + &constant;
+            range_proofs.push(RangeProofCommitted::new(&value, bits, r, params).map_err(|_| {
+                PSError::GeneralError {
+                    msg: format!("Message at index {} does not satisfy the predicate", index),
+                }
+            })?);
+        }
+        committed.push(PredicateCommitted {
+            predicate: predicate.clone(),
+            range_proofs,
+        });
+    }
+    let pok = PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)?;
+    Ok((pok, committed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pok_with_predicates() {
+        // Age over 18 and salary under 100000 with 32 bit range proofs, the name at 0 revealed
+        let params = Params::new("test".as_bytes());
+        let bits = 32;
+        let (sk, vk) = keygen(4, &params);
+        let msgs = vec![
+            FieldElement::random(),
+            FieldElement::from(25u64),
+            FieldElement::from(85_000u64),
+            FieldElement::random(),
+        ];
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let predicates: Vec<Predicate> = vec![
+            ProveGreaterThan {
+                index: 1,
+                bound: FieldElement::from(18u64),
+            }
+            .into(),
+            ProveLessThan {
+                index: 2,
+                bound: FieldElement::from(100_000u64),
+            }
+            .into(),
+        ];
+        let mut revealed = HashSet::new();
+        revealed.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        let (pok, committed) =
+            init_pok_with_predicates(&sig, &vk, &params, msgs.clone(), revealed.clone(), &predicates, bits).unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        for c in &committed {
+            chal_bytes.append(&mut c.to_bytes());
+        }
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let predicate_proofs = committed.into_iter().map(|c| c.gen_proof(&challenge)).collect::<Vec<_>>();
+
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+        for (p, pp) in predicates.iter().zip(predicate_proofs.iter()) {
+            chal_bytes.append(&mut pp.get_bytes_for_challenge(p));
+        }
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+        for (p, pp) in predicates.iter().zip(predicate_proofs.iter()) {
+            assert!(pp.verify(p, &proof, &revealed, bits, &params, &challenge).unwrap());
+        }
+
+        // A different bound or message fails
+        let other: Predicate = ProveGreaterThan {
+            index: 1,
+            bound: FieldElement::from(21u64),
+        }
+        .into();
+        assert!(!predicate_proofs[0].verify(&other, &proof, &revealed, bits, &params, &challenge).unwrap());
+        let other: Predicate = ProveGreaterThan {
+            index: 3,
+            bound: FieldElement::from(18u64),
+        }
+        .into();
+        assert!(!predicate_proofs[0].verify(&other, &proof, &revealed, bits, &params, &challenge).unwrap());
+        // A less than proof is not a greater than proof
+        let other: Predicate = ProveGreaterThan {
+            index: 2,
+            bound: FieldElement::from(100_000u64),
+        }
+        .into();
+        assert!(!predicate_proofs[1].verify(&other, &proof, &revealed, bits, &params, &challenge).unwrap());
+
+        // Predicates that don't hold can't be proven
+        for p in [
+            Predicate::from(ProveGreaterThan {
+                index: 1,
+                bound: FieldElement::from(25u64),
+            }),
+            Predicate::from(ProveLessThan {
+                index: 2,
+                bound: FieldElement::from(85_000u64),
+            }),
+            Predicate::from(ProveLessThan {
+                index: 3,
+                bound: FieldElement::from(100u64),
+            }),
+        ]
+        .iter()
+        {
+            let p = [p.clone()];
+            assert!(init_pok_with_predicates(&sig, &vk, &params, msgs.clone(), revealed.clone(), &p, bits).is_err());
+        }
+    }
+}
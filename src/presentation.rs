@@ -0,0 +1,373 @@
+// Presentation of several credentials, possibly from both the 2016 and the 2018 schemes, under a single challenge.
+// The group used for signatures is fixed at compile time (features `SignatureG1` and `SignatureG2` are mutually
+// exclusive) so all credentials in a presentation use the same group. With `ChallengeVersion::Legacy` the challenge is
+// computed over the commitments of all proofs of knowledge (each prefixed with its scheme tag) and a verifier supplied
+// nonce. With `ChallengeVersion::V1` it is a domain separated challenge over the `V1` challenge bytes of each proof,
+// the number of proofs and the nonce, each length prefixed, so a proof can't be dropped, reordered or moved to another
+// scheme without changing the challenge. The verifier passes the version it requires, the version in the
+// presentation is set by the prover so it is not trusted else a prover could downgrade to `Legacy`.
+// `ProofAggregator` and `AggregatedProof` are other names for the builder and the presentation.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Scheme, Verkey};
+use crate::pok_sig::{ChallengeVersion, PoKOfSignature, PoKOfSignatureProof};
+use crate::pok_sig_2018::PoKOfSignature as PoKOfSignature18;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for the challenge of a presentation with `ChallengeVersion::V1`
+pub const PRESENTATION_CHALLENGE_DST: &[u8] = b"PS-SIG-Presentation";
+
+/// Collects proofs of knowledge of signatures before the challenge is computed
+pub struct PresentationBuilder {
+    version: ChallengeVersion,
+    poks: Vec<(Scheme, PoKOfSignature)>,
+}
+
+/// Proof of knowledge of a signature along with the scheme of the signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PresentedCredential {
+    pub scheme: Scheme,
+    pub proof: PoKOfSignatureProof,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Presentation {
+    pub credentials: Vec<PresentedCredential>,
+    /// Construction of the challenge, `Legacy` for presentations serialized before it was added
+    #[serde(default)]
+    pub version: ChallengeVersion,
+}
+
+/// Collects proofs of knowledge of signatures of several credentials to create them under a single challenge
+pub type ProofAggregator = PresentationBuilder;
+
+/// Proofs of knowledge of signatures created with a single challenge
+pub type AggregatedProof = Presentation;
+
+/// What the verifier expects about a credential in the presentation.
+pub struct CredentialStatement<'a> {
+    pub scheme: Scheme,
+    pub vk: &'a Verkey,
+    pub revealed_msgs: HashMap<usize, FieldElement>,
+}
+
+/// Challenge over the challenge bytes of each proof, prefixed with the scheme tag for `Legacy`, and the nonce. For
+/// `V1`, `PRESENTATION_CHALLENGE_DST`, the number of proofs, the bytes of each proof and the nonce are each prefixed
+/// with their length as 4 byte big-endian.
+fn presentation_challenge(version: ChallengeVersion, proof_bytes: Vec<(Scheme, Vec<u8>)>, nonce: &[u8]) -> FieldElement {
+    let mut bytes = vec![];
+    match version {
+        ChallengeVersion::Legacy => {
+            for (scheme, mut b) in proof_bytes {
+                bytes.push(scheme.to_byte());
+                bytes.append(&mut b);
+            }
+            bytes.extend_from_slice(nonce);
+        }
+        ChallengeVersion::V1 => {
+            let append_with_length = |bytes: &mut Vec<u8>, b: &[u8]| {
+                bytes.extend_from_slice(&(b.len() as u32).to_be_bytes());
+                bytes.extend_from_slice(b);
+            };
+            append_with_length(&mut bytes, PRESENTATION_CHALLENGE_DST);
+            append_with_length(&mut bytes, &(proof_bytes.len() as u32).to_be_bytes());
+            for (_, b) in &proof_bytes {
+                append_with_length(&mut bytes, b);
+            }
+            append_with_length(&mut bytes, nonce);
+        }
+    }
+    FieldElement::from_msg_hash(&bytes)
+}
+
+impl Default for PresentationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PresentationBuilder {
+    /// Builder with the `Legacy` challenge, same as presentations created before `ChallengeVersion` was added
+    pub fn new() -> Self {
+        Self::new_with_version(ChallengeVersion::Legacy)
+    }
+
+    /// Builder with the challenge computed as `version`, use `ChallengeVersion::V1` for new deployments
+    pub fn new_with_version(version: ChallengeVersion) -> Self {
+        Self { version, poks: vec![] }
+    }
+
+    /// Add proof of knowledge of a signature from the 2016 scheme. Returns the index of the credential in
+    /// the presentation.
+    pub fn add_2016(&mut self, pok: PoKOfSignature) -> usize {
+        self.poks.push((Scheme::PS2016, pok));
+        self.poks.len() - 1
+    }
+
+    /// Add proof of knowledge of a signature from the 2018 scheme. Returns the index of the credential in
+    /// the presentation.
+    pub fn add_2018(&mut self, pok: PoKOfSignature18) -> usize {
+        self.poks.push((Scheme::PS2018, pok.0));
+        self.poks.len() - 1
+    }
+
+    /// The challenge over all added proofs of knowledge and the nonce
+    pub fn challenge(&self, nonce: &[u8]) -> FieldElement {
+        let proof_bytes = self
+            .poks
+            .iter()
+            .map(|(scheme, pok)| (*scheme, pok.to_bytes_for_challenge_with_scheme(self.version, *scheme)))
+            .collect();
+        presentation_challenge(self.version, proof_bytes, nonce)
+    }
+
+    /// Compute the challenge over all proofs of knowledge and the nonce and generate the proofs
+    pub fn finish(self, nonce: &[u8]) -> Result<Presentation, PSError> {
+        if self.poks.is_empty() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Provide at least one credential"),
+            });
+        }
+        let challenge = self.challenge(nonce);
+        let mut credentials = Vec::with_capacity(self.poks.len());
+        for (scheme, pok) in self.poks {
+            credentials.push(PresentedCredential {
+                scheme,
+                proof: pok.gen_proof(&challenge)?,
+            });
+        }
+        Ok(Presentation {
+            credentials,
+            version: self.version,
+        })
+    }
+}
+
+impl Presentation {
+    /// The challenge the proofs should have been created with. `statements` should be in the same order as the
+    /// credentials were added to the presentation.
+    pub fn challenge(
+        &self,
+        statements: &[CredentialStatement],
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<FieldElement, PSError> {
+        if statements.len() != self.credentials.len() {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Presentation has {} credentials but {} statements given",
+                    self.credentials.len(),
+                    statements.len()
+                ),
+            });
+        }
+        let mut proof_bytes = Vec::with_capacity(statements.len());
+        for (cred, stmt) in self.credentials.iter().zip(statements.iter()) {
+            Self::check_revealed_indices(stmt)?;
+            let revealed_msg_indices = stmt.revealed_msgs.keys().cloned().collect::<HashSet<usize>>();
+            // The scheme of the statement is hashed so a proof presented for another scheme gets another challenge
+            proof_bytes.push((
+                stmt.scheme,
+                cred.proof.get_bytes_for_challenge_with_scheme(
+                    revealed_msg_indices,
+                    stmt.vk,
+                    params,
+                    self.version,
+                    stmt.scheme,
+                ),
+            ));
+        }
+        Ok(presentation_challenge(self.version, proof_bytes, nonce))
+    }
+
+    /// Verify all proofs in the presentation. `statements` should be in the same order as the credentials
+    /// were added to the presentation. Fails if the presentation's challenge is not `version`, the version the
+    /// verifier requires.
+    pub fn verify(
+        &self,
+        statements: &[CredentialStatement],
+        params: &Params,
+        nonce: &[u8],
+        version: ChallengeVersion,
+    ) -> Result<bool, PSError> {
+        if self.version != version {
+            return Err(PSError::GeneralError {
+                msg: format!(
+                    "Presentation has challenge version {:?} but {:?} is required",
+                    self.version, version
+                ),
+            });
+        }
+        let challenge = self.challenge(statements, params, nonce)?;
+        for (cred, stmt) in self.credentials.iter().zip(statements.iter()) {
+            if cred.scheme != stmt.scheme
+                || !cred
+                    .proof
+                    .verify(stmt.vk, params, stmt.revealed_msgs.clone(), &challenge)?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Revealed indices should be valid message indices. For the 2018 scheme, the last element of the
+    /// verkey corresponds to m' which should never be revealed so the verkey can't be empty.
+    fn check_revealed_indices(stmt: &CredentialStatement) -> Result<(), PSError> {
+        let msg_count = match stmt.scheme {
+            Scheme::PS2016 => stmt.vk.Y_tilde.len(),
+            Scheme::PS2018 => stmt.vk.Y_tilde.len().checked_sub(1).ok_or(PSError::UnsupportedNoOfMessages {
+                expected: 1,
+                given: 0,
+            })?,
+        };
+        for idx in stmt.revealed_msgs.keys() {
+            if *idx >= msg_count {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", idx, msg_count),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, keygen_2018};
+    use crate::signature::Signature;
+    use crate::signature_2018::Signature as Signature18;
+
+    #[test]
+    fn test_mixed_scheme_presentation() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs_1 = 5;
+        let (sk_1, vk_1) = keygen(count_msgs_1, &params);
+        let msgs_1 = (0..count_msgs_1).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_1 = Signature::new(msgs_1.as_slice(), &sk_1, &params).unwrap();
+
+        let count_msgs_2 = 4;
+        let (sk_2, vk_2) = keygen_2018(count_msgs_2, &params);
+        let msgs_2 = (0..count_msgs_2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_2 = Signature18::new(msgs_2.as_slice(), &sk_2).unwrap();
+
+        let mut revealed_1 = HashSet::new();
+        revealed_1.insert(1);
+        let mut revealed_2 = HashSet::new();
+        revealed_2.insert(0);
+        revealed_2.insert(3);
+
+        let pok_1 = PoKOfSignature::init(&sig_1, &vk_1, &params, msgs_1.clone(), None, revealed_1.clone()).unwrap();
+        let pok_2 = PoKOfSignature18::init(&sig_2, &vk_2, &params, msgs_2.clone(), None, revealed_2.clone()).unwrap();
+
+        let nonce = FieldElement::random().to_bytes();
+        let mut builder = PresentationBuilder::new();
+        builder.add_2016(pok_1);
+        builder.add_2018(pok_2);
+        let presentation = builder.finish(&nonce).unwrap();
+
+        let revealed_msgs_1 = revealed_1.iter().map(|i| (*i, msgs_1[*i].clone())).collect::<HashMap<usize, FieldElement>>();
+        let revealed_msgs_2 = revealed_2.iter().map(|i| (*i, msgs_2[*i].clone())).collect::<HashMap<usize, FieldElement>>();
+        let statements = vec![
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_1, revealed_msgs: revealed_msgs_1.clone() },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_2, revealed_msgs: revealed_msgs_2.clone() },
+        ];
+        assert!(presentation.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).unwrap());
+
+        // Different nonce fails
+        assert!(!presentation
+            .verify(&statements, &params, "other nonce".as_bytes(), ChallengeVersion::Legacy)
+            .unwrap());
+
+        // Wrong scheme fails
+        let statements = vec![
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_1, revealed_msgs: revealed_msgs_1.clone() },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_2, revealed_msgs: revealed_msgs_2.clone() },
+        ];
+        assert!(!presentation.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).unwrap());
+
+        // Revealing m' of the 2018 signature is not allowed
+        let mut revealed_msgs_2_bad = revealed_msgs_2;
+        revealed_msgs_2_bad.insert(count_msgs_2, sig_2.m_prime.clone());
+        let statements = vec![
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_1, revealed_msgs: revealed_msgs_1 },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_2, revealed_msgs: revealed_msgs_2_bad },
+        ];
+        assert!(presentation.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).is_err());
+
+        // A 2018 verkey without the element for m' is an error and not a panic
+        let mut empty_vk = vk_2.clone();
+        empty_vk.Y_tilde.clear();
+        let statements = vec![
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_1, revealed_msgs: HashMap::new() },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &empty_vk, revealed_msgs: HashMap::new() },
+        ];
+        assert!(presentation.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).is_err());
+    }
+
+    #[test]
+    fn test_presentation_with_v1_challenge() {
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(5, &params);
+        let msgs_1 = (0..5).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_1 = Signature::new(msgs_1.as_slice(), &sk_1, &params).unwrap();
+        let (sk_2, vk_2) = keygen(3, &params);
+        let msgs_2 = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_2 = Signature::new(msgs_2.as_slice(), &sk_2, &params).unwrap();
+        let (sk_3, vk_3) = keygen_2018(4, &params);
+        let msgs_3 = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_3 = Signature18::new(msgs_3.as_slice(), &sk_3).unwrap();
+
+        let mut revealed_1 = HashSet::new();
+        revealed_1.insert(2);
+        let revealed_2 = HashSet::new();
+        let mut revealed_3 = HashSet::new();
+        revealed_3.insert(1);
+
+        let nonce = FieldElement::random().to_bytes();
+        let mut aggregator = ProofAggregator::new_with_version(ChallengeVersion::V1);
+        aggregator.add_2016(PoKOfSignature::init(&sig_1, &vk_1, &params, msgs_1.clone(), None, revealed_1.clone()).unwrap());
+        aggregator.add_2016(PoKOfSignature::init(&sig_2, &vk_2, &params, msgs_2.clone(), None, revealed_2.clone()).unwrap());
+        aggregator.add_2018(PoKOfSignature18::init(&sig_3, &vk_3, &params, msgs_3.clone(), None, revealed_3.clone()).unwrap());
+        let challenge = aggregator.challenge(&nonce);
+        let proof = aggregator.finish(&nonce).unwrap();
+        assert_eq!(proof.version, ChallengeVersion::V1);
+
+        let revealed_msgs_1 = revealed_1.iter().map(|i| (*i, msgs_1[*i].clone())).collect::<HashMap<usize, FieldElement>>();
+        let revealed_msgs_3 = revealed_3.iter().map(|i| (*i, msgs_3[*i].clone())).collect::<HashMap<usize, FieldElement>>();
+        let statements = vec![
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_1, revealed_msgs: revealed_msgs_1.clone() },
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_2, revealed_msgs: HashMap::new() },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_3, revealed_msgs: revealed_msgs_3.clone() },
+        ];
+        assert_eq!(proof.challenge(&statements, &params, &nonce).unwrap(), challenge);
+        assert!(proof.verify(&statements, &params, &nonce, ChallengeVersion::V1).unwrap());
+        assert!(!proof.verify(&statements, &params, "other nonce".as_bytes(), ChallengeVersion::V1).unwrap());
+
+        // A presentation claiming the legacy challenge is rejected when the verifier requires V1, and the V1 proofs
+        // don't verify with the legacy challenge
+        let mut legacy = proof.clone();
+        legacy.version = ChallengeVersion::Legacy;
+        assert!(legacy.verify(&statements, &params, &nonce, ChallengeVersion::V1).is_err());
+        assert!(!legacy.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).unwrap());
+        assert!(proof.verify(&statements, &params, &nonce, ChallengeVersion::Legacy).is_err());
+
+        // Dropping or reordering proofs fails
+        let mut dropped = proof.clone();
+        dropped.credentials.pop();
+        assert!(!dropped.verify(&statements[..2], &params, &nonce, ChallengeVersion::V1).unwrap());
+        let mut reordered = proof.clone();
+        reordered.credentials.swap(0, 1);
+        let swapped_statements = vec![
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_2, revealed_msgs: HashMap::new() },
+            CredentialStatement { scheme: Scheme::PS2016, vk: &vk_1, revealed_msgs: revealed_msgs_1 },
+            CredentialStatement { scheme: Scheme::PS2018, vk: &vk_3, revealed_msgs: revealed_msgs_3 },
+        ];
+        assert!(!reordered.verify(&swapped_statements, &params, &nonce, ChallengeVersion::V1).unwrap());
+        assert!(proof.verify(&statements[..2], &params, &nonce, ChallengeVersion::V1).is_err());
+        assert!(ProofAggregator::new().finish(&nonce).is_err());
+    }
+}
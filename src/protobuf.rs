@@ -0,0 +1,370 @@
+// Protobuf messages, for use with prost, and conversions for keys, signatures, proofs and blind signature requests
+// so non-Rust services can exchange them with the schema in proto/ps_sig.proto. The messages are written by hand
+// to match the schema so protoc is not needed to build. Field and group elements are bytes of their `to_bytes`
+// representation, conversion to the crate's types validates the group elements. Enabled with the feature `protobuf`.
+
+use crate::blind_signature::ProofSignatureGroup;
+use crate::errors::PSError;
+use crate::keys;
+use crate::pok_sig::{self, ProofOtherGroup};
+use crate::signature;
+use crate::signature_2018;
+use crate::validation::Validate;
+use crate::{SignatureGroup, VerkeyGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use std::convert::TryFrom;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Params {
+    #[prost(bytes, tag = "1")]
+    pub g: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub g_tilde: Vec<u8>,
+    /// Commitment generators, `commitment_h_0` is empty if there are none
+    #[prost(bytes, tag = "3")]
+    pub commitment_h_0: Vec<u8>,
+    #[prost(bytes, repeated, tag = "4")]
+    pub commitment_h: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Verkey {
+    #[prost(bytes, tag = "1")]
+    pub x_tilde: Vec<u8>,
+    #[prost(bytes, repeated, tag = "2")]
+    pub y_tilde: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Signature {
+    #[prost(bytes, tag = "1")]
+    pub sigma_1: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub sigma_2: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Signature18 {
+    #[prost(bytes, tag = "1")]
+    pub m_prime: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub sig: Option<Signature>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProofVc {
+    #[prost(bytes, tag = "1")]
+    pub commitment: Vec<u8>,
+    #[prost(bytes, repeated, tag = "2")]
+    pub responses: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PoKOfSignatureProof {
+    #[prost(message, optional, tag = "1")]
+    pub sig: Option<Signature>,
+    #[prost(bytes, tag = "2")]
+    pub j: Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub proof_vc: Option<ProofVc>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlindSignatureRequest {
+    #[prost(bytes, tag = "1")]
+    pub commitment: Vec<u8>,
+    #[prost(bytes, repeated, tag = "2")]
+    pub known_messages: Vec<Vec<u8>>,
+    #[prost(message, optional, tag = "3")]
+    pub pok_commitment: Option<ProofVc>,
+}
+
+fn missing(field: &str) -> PSError {
+    PSError::InvalidBytes {
+        msg: format!("Missing field {}", field),
+    }
+}
+
+fn group_element<T: GroupElement + Validate>(bytes: &[u8]) -> Result<T, PSError> {
+    let elem = T::from_bytes(bytes)?;
+    elem.validate()?;
+    Ok(elem)
+}
+
+fn field_elements(bytes: &[Vec<u8>]) -> Result<FieldElementVector, PSError> {
+    let mut elems = FieldElementVector::with_capacity(bytes.len());
+    for b in bytes {
+        elems.push(FieldElement::from_bytes(b)?);
+    }
+    Ok(elems)
+}
+
+impl From<&keys::Params> for Params {
+    fn from(params: &keys::Params) -> Self {
+        let (commitment_h_0, commitment_h) = match &params.commitment_gens {
+            Some(gens) => (gens.h_0.to_bytes(), gens.h.iter().map(|h| h.to_bytes()).collect()),
+            None => (vec![], vec![]),
+        };
+        Self {
+            g: params.g.to_bytes(),
+            g_tilde: params.g_tilde.to_bytes(),
+            commitment_h_0,
+            commitment_h,
+        }
+    }
+}
+
+impl TryFrom<Params> for keys::Params {
+    type Error = PSError;
+
+    fn try_from(params: Params) -> Result<Self, PSError> {
+        let commitment_gens = if params.commitment_h_0.is_empty() {
+            None
+        } else {
+            Some(keys::CommitmentGenerators {
+                h_0: group_element::<SignatureGroup>(&params.commitment_h_0)?,
+                h: params
+                    .commitment_h
+                    .iter()
+                    .map(|h| group_element::<SignatureGroup>(h))
+                    .collect::<Result<Vec<_>, PSError>>()?,
+            })
+        };
+        Ok(Self {
+            g: group_element::<SignatureGroup>(&params.g)?,
+            g_tilde: group_element::<VerkeyGroup>(&params.g_tilde)?,
+            commitment_gens,
+        })
+    }
+}
+
+impl From<&keys::Verkey> for Verkey {
+    fn from(vk: &keys::Verkey) -> Self {
+        Self {
+            x_tilde: vk.X_tilde.to_bytes(),
+            y_tilde: vk.Y_tilde.iter().map(|y| y.to_bytes()).collect(),
+        }
+    }
+}
+
+impl TryFrom<Verkey> for keys::Verkey {
+    type Error = PSError;
+
+    fn try_from(vk: Verkey) -> Result<Self, PSError> {
+        let mut Y_tilde = Vec::with_capacity(vk.y_tilde.len());
+        for y in &vk.y_tilde {
+            Y_tilde.push(group_element::<VerkeyGroup>(y)?);
+        }
+        Ok(Self {
+            X_tilde: group_element::<VerkeyGroup>(&vk.x_tilde)?,
+            Y_tilde,
+            metadata: None,
+        })
+    }
+}
+
+impl From<&signature::Signature> for Signature {
+    fn from(sig: &signature::Signature) -> Self {
+        Self {
+            sigma_1: sig.sigma_1.to_bytes(),
+            sigma_2: sig.sigma_2.to_bytes(),
+        }
+    }
+}
+
+impl TryFrom<Signature> for signature::Signature {
+    type Error = PSError;
+
+    fn try_from(sig: Signature) -> Result<Self, PSError> {
+        Ok(Self {
+            sigma_1: group_element::<SignatureGroup>(&sig.sigma_1)?,
+            sigma_2: group_element::<SignatureGroup>(&sig.sigma_2)?,
+        })
+    }
+}
+
+impl From<&signature_2018::Signature> for Signature18 {
+    fn from(sig: &signature_2018::Signature) -> Self {
+        Self {
+            m_prime: sig.m_prime.to_bytes(),
+            sig: Some(Signature::from(&sig.sig)),
+        }
+    }
+}
+
+impl TryFrom<Signature18> for signature_2018::Signature {
+    type Error = PSError;
+
+    fn try_from(sig: Signature18) -> Result<Self, PSError> {
+        Ok(Self {
+            m_prime: FieldElement::from_bytes(&sig.m_prime)?,
+            sig: signature::Signature::try_from(sig.sig.ok_or_else(|| missing("sig"))?)?,
+        })
+    }
+}
+
+impl From<&ProofOtherGroup> for ProofVc {
+    fn from(proof: &ProofOtherGroup) -> Self {
+        Self {
+            commitment: proof.commitment.to_bytes(),
+            responses: proof.responses.iter().map(|r| r.to_bytes()).collect(),
+        }
+    }
+}
+
+impl TryFrom<ProofVc> for ProofOtherGroup {
+    type Error = PSError;
+
+    fn try_from(proof: ProofVc) -> Result<Self, PSError> {
+        Ok(Self {
+            commitment: group_element::<VerkeyGroup>(&proof.commitment)?,
+            responses: field_elements(&proof.responses)?,
+        })
+    }
+}
+
+impl From<&ProofSignatureGroup> for ProofVc {
+    fn from(proof: &ProofSignatureGroup) -> Self {
+        Self {
+            commitment: proof.commitment.to_bytes(),
+            responses: proof.responses.iter().map(|r| r.to_bytes()).collect(),
+        }
+    }
+}
+
+impl TryFrom<ProofVc> for ProofSignatureGroup {
+    type Error = PSError;
+
+    fn try_from(proof: ProofVc) -> Result<Self, PSError> {
+        Ok(Self {
+            commitment: group_element::<SignatureGroup>(&proof.commitment)?,
+            responses: field_elements(&proof.responses)?,
+        })
+    }
+}
+
+impl From<&pok_sig::PoKOfSignatureProof> for PoKOfSignatureProof {
+    fn from(proof: &pok_sig::PoKOfSignatureProof) -> Self {
+        Self {
+            sig: Some(Signature::from(&proof.sig)),
+            j: proof.J.to_bytes(),
+            proof_vc: Some(ProofVc::from(&proof.proof_vc)),
+        }
+    }
+}
+
+impl TryFrom<PoKOfSignatureProof> for pok_sig::PoKOfSignatureProof {
+    type Error = PSError;
+
+    fn try_from(proof: PoKOfSignatureProof) -> Result<Self, PSError> {
+        Ok(Self {
+            sig: signature::Signature::try_from(proof.sig.ok_or_else(|| missing("sig"))?)?,
+            J: group_element::<VerkeyGroup>(&proof.j)?,
+            proof_vc: ProofOtherGroup::try_from(proof.proof_vc.ok_or_else(|| missing("proof_vc"))?)?,
+        })
+    }
+}
+
+impl BlindSignatureRequest {
+    /// `commitment` and `known_messages` are the arguments of `BlindSignature::new`, `pok_commitment` is the
+    /// proof of knowledge of the opening of the commitment
+    pub fn new(
+        commitment: &SignatureGroup,
+        known_messages: &[FieldElement],
+        pok_commitment: &ProofSignatureGroup,
+    ) -> Self {
+        Self {
+            commitment: commitment.to_bytes(),
+            known_messages: known_messages.iter().map(|m| m.to_bytes()).collect(),
+            pok_commitment: Some(ProofVc::from(pok_commitment)),
+        }
+    }
+
+    /// Returns the commitment, the known messages and the proof of knowledge of the opening of the commitment
+    pub fn parse(self) -> Result<(SignatureGroup, Vec<FieldElement>, ProofSignatureGroup), PSError> {
+        let commitment = group_element::<SignatureGroup>(&self.commitment)?;
+        let mut known_messages = Vec::with_capacity(self.known_messages.len());
+        for m in &self.known_messages {
+            known_messages.push(FieldElement::from_bytes(m)?);
+        }
+        let pok = ProofSignatureGroup::try_from(self.pok_commitment.ok_or_else(|| missing("pok_commitment"))?)?;
+        Ok((commitment, known_messages, pok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use prost::Message;
+    use std::collections::{HashMap, HashSet};
+
+    fn round_trip<M: Message + Default>(msg: &M) -> M {
+        let mut buf = Vec::with_capacity(msg.encoded_len());
+        msg.encode(&mut buf).unwrap();
+        M::decode(buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_protobuf() {
+        let count_msgs = 4;
+        let params = keys::Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = signature::Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let params_1 = keys::Params::try_from(round_trip(&Params::from(&params))).unwrap();
+        assert_eq!(params_1.g, params.g);
+        assert_eq!(params_1.g_tilde, params.g_tilde);
+        assert!(params_1.commitment_gens.is_none());
+        let params_gens = keys::Params::new_with_commitment_generators("test".as_bytes(), 2);
+        let params_gens_1 = keys::Params::try_from(round_trip(&Params::from(&params_gens))).unwrap();
+        assert_eq!(params_gens_1.commitment_gens.unwrap().h, params_gens.commitment_gens.unwrap().h);
+        let vk_1 = keys::Verkey::try_from(round_trip(&Verkey::from(&vk))).unwrap();
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+        let sig_1 = signature::Signature::try_from(round_trip(&Signature::from(&sig))).unwrap();
+        assert!(sig_1.verify(msgs.clone(), &vk_1, &params_1).unwrap());
+
+        let mut revealed_msg_indices = HashSet::new();
+        revealed_msg_indices.insert(0);
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed_msg_indices.clone()).unwrap();
+        let chal = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&chal).unwrap();
+        let proof_1 =
+            pok_sig::PoKOfSignatureProof::try_from(round_trip(&PoKOfSignatureProof::from(&proof))).unwrap();
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+        let chal_verifier = FieldElement::from_msg_hash(&proof_1.get_bytes_for_challenge(
+            revealed_msg_indices,
+            &vk,
+            &params,
+        ));
+        assert!(proof_1.verify(&vk, &params, revealed_msgs, &chal_verifier).unwrap());
+
+        let (sk_18, vk_18) = crate::keys::keygen_2018(count_msgs, &params);
+        let sig_18 = signature_2018::Signature::new(msgs.as_slice(), &sk_18).unwrap();
+        let sig_18_1 = signature_2018::Signature::try_from(round_trip(&Signature18::from(&sig_18))).unwrap();
+        assert!(sig_18_1.verify(msgs.clone(), &vk_18, &params).unwrap());
+
+        let pok_commitment = ProofSignatureGroup {
+            commitment: SignatureGroup::random(),
+            responses: FieldElementVector::random(2),
+        };
+        let commitment = SignatureGroup::random();
+        let request = round_trip(&BlindSignatureRequest::new(&commitment, &msgs[2..], &pok_commitment));
+        let (commitment_1, known_messages, pok_commitment_1) = request.parse().unwrap();
+        assert_eq!(commitment_1, commitment);
+        assert_eq!(known_messages.as_slice(), &msgs[2..]);
+        assert_eq!(pok_commitment_1.responses, pok_commitment.responses);
+
+        // Missing and invalid fields are rejected
+        let mut msg = PoKOfSignatureProof::from(&proof);
+        msg.proof_vc = None;
+        assert!(pok_sig::PoKOfSignatureProof::try_from(msg).is_err());
+        let mut msg = Signature::from(&sig);
+        msg.sigma_1.pop();
+        assert!(signature::Signature::try_from(msg).is_err());
+    }
+}
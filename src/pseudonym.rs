@@ -0,0 +1,223 @@
+// Domain specific pseudonyms from a hidden link secret, a message of the credential known only to the holder (like
+// one signed blindly). The pseudonym for a domain (like a verifier's identity) is nym = H(domain)^link_secret, so a
+// holder has the same pseudonym at a verifier across presentations and credentials with the same link secret, but
+// pseudonyms for different domains can't be linked without the link secret. The holder proves in the proof of
+// knowledge of the signature that the exponent of nym is the hidden link secret by committing t = H(domain)^r with r,
+// the blinding of the link secret in the proof of knowledge, so the verifier checks H(domain)^s * nym^c == t with the
+// response s for the link secret. The domain and the pseudonym are hashed into the challenge. The verifier must use its
+// own domain and not one given by the holder.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::VerkeyGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::HashSet;
+
+/// Domain separation tag for hashing a domain to the base of its pseudonyms
+pub const PSEUDONYM_DST: &[u8] = b"PS-SIG-PSEUDONYM";
+
+/// Base of the pseudonyms for `domain`
+pub fn domain_base(domain: &[u8]) -> VerkeyGroup {
+    let mut bytes = PSEUDONYM_DST.to_vec();
+    bytes.extend_from_slice(domain);
+    VerkeyGroup::from_msg_hash(&bytes)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pseudonym {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub nym: VerkeyGroup,
+}
+
+impl Pseudonym {
+    /// Pseudonym H(domain)^link_secret
+    pub fn new(link_secret: &FieldElement, domain: &[u8]) -> Self {
+        Self {
+            nym: &domain_base(domain) * link_secret,
+        }
+    }
+
+    /// Bytes of the pseudonym which the verifier can use as the holder's identifier
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.nym.to_bytes()
+    }
+}
+
+/// Bytes of the domain, prefixed with its length as 4 byte big-endian, the pseudonym and the commitment for the
+/// challenge
+fn challenge_bytes(domain: &[u8], pseudonym: &Pseudonym, t: &VerkeyGroup) -> Vec<u8> {
+    let mut bytes = (domain.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(domain);
+    bytes.append(&mut pseudonym.to_bytes());
+    bytes.append(&mut t.to_bytes());
+    bytes
+}
+
+/// Prover's state of the proof that a pseudonym is for the hidden link secret of a proof of knowledge of a signature
+#[derive(Clone, Debug)]
+pub struct PseudonymCommitted {
+    pub domain: Vec<u8>,
+    pub pseudonym: Pseudonym,
+    pub t: VerkeyGroup,
+}
+
+/// Proof that the exponent of the pseudonym is the link secret, the response for the link secret is the one in the
+/// proof of knowledge of the signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PseudonymProof {
+    pub pseudonym: Pseudonym,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub t: VerkeyGroup,
+}
+
+impl PseudonymCommitted {
+    /// Create the pseudonym of `link_secret` for `domain` and commit with `blinding`, the blinding of the link secret
+    /// in the proof of knowledge of the signature
+    pub fn new(link_secret: &FieldElement, blinding: &FieldElement, domain: &[u8]) -> Self {
+        let base = domain_base(domain);
+        Self {
+            domain: domain.to_vec(),
+            pseudonym: Pseudonym {
+                nym: &base * link_secret,
+            },
+            t: &base * blinding,
+        }
+    }
+
+    /// Bytes to append to the challenge bytes of the proof of knowledge of the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        challenge_bytes(&self.domain, &self.pseudonym, &self.t)
+    }
+
+    /// The response is the one for the link secret in the proof of knowledge so no challenge is needed
+    pub fn gen_proof(self) -> PseudonymProof {
+        PseudonymProof {
+            pseudonym: self.pseudonym,
+            t: self.t,
+        }
+    }
+}
+
+impl PseudonymProof {
+    /// Same bytes as `PseudonymCommitted::to_bytes` for the verifier's challenge with the verifier's `domain`
+    pub fn get_bytes_for_challenge(&self, domain: &[u8]) -> Vec<u8> {
+        challenge_bytes(domain, &self.pseudonym, &self.t)
+    }
+
+    /// Verify that the pseudonym for `domain` is of the hidden message at `link_secret_idx` of `pok_proof`.
+    /// `pok_proof` must be verified with the same challenge, computed over it and this proof.
+    pub fn verify(
+        &self,
+        pok_proof: &PoKOfSignatureProof,
+        link_secret_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+        domain: &[u8],
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if self.pseudonym.nym.is_identity() {
+            return Ok(false);
+        }
+        let s = pok_proof.get_resp_for_hidden_message(link_secret_idx, revealed_msg_indices)?;
+        // H(domain)^s * nym^challenge == t
+        let t = (&domain_base(domain) * &s) + (&self.pseudonym.nym * challenge);
+        Ok(t == self.t)
+    }
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and create the pseudonym for
+/// `domain` of the hidden link secret at `link_secret_idx` with a proof that it is of the link secret. The challenge is
+/// computed over the bytes of the proof of knowledge followed by those of the pseudonym.
+pub fn init_pok_with_pseudonym(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    link_secret_idx: usize,
+    domain: &[u8],
+) -> Result<(PoKOfSignature, PseudonymCommitted), PSError> {
+    if link_secret_idx >= messages.len() {
+        return Err(PSError::GeneralError {
+            msg: format!("Index {} should be less than {}", link_secret_idx, messages.len()),
+        });
+    }
+    let blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let blinding = &blindings[hidden_message_position(link_secret_idx, &revealed_msg_indices)?];
+    let committed = PseudonymCommitted::new(&messages[link_secret_idx], blinding, domain);
+    let pok = PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)?;
+    Ok((pok, committed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pok_with_pseudonym() {
+        // 2 credentials with the same link secret at index 0, the message at 2 of the first revealed
+        let params = Params::new("test".as_bytes());
+        let link_secret = FieldElement::random();
+        let (sk_1, vk_1) = keygen(4, &params);
+        let mut msgs_1 = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        msgs_1[0] = link_secret.clone();
+        let sig_1 = Signature::new(msgs_1.as_slice(), &sk_1, &params).unwrap();
+        let (sk_2, vk_2) = keygen(3, &params);
+        let mut msgs_2 = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        msgs_2[0] = link_secret.clone();
+        let sig_2 = Signature::new(msgs_2.as_slice(), &sk_2, &params).unwrap();
+
+        let mut revealed = HashSet::new();
+        revealed.insert(2);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(2, msgs_1[2].clone());
+        let domain = b"verifier-1";
+
+        let present = |sig: &Signature, vk: &Verkey, msgs: &[FieldElement], revealed: &HashSet<usize>, domain: &[u8]| {
+            let (pok, committed) =
+                init_pok_with_pseudonym(sig, vk, &params, msgs.to_vec(), revealed.clone(), 0, domain).unwrap();
+            let mut chal_bytes = pok.to_bytes();
+            chal_bytes.append(&mut committed.to_bytes());
+            let challenge = FieldElement::from_msg_hash(&chal_bytes);
+            (pok.gen_proof(&challenge).unwrap(), committed.gen_proof())
+        };
+
+        let (proof, nym_proof) = present(&sig_1, &vk_1, &msgs_1, &revealed, domain);
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk_1, &params);
+        chal_bytes.append(&mut nym_proof.get_bytes_for_challenge(domain));
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk_1, &params, revealed_msgs, &challenge).unwrap());
+        assert!(nym_proof.verify(&proof, 0, &revealed, domain, &challenge).unwrap());
+        assert_eq!(nym_proof.pseudonym, Pseudonym::new(&link_secret, domain));
+
+        // Another domain gives another challenge and the proof fails
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk_1, &params);
+        chal_bytes.append(&mut nym_proof.get_bytes_for_challenge(b"verifier-2"));
+        let other_challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(!nym_proof.verify(&proof, 0, &revealed, b"verifier-2", &other_challenge).unwrap());
+        // The pseudonym is not of another hidden message
+        assert!(!nym_proof.verify(&proof, 1, &revealed, domain, &challenge).unwrap());
+        assert!(nym_proof.verify(&proof, 2, &revealed, domain, &challenge).is_err());
+        // Another pseudonym fails
+        let mut wrong = nym_proof.clone();
+        wrong.pseudonym = Pseudonym::new(&FieldElement::random(), domain);
+        assert!(!wrong.verify(&proof, 0, &revealed, domain, &challenge).unwrap());
+
+        // Same pseudonym at the same verifier from the other credential and a different one at another verifier
+        let (_, nym_proof_2) = present(&sig_2, &vk_2, &msgs_2, &HashSet::new(), domain);
+        assert_eq!(nym_proof_2.pseudonym, nym_proof.pseudonym);
+        let (_, nym_proof_3) = present(&sig_1, &vk_1, &msgs_1, &revealed, b"verifier-2");
+        assert_ne!(nym_proof_3.pseudonym, nym_proof.pseudonym);
+
+        // The link secret can't be revealed
+        let mut revealed_link_secret = HashSet::new();
+        revealed_link_secret.insert(0);
+        assert!(init_pok_with_pseudonym(&sig_1, &vk_1, &params, msgs_1.clone(), revealed_link_secret, 0, domain).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+// Range proofs, 0 <= m < 2^n, for hidden messages of a proof of knowledge of a signature (2016 scheme). The message
+// is committed as C = g^m * h^rho in the signature group and its bits b_k as C_k = g^b_k * h^rho_k with
+// rho = sum of 2^k * rho_k, so that C is the product of C_k^(2^k). For each bit an OR proof shows that C_k or C_k / g
+// is a power of h, and knowledge of the opening of C is proven with the same blinding for m as in the proof of
+// knowledge of the signature, so the response for m is the same in both and the verifier knows the ranged value is
+// the signed one. The bulletproofs crate is over Ristretto and can't share the blinding with a proof over BLS12-381,
+// so this proof is linear in n rather than logarithmic. Bounds other than 0 and 2^n, like age >= 18, are proven by
+// signing the shifted value or with a linear relation (`linear_relations`) to a ranged message.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::HashSet;
+
+/// Domain separation tag for the generator h of the commitments
+pub const RANGE_PROOF_H_DST: &[u8] = b"PS-SIG-RANGE-PROOF-H";
+
+/// Domain separation tag of the bytes of the range proof for the challenge
+pub const RANGE_PROOF_DST: &[u8] = b"PS-SIG-RANGE-PROOF";
+
+/// Largest n of a range [0, 2^n), 2^n is less than half the order of the group so that sums of 2 values in range
+/// don't wrap around
+pub const MAX_RANGE_BITS: usize = 252;
+
+/// Generator h of the commitments, derived from the params so no one knows its discrete log to base g
+fn generator_h(params: &Params) -> SignatureGroup {
+    let mut bytes = RANGE_PROOF_H_DST.to_vec();
+    bytes.append(&mut params.g.to_bytes());
+    SignatureGroup::from_msg_hash(&bytes)
+}
+
+/// The `bits` least significant bits of the message, failing if it is not less than 2^bits
+pub(crate) fn message_bits(message: &FieldElement, bits: usize) -> Result<Vec<bool>, PSError> {
+    if bits == 0 || bits > MAX_RANGE_BITS {
+        return Err(PSError::GeneralError {
+            msg: format!("No of bits {} should be between 1 and {}", bits, MAX_RANGE_BITS),
+        });
+    }
+    // Big-endian bytes
+    let bytes = message.to_bytes();
+    let bit = |k: usize| (bytes[bytes.len() - 1 - k / 8] >> (k % 8)) & 1 == 1;
+    if (bits..bytes.len() * 8).any(bit) {
+        return Err(PSError::GeneralError {
+            msg: format!("Message is not less than 2^{}", bits),
+        });
+    }
+    Ok((0..bits).map(bit).collect())
+}
+
+#[derive(Clone, Debug)]
+struct BitCommitted {
+    bit: bool,
+    rho: FieldElement,
+    w: FieldElement,
+    sim_challenge: FieldElement,
+    sim_response: FieldElement,
+    commitment: SignatureGroup,
+    a_0: SignatureGroup,
+    a_1: SignatureGroup,
+}
+
+/// Proof for one bit: C_k = h^rho_k (bit 0) or C_k / g = h^rho_k (bit 1). The challenge for bit 1 is the proof's
+/// challenge minus `c_0`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BitProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub commitment: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub a_0: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub a_1: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub c_0: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub z_0: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub z_1: FieldElement,
+}
+
+/// Prover's state of a range proof between the commitments and the challenge. It has the secrets of the proof so
+/// it is not serializable.
+#[derive(Clone, Debug)]
+pub struct RangeProofCommitted {
+    rho: FieldElement,
+    r_rho: FieldElement,
+    bits: Vec<BitCommitted>,
+    pub commitment: SignatureGroup,
+    pub t: SignatureGroup,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RangeProof {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub commitment: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub t: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub s_rho: FieldElement,
+    pub bit_proofs: Vec<BitProof>,
+}
+
+fn bytes_for_challenge<'a, I: Iterator<Item = (&'a SignatureGroup, &'a SignatureGroup, &'a SignatureGroup)>>(
+    commitment: &SignatureGroup,
+    t: &SignatureGroup,
+    bits: I,
+) -> Vec<u8> {
+    let mut bytes = RANGE_PROOF_DST.to_vec();
+    bytes.append(&mut commitment.to_bytes());
+    bytes.append(&mut t.to_bytes());
+    for (c, a_0, a_1) in bits {
+        bytes.append(&mut c.to_bytes());
+        bytes.append(&mut a_0.to_bytes());
+        bytes.append(&mut a_1.to_bytes());
+    }
+    bytes
+}
+
+impl RangeProofCommitted {
+    /// Commit to prove that `message` is less than 2^`bits`. `blinding` must be the blinding of the message in the
+    /// proof of knowledge of the signature.
+    pub fn new(message: &FieldElement, bits: usize, blinding: &FieldElement, params: &Params) -> Result<Self, PSError> {
+        let message_bits = message_bits(message, bits)?;
+        let g = &params.g;
+        let h = generator_h(params);
+        let mut rho = FieldElement::zero();
+        let mut power = FieldElement::one();
+        let mut bit_commitments = Vec::with_capacity(bits);
+        for bit in message_bits {
+            let rho_k = FieldElement::random();
+            let mut commitment = &h * &rho_k;
+            if bit {
+                commitment += g.clone();
+            }
+            // The branch of the other bit value is simulated
+            let w = FieldElement::random();
+            let sim_challenge = FieldElement::random();
+            let sim_response = FieldElement::random();
+            let other = if bit { commitment.clone() } else { &commitment - g };
+            let a_sim = (&h * &sim_response) + (&other * &sim_challenge);
+            let a_real = &h * &w;
+            let (a_0, a_1) = if bit { (a_sim, a_real) } else { (a_real, a_sim) };
+            rho += &power * &rho_k;
+            power = &power + &power;
+            bit_commitments.push(BitCommitted {
+                bit,
+                rho: rho_k,
+                w,
+                sim_challenge,
+                sim_response,
+                commitment,
+                a_0,
+                a_1,
+            });
+        }
+        let commitment = (g * message) + (&h * &rho);
+        let r_rho = FieldElement::random();
+        let t = (g * blinding) + (&h * &r_rho);
+        Ok(Self {
+            rho,
+            r_rho,
+            bits: bit_commitments,
+            commitment,
+            t,
+        })
+    }
+
+    /// Bytes of the commitments to append to the challenge bytes of the proof of knowledge of the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bytes_for_challenge(
+            &self.commitment,
+            &self.t,
+            self.bits.iter().map(|b| (&b.commitment, &b.a_0, &b.a_1)),
+        )
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> RangeProof {
+        let s_rho = &self.r_rho - &(challenge * &self.rho);
+        let bit_proofs = self
+            .bits
+            .into_iter()
+            .map(|b| {
+                let c_real = challenge - &b.sim_challenge;
+                let z_real = &b.w - &(&c_real * &b.rho);
+                let (c_0, z_0, z_1) = if b.bit {
+                    (b.sim_challenge, b.sim_response, z_real)
+                } else {
+                    (c_real, z_real, b.sim_response)
+                };
+                BitProof {
+                    commitment: b.commitment,
+                    a_0: b.a_0,
+                    a_1: b.a_1,
+                    c_0,
+                    z_0,
+                    z_1,
+                }
+            })
+            .collect();
+        RangeProof {
+            commitment: self.commitment,
+            t: self.t,
+            s_rho,
+            bit_proofs,
+        }
+    }
+}
+
+impl RangeProof {
+    /// Same bytes as `RangeProofCommitted::to_bytes` for the verifier's challenge
+    pub fn get_bytes_for_challenge(&self) -> Vec<u8> {
+        bytes_for_challenge(
+            &self.commitment,
+            &self.t,
+            self.bit_proofs.iter().map(|b| (&b.commitment, &b.a_0, &b.a_1)),
+        )
+    }
+
+    /// Verify that the hidden message at `msg_idx` of `pok_proof` is less than 2^`bits`. `pok_proof` must be verified
+    /// with the same challenge, which must be computed over it and this proof.
+    pub fn verify(
+        &self,
+        pok_proof: &PoKOfSignatureProof,
+        msg_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+        bits: usize,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        let s_m = pok_proof.get_resp_for_hidden_message(msg_idx, revealed_msg_indices)?;
+        Ok(self.verify_with_response(&s_m, bits, params, challenge))
+    }
+
+    /// Verify that the value committed is less than 2^`bits` where `response` is the response for the value in the
+    /// proof the range proof is bound to. For a value that is a linear combination of messages, the response is the
+    /// same combination of their responses, with the challenge times the constant term subtracted.
+    pub fn verify_with_response(
+        &self,
+        response: &FieldElement,
+        bits: usize,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> bool {
+        if bits == 0 || bits > MAX_RANGE_BITS || self.bit_proofs.len() != bits {
+            return false;
+        }
+        let g = &params.g;
+        let h = generator_h(params);
+
+        // C == product of C_k^(2^k)
+        let mut sum = SignatureGroup::identity();
+        for b in self.bit_proofs.iter().rev() {
+            sum = &(&sum + &sum) + &b.commitment;
+        }
+        if sum != self.commitment {
+            return false;
+        }
+
+        // Knowledge of the opening of C with the response for the value from the proof it is bound to
+        if (g * response) + (&h * &self.s_rho) + (&self.commitment * challenge) != self.t {
+            return false;
+        }
+
+        for b in &self.bit_proofs {
+            let c_1 = challenge - &b.c_0;
+            if (&h * &b.z_0) + (&b.commitment * &b.c_0) != b.a_0 {
+                return false;
+            }
+            if (&h * &b.z_1) + (&(&b.commitment - g) * &c_1) != b.a_1 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and range proofs for the
+/// hidden messages at the indices of `ranges`, each with its no of bits. The challenge is computed over the bytes of
+/// the proof of knowledge followed by those of each range proof.
+pub fn init_pok_with_range_proofs(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    ranges: &[(usize, usize)],
+) -> Result<(PoKOfSignature, Vec<RangeProofCommitted>), PSError> {
+    let blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let mut range_proofs = Vec::with_capacity(ranges.len());
+    for (i, bits) in ranges {
+        if *i >= messages.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, messages.len()),
+            });
+        }
+        let pos = hidden_message_position(*i, &revealed_msg_indices)?;
+        range_proofs.push(RangeProofCommitted::new(&messages[*i], *bits, &blindings[pos], params)?);
+    }
+    let pok = PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)?;
+    Ok((pok, range_proofs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_pok_with_range_proofs() {
+        // Age in [0, 2^7), salary in [0, 2^32), the name at 0 revealed
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let msgs = vec![
+            FieldElement::random(),
+            FieldElement::from(25u64),
+            FieldElement::from(85_000u64),
+            FieldElement::random(),
+        ];
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let ranges = vec![(1, 7), (2, 32)];
+        let mut revealed = HashSet::new();
+        revealed.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        let (pok, committed) =
+            init_pok_with_range_proofs(&sig, &vk, &params, msgs.clone(), revealed.clone(), &ranges).unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        for c in &committed {
+            chal_bytes.append(&mut c.to_bytes());
+        }
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let range_proofs = committed.into_iter().map(|c| c.gen_proof(&challenge)).collect::<Vec<_>>();
+
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+        for r in &range_proofs {
+            chal_bytes.append(&mut r.get_bytes_for_challenge());
+        }
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+        for (r, (i, bits)) in range_proofs.iter().zip(ranges.iter()) {
+            assert!(r.verify(&proof, *i, &revealed, *bits, &params, &challenge).unwrap());
+        }
+
+        // A range proof for another message or another range fails
+        assert!(!range_proofs[0].verify(&proof, 2, &revealed, 7, &params, &challenge).unwrap());
+        assert!(!range_proofs[0].verify(&proof, 1, &revealed, 8, &params, &challenge).unwrap());
+        assert!(range_proofs[0].verify(&proof, 0, &revealed, 7, &params, &challenge).is_err());
+        let mut tampered = range_proofs[1].clone();
+        tampered.commitment += params.g.clone();
+        assert!(!tampered.verify(&proof, 2, &revealed, 32, &params, &challenge).unwrap());
+
+        // Messages out of range can't be proven
+        assert!(init_pok_with_range_proofs(&sig, &vk, &params, msgs.clone(), revealed.clone(), &[(1, 4)]).is_err());
+        assert!(init_pok_with_range_proofs(&sig, &vk, &params, msgs.clone(), revealed.clone(), &[(3, 64)]).is_err());
+        assert!(init_pok_with_range_proofs(&sig, &vk, &params, msgs.clone(), revealed.clone(), &[(3, 253)]).is_err());
+        assert!(init_pok_with_range_proofs(&sig, &vk, &params, msgs, revealed, &[(1, 0)]).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// Serde helpers, used with `#[serde(with = "...")]`, which serialize field and group elements as lowercase hex
+// strings of their `to_bytes` representation in human readable formats like JSON and as usual in binary formats
+// like bincode. Enabled with the feature `serde_hex`, which applies them to the keys, signatures and proofs.
+
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+/// Lowercase hex encoding of `bytes`
+pub fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdef";
+    let mut s = String::with_capacity(2 * bytes.len());
+    for b in bytes {
+        s.push(DIGITS[(b >> 4) as usize] as char);
+        s.push(DIGITS[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+/// Decode hex string, both lowercase and uppercase digits are accepted
+pub fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    fn digit(c: u8) -> Result<u8, String> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err(format!("Invalid hex digit {:?}", c as char)),
+        }
+    }
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return Err(String::from("Hex string should have even length"));
+    }
+    s.chunks(2)
+        .map(|pair| Ok((digit(pair[0])? << 4) | digit(pair[1])?))
+        .collect()
+}
+
+/// Elements that can be serialized as hex
+pub trait HexElement: Sized + Serialize + for<'de> Deserialize<'de> {
+    fn to_hex_bytes(&self) -> Vec<u8>;
+    fn from_hex_bytes(bytes: &[u8]) -> Result<Self, String>;
+}
+
+impl HexElement for FieldElement {
+    fn to_hex_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_hex_bytes(bytes: &[u8]) -> Result<Self, String> {
+        FieldElement::from_bytes(bytes).map_err(|e| format!("{:?}", e))
+    }
+}
+
+impl HexElement for G1 {
+    fn to_hex_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_hex_bytes(bytes: &[u8]) -> Result<Self, String> {
+        G1::from_bytes(bytes).map_err(|e| format!("{:?}", e))
+    }
+}
+
+impl HexElement for G2 {
+    fn to_hex_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    fn from_hex_bytes(bytes: &[u8]) -> Result<Self, String> {
+        G2::from_bytes(bytes).map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// For fields of type `FieldElement`, `G1` or `G2`
+pub mod element {
+    use super::*;
+
+    pub fn serialize<T: HexElement, S: Serializer>(elem: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(&elem.to_hex_bytes()))
+        } else {
+            elem.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, T: HexElement, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let bytes = from_hex(&s).map_err(D::Error::custom)?;
+            T::from_hex_bytes(&bytes).map_err(D::Error::custom)
+        } else {
+            T::deserialize(deserializer)
+        }
+    }
+}
+
+/// For fields of type `Vec` of `FieldElement`, `G1` or `G2`
+pub mod elements {
+    use super::*;
+
+    pub fn serialize<T: HexElement, S: Serializer>(elems: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut seq = serializer.serialize_seq(Some(elems.len()))?;
+            for e in elems {
+                seq.serialize_element(&to_hex(&e.to_hex_bytes()))?;
+            }
+            seq.end()
+        } else {
+            elems.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, T: HexElement, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+        if deserializer.is_human_readable() {
+            let strings = Vec::<String>::deserialize(deserializer)?;
+            strings
+                .iter()
+                .map(|s| {
+                    let bytes = from_hex(s).map_err(D::Error::custom)?;
+                    T::from_hex_bytes(&bytes).map_err(D::Error::custom)
+                })
+                .collect()
+        } else {
+            Vec::<T>::deserialize(deserializer)
+        }
+    }
+}
+
+/// For fields of type `FieldElementVector`
+pub mod field_element_vector {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(elems: &FieldElementVector, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            super::elements::serialize(elems.as_slice(), serializer)
+        } else {
+            elems.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FieldElementVector, D::Error> {
+        if deserializer.is_human_readable() {
+            let elems = super::elements::deserialize::<FieldElement, D>(deserializer)?;
+            let mut v = FieldElementVector::with_capacity(elems.len());
+            for e in elems {
+                v.push(e);
+            }
+            Ok(v)
+        } else {
+            FieldElementVector::deserialize(deserializer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let bytes = vec![0u8, 1, 15, 16, 171, 255];
+        let s = to_hex(&bytes);
+        assert_eq!(s, "00010f10abff");
+        assert_eq!(from_hex(&s).unwrap(), bytes);
+        assert_eq!(from_hex("00010F10ABFF").unwrap(), bytes);
+        assert!(from_hex("0").is_err());
+        assert!(from_hex("0g").is_err());
+    }
+
+    #[test]
+    fn test_json_hex() {
+        use crate::keys::{keygen, Params, Verkey};
+        use crate::signature::Signature;
+
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let json = serde_json::to_string(&sig).unwrap();
+        assert!(json.contains(&to_hex(&sig.sigma_1.to_bytes())));
+        let sig_1: Signature = serde_json::from_str(&json).unwrap();
+
+        let json = serde_json::to_string(&vk).unwrap();
+        assert!(json.contains(&to_hex(&vk.Y_tilde[2].to_bytes())));
+        let vk_1: Verkey = serde_json::from_str(&json).unwrap();
+        assert!(sig_1.verify(msgs, &vk_1, &params).unwrap());
+    }
+}
@@ -1,27 +1,77 @@
 // Scheme defined in 2016 paper, CT-RSA 2016 (eprint 2015/525), section 4.2.
 // The idea for blind signatures can be taken from Coconut
 
-use crate::errors::PSError;
-use crate::{ate_2_pairing, VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec};
+use crate::errors::{PSError, VerificationError};
+use crate::{ate_2_pairing, ate_multi_pairing, VerkeyGroup, VerkeyGroupVec, SignatureGroup, SignatureGroupVec, SIGNATURE_GROUP_SIZE};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
-use crate::keys::{Params, Sigkey, Verkey};
+use crate::keys::{wipe, FixedBaseTable, Params, PreparedSigkey, PreparedVerkey, Scheme, Sigkey, Signer, Verkey};
+use crate::multibase::{self, Base};
+use crate::validation::{deserialize_validated, Validate};
+use crate::wire;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Domain separation tag for deriving the randomness of each signature in `Signature::batch_new`
+pub const BATCH_SIGNING_DST: &[u8] = b"PS-SIG-BATCH-SIGNING";
+
+/// Domain separation tag for hashing byte strings to messages in `Signature::message_from_bytes`
+pub const MESSAGE_HASH_DST: &[u8] = b"PS-SIG-MESSAGE";
+
+/// No of messages `Signature::new_from_iter` holds and passes to `Signer::sign_exponent` at once
+const FROM_ITER_CHUNK_SIZE: usize = 128;
 
 /// Created by the signer when no blinded messages. Also the receiver of a blind signature can get
-/// this by unblinding the blind signature.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// this by unblinding the blind signature. Deserializing rejects a signature with an identity element or an element not
+/// in the prime order subgroup with `PSError::InvalidGroupElement`.
+#[derive(Clone, Debug, Serialize)]
 pub struct Signature {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub sigma_1: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub sigma_2: SignatureGroup,
 }
 
+/// Fields of `Signature` as deserialized, before validation
+#[derive(Deserialize)]
+struct SignatureFields {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    sigma_1: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    sigma_2: SignatureGroup,
+}
+
+impl<'de> serde::Deserialize<'de> for Signature {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_validated(deserializer, |f: SignatureFields| Signature {
+            sigma_1: f.sigma_1,
+            sigma_2: f.sigma_2,
+        })
+    }
+}
+
 impl Signature {
     /// Create a new signature. The signature generation involves generating a random value for `sigma_1` so different
     /// calls to this method with same messages, signing key and params will give different value
-    pub fn new(messages: &[FieldElement], sigkey: &Sigkey, params: &Params) -> Result<Self, PSError> {
+    pub fn new<S: Signer>(messages: &[FieldElement], sigkey: &S, params: &Params) -> Result<Self, PSError> {
+        Self::new_with_rng(messages, sigkey, params, &mut rand::thread_rng())
+    }
+
+    /// Same as `new` but the random value is generated using `rng`
+    pub fn new_with_rng<S: Signer, R: RngCore + CryptoRng>(
+        messages: &[FieldElement],
+        sigkey: &S,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<Self, PSError> {
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+        }
         Self::check_sigkey_and_messages_compat(messages, sigkey)?;
         // A random h should be generated which is same as generating a random u and then computing h = g^u
-        let u = FieldElement::random();
+        let mut u = FieldElement::random_using_rng(rng);
         let (sigma_1, sigma_2) = Self::sign_with_sigma_1_generated_from_given_exp(
             messages,
             sigkey,
@@ -29,24 +79,182 @@ impl Signature {
             0,
             &params.g,
         )?;
+        wipe(&mut u);
         Ok(Self { sigma_1, sigma_2 })
     }
 
+    /// Same as `new` but with a key prepared for repeated signing, sigma_1 = g^u and sigma_2 = g^(u*(x + y_1*m_1 + ...))
+    /// are computed with the key's table of multiples of g. The table is scanned in constant time so neither u nor the
+    /// signing key leaks through timing or cache accesses.
+    pub fn new_with_prepared_sigkey(
+        messages: &[FieldElement],
+        sigkey: &PreparedSigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        sigkey.check_params(params)?;
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+        }
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let indexed_messages = messages.iter().enumerate().collect::<Vec<_>>();
+        let mut exp = sigkey.signing_exponent(&indexed_messages)?;
+        let mut u = FieldElement::random();
+        exp = &exp * &u;
+        let sigma_1 = sigkey.g_table.mul(&u);
+        let sigma_2 = sigkey.g_table.mul(&exp);
+        wipe(&mut u);
+        wipe(&mut exp);
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Same as `new` but the `count` messages are taken from an iterator and signed `FROM_ITER_CHUNK_SIZE` messages
+    /// at a time, for very large message vectors which should not be held in memory at once. Each chunk is signed with
+    /// `Signer::sign_exponent` so any signer can be used. As each call includes x, sigma_2 is
+    /// sigma_1^x * (sigma_1^(x + y_1*m_1 + ...) / sigma_1^x) * ... over the chunks. Fails if the iterator does not give
+    /// exactly `count` messages.
+    pub fn new_from_iter<I: IntoIterator<Item = FieldElement>, S: Signer>(
+        messages: I,
+        count: usize,
+        sigkey: &S,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2016, count, Some(params))?;
+        }
+        if count != sigkey.key_size() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sigkey.key_size(),
+                given: count,
+            });
+        }
+        let mut u = FieldElement::random();
+        let sigma_1 = &params.g * &u;
+        wipe(&mut u);
+        let sigma_1_x = sigkey.sign_exponent(&sigma_1, &[])?;
+        let mut sigma_2 = sigma_1_x.clone();
+        let mut chunk = Vec::with_capacity(FROM_ITER_CHUNK_SIZE);
+        let mut given = 0;
+        let mut iter = messages.into_iter();
+        loop {
+            let next = iter.next();
+            let done = next.is_none();
+            if let Some(m) = next {
+                if given == count {
+                    return Err(PSError::UnsupportedNoOfMessages {
+                        expected: count,
+                        given: given + 1,
+                    });
+                }
+                chunk.push((given, m));
+                given += 1;
+            }
+            if chunk.len() == FROM_ITER_CHUNK_SIZE || (done && !chunk.is_empty()) {
+                let indexed_messages = chunk.iter().map(|(i, m)| (*i, m)).collect::<Vec<_>>();
+                let part = sigkey.sign_exponent(&sigma_1, &indexed_messages)? - &sigma_1_x;
+                sigma_2 += &part;
+                chunk.clear();
+            }
+            if done {
+                break;
+            }
+        }
+        if given != count {
+            return Err(PSError::UnsupportedNoOfMessages { expected: count, given });
+        }
+        Ok(Self { sigma_1, sigma_2 })
+    }
+
+    /// Sign each message vector of `batches`, for an issuer signing many credentials at once. The random value u of
+    /// each signature is derived from a single random seed and the checks on the key are done once per distinct no
+    /// of messages. sigma_1 = g^u of all signatures is computed with a table of multiples of g (as in
+    /// `PreparedSigkey`) built once for the batch so it needs additions instead of a scalar multiplication, sigma_2
+    /// needs the key and is computed by `sigkey`. The table is scanned in constant time so u does not leak through
+    /// timing. With feature `parallel` the signatures are created in parallel.
+    pub fn batch_new<S: Signer + Sync>(
+        batches: &[Vec<FieldElement>],
+        sigkey: &S,
+        params: &Params,
+    ) -> Result<Vec<Self>, PSError> {
+        Self::batch_new_with_rng(batches, sigkey, params, &mut rand::thread_rng())
+    }
+
+    /// Same as `batch_new` but the seed is generated using `rng`
+    pub fn batch_new_with_rng<S: Signer + Sync, R: RngCore + CryptoRng>(
+        batches: &[Vec<FieldElement>],
+        sigkey: &S,
+        params: &Params,
+        rng: &mut R,
+    ) -> Result<Vec<Self>, PSError> {
+        let mut checked_sizes = vec![];
+        for messages in batches {
+            if !checked_sizes.contains(&messages.len()) {
+                if let Some(metadata) = sigkey.metadata() {
+                    metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+                }
+                Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+                checked_sizes.push(messages.len());
+            }
+        }
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let mut prefix = BATCH_SIGNING_DST.to_vec();
+        prefix.extend_from_slice(&seed);
+        let g_table = FixedBaseTable::new(&params.g);
+        let sign = |(i, messages): (usize, &Vec<FieldElement>)| -> Result<Self, PSError> {
+            let mut bytes = prefix.clone();
+            bytes.extend_from_slice(&(i as u64).to_be_bytes());
+            let mut u = FieldElement::from_msg_hash(&bytes);
+            let sigma_1 = g_table.mul(&u);
+            wipe(&mut u);
+            let sigma_2 = Self::sign_with_given_sigma_1(messages, sigkey, 0, &sigma_1)?;
+            Ok(Self { sigma_1, sigma_2 })
+        };
+        #[cfg(feature = "parallel")]
+        let sigs: Result<Vec<Self>, PSError> = {
+            use rayon::prelude::*;
+            batches.par_iter().enumerate().map(sign).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let sigs: Result<Vec<Self>, PSError> = batches.iter().enumerate().map(sign).collect();
+        #[cfg(feature = "zeroize")]
+        {
+            zeroize::Zeroize::zeroize(&mut seed);
+            zeroize::Zeroize::zeroize(&mut prefix);
+        }
+        sigs
+    }
+
     /// Create a new signature. The signature generation doesn't involve generating a random value but
     /// the messages are hashed to get a pseudorandom value for `sigma_1`. Hence different calls to this method
     /// with same messages and signing key will give same value
-    pub fn new_deterministic(messages: &[FieldElement], sigkey: &Sigkey) -> Result<Self, PSError> {
+    pub fn new_deterministic<S: Signer>(messages: &[FieldElement], sigkey: &S) -> Result<Self, PSError> {
         Self::check_sigkey_and_messages_compat(messages, sigkey)?;
         let sigma_1 = Self::generate_sigma_1_from_messages(messages);
         let sigma_2 = Self::sign_with_given_sigma_1(messages, sigkey, 0, &sigma_1)?;
         Ok(Self {sigma_1, sigma_2})
     }
 
+    /// Same as `new_deterministic` but `sigma_1` is derived by hashing the signing key along with the messages and
+    /// `context`, like RFC 6979 nonces, so signing the same messages in the same context gives the same signature
+    /// but no one without the signing key can compute `sigma_1` in advance
+    pub fn new_deterministic_keyed(messages: &[FieldElement], sigkey: &Sigkey, context: &[u8]) -> Result<Self, PSError> {
+        if let Some(metadata) = &sigkey.metadata {
+            metadata.check(Scheme::PS2016, messages.len(), None)?;
+        }
+        Self::check_sigkey_and_messages_compat(messages, sigkey)?;
+        let mut bytes = sigkey.deterministic_signing_input(Scheme::PS2016, messages, context);
+        let sigma_1 = SignatureGroup::from_msg_hash(&bytes);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut bytes);
+        let sigma_2 = Self::sign_with_given_sigma_1(messages, sigkey, 0, &sigma_1)?;
+        Ok(Self {sigma_1, sigma_2})
+    }
+
     /// Generate signature when first element of signature tuple is generated using given exponent
     /// Does only 1 scalar multiplication
-    pub fn sign_with_sigma_1_generated_from_given_exp(
+    pub fn sign_with_sigma_1_generated_from_given_exp<S: Signer>(
         messages: &[FieldElement],
-        sigkey: &Sigkey,
+        sigkey: &S,
         u: &FieldElement,
         offset: usize,
         g: &SignatureGroup,
@@ -58,23 +266,19 @@ impl Signature {
     }
 
     /// Generate signature when first element of signature tuple is given
-    pub fn sign_with_given_sigma_1(messages: &[FieldElement],
-                                   sigkey: &Sigkey,
+    pub fn sign_with_given_sigma_1<S: Signer>(messages: &[FieldElement],
+                                   sigkey: &S,
                                    offset: usize,
                                    h: &SignatureGroup) -> Result<SignatureGroup, PSError> {
-        if sigkey.y.len() != offset + messages.len() {
+        if sigkey.key_size() != offset + messages.len() {
             return Err(PSError::UnsupportedNoOfMessages {
                 expected: offset + messages.len(),
-                given: sigkey.y.len()
+                given: sigkey.key_size()
             });
         }
         // h^(x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ...) = g^{u * (x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ...)}
-        let mut exp = sigkey.x.clone();
-        for i in 0..messages.len() {
-            exp += &sigkey.y[offset + i] * &messages[i];
-        }
-        let h_exp = h * &exp;
-        Ok(h_exp)
+        let indexed_messages = messages.iter().enumerate().map(|(i, m)| (offset + i, m)).collect::<Vec<_>>();
+        sigkey.sign_exponent(h, &indexed_messages)
     }
 
     /// Verify a signature. Can verify unblinded sig received from a signer and the aggregate sig as well.
@@ -84,31 +288,291 @@ impl Signature {
         vk: &Verkey,
         params: &Params,
     ) -> Result<bool, PSError> {
-        if vk.Y_tilde.len() != messages.len() {
+        match self.verify_strict(messages, vk, params) {
+            Ok(()) => Ok(true),
+            Err(VerificationError::IdentitySignature) | Err(VerificationError::PairingCheckFailed) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Same as `verify` but the `count` messages are taken from an iterator, Y_m is accumulated with a
+    /// multi-exponentiation over chunks of the messages. Fails if the iterator does not give exactly `count` messages.
+    pub fn verify_from_iter<I: IntoIterator<Item = FieldElement>>(
+        &self,
+        messages: I,
+        count: usize,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        const CHUNK_SIZE: usize = 256;
+        if let Some(metadata) = &vk.metadata {
+            metadata.check(Scheme::PS2016, count, Some(params))?;
+        }
+        if count != vk.Y_tilde.len() {
             return Err(PSError::UnsupportedNoOfMessages {
+                expected: vk.Y_tilde.len(),
+                given: count,
+            });
+        }
+        let mut Y_m = vk.X_tilde.clone();
+        let mut bases = VerkeyGroupVec::with_capacity(CHUNK_SIZE);
+        let mut exps = FieldElementVector::with_capacity(CHUNK_SIZE);
+        let mut given = 0;
+        for m in messages {
+            if given == count {
+                return Err(PSError::UnsupportedNoOfMessages {
+                    expected: count,
+                    given: given + 1,
+                });
+            }
+            bases.push(vk.Y_tilde[given].clone());
+            exps.push(m);
+            given += 1;
+            if bases.len() == CHUNK_SIZE {
+                Y_m += bases.multi_scalar_mul_var_time(&exps).unwrap();
+                bases = VerkeyGroupVec::with_capacity(CHUNK_SIZE);
+                exps = FieldElementVector::with_capacity(CHUNK_SIZE);
+            }
+        }
+        if given != count {
+            return Err(PSError::UnsupportedNoOfMessages { expected: count, given });
+        }
+        if given % CHUNK_SIZE != 0 {
+            Y_m += bases.multi_scalar_mul_var_time(&exps).unwrap();
+        }
+        if self.is_identity() {
+            return Ok(false);
+        }
+        let e = ate_2_pairing(&self.sigma_1, &Y_m, &(self.sigma_2.negation()), &params.g_tilde);
+        Ok(e.is_one())
+    }
+
+    /// The message for the byte string `bytes`, its hash with `MESSAGE_HASH_DST`
+    pub fn message_from_bytes(bytes: &[u8]) -> FieldElement {
+        FieldElement::from_msg_hash(&[MESSAGE_HASH_DST, bytes].concat())
+    }
+
+    /// Same as `new` but each message is a byte string converted with `message_from_bytes`
+    pub fn sign_bytes<S: Signer>(messages: &[&[u8]], sigkey: &S, params: &Params) -> Result<Self, PSError> {
+        let messages = messages.iter().map(|m| Self::message_from_bytes(m)).collect::<Vec<_>>();
+        Self::new(&messages, sigkey, params)
+    }
+
+    /// Verify a signature created with `sign_bytes`
+    pub fn verify_bytes(&self, messages: &[&[u8]], vk: &Verkey, params: &Params) -> Result<bool, PSError> {
+        let messages = messages.iter().map(|m| Self::message_from_bytes(m)).collect();
+        self.verify(messages, vk, params)
+    }
+
+    /// Same as `verify` but an invalid signature is an error telling why it is invalid
+    pub fn verify_strict(
+        &self,
+        messages: Vec<FieldElement>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(), VerificationError> {
+        if let Some(metadata) = &vk.metadata {
+            metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+        }
+        if vk.Y_tilde.len() != messages.len() {
+            return Err(VerificationError::UnsupportedNoOfMessages {
                 expected: vk.Y_tilde.len(),
                 given: messages.len()
             });
         }
+        if self.is_identity() {
+            return Err(VerificationError::IdentitySignature);
+        }
+        if !self.pairing_check(messages, vk, params) {
+            return Err(VerificationError::PairingCheckFailed);
+        }
+        Ok(())
+    }
+
+    /// Verify a signature using a verkey on which precomputation has been done.
+    pub fn verify_with_prepared_verkey(
+        &self,
+        messages: Vec<FieldElement>,
+        pvk: &PreparedVerkey,
+    ) -> Result<bool, PSError> {
+        pvk.check_metadata(messages.len())?;
+        Self::check_verkey_and_messages_compat(messages.as_slice(), &pvk.vk)?;
         if self.is_identity() {
             return Ok(false);
         }
+        let Y_m = Self::aggregate_verkey_with_messages(messages, &pvk.vk);
+        // e(sigma_1, Y_m) * e(sigma_2, -g_tilde) == 1
+        let e = ate_2_pairing(&self.sigma_1, &Y_m, &self.sigma_2, &pvk.neg_g_tilde);
+        Ok(e.is_one())
+    }
+
+    /// Verify many signatures, each with its messages, under the same verkey. The pairing equations
+    /// e(sigma_1_i, X_tilde * Y_tilde_1^m_i_1 * ...) == e(sigma_2_i, g_tilde) are combined with random exponents r_i into
+    /// e(sigma_1_1^r_1 * ..., X_tilde) * e(sigma_1_1^(r_1*m_1_1) * ..., Y_tilde_1) * ... == e(sigma_2_1^r_1 * ..., g_tilde),
+    /// a single multi-pairing with as many pairings as messages + 2 irrespective of the no of signatures. Returns true
+    /// only if all signatures are valid, except with negligible probability.
+    pub fn batch_verify(
+        sigs_and_msgs: &[(&Signature, &[FieldElement])],
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        for (sig, messages) in sigs_and_msgs {
+            if let Some(metadata) = &vk.metadata {
+                metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+            }
+            Self::check_verkey_and_messages_compat(messages, vk)?;
+            if sig.is_identity() {
+                return Ok(false);
+            }
+        }
+        if sigs_and_msgs.is_empty() {
+            return Ok(true);
+        }
 
-        Ok(self.pairing_check(messages, vk, params))
+        let r = FieldElementVector::random(sigs_and_msgs.len());
+        let mut sigma_1s = SignatureGroupVec::with_capacity(sigs_and_msgs.len());
+        let mut sigma_2s = SignatureGroupVec::with_capacity(sigs_and_msgs.len());
+        for (sig, _) in sigs_and_msgs {
+            sigma_1s.push(sig.sigma_1.clone());
+            sigma_2s.push(sig.sigma_2.clone());
+        }
+        let sigma_1_X = sigma_1s.multi_scalar_mul_var_time(&r).unwrap();
+        let sigma_1_Y = (0..vk.Y_tilde.len())
+            .map(|j| {
+                let mut exps = FieldElementVector::with_capacity(sigs_and_msgs.len());
+                for (r_i, (_, messages)) in r.iter().zip(sigs_and_msgs.iter()) {
+                    exps.push(r_i * &messages[j]);
+                }
+                sigma_1s.multi_scalar_mul_var_time(&exps).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let neg_sigma_2 = sigma_2s.multi_scalar_mul_var_time(&r).unwrap().negation();
+
+        let mut pairs = vec![(&sigma_1_X, &vk.X_tilde), (&neg_sigma_2, &params.g_tilde)];
+        for (s, Y) in sigma_1_Y.iter().zip(vk.Y_tilde.iter()) {
+            pairs.push((s, Y));
+        }
+        Ok(ate_multi_pairing(pairs).is_one())
     }
 
-    /// Byte representation of the signature
+    /// Verify many signatures, each with its messages and verkey, possibly of different signers. The pairing
+    /// equations are combined with random exponents r_i into
+    /// e(sigma_1_1^r_1, Y_m_1) * e(sigma_1_2^r_2, Y_m_2) * ... == e(sigma_2_1^r_1 * sigma_2_2^r_2 * ..., g_tilde) where
+    /// Y_m_i = X_tilde_i * Y_tilde_i_1^m_i_1 * ..., a single multi-pairing with one pairing per signature + 1. Returns
+    /// true only if all signatures are valid, except with negligible probability. Use `batch_verify` when all
+    /// signatures are under the same verkey.
+    pub fn batch_verify_with_verkeys(
+        sigs_msgs_and_vks: &[(&Signature, &[FieldElement], &Verkey)],
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        for (sig, messages, vk) in sigs_msgs_and_vks {
+            if let Some(metadata) = &vk.metadata {
+                metadata.check(Scheme::PS2016, messages.len(), Some(params))?;
+            }
+            Self::check_verkey_and_messages_compat(messages, vk)?;
+            if sig.is_identity() {
+                return Ok(false);
+            }
+        }
+        if sigs_msgs_and_vks.is_empty() {
+            return Ok(true);
+        }
+
+        let r = FieldElementVector::random(sigs_msgs_and_vks.len());
+        let mut sigma_1s = Vec::with_capacity(sigs_msgs_and_vks.len());
+        let mut Y_ms = Vec::with_capacity(sigs_msgs_and_vks.len());
+        let mut sigma_2s = SignatureGroupVec::with_capacity(sigs_msgs_and_vks.len());
+        for (r_i, (sig, messages, vk)) in r.iter().zip(sigs_msgs_and_vks.iter()) {
+            sigma_1s.push(&sig.sigma_1 * r_i);
+            Y_ms.push(Self::aggregate_verkey_with_messages(messages.to_vec(), vk));
+            sigma_2s.push(sig.sigma_2.clone());
+        }
+        let neg_sigma_2 = sigma_2s.multi_scalar_mul_var_time(&r).unwrap().negation();
+
+        let mut pairs = vec![(&neg_sigma_2, &params.g_tilde)];
+        for (s, Y_m) in sigma_1s.iter().zip(Y_ms.iter()) {
+            pairs.push((s, Y_m));
+        }
+        Ok(ate_multi_pairing(pairs).is_one())
+    }
+
+    /// Byte representation of the signature, the header (see `wire`) followed by sigma_1 and sigma_2
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
+        let mut bytes = Vec::with_capacity(Self::serialized_size());
+        // Writing to a Vec does not fail
+        self.write_bytes(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Same as `to_bytes` but writes to `writer`
+    pub fn write_bytes<W: Write>(&self, writer: &mut W) -> Result<(), PSError> {
+        wire::write_header(Scheme::PS2016, writer)?;
+        self.write_bytes_without_header(writer)
+    }
+
+    /// Same as `to_bytes` but writes to the start of `buf`, which should have at least `serialized_len` bytes.
+    /// Returns the no of bytes written.
+    pub fn write_into(&self, buf: &mut [u8]) -> Result<usize, PSError> {
+        wire::write_into(self.serialized_len(), buf, |w| self.write_bytes(w))
+    }
+
+    /// Size in bytes of the output of `to_bytes`, same as `serialized_size`
+    pub fn serialized_len(&self) -> usize {
+        Self::serialized_size()
+    }
+
+    /// Parse a signature from the output of `to_bytes`. The group elements are validated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        let rest = wire::read_header_for_scheme(bytes, Scheme::PS2016)?;
+        if rest.len() != 2 * SIGNATURE_GROUP_SIZE {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE + 2 * SIGNATURE_GROUP_SIZE,
+                given: bytes.len(),
+            });
+        }
+        Self::from_bytes_without_header(rest)
+    }
+
+    /// Size in bytes of the output of `to_bytes`
+    pub fn serialized_size() -> usize {
+        wire::HEADER_SIZE + 2 * SIGNATURE_GROUP_SIZE
+    }
+
+    /// sigma_1 followed by sigma_2, for encodings of signatures embedded in other structures
+    pub(crate) fn to_bytes_without_header(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 * SIGNATURE_GROUP_SIZE);
         bytes.append(&mut self.sigma_1.to_bytes());
         bytes.append(&mut self.sigma_2.to_bytes());
         bytes
     }
 
+    pub(crate) fn write_bytes_without_header<W: Write>(&self, writer: &mut W) -> Result<(), PSError> {
+        writer.write_all(&self.sigma_1.to_bytes())?;
+        writer.write_all(&self.sigma_2.to_bytes())?;
+        Ok(())
+    }
+
+    pub(crate) fn from_bytes_without_header(bytes: &[u8]) -> Result<Self, PSError> {
+        if bytes.len() != 2 * SIGNATURE_GROUP_SIZE {
+            return Err(PSError::IncorrectByteLength {
+                expected: 2 * SIGNATURE_GROUP_SIZE,
+                given: bytes.len(),
+            });
+        }
+        let sigma_1 = SignatureGroup::from_bytes(&bytes[..SIGNATURE_GROUP_SIZE])?;
+        let sigma_2 = SignatureGroup::from_bytes(&bytes[SIGNATURE_GROUP_SIZE..])?;
+        let sig = Self { sigma_1, sigma_2 };
+        sig.validate()?;
+        Ok(sig)
+    }
+
     pub fn check_verkey_and_messages_compat(
         messages: &[FieldElement],
         verkey: &Verkey,
     ) -> Result<(), PSError> {
+        if let Some(metadata) = &verkey.metadata {
+            metadata.check(Scheme::PS2016, messages.len(), None)?;
+        }
         if messages.len() != verkey.Y_tilde.len() {
             return Err(PSError::UnsupportedNoOfMessages {
                 expected: messages.len(),
@@ -118,19 +582,97 @@ impl Signature {
         Ok(())
     }
 
-    pub fn check_sigkey_and_messages_compat(
+    pub fn check_sigkey_and_messages_compat<S: Signer>(
         messages: &[FieldElement],
-        sigkey: &Sigkey,
+        sigkey: &S,
     ) -> Result<(), PSError> {
-        if sigkey.y.len() != messages.len() {
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2016, messages.len(), None)?;
+        }
+        if sigkey.key_size() != messages.len() {
             return Err(PSError::UnsupportedNoOfMessages {
                 expected: messages.len(),
-                given: sigkey.y.len()
+                given: sigkey.key_size()
             });
         }
         Ok(())
     }
 
+    /// Called by the signer to update this signature on `old_msgs` when only the message at `index` changes from `old`
+    /// to `new`, instead of signing all messages again. sigma_2 is multiplied by sigma_1^(y_index*(new - old)),
+    /// computed as sigma_1^(x + y_index*new) / sigma_1^(x + y_index*old) so any `Signer` works. The signature is
+    /// first verified on `old_msgs` with `vk`, the signer's verkey, and `old` must be the message at `index`, else a
+    /// holder lying about `old` would get a signature on a message of its choice.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_message<S: Signer>(
+        &self,
+        old_msgs: &[FieldElement],
+        index: usize,
+        old: &FieldElement,
+        new: &FieldElement,
+        sigkey: &S,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        Self::check_old_message(old_msgs, index, old)?;
+        if !self.verify(old_msgs.to_vec(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature is not valid on the old messages"),
+            });
+        }
+        Ok(Self {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2: Self::updated_sigma_2(&self.sigma_1, &self.sigma_2, index, old, new, sigkey)?,
+        })
+    }
+
+    /// `old` should be the message at `index` of `old_msgs`
+    pub(crate) fn check_old_message(old_msgs: &[FieldElement], index: usize, old: &FieldElement) -> Result<(), PSError> {
+        match old_msgs.get(index) {
+            Some(m) if m == old => Ok(()),
+            Some(_) => Err(PSError::GeneralError {
+                msg: format!("Message at index {} is not the old message", index),
+            }),
+            None => Err(PSError::GeneralError {
+                msg: format!("Message index {} should be less than {}", index, old_msgs.len()),
+            }),
+        }
+    }
+
+    /// sigma_2 * sigma_1^(y_index*(new - old))
+    pub(crate) fn updated_sigma_2<S: Signer>(
+        sigma_1: &SignatureGroup,
+        sigma_2: &SignatureGroup,
+        index: usize,
+        old: &FieldElement,
+        new: &FieldElement,
+        sigkey: &S,
+    ) -> Result<SignatureGroup, PSError> {
+        let with_new = sigkey.sign_exponent(sigma_1, &[(index, new)])?;
+        let with_old = sigkey.sign_exponent(sigma_1, &[(index, old)])?;
+        Ok(sigma_2 + &(&with_new + &with_old.negation()))
+    }
+
+    /// Fresh signature on the same messages unlinkable to this one, (sigma_1^r, sigma_2^r) for a random r. Can be
+    /// done by anyone holding the signature.
+    pub fn randomize(&self) -> Self {
+        self.randomize_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Same as `randomize` but r is generated using `rng`
+    pub fn randomize_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Self {
+        self.randomize_with(&FieldElement::random_using_rng(rng), &FieldElement::zero())
+    }
+
+    /// (sigma_1^r, (sigma_2 * sigma_1^t)^r). With t as 0 this is `randomize` with the given r, otherwise the result
+    /// is a signature on the messages and t, verifiable with X_tilde * g_tilde^t, as used in the proof of knowledge
+    /// of a signature.
+    pub fn randomize_with(&self, r: &FieldElement, t: &FieldElement) -> Self {
+        let sigma_1 = &self.sigma_1 * r;
+        let sigma_2 = (&self.sigma_2 + (&self.sigma_1 * t)) * r;
+        Self { sigma_1, sigma_2 }
+    }
+
     /// Checks if a signature has identity elements. A valid signature should not have identity elements.
     pub fn is_identity(&self) -> bool {
         self.sigma_1.is_identity() || self.sigma_2.is_identity()
@@ -138,23 +680,27 @@ impl Signature {
 
     /// Do the multi-exp and pairing check during verification.
     pub(crate) fn pairing_check(&self, messages: Vec<FieldElement>, vk: &Verkey, params: &Params) -> bool {
+        let Y_m = Self::aggregate_verkey_with_messages(messages, vk);
+        // e(sigma_1, Y_m) == e(sigma_2, g2) => e(sigma_1, Y_m) * e(-sigma_2, g2) == 1, if precomputation can be used, then
+        // inverse in sigma_2 can be avoided since inverse of g_tilde can be precomputed
+        let e = ate_2_pairing(&self.sigma_1, &Y_m, &(self.sigma_2.negation()), &params.g_tilde);
+        e.is_one()
+    }
+
+    /// Y_m = X_tilde * Y_tilde[1]^m_1 * Y_tilde[2]^m_2 * ...Y_tilde[i]^m_i
+    fn aggregate_verkey_with_messages(messages: Vec<FieldElement>, vk: &Verkey) -> VerkeyGroup {
         let mut Y_m_bases = VerkeyGroupVec::with_capacity(messages.len());
         let mut Y_m_exps = FieldElementVector::with_capacity(messages.len());
         for (i, msg) in messages.into_iter().enumerate() {
             Y_m_bases.push(vk.Y_tilde[i].clone());
             Y_m_exps.push(msg);
         }
-        // Y_m = X_tilde * Y_tilde[1]^m_1 * Y_tilde[2]^m_2 * ...Y_tilde[i]^m_i
-        let Y_m = &vk.X_tilde + &(Y_m_bases.multi_scalar_mul_var_time(&Y_m_exps).unwrap());
-        // e(sigma_1, Y_m) == e(sigma_2, g2) => e(sigma_1, Y_m) * e(-sigma_2, g2) == 1, if precomputation can be used, then
-        // inverse in sigma_2 can be avoided since inverse of g_tilde can be precomputed
-        let e = ate_2_pairing(&self.sigma_1, &Y_m, &(self.sigma_2.negation()), &params.g_tilde);
-        e.is_one()
+        &vk.X_tilde + &(Y_m_bases.multi_scalar_mul_var_time(&Y_m_exps).unwrap())
     }
 
     /// Generate first element of the signature by hashing the messages. Since all messages are of
     /// same size, the is no need of a delimiter between the byte representation of the messages.
-    fn generate_sigma_1_from_messages(messages: &[FieldElement]) -> SignatureGroup {
+    pub(crate) fn generate_sigma_1_from_messages(messages: &[FieldElement]) -> SignatureGroup {
         let mut msg_bytes = vec![];
         for i in messages {
             msg_bytes.append(&mut i.to_bytes());
@@ -163,10 +709,26 @@ impl Signature {
     }
 }
 
+/// Multibase (base58btc) encoding of `to_bytes`
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", multibase::encode(Base::Base58Btc, &self.to_bytes()))
+    }
+}
+
+/// Parse multibase (base58btc or base64url) encoding of `to_bytes`
+impl FromStr for Signature {
+    type Err = PSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&multibase::decode(s)?.1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::keys::keygen;
+    use crate::keys::{keygen, KeyMetadata};
     // For benchmarking
     use std::time::{Duration, Instant};
 
@@ -193,4 +755,382 @@ mod tests {
             assert!(sig.verify(msgs, &vk, &params).unwrap());
         }
     }
+
+    #[test]
+    fn test_signing_with_prepared_sigkey() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(5, &params);
+        let psk = PreparedSigkey::new(sk.clone(), &params);
+        let msgs = (0..5).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new_with_prepared_sigkey(&msgs, &psk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        // Works as a `Signer` as well
+        let sig = Signature::new(&msgs, &psk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        assert!(Signature::new_with_prepared_sigkey(&msgs[..4], &psk, &params).is_err());
+        let other_params = Params::new("other".as_bytes());
+        assert!(Signature::new_with_prepared_sigkey(&msgs, &psk, &other_params).is_err());
+
+        let n = 50;
+        let start = Instant::now();
+        for _ in 0..n {
+            Signature::new(&msgs, &sk, &params).unwrap();
+        }
+        println!("Time to create {} signatures {:?}", n, start.elapsed());
+        let start = Instant::now();
+        for _ in 0..n {
+            Signature::new_with_prepared_sigkey(&msgs, &psk, &params).unwrap();
+        }
+        println!("Time to create {} signatures with prepared sigkey {:?}", n, start.elapsed());
+    }
+
+    #[test]
+    fn test_signing_from_iter() {
+        let params = Params::new("test".as_bytes());
+        let count = 600;
+        let (sk, vk) = keygen(count, &params);
+        // Messages derived from their index so they are never all in memory
+        let msg = |i: usize| FieldElement::from_msg_hash(&(i as u64).to_be_bytes());
+        let sig = Signature::new_from_iter((0..count).map(msg), count, &sk, &params).unwrap();
+        assert!(sig.verify_from_iter((0..count).map(msg), count, &vk, &params).unwrap());
+        let msgs = (0..count).map(msg).collect::<Vec<_>>();
+        assert!(sig.verify(msgs, &vk, &params).unwrap());
+        let sig_1 = Signature::new(&(0..count).map(msg).collect::<Vec<_>>(), &sk, &params).unwrap();
+        assert!(sig_1.verify_from_iter((0..count).map(msg), count, &vk, &params).unwrap());
+
+        assert!(!sig.verify_from_iter((1..=count).map(msg), count, &vk, &params).unwrap());
+        assert!(Signature::new_from_iter((0..count - 1).map(msg), count, &sk, &params).is_err());
+        assert!(Signature::new_from_iter((0..count + 1).map(msg), count, &sk, &params).is_err());
+        assert!(sig.verify_from_iter((0..count - 1).map(msg), count, &vk, &params).is_err());
+        assert!(sig.verify_from_iter((0..count).map(msg), count - 1, &vk, &params).is_err());
+
+        // Any signer can be used
+        let psk = PreparedSigkey::new(sk.clone(), &params);
+        let sig = Signature::new_from_iter((0..count).map(msg), count, &psk, &params).unwrap();
+        assert!(sig.verify_from_iter((0..count).map(msg), count, &vk, &params).unwrap());
+        let signer: &dyn Signer = &sk;
+        let sig = Signature::new_from_iter((0..count).map(msg), count, signer, &params).unwrap();
+        assert!(sig.verify_from_iter((0..count).map(msg), count, &vk, &params).unwrap());
+
+        // Counts that are a multiple of the chunk size and smaller than it
+        for count in &[FROM_ITER_CHUNK_SIZE, 2 * FROM_ITER_CHUNK_SIZE, 1] {
+            let (sk, vk) = keygen(*count, &params);
+            let sig = Signature::new_from_iter((0..*count).map(msg), *count, &sk, &params).unwrap();
+            assert!(sig.verify_from_iter((0..*count).map(msg), *count, &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sign_bytes() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params);
+        let msgs: Vec<&[u8]> = vec![b"Alice", b"1990-01-01", b""];
+        let sig = Signature::sign_bytes(&msgs, &sk, &params).unwrap();
+        assert!(sig.verify_bytes(&msgs, &vk, &params).unwrap());
+        let field_msgs = msgs.iter().map(|m| Signature::message_from_bytes(m)).collect::<Vec<_>>();
+        assert!(sig.verify(field_msgs, &vk, &params).unwrap());
+
+        let other: Vec<&[u8]> = vec![b"Alice", b"1990-01-02", b""];
+        assert!(!sig.verify_bytes(&other, &vk, &params).unwrap());
+        assert!(sig.verify_bytes(&msgs[..2], &vk, &params).is_err());
+        assert_ne!(Signature::message_from_bytes(b"a"), FieldElement::from_msg_hash(b"a"));
+    }
+
+    #[test]
+    fn test_deterministic_keyed_signature() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let (sk_1, _) = keygen(4, &params);
+        let msgs = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new_deterministic_keyed(&msgs, &sk, b"context").unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Same key, messages and context give the same signature, anything else changes sigma_1
+        let sig_again = Signature::new_deterministic_keyed(&msgs, &sk, b"context").unwrap();
+        assert_eq!(sig.sigma_1, sig_again.sigma_1);
+        assert_eq!(sig.sigma_2, sig_again.sigma_2);
+        let sig_other_ctx = Signature::new_deterministic_keyed(&msgs, &sk, b"other context").unwrap();
+        assert!(sig_other_ctx.verify(msgs.clone(), &vk, &params).unwrap());
+        assert_ne!(sig.sigma_1, sig_other_ctx.sigma_1);
+        assert_ne!(sig.sigma_1, Signature::new_deterministic_keyed(&msgs, &sk_1, b"context").unwrap().sigma_1);
+        // Not the publicly computable sigma_1 of `new_deterministic`
+        assert_ne!(sig.sigma_1, Signature::new_deterministic(&msgs, &sk).unwrap().sigma_1);
+        assert!(Signature::new_deterministic_keyed(&msgs[..3], &sk, b"context").is_err());
+    }
+
+    #[test]
+    fn test_verify_strict() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        assert!(sig.verify_strict(msgs.clone(), &vk, &params).is_ok());
+
+        match sig.verify_strict(msgs[..2].to_vec(), &vk, &params) {
+            Err(VerificationError::UnsupportedNoOfMessages { expected: 3, given: 2 }) => (),
+            _ => panic!("Expected UnsupportedNoOfMessages"),
+        }
+        let mut wrong_msgs = msgs.clone();
+        wrong_msgs[1] = FieldElement::random();
+        match sig.verify_strict(wrong_msgs, &vk, &params) {
+            Err(VerificationError::PairingCheckFailed) => (),
+            _ => panic!("Expected PairingCheckFailed"),
+        }
+        let identity = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: sig.sigma_2.clone(),
+        };
+        match identity.verify_strict(msgs.clone(), &vk, &params) {
+            Err(VerificationError::IdentitySignature) => (),
+            _ => panic!("Expected IdentitySignature"),
+        }
+        assert!(!identity.verify(msgs, &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let msgs = (0..20)
+            .map(|_| (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>())
+            .collect::<Vec<_>>();
+        let sigs = msgs
+            .iter()
+            .map(|m| Signature::new(m.as_slice(), &sk, &params).unwrap())
+            .collect::<Vec<_>>();
+        let mut sigs_and_msgs = sigs.iter().zip(msgs.iter().map(|m| m.as_slice())).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        assert!(Signature::batch_verify(&sigs_and_msgs, &vk, &params).unwrap());
+        println!("Time to verify {} signatures in a batch {:?}", sigs.len(), start.elapsed());
+        let start = Instant::now();
+        for (sig, m) in &sigs_and_msgs {
+            assert!(sig.verify(m.to_vec(), &vk, &params).unwrap());
+        }
+        println!("Time to verify {} signatures individually {:?}", sigs.len(), start.elapsed());
+        assert!(Signature::batch_verify(&[], &vk, &params).unwrap());
+
+        // A single invalid signature, swapped messages or wrong verkey fail the batch
+        let mut wrong = sigs[5].clone();
+        wrong.sigma_2 = &wrong.sigma_2 + &params.g;
+        let mut with_wrong = sigs_and_msgs.clone();
+        with_wrong[5].0 = &wrong;
+        assert!(!Signature::batch_verify(&with_wrong, &vk, &params).unwrap());
+        sigs_and_msgs.swap(0, 1);
+        sigs_and_msgs[0].0 = &sigs[0];
+        assert!(!Signature::batch_verify(&sigs_and_msgs, &vk, &params).unwrap());
+        sigs_and_msgs[0].0 = &sigs[1];
+        let (_, vk_1) = keygen(4, &params);
+        assert!(!Signature::batch_verify(&sigs_and_msgs, &vk_1, &params).unwrap());
+        let short = vec![(&sigs[0], &msgs[0][..3])];
+        assert!(Signature::batch_verify(&short, &vk, &params).is_err());
+    }
+
+    #[test]
+    fn test_batch_verify_with_verkeys() {
+        let params = Params::new("test".as_bytes());
+        let keys = (1..=5).map(|i| keygen(i, &params)).collect::<Vec<_>>();
+        let (_, other_vk) = keygen(1, &params);
+        let msgs = (0..15)
+            .map(|i| (0..=(i % 5)).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>())
+            .collect::<Vec<_>>();
+        let sigs = msgs
+            .iter()
+            .map(|m| Signature::new(m.as_slice(), &keys[m.len() - 1].0, &params).unwrap())
+            .collect::<Vec<_>>();
+        let mut triples = sigs
+            .iter()
+            .zip(msgs.iter())
+            .map(|(s, m)| (s, m.as_slice(), &keys[m.len() - 1].1))
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        assert!(Signature::batch_verify_with_verkeys(&triples, &params).unwrap());
+        println!("Time to verify {} signatures of different signers in a batch {:?}", sigs.len(), start.elapsed());
+        assert!(Signature::batch_verify_with_verkeys(&[], &params).unwrap());
+
+        // A single invalid signature or a signature attributed to the wrong signer fails the batch
+        let mut wrong = sigs[7].clone();
+        wrong.sigma_2 = &wrong.sigma_2 + &params.g;
+        let mut with_wrong = triples.clone();
+        with_wrong[7].0 = &wrong;
+        assert!(!Signature::batch_verify_with_verkeys(&with_wrong, &params).unwrap());
+        triples[0].2 = &other_vk;
+        assert!(!Signature::batch_verify_with_verkeys(&triples, &params).unwrap());
+        triples[0].2 = &keys[1].1;
+        assert!(Signature::batch_verify_with_verkeys(&triples, &params).is_err());
+    }
+
+    #[test]
+    fn test_batch_signing() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let batches = (0..20)
+            .map(|_| (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>())
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let sigs = Signature::batch_new(&batches, &sk, &params).unwrap();
+        println!("Time to sign {} message vectors in a batch {:?}", batches.len(), start.elapsed());
+        assert_eq!(sigs.len(), batches.len());
+        for (sig, msgs) in sigs.iter().zip(batches.iter()) {
+            assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        }
+        assert_ne!(sigs[0].sigma_1, sigs[1].sigma_1);
+
+        // sigma_1 from the table is g^u with u derived from the seed
+        {
+            use rand::rngs::StdRng;
+            use rand::{RngCore, SeedableRng};
+            let sigs = Signature::batch_new_with_rng(&batches, &sk, &params, &mut StdRng::seed_from_u64(7)).unwrap();
+            let mut seed = [0u8; 32];
+            StdRng::seed_from_u64(7).fill_bytes(&mut seed);
+            for (i, sig) in sigs.iter().enumerate() {
+                let mut bytes = BATCH_SIGNING_DST.to_vec();
+                bytes.extend_from_slice(&seed);
+                bytes.extend_from_slice(&(i as u64).to_be_bytes());
+                assert_eq!(sig.sigma_1, &params.g * &FieldElement::from_msg_hash(&bytes));
+            }
+        }
+
+        let mut wrong = batches.clone();
+        wrong[5].pop();
+        assert!(Signature::batch_new(&wrong, &sk, &params).is_err());
+    }
+
+    #[test]
+    fn test_signature_randomization() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let sig_1 = sig.randomize();
+        assert_ne!(sig_1.sigma_1, sig.sigma_1);
+        assert!(sig_1.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // With t, it is a signature on the messages and t
+        let t = FieldElement::random();
+        let sig_2 = sig.randomize_with(&FieldElement::random(), &t);
+        assert!(!sig_2.verify(msgs.clone(), &vk, &params).unwrap());
+        let mut vk_t = vk.clone();
+        vk_t.X_tilde = &vk.X_tilde + &(&params.g_tilde * &t);
+        assert!(sig_2.verify(msgs, &vk_t, &params).unwrap());
+    }
+
+    #[test]
+    fn test_signature_update_message() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let new = FieldElement::random();
+        let sig_1 = sig.update_message(&msgs, 2, &msgs[2], &new, &sk, &vk, &params).unwrap();
+        assert_eq!(sig_1.sigma_1, sig.sigma_1);
+        assert!(!sig_1.verify(msgs.clone(), &vk, &params).unwrap());
+        let mut new_msgs = msgs.clone();
+        new_msgs[2] = new.clone();
+        assert!(sig_1.verify(new_msgs.clone(), &vk, &params).unwrap());
+
+        // A wrong old message, a signature not on the old messages or a wrong index is rejected
+        let wrong_old = FieldElement::random();
+        assert!(sig.update_message(&msgs, 2, &wrong_old, &new, &sk, &vk, &params).is_err());
+        let mut lied_msgs = msgs.clone();
+        lied_msgs[2] = wrong_old.clone();
+        assert!(sig.update_message(&lied_msgs, 2, &wrong_old, &new, &sk, &vk, &params).is_err());
+        assert!(sig_1.update_message(&msgs, 2, &msgs[2], &new, &sk, &vk, &params).is_err());
+        assert!(sig.update_message(&msgs, count_msgs, &new, &new, &sk, &vk, &params).is_err());
+        let (_, other_vk) = keygen(count_msgs, &params);
+        assert!(sig.update_message(&msgs, 2, &msgs[2], &new, &sk, &other_vk, &params).is_err());
+    }
+
+    #[test]
+    fn test_signature_verify_with_prepared_verkey() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let pvk = PreparedVerkey::new(vk, &params);
+        for _ in 0..10 {
+            let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+            let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+            assert!(sig.verify_with_prepared_verkey(msgs.clone(), &pvk).unwrap());
+            let mut wrong_msgs = msgs;
+            wrong_msgs[0] = FieldElement::random();
+            assert!(!sig.verify_with_prepared_verkey(wrong_msgs, &pvk).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_signature_verify_with_prepared_verkey_checks_metadata() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        // Metadata of another scheme
+        let vk_2018 = vk.clone().with_metadata(KeyMetadata::new(Scheme::PS2018, count_msgs, &params));
+        let pvk = PreparedVerkey::new(vk_2018, &params);
+        match sig.verify_with_prepared_verkey(msgs.clone(), &pvk) {
+            Err(PSError::IncompatibleKey { .. }) => (),
+            _ => panic!("Expected IncompatibleKey"),
+        }
+
+        // Prepared with other params than the key's
+        let pvk = PreparedVerkey::new(vk.clone(), &Params::new("other".as_bytes()));
+        match sig.verify_with_prepared_verkey(msgs.clone(), &pvk) {
+            Err(PSError::IncompatibleKey { .. }) => (),
+            _ => panic!("Expected IncompatibleKey"),
+        }
+
+        // Metadata for another no of messages
+        let vk_other_count = vk.clone().with_metadata(KeyMetadata::new(Scheme::PS2016, count_msgs + 1, &params));
+        let pvk = PreparedVerkey::new(vk_other_count, &params);
+        assert!(sig.verify_with_prepared_verkey(msgs.clone(), &pvk).is_err());
+
+        let pvk = PreparedVerkey::new(vk, &params);
+        assert!(sig.verify_with_prepared_verkey(msgs, &pvk).unwrap());
+    }
+
+    #[test]
+    fn test_signature_to_and_from_bytes() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let bytes = sig.to_bytes();
+        assert_eq!(bytes.len(), Signature::serialized_size());
+        let mut buf = vec![0u8; sig.serialized_len() + 10];
+        assert_eq!(sig.write_into(&mut buf).unwrap(), bytes.len());
+        assert_eq!(&buf[..bytes.len()], bytes.as_slice());
+        assert!(sig.write_into(&mut buf[..bytes.len() - 1]).is_err());
+        let sig_1 = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(sig.sigma_1, sig_1.sigma_1);
+        assert_eq!(sig.sigma_2, sig_1.sigma_2);
+        assert!(sig_1.verify(msgs, &vk, &params).unwrap());
+
+        let sig_2 = Signature::from_str(&sig.to_string()).unwrap();
+        assert_eq!(sig.sigma_2, sig_2.sigma_2);
+
+        match Signature::from_bytes(&bytes[..bytes.len() - 1]) {
+            Err(PSError::IncorrectByteLength { expected, given }) => {
+                assert_eq!(expected, bytes.len());
+                assert_eq!(given, bytes.len() - 1);
+            }
+            _ => panic!("Expected IncorrectByteLength"),
+        }
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = wire::WIRE_FORMAT_VERSION + 1;
+        match Signature::from_bytes(&wrong_version) {
+            Err(PSError::UnsupportedVersion { .. }) => (),
+            _ => panic!("Expected UnsupportedVersion"),
+        }
+    }
 }
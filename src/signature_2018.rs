@@ -1,14 +1,21 @@
 // Scheme defined in 2018 paper, CT-RSA 2018 (eprint 2017/1197), section 4.2.
 
-use crate::{SignatureGroup, VerkeyGroupVec, ate_2_pairing};
+use crate::{SignatureGroup, VerkeyGroupVec, ate_2_pairing, FIELD_ELEMENT_SIZE, SIGNATURE_GROUP_SIZE};
 use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
-use crate::keys::{Sigkey, Params, Verkey};
-use crate::errors::PSError;
+use crate::keys::{Signer, Params, Scheme, Sigkey, Verkey};
+use crate::errors::{PSError, VerificationError};
+use crate::multibase::{self, Base};
+use crate::wire;
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 use crate::signature::Signature as Sig16;
 use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Signature {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
     pub m_prime: FieldElement,
     pub sig: Sig16
 }
@@ -17,9 +24,18 @@ impl Signature {
     /// Create a new signature. The signature generation involves generating random values for `m'`
     /// and `sigma_1` so different calls to this method with same messages and signing key will give
     /// different value
-    pub fn new(messages: &[FieldElement], sigkey: &Sigkey) -> Result<Self, PSError> {
-        let m_prime = FieldElement::random();
-        let sigma_1 = SignatureGroup::random();
+    pub fn new<S: Signer>(messages: &[FieldElement], sigkey: &S) -> Result<Self, PSError> {
+        Self::new_with_rng(messages, sigkey, &mut rand::thread_rng())
+    }
+
+    /// Same as `new` but the random values are generated using `rng`
+    pub fn new_with_rng<S: Signer, R: RngCore + CryptoRng>(
+        messages: &[FieldElement],
+        sigkey: &S,
+        rng: &mut R,
+    ) -> Result<Self, PSError> {
+        let m_prime = FieldElement::random_using_rng(rng);
+        let sigma_1 = SignatureGroup::random_using_rng(rng);
         let sigma_2 = Self::sign_with_given_sigma_1(messages, &m_prime, sigkey, 0, &sigma_1)?;
         Ok(Self { m_prime, sig: Sig16 {sigma_1, sigma_2} })
     }
@@ -27,9 +43,18 @@ impl Signature {
     /// Create a new signature. The signature generation involves generating `m'` by hashing the messages
     /// but generating a random value for `sigma_1` so different calls to this method with same messages
     /// and signing key will give different value
-    pub fn new_with_deterministic_m(messages: &[FieldElement], sigkey: &Sigkey) -> Result<Self, PSError> {
+    pub fn new_with_deterministic_m<S: Signer>(messages: &[FieldElement], sigkey: &S) -> Result<Self, PSError> {
+        Self::new_with_deterministic_m_and_rng(messages, sigkey, &mut rand::thread_rng())
+    }
+
+    /// Same as `new_with_deterministic_m` but `sigma_1` is generated using `rng`
+    pub fn new_with_deterministic_m_and_rng<S: Signer, R: RngCore + CryptoRng>(
+        messages: &[FieldElement],
+        sigkey: &S,
+        rng: &mut R,
+    ) -> Result<Self, PSError> {
         let m_prime = Self::generate_m_prime_from_messages(messages);
-        let sigma_1 = SignatureGroup::random();
+        let sigma_1 = SignatureGroup::random_using_rng(rng);
         let sigma_2 = Self::sign_with_given_sigma_1(messages, &m_prime, sigkey, 0, &sigma_1)?;
         Ok(Self { m_prime, sig: Sig16 {sigma_1, sigma_2} })
     }
@@ -37,62 +62,173 @@ impl Signature {
     /// Create a new signature. The signature generation doesn't involve generating any random value
     /// but the messages are hashed to get a pseudorandom values for `m'` and `sigma_1`. Hence different
     /// calls to this method with same messages and signing key will give same value
-    pub fn new_deterministic(messages: &[FieldElement], sigkey: &Sigkey) -> Result<Self, PSError> {
+    pub fn new_deterministic<S: Signer>(messages: &[FieldElement], sigkey: &S) -> Result<Self, PSError> {
         let (m_prime, sigma_1) = Self::generate_m_prime_and_sigma_1_from_messages(messages);
         let sigma_2 = Self::sign_with_given_sigma_1(messages, &m_prime, sigkey, 0, &sigma_1)?;
         Ok(Self { m_prime, sig: Sig16 {sigma_1, sigma_2} })
     }
 
+    /// Same as `new_deterministic` but `m'` and `sigma_1` are derived by hashing the signing key along with the
+    /// messages and `context`, see `Sig16::new_deterministic_keyed`
+    pub fn new_deterministic_keyed(messages: &[FieldElement], sigkey: &Sigkey, context: &[u8]) -> Result<Self, PSError> {
+        let mut bytes = sigkey.deterministic_signing_input(Scheme::PS2018, messages, context);
+        let (m_prime, sigma_1) = hash_to_elements!(&bytes; FieldElement, SignatureGroup);
+        #[cfg(feature = "zeroize")]
+        zeroize::Zeroize::zeroize(&mut bytes);
+        let sigma_2 = Self::sign_with_given_sigma_1(messages, &m_prime, sigkey, 0, &sigma_1)?;
+        Ok(Self { m_prime, sig: Sig16 {sigma_1, sigma_2} })
+    }
+
     /// Verify a signature. Most of the logic is same as from the 2016 scheme
     pub fn verify(
         &self,
-        mut messages: Vec<FieldElement>,
+        messages: Vec<FieldElement>,
         vk: &Verkey,
         params: &Params,
     ) -> Result<bool, PSError> {
+        match self.verify_strict(messages, vk, params) {
+            Ok(()) => Ok(true),
+            Err(VerificationError::IdentitySignature) | Err(VerificationError::PairingCheckFailed) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Same as `verify` but an invalid signature is an error telling why it is invalid
+    pub fn verify_strict(
+        &self,
+        mut messages: Vec<FieldElement>,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<(), VerificationError> {
+        if let Some(metadata) = &vk.metadata {
+            metadata.check(Scheme::PS2018, messages.len(), Some(params))?;
+        }
         if vk.Y_tilde.len() != (messages.len() + 1) {
-            return Err(PSError::UnsupportedNoOfMessages {
+            return Err(VerificationError::UnsupportedNoOfMessages {
                 expected: vk.Y_tilde.len(),
                 given: messages.len() + 1
             });
         }
         if self.sig.is_identity() {
-            return Ok(false);
+            return Err(VerificationError::IdentitySignature);
         }
 
         messages.push(self.m_prime.clone());
 
-        Ok(Sig16::pairing_check(&self.sig, messages, vk, params))
+        if !Sig16::pairing_check(&self.sig, messages, vk, params) {
+            return Err(VerificationError::PairingCheckFailed);
+        }
+        Ok(())
     }
 
-    /// Byte representation of the signature
+    /// Same as `Sig16::update_message`, `old_msgs` are without m' and `index` can't be that of m'
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_message<S: Signer>(
+        &self,
+        old_msgs: &[FieldElement],
+        index: usize,
+        old: &FieldElement,
+        new: &FieldElement,
+        sigkey: &S,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        // The last element of `y` is for m'
+        if index + 1 >= sigkey.key_size() {
+            return Err(PSError::GeneralError {
+                msg: format!("Message index {} should be less than {}", index, sigkey.key_size().saturating_sub(1)),
+            });
+        }
+        Sig16::check_old_message(old_msgs, index, old)?;
+        if !self.verify(old_msgs.to_vec(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature is not valid on the old messages"),
+            });
+        }
+        let sigma_2 = Sig16::updated_sigma_2(&self.sig.sigma_1, &self.sig.sigma_2, index, old, new, sigkey)?;
+        Ok(Self { m_prime: self.m_prime.clone(), sig: Sig16 { sigma_1: self.sig.sigma_1.clone(), sigma_2 } })
+    }
+
+    /// Fresh signature on the same messages unlinkable to this one except by m', see `Sig16::randomize`
+    pub fn randomize(&self) -> Self {
+        self.randomize_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Same as `randomize` but the randomness is generated using `rng`
+    pub fn randomize_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Self {
+        Self { m_prime: self.m_prime.clone(), sig: self.sig.randomize_with_rng(rng) }
+    }
+
+    /// Same as `Sig16::randomize_with`, m' is unchanged
+    pub fn randomize_with(&self, r: &FieldElement, t: &FieldElement) -> Self {
+        Self { m_prime: self.m_prime.clone(), sig: self.sig.randomize_with(r, t) }
+    }
+
+    /// Byte representation of the signature, the header (see `wire`) followed by m', sigma_1 and sigma_2
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = vec![];
-        bytes.append(&mut self.m_prime.to_bytes());
-        bytes.append(&mut self.sig.to_bytes());
+        let mut bytes = Vec::with_capacity(Self::serialized_size());
+        // Writing to a Vec does not fail
+        self.write_bytes(&mut bytes).unwrap();
         bytes
     }
 
+    /// Same as `to_bytes` but writes to `writer`
+    pub fn write_bytes<W: Write>(&self, writer: &mut W) -> Result<(), PSError> {
+        wire::write_header(Scheme::PS2018, writer)?;
+        writer.write_all(&self.m_prime.to_bytes())?;
+        self.sig.write_bytes_without_header(writer)
+    }
+
+    /// Same as `to_bytes` but writes to the start of `buf`, which should have at least `serialized_len` bytes.
+    /// Returns the no of bytes written.
+    pub fn write_into(&self, buf: &mut [u8]) -> Result<usize, PSError> {
+        wire::write_into(self.serialized_len(), buf, |w| self.write_bytes(w))
+    }
+
+    /// Size in bytes of the output of `to_bytes`, same as `serialized_size`
+    pub fn serialized_len(&self) -> usize {
+        Self::serialized_size()
+    }
+
+    /// Parse a signature from the output of `to_bytes`. The group elements are validated.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PSError> {
+        let rest = wire::read_header_for_scheme(bytes, Scheme::PS2018)?;
+        let expected = FIELD_ELEMENT_SIZE + 2 * SIGNATURE_GROUP_SIZE;
+        if rest.len() != expected {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE + expected,
+                given: bytes.len(),
+            });
+        }
+        let m_prime = FieldElement::from_bytes(&rest[..FIELD_ELEMENT_SIZE])?;
+        let sig = Sig16::from_bytes_without_header(&rest[FIELD_ELEMENT_SIZE..])?;
+        Ok(Self { m_prime, sig })
+    }
+
+    /// Size in bytes of the output of `to_bytes`
+    pub fn serialized_size() -> usize {
+        wire::HEADER_SIZE + FIELD_ELEMENT_SIZE + 2 * SIGNATURE_GROUP_SIZE
+    }
+
     /// Generate signature when first element of signature tuple is given
-    fn sign_with_given_sigma_1(messages: &[FieldElement],
+    pub(crate) fn sign_with_given_sigma_1<S: Signer>(messages: &[FieldElement],
                                    m_prime: &FieldElement,
-                                   sigkey: &Sigkey,
+                                   sigkey: &S,
                                    offset: usize,
                                    h: &SignatureGroup) -> Result<SignatureGroup, PSError> {
-        if sigkey.y.len() != (offset + messages.len() + 1) {
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2018, offset + messages.len(), None)?;
+        }
+        if sigkey.key_size() != (offset + messages.len() + 1) {
             return Err(PSError::UnsupportedNoOfMessages {
                 expected: offset + messages.len() + 1,
-                given: sigkey.y.len()
+                given: sigkey.key_size()
             });
         }
         // h^(x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ... + y_last*m') = g^{u * (x + y_j*m_j + y_{j+1}*m_{j+1} + y_{j+2}*m_{j+2} + ... + y_last*m')}
-        let mut exp = sigkey.x.clone();
-        for i in 0..messages.len() {
-            exp += &sigkey.y[offset + i] * &messages[i];
-        }
-        exp += &sigkey.y[offset + messages.len()] * m_prime;
-        let h_exp = h * &exp;
-        Ok(h_exp)
+        let mut indexed_messages = messages.iter().enumerate().map(|(i, m)| (offset + i, m)).collect::<Vec<_>>();
+        indexed_messages.push((offset + messages.len(), m_prime));
+        sigkey.sign_exponent(h, &indexed_messages)
     }
 
     /// Generate m' by hashing the messages. Since all messages are of
@@ -107,24 +243,22 @@ impl Signature {
 
     /// Generate m' and sigma_1, by hashing the messages. Since all messages are of
     /// same size, the is no need of a delimiter between the byte representation of the messages.
-    fn generate_m_prime_and_sigma_1_from_messages(messages: &[FieldElement]) -> (FieldElement, SignatureGroup) {
+    pub(crate) fn generate_m_prime_and_sigma_1_from_messages(messages: &[FieldElement]) -> (FieldElement, SignatureGroup) {
         let mut msg_bytes = vec![];
         for i in messages {
             msg_bytes.append(&mut i.to_bytes());
         }
-        // TODO: Hashing twice is inefficient. Expose API (a macro probably) in the wrapper to return any
-        // number of group or field elements. The macro would take types like G1, G2, etc as args and count
-        // them to decide the number of bytes the XOF should return and then call iterate over them and call
-        // the type's from_msg_hash on the appropriate byte slice
-        let m_prime = FieldElement::from_msg_hash(&msg_bytes);
-        let sigma_1 = SignatureGroup::from_msg_hash(&msg_bytes);
-        (m_prime, sigma_1)
+        // The messages are hashed only once and both m' and sigma_1 are created from the XOF output
+        hash_to_elements!(&msg_bytes; FieldElement, SignatureGroup)
     }
 
     pub fn check_verkey_and_messages_compat(
         messages: &[FieldElement],
         verkey: &Verkey,
     ) -> Result<(), PSError> {
+        if let Some(metadata) = &verkey.metadata {
+            metadata.check(Scheme::PS2018, messages.len(), None)?;
+        }
         // `Y_tilde` would have a value corresponding to `m'` as well
         if (messages.len() + 1) != verkey.Y_tilde.len() {
             return Err(PSError::UnsupportedNoOfMessages {
@@ -135,21 +269,40 @@ impl Signature {
         Ok(())
     }
 
-    pub fn check_sigkey_and_messages_compat(
+    pub fn check_sigkey_and_messages_compat<S: Signer>(
         messages: &[FieldElement],
-        sigkey: &Sigkey,
+        sigkey: &S,
     ) -> Result<(), PSError> {
+        if let Some(metadata) = sigkey.metadata() {
+            metadata.check(Scheme::PS2018, messages.len(), None)?;
+        }
         // `y` would have a value corresponding to `m'` as well
-        if sigkey.y.len() != (messages.len() + 1) {
+        if sigkey.key_size() != (messages.len() + 1) {
             return Err(PSError::UnsupportedNoOfMessages {
                 expected: messages.len() + 1,
-                given: sigkey.y.len(),
+                given: sigkey.key_size(),
             });
         }
         Ok(())
     }
 }
 
+/// Multibase (base58btc) encoding of `to_bytes`
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", multibase::encode(Base::Base58Btc, &self.to_bytes()))
+    }
+}
+
+/// Parse multibase (base58btc or base64url) encoding of `to_bytes`
+impl FromStr for Signature {
+    type Err = PSError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&multibase::decode(s)?.1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +343,94 @@ mod tests {
             assert!(sig.verify(msgs, &vk, &params).unwrap());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_deterministic_keyed_signature() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new_deterministic_keyed(&msgs, &sk, b"context").unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        let sig_again = Signature::new_deterministic_keyed(&msgs, &sk, b"context").unwrap();
+        assert_eq!(sig.m_prime, sig_again.m_prime);
+        assert_eq!(sig.sig.sigma_1, sig_again.sig.sigma_1);
+        let sig_other_ctx = Signature::new_deterministic_keyed(&msgs, &sk, b"other context").unwrap();
+        assert_ne!(sig.m_prime, sig_other_ctx.m_prime);
+        assert_ne!(sig.m_prime, Signature::new_deterministic(&msgs, &sk).unwrap().m_prime);
+    }
+
+    #[test]
+    fn test_verify_strict() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+        assert!(sig.verify_strict(msgs.clone(), &vk, &params).is_ok());
+        match sig.verify_strict(msgs[..2].to_vec(), &vk, &params) {
+            Err(VerificationError::UnsupportedNoOfMessages { .. }) => (),
+            _ => panic!("Expected UnsupportedNoOfMessages"),
+        }
+        let mut wrong = sig.clone();
+        wrong.m_prime = FieldElement::random();
+        match wrong.verify_strict(msgs, &vk, &params) {
+            Err(VerificationError::PairingCheckFailed) => (),
+            _ => panic!("Expected PairingCheckFailed"),
+        }
+    }
+
+    #[test]
+    fn test_signature_randomization() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+        let sig_1 = sig.randomize();
+        assert_ne!(sig_1.sig.sigma_1, sig.sig.sigma_1);
+        assert_eq!(sig_1.m_prime, sig.m_prime);
+        assert!(sig_1.verify(msgs.clone(), &vk, &params).unwrap());
+        let sig_2 = sig.randomize_with(&FieldElement::random(), &FieldElement::zero());
+        assert!(sig_2.verify(msgs, &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_signature_update_message() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(3, &params);
+        let mut msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+        let new = FieldElement::random();
+        let sig_1 = sig.update_message(&msgs, 0, &msgs[0], &new, &sk, &vk, &params).unwrap();
+        // A wrong old message is rejected
+        assert!(sig.update_message(&msgs, 0, &FieldElement::random(), &new, &sk, &vk, &params).is_err());
+        let old_msgs = msgs.clone();
+        msgs[0] = new;
+        assert!(sig_1.verify(msgs.clone(), &vk, &params).unwrap());
+        // Can't update m'
+        assert!(sig.update_message(&old_msgs, 3, &sig.m_prime, &msgs[0], &sk, &vk, &params).is_err());
+    }
+
+    #[test]
+    fn test_signature_to_and_from_bytes() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 5;
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk).unwrap();
+
+        let bytes = sig.to_bytes();
+        assert_eq!(bytes.len(), Signature::serialized_size());
+        let mut buf = vec![0u8; sig.serialized_len()];
+        assert_eq!(sig.write_into(&mut buf).unwrap(), bytes.len());
+        assert_eq!(buf, bytes);
+        let sig_1 = Signature::from_bytes(&bytes).unwrap();
+        assert_eq!(sig.m_prime, sig_1.m_prime);
+        assert_eq!(sig.sig.sigma_1, sig_1.sig.sigma_1);
+        assert_eq!(sig.sig.sigma_2, sig_1.sig.sigma_2);
+        assert!(sig_1.verify(msgs, &vk, &params).unwrap());
+
+        assert!(Signature::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let sig_2: Signature = sig.to_string().parse().unwrap();
+        assert_eq!(sig.m_prime, sig_2.m_prime);
+        assert!(Signature::from_bytes(&sig.sig.to_bytes()).is_err());
+    }
+}
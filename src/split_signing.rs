@@ -0,0 +1,249 @@
+// 2-of-2 signing (2016 scheme) where the signing key is split into additive shares held by 2 servers, i.e.
+// x = x_1 + x_2 and y_i = y_1_i + y_2_i, so compromise of a single server does not expose the signing key.
+// Both servers need to agree on sigma_1 whose discrete log should not be known to either of them, else that server
+// can learn g^x and g^y_i and forge signatures. So sigma_1 is generated by hashing the messages and a session id,
+// like in `Signature::new_deterministic`. Each server then computes sigma_1^{x_j + y_j_1*m_1 + y_j_2*m_2 + ...} and
+// the product of both is sigma_1^{x + y_1*m_1 + y_2*m_2 + ...}, a regular signature verifiable with the unchanged
+// verkey.
+// In the multiplicative mode, for server assisted signing on a user's device, the key is split as x = d * x' and
+// y_i = d * y_i' with the device holding d and the server holding x' and y_i'. The device sends the request to the
+// server which answers with sigma_1^{x' + y_1'*m_1 + ...} and the device raises it to d. The server's share is
+// uniformly random and independent of the key and the device's share is a single random value so neither can sign
+// alone.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::signature::Signature;
+use crate::SignatureGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Additive share of a signing key held by one of the 2 servers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdditiveSigkeyShare {
+    pub x: FieldElement,
+    pub y: Vec<FieldElement>,
+}
+
+impl AdditiveSigkeyShare {
+    /// Split an existing signing key into 2 shares. The original key should be deleted after the shares
+    /// are given to the servers.
+    pub fn split(sigkey: &Sigkey) -> (Self, Self) {
+        let x_1 = FieldElement::random();
+        let x_2 = &sigkey.x - &x_1;
+        let mut y_1 = Vec::with_capacity(sigkey.y.len());
+        let mut y_2 = Vec::with_capacity(sigkey.y.len());
+        for y_i in &sigkey.y {
+            let r = FieldElement::random();
+            y_2.push(y_i - &r);
+            y_1.push(r);
+        }
+        (Self { x: x_1, y: y_1 }, Self { x: x_2, y: y_2 })
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.y.len()
+    }
+}
+
+/// Multiplicative share of a signing key held by the device
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceKeyShare {
+    pub d: FieldElement,
+}
+
+/// Multiplicative share of a signing key held by the server, the signing key divided by the device's share
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ServerKeyShare {
+    pub x: FieldElement,
+    pub y: Vec<FieldElement>,
+}
+
+/// Split an existing signing key into the shares of the device and the server. The original key should be deleted
+/// after the shares are given to the device and the server.
+pub fn split_multiplicative(sigkey: &Sigkey) -> (DeviceKeyShare, ServerKeyShare) {
+    let d = FieldElement::random();
+    let d_inv = d.inverse();
+    let server = ServerKeyShare {
+        x: &sigkey.x * &d_inv,
+        y: sigkey.y.iter().map(|y_i| y_i * &d_inv).collect(),
+    };
+    (DeviceKeyShare { d }, server)
+}
+
+impl ServerKeyShare {
+    pub fn msg_count(&self) -> usize {
+        self.y.len()
+    }
+}
+
+/// Domain separation tag for deriving sigma_1 from the messages and the session id in `SplitSigningRequest::sigma_1`
+pub const SPLIT_SIGNING_SIGMA_1_DST: &[u8] = b"PS-SIG-SPLIT-SIGNING-SIGMA-1";
+
+/// Sent to both servers to request a signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitSigningRequest {
+    pub messages: Vec<FieldElement>,
+    /// Unique for each signing session
+    pub session_id: Vec<u8>,
+}
+
+/// Sent by each server in response to a `SplitSigningRequest`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub sigma_2: SignatureGroup,
+}
+
+impl SplitSigningRequest {
+    pub fn new(messages: Vec<FieldElement>, session_id: &[u8]) -> Self {
+        Self {
+            messages,
+            session_id: session_id.to_vec(),
+        }
+    }
+
+    /// sigma_1 is generated by hashing `SPLIT_SIGNING_SIGMA_1_DST`, the no of messages, the messages and the session
+    /// id so no server knows its discrete log and the same messages hashed elsewhere in the crate give another element
+    pub fn sigma_1(&self) -> SignatureGroup {
+        let mut bytes = SPLIT_SIGNING_SIGMA_1_DST.to_vec();
+        bytes.extend_from_slice(&(self.messages.len() as u64).to_be_bytes());
+        for m in &self.messages {
+            bytes.append(&mut m.to_bytes());
+        }
+        bytes.extend_from_slice(&self.session_id);
+        SignatureGroup::from_msg_hash(&bytes)
+    }
+
+    /// Called by each server with its share
+    pub fn sign(&self, share: &AdditiveSigkeyShare) -> Result<PartialSignature, PSError> {
+        self.sign_with_exponents(&share.x, &share.y)
+    }
+
+    /// Called by the server in the multiplicative mode with its share
+    pub fn sign_as_server(&self, share: &ServerKeyShare) -> Result<PartialSignature, PSError> {
+        self.sign_with_exponents(&share.x, &share.y)
+    }
+
+    /// sigma_1^{x + y_1*m_1 + y_2*m_2 + ...}
+    fn sign_with_exponents(&self, x: &FieldElement, y: &[FieldElement]) -> Result<PartialSignature, PSError> {
+        if y.len() != self.messages.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: y.len(),
+                given: self.messages.len(),
+            });
+        }
+        let mut exp = x.clone();
+        for (y_i, m_i) in y.iter().zip(self.messages.iter()) {
+            exp += y_i * m_i;
+        }
+        Ok(PartialSignature {
+            sigma_2: &self.sigma_1() * &exp,
+        })
+    }
+
+    /// Combine the partial signatures of both servers into a signature
+    pub fn combine(&self, partial_1: &PartialSignature, partial_2: &PartialSignature) -> Signature {
+        Signature {
+            sigma_1: self.sigma_1(),
+            sigma_2: &partial_1.sigma_2 + &partial_2.sigma_2,
+        }
+    }
+
+    /// Called by the device in the multiplicative mode with the server's partial signature. Fails if the resulting
+    /// signature does not verify, i.e. the server misbehaved.
+    pub fn finish_as_device(
+        &self,
+        share: &DeviceKeyShare,
+        server_partial: &PartialSignature,
+        vk: &Verkey,
+        params: &Params,
+    ) -> Result<Signature, PSError> {
+        let sig = Signature {
+            sigma_1: self.sigma_1(),
+            sigma_2: &server_partial.sigma_2 * &share.d,
+        };
+        if !sig.verify(self.messages.clone(), vk, params)? {
+            return Err(PSError::GeneralError {
+                msg: String::from("Signature from the server's partial signature does not verify"),
+            });
+        }
+        Ok(sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_split_signing() {
+        let params = Params::new("test".as_bytes());
+        for i in 0..10 {
+            let count_msgs = (i % 5) + 1;
+            let (sk, vk) = keygen(count_msgs, &params);
+            let (share_1, share_2) = AdditiveSigkeyShare::split(&sk);
+
+            let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+            let request = SplitSigningRequest::new(msgs.clone(), "session 1".as_bytes());
+            let partial_1 = request.sign(&share_1).unwrap();
+            let partial_2 = request.sign(&share_2).unwrap();
+            let sig = request.combine(&partial_1, &partial_2);
+            assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+            // A single share cannot sign
+            let sig_1 = Signature {
+                sigma_1: request.sigma_1(),
+                sigma_2: partial_1.sigma_2,
+            };
+            assert!(!sig_1.verify(msgs, &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_split_signing_sigma_1_domain_separated() {
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        // With an empty session id, sigma_1 differs from the one of `Signature::new_deterministic`
+        let request = SplitSigningRequest::new(msgs.clone(), &[]);
+        assert_ne!(request.sigma_1(), Signature::generate_sigma_1_from_messages(&msgs));
+        let mut bytes = vec![];
+        for m in &msgs {
+            bytes.append(&mut m.to_bytes());
+        }
+        assert_ne!(request.sigma_1(), SignatureGroup::from_msg_hash(&bytes));
+
+        // A message moved into the session id gives another sigma_1
+        let moved = SplitSigningRequest::new(msgs[..2].to_vec(), &msgs[2].to_bytes());
+        assert_ne!(request.sigma_1(), moved.sigma_1());
+    }
+
+    #[test]
+    fn test_multiplicative_split_signing() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (device, server) = split_multiplicative(&sk);
+
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let request = SplitSigningRequest::new(msgs.clone(), "session 1".as_bytes());
+        let partial = request.sign_as_server(&server).unwrap();
+        let sig = request.finish_as_device(&device, &partial, &vk, &params).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Server alone cannot sign
+        let sig_1 = Signature {
+            sigma_1: request.sigma_1(),
+            sigma_2: partial.sigma_2.clone(),
+        };
+        assert!(!sig_1.verify(msgs.clone(), &vk, &params).unwrap());
+
+        // Device detects a wrong partial signature
+        let bad = PartialSignature {
+            sigma_2: SignatureGroup::random(),
+        };
+        assert!(request.finish_as_device(&device, &bad, &vk, &params).is_err());
+
+        let request = SplitSigningRequest::new(msgs[1..].to_vec(), "session 2".as_bytes());
+        assert!(request.sign_as_server(&server).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// Structure-preserving signatures on equivalence classes (SPS-EQ) by Fuchsbauer, Hanser and Slamanig,
+// "Structure-Preserving Signatures on Equivalence Classes and Constant-Size Anonymous Credentials" (eprint 2014/944).
+// Unlike the PS signatures in this crate which sign field elements, these sign a vector of group elements
+// (M_1, M_2, ..., M_l) from `SignatureGroup`, so other public keys or commitments can be certified directly.
+// A signature on (M_1, M_2, ..., M_l) can be adapted by anyone to a signature on (M_1^mu, M_2^mu, ..., M_l^mu).
+// - Keygen: secret key (x_1, x_2, ..., x_l), public key (X_tilde_1, X_tilde_2, ..., X_tilde_l) = (g_tilde^x_1, ...)
+// - Sign: pick random y, Z = (M_1^x_1 * M_2^x_2 * ... M_l^x_l)^y, Y = g^{1/y}, Y_tilde = g_tilde^{1/y}
+// - Verify: e(M_1, X_tilde_1) * e(M_2, X_tilde_2) * ... e(M_l, X_tilde_l) == e(Z, Y_tilde) and e(Y, g_tilde) == e(g, Y_tilde)
+// l must be at least 2. With l = 1 all non-identity elements form a single equivalence class so a signature on
+// one element could be adapted to a signature on any element.
+
+use crate::errors::PSError;
+use crate::keys::Params;
+use crate::{ate_2_pairing, ate_multi_pairing, SignatureGroup, SignatureGroupVec, VerkeyGroup};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+/// Minimum number of group elements signed, see the comment at the top
+pub const SPS_MIN_MESSAGES: usize = 2;
+
+fn check_count_messages(count_messages: usize) -> Result<(), PSError> {
+    if count_messages < SPS_MIN_MESSAGES {
+        return Err(PSError::TooFewMessages {
+            minimum: SPS_MIN_MESSAGES,
+            given: count_messages,
+        });
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SPSSigkey {
+    pub x: Vec<FieldElement>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SPSVerkey {
+    pub X_tilde: Vec<VerkeyGroup>,
+}
+
+/// Generate signing and verification keys for signing `count_messages` group elements. `count_messages`
+/// must be at least `SPS_MIN_MESSAGES`.
+pub fn sps_keygen(
+    count_messages: usize,
+    params: &Params,
+) -> Result<(SPSSigkey, SPSVerkey), PSError> {
+    check_count_messages(count_messages)?;
+    let mut x = vec![];
+    let mut X_tilde = vec![];
+    for _ in 0..count_messages {
+        let x_i = FieldElement::random();
+        X_tilde.push(&params.g_tilde * &x_i);
+        x.push(x_i);
+    }
+    Ok((SPSSigkey { x }, SPSVerkey { X_tilde }))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SPSSignature {
+    pub Z: SignatureGroup,
+    pub Y: SignatureGroup,
+    pub Y_tilde: VerkeyGroup,
+}
+
+impl SPSSignature {
+    /// Sign the group elements `messages`
+    pub fn new(
+        messages: &[SignatureGroup],
+        sigkey: &SPSSigkey,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        check_count_messages(sigkey.x.len())?;
+        if messages.len() != sigkey.x.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: sigkey.x.len(),
+                given: messages.len(),
+            });
+        }
+        let y = FieldElement::random();
+        let y_inv = y.inverse();
+        // Z = (M_1^x_1 * M_2^x_2 * ... M_l^x_l)^y = M_1^{x_1*y} * M_2^{x_2*y} * ... M_l^{x_l*y}
+        let bases = SignatureGroupVec::from(messages);
+        let mut exps = FieldElementVector::with_capacity(messages.len());
+        for x_i in &sigkey.x {
+            exps.push(x_i * &y);
+        }
+        let Z = bases.multi_scalar_mul_const_time(&exps).unwrap();
+        let Y = &params.g * &y_inv;
+        let Y_tilde = &params.g_tilde * &y_inv;
+        Ok(Self { Z, Y, Y_tilde })
+    }
+
+    /// Verify the signature on group elements `messages`
+    pub fn verify(
+        &self,
+        messages: &[SignatureGroup],
+        vk: &SPSVerkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        check_count_messages(vk.X_tilde.len())?;
+        if messages.len() != vk.X_tilde.len() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: vk.X_tilde.len(),
+                given: messages.len(),
+            });
+        }
+        if self.Z.is_identity() || self.Y.is_identity() || self.Y_tilde.is_identity() {
+            return Ok(false);
+        }
+        if messages.iter().any(|m| m.is_identity()) {
+            return Ok(false);
+        }
+
+        // e(M_1, X_tilde_1) * e(M_2, X_tilde_2) * ... e(M_l, X_tilde_l) * e(-Z, Y_tilde) == 1
+        let neg_Z = self.Z.negation();
+        let mut pairs = messages
+            .iter()
+            .zip(vk.X_tilde.iter())
+            .collect::<Vec<(&SignatureGroup, &VerkeyGroup)>>();
+        pairs.push((&neg_Z, &self.Y_tilde));
+        if !ate_multi_pairing(pairs).is_one() {
+            return Ok(false);
+        }
+
+        // e(Y, g_tilde) == e(g, Y_tilde) => e(Y, g_tilde) * e(-g, Y_tilde) == 1
+        let e = ate_2_pairing(
+            &self.Y,
+            &params.g_tilde,
+            &params.g.negation(),
+            &self.Y_tilde,
+        );
+        Ok(e.is_one())
+    }
+
+    /// Adapt the signature on (M_1, M_2, ..., M_l) to a signature on (M_1^mu, M_2^mu, ..., M_l^mu). The resulting
+    /// signature is randomized as well and cannot be linked to the original signature.
+    pub fn change_representation(
+        &self,
+        messages: &[SignatureGroup],
+        mu: &FieldElement,
+    ) -> (Vec<SignatureGroup>, Self) {
+        let psi = FieldElement::random();
+        let psi_inv = psi.inverse();
+        let new_messages = messages.iter().map(|m| m * mu).collect::<Vec<SignatureGroup>>();
+        let Z = &self.Z * &(mu * &psi);
+        let Y = &self.Y * &psi_inv;
+        let Y_tilde = &self.Y_tilde * &psi_inv;
+        (new_messages, Self { Z, Y, Y_tilde })
+    }
+
+    /// Byte representation of the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.Z.to_bytes());
+        bytes.append(&mut self.Y.to_bytes());
+        bytes.append(&mut self.Y_tilde.to_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sps_sign_verify() {
+        let params = Params::new("test".as_bytes());
+        for i in 0..10 {
+            let count_msgs = (i % 5) + 2;
+            let (sk, vk) = sps_keygen(count_msgs, &params).unwrap();
+            let msgs = (0..count_msgs)
+                .map(|_| SignatureGroup::random())
+                .collect::<Vec<SignatureGroup>>();
+            let sig = SPSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+            assert!(sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+
+            // Different messages fail
+            let mut other_msgs = msgs.clone();
+            other_msgs[0] = SignatureGroup::random();
+            assert!(!sig.verify(other_msgs.as_slice(), &vk, &params).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sps_change_representation() {
+        let count_msgs = 3;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = sps_keygen(count_msgs, &params).unwrap();
+        let msgs = (0..count_msgs)
+            .map(|_| SignatureGroup::random())
+            .collect::<Vec<SignatureGroup>>();
+        let sig = SPSSignature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let mu = FieldElement::random();
+        let (new_msgs, new_sig) = sig.change_representation(msgs.as_slice(), &mu);
+        assert!(new_sig.verify(new_msgs.as_slice(), &vk, &params).unwrap());
+        assert!(!new_sig.verify(msgs.as_slice(), &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_sps_single_message_refused() {
+        let params = Params::new("test".as_bytes());
+        for count_msgs in 0..2 {
+            match sps_keygen(count_msgs, &params) {
+                Err(PSError::TooFewMessages { minimum, given }) => {
+                    assert_eq!(minimum, SPS_MIN_MESSAGES);
+                    assert_eq!(given, count_msgs);
+                }
+                _ => panic!("keygen for {} messages should fail", count_msgs),
+            }
+        }
+
+        // Keys for 1 message built by hand are refused by signing and verification as well
+        let x = FieldElement::random();
+        let sk = SPSSigkey { x: vec![x.clone()] };
+        let vk = SPSVerkey {
+            X_tilde: vec![&params.g_tilde * &x],
+        };
+        let msgs = vec![SignatureGroup::random()];
+        let e = SPSSignature::new(msgs.as_slice(), &sk, &params).unwrap_err();
+        assert_eq!(e.code(), crate::errors::PSErrorCode::TooFewMessages);
+
+        let (sk_2, _) = sps_keygen(2, &params).unwrap();
+        let sig = SPSSignature::new(
+            &[SignatureGroup::random(), SignatureGroup::random()],
+            &sk_2,
+            &params,
+        )
+        .unwrap();
+        let e = sig.verify(msgs.as_slice(), &vk, &params).unwrap_err();
+        assert_eq!(e.code(), crate::errors::PSErrorCode::TooFewMessages);
+    }
+}
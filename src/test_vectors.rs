@@ -0,0 +1,283 @@
+// Deterministic test vectors for other implementations. All randomness (keys, messages, blindings, randomizers) is
+// derived from a seed with `xof::ElementReader` so the same seed always gives the same vectors. Elements are lowercase
+// hex of their `to_bytes` representation and signatures, verkeys and proofs are hex of their `to_bytes` encoding
+// (see `wire`). The challenge of the proof is `FieldElement::from_msg_hash` of `challenge_bytes`.
+// Enabled with the feature `test_vectors`, run `cargo run --example test_vectors --features test_vectors` to print
+// the vectors for the default seed as JSON.
+
+use crate::blind_signature::{BlindSignature, BlindingKey, IssuerPRFKey};
+use crate::errors::PSError;
+use crate::keys::{Params, Scheme, Sigkey, Verkey};
+use crate::pok_sig::PoKOfSignature;
+use crate::serde_hex::to_hex;
+use crate::signature::Signature;
+use crate::signature_2018::Signature as Signature18;
+use crate::xof::ElementReader;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::{HashMap, HashSet};
+
+/// Seed used for the published vectors
+pub const DEFAULT_SEED: &[u8] = b"PS-SIG-TEST-VECTORS";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParamsVector {
+    pub label: String,
+    pub g: String,
+    pub g_tilde: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeygenVector {
+    pub scheme: String,
+    pub count_messages: usize,
+    pub x: String,
+    pub y: Vec<String>,
+    pub verkey: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignatureVector {
+    pub scheme: String,
+    pub messages: Vec<String>,
+    /// Created with `new_deterministic` of the scheme
+    pub signature: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlindSignatureVector {
+    /// Messages at these indices are committed, the rest are known to the signer
+    pub committed_indices: Vec<usize>,
+    pub messages: Vec<String>,
+    pub blinding: String,
+    /// g^blinding * product of Y_i^m_i for committed indices i, Y from `BlindingKey`
+    pub commitment: String,
+    /// Created with `new_stateless_with_known_messages_at_indices`
+    pub prf_key: String,
+    pub context: String,
+    pub blind_signature: String,
+    pub unblinded_signature: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoKVector {
+    /// Signature from the 2016 scheme vector
+    pub revealed_indices: Vec<usize>,
+    /// Randomizer of the signature
+    pub r: String,
+    pub t: String,
+    /// Blindings for `t` followed by the hidden messages in increasing order of index
+    pub blindings: Vec<String>,
+    pub challenge_bytes: String,
+    pub challenge: String,
+    pub proof: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub seed: String,
+    pub group: String,
+    pub params: ParamsVector,
+    pub keygen: Vec<KeygenVector>,
+    pub signatures: Vec<SignatureVector>,
+    pub blind_signature: BlindSignatureVector,
+    pub pok_signature: PoKVector,
+}
+
+fn hex_field_elements(elems: &[FieldElement]) -> Vec<String> {
+    elems.iter().map(|e| to_hex(&e.to_bytes())).collect()
+}
+
+fn reader(seed: &[u8], label: &[u8]) -> ElementReader {
+    let mut bytes = seed.to_vec();
+    bytes.extend_from_slice(label);
+    ElementReader::new(&bytes)
+}
+
+fn field_elements(reader: &mut ElementReader, count: usize) -> Vec<FieldElement> {
+    (0..count).map(|_| reader.next_element::<FieldElement>()).collect()
+}
+
+/// Same as `keygen` but with the signing key derived from `reader`
+fn keygen_from_reader(reader: &mut ElementReader, key_size: usize, params: &Params) -> (Sigkey, Verkey) {
+    let sk = Sigkey {
+        x: reader.next_element::<FieldElement>(),
+        y: field_elements(reader, key_size),
+        metadata: None,
+    };
+    let vk = Verkey::from_sigkey(&sk, params);
+    (sk, vk)
+}
+
+fn keygen_vector(scheme: Scheme, count_messages: usize, sk: &Sigkey, vk: &Verkey) -> KeygenVector {
+    KeygenVector {
+        scheme: format!("{:?}", scheme),
+        count_messages,
+        x: to_hex(&sk.x.to_bytes()),
+        y: hex_field_elements(&sk.y),
+        verkey: to_hex(&vk.to_bytes(scheme)),
+    }
+}
+
+/// Generate the vectors for `seed`
+pub fn generate(seed: &[u8]) -> Result<TestVectors, PSError> {
+    let count_msgs = 4;
+    let label = "test-vectors";
+    let params = Params::new(label.as_bytes());
+
+    // Keys and signatures of both schemes
+    let (sk, vk) = keygen_from_reader(&mut reader(seed, b"keygen-2016"), count_msgs, &params);
+    let (sk_18, vk_18) = keygen_from_reader(
+        &mut reader(seed, b"keygen-2018"),
+        Scheme::PS2018.key_size(count_msgs),
+        &params,
+    );
+    let msgs = field_elements(&mut reader(seed, b"messages"), count_msgs);
+    let sig = Signature::new_deterministic(&msgs, &sk)?;
+    let sig_18 = Signature18::new_deterministic(&msgs, &sk_18)?;
+
+    // Blind signature over the first 2 messages committed and the rest known
+    let mut blind_reader = reader(seed, b"blind-signature");
+    let blinding = blind_reader.next_element::<FieldElement>();
+    let prf_key = IssuerPRFKey {
+        key: blind_reader.next_element::<FieldElement>().to_bytes(),
+    };
+    let context = "context";
+    let committed_indices = vec![0, 1];
+    let blinding_key = BlindingKey::new(&sk, &params);
+    let mut commitment = &params.g * &blinding;
+    let mut known = HashMap::new();
+    for (i, m) in msgs.iter().enumerate() {
+        if committed_indices.contains(&i) {
+            commitment += &blinding_key.Y[i] * m;
+        } else {
+            known.insert(i, m.clone());
+        }
+    }
+    let blind_sig = BlindSignature::new_stateless_with_known_messages_at_indices(
+        &commitment,
+        &known,
+        &sk,
+        &blinding_key,
+        &params,
+        &prf_key,
+        context.as_bytes(),
+    )?;
+    let unblinded_sig = BlindSignature::unblind(&blind_sig, &blinding);
+
+    // Proof of knowledge of the 2016 signature revealing message 1
+    let revealed_indices = vec![1];
+    let revealed_set = revealed_indices.iter().cloned().collect::<HashSet<usize>>();
+    let mut pok_reader = reader(seed, b"pok-signature");
+    let r = pok_reader.next_element::<FieldElement>();
+    let t = pok_reader.next_element::<FieldElement>();
+    let blindings = field_elements(&mut pok_reader, count_msgs - revealed_indices.len() + 1);
+    let sigma_prime = PoKOfSignature::transform_sig_with_randomness(&sig, &r, &t);
+    let (secrets, J, pok_vc) = PoKOfSignature::commit_for_pok(
+        msgs.clone(),
+        blindings.iter().map(Some).collect(),
+        &revealed_set,
+        t.clone(),
+        &vk,
+        &params,
+    );
+    let pok = PoKOfSignature {
+        secrets,
+        sig: sigma_prime,
+        J,
+        pok_vc,
+    };
+    let challenge_bytes = pok.to_bytes();
+    let challenge = FieldElement::from_msg_hash(&challenge_bytes);
+    let proof = pok.gen_proof(&challenge)?;
+
+    Ok(TestVectors {
+        seed: to_hex(seed),
+        group: format!("{:?}", crate::wire::GROUP_MARKER),
+        params: ParamsVector {
+            label: String::from(label),
+            g: to_hex(&params.g.to_bytes()),
+            g_tilde: to_hex(&params.g_tilde.to_bytes()),
+        },
+        keygen: vec![
+            keygen_vector(Scheme::PS2016, count_msgs, &sk, &vk),
+            keygen_vector(Scheme::PS2018, count_msgs, &sk_18, &vk_18),
+        ],
+        signatures: vec![
+            SignatureVector {
+                scheme: format!("{:?}", Scheme::PS2016),
+                messages: hex_field_elements(&msgs),
+                signature: to_hex(&sig.to_bytes()),
+            },
+            SignatureVector {
+                scheme: format!("{:?}", Scheme::PS2018),
+                messages: hex_field_elements(&msgs),
+                signature: to_hex(&sig_18.to_bytes()),
+            },
+        ],
+        blind_signature: BlindSignatureVector {
+            committed_indices,
+            messages: hex_field_elements(&msgs),
+            blinding: to_hex(&blinding.to_bytes()),
+            commitment: to_hex(&commitment.to_bytes()),
+            prf_key: to_hex(&prf_key.key),
+            context: String::from(context),
+            blind_signature: to_hex(&blind_sig.to_bytes()),
+            unblinded_signature: to_hex(&unblinded_sig.to_bytes()),
+        },
+        pok_signature: PoKVector {
+            revealed_indices,
+            r: to_hex(&r.to_bytes()),
+            t: to_hex(&t.to_bytes()),
+            blindings: hex_field_elements(&blindings),
+            challenge_bytes: to_hex(&challenge_bytes),
+            challenge: to_hex(&challenge.to_bytes()),
+            proof: to_hex(&proof.to_bytes()),
+        },
+    })
+}
+
+impl TestVectors {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pok_sig::PoKOfSignatureProof;
+    use crate::serde_hex::from_hex;
+
+    #[test]
+    fn test_vectors_deterministic_and_valid() {
+        let vectors = generate(DEFAULT_SEED).unwrap();
+        assert_eq!(vectors.to_json(), generate(DEFAULT_SEED).unwrap().to_json());
+        assert_ne!(vectors.to_json(), generate(b"other seed").unwrap().to_json());
+
+        let params = Params::new(vectors.params.label.as_bytes());
+        let (vk, _) = Verkey::from_bytes(&from_hex(&vectors.keygen[0].verkey).unwrap()).unwrap();
+        let msgs = vectors.signatures[0]
+            .messages
+            .iter()
+            .map(|m| FieldElement::from_bytes(&from_hex(m).unwrap()).unwrap())
+            .collect::<Vec<FieldElement>>();
+        let sig = Signature::from_bytes(&from_hex(&vectors.signatures[0].signature).unwrap()).unwrap();
+        assert!(sig.verify(msgs.clone(), &vk, &params).unwrap());
+        let (vk_18, _) = Verkey::from_bytes(&from_hex(&vectors.keygen[1].verkey).unwrap()).unwrap();
+        let sig_18 = Signature18::from_bytes(&from_hex(&vectors.signatures[1].signature).unwrap()).unwrap();
+        assert!(sig_18.verify(msgs.clone(), &vk_18, &params).unwrap());
+        let unblinded =
+            Signature::from_bytes(&from_hex(&vectors.blind_signature.unblinded_signature).unwrap()).unwrap();
+        assert!(unblinded.verify(msgs.clone(), &vk, &params).unwrap());
+
+        let proof = PoKOfSignatureProof::from_bytes(&from_hex(&vectors.pok_signature.proof).unwrap()).unwrap();
+        let revealed_set = vectors.pok_signature.revealed_indices.iter().cloned().collect::<HashSet<usize>>();
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_set, &vk, &params);
+        assert_eq!(to_hex(&chal_bytes), vectors.pok_signature.challenge_bytes);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+    }
+}
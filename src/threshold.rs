@@ -0,0 +1,921 @@
+// Threshold (t-of-n) sharing of a signing key. x and each y_i are shared with Shamir secret sharing over participant
+// ids 1 to n so any `threshold` shares determine the key and fewer reveal nothing about it. The shares are created
+// by `dkg` without any party knowing the key, or by a trusted dealer from an existing key with `share_sigkey`.
+// The dealer publishes Feldman commitments g_tilde^coefficient to the coefficients of the polynomials so each share
+// holder, and the dealer's auditors, can check with `verify_share` that a share is consistent with the commitments
+// and with `FeldmanCommitments::is_for_verkey` that the commitments are for the published verkey.
+// Shares are refreshed proactively, without changing the key, by each participant dealing shares of zero
+// (`refresh_dealing`) and every participant adding the shares it receives to its own (`refresh_share`). Shares from
+// before a refresh can't be combined with shares from after it so an adversary has to compromise `threshold`
+// participants between two refreshes to learn the key. Like in `dkg`, refresh shares must be sent over private
+// channels and the participants must agree on the dealers whose refresh is applied, e.g. those against whom nobody
+// complained.
+// Any `threshold` share holders sign together. They agree on sigma_1 (and m' for the 2018 scheme), e.g. derived from
+// the messages with `PartialSignature::sigma_1_for_messages`, each creates a `PartialSignature` with its share and
+// `aggregate_partials` interpolates the partial signatures in the exponent to get a signature verifiable with the
+// verkey like any other. A partial signature can be checked against the verkey of its share (obtained from the
+// commitments) and the agreed sigma_1 so `aggregate_partials_robust` excludes incorrect partial signatures and
+// identifies their signers instead of producing a signature that fails verification.
+
+use crate::errors::PSError;
+use crate::keys::{sign_exponent_with_secrets, Params, Sigkey, Signer, Verkey};
+use crate::signature::Signature;
+use crate::signature_2018::Signature as Signature2018;
+use crate::{SignatureGroup, SignatureGroupVec, VerkeyGroup, VerkeyGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+
+/// Share of the signing key of a participant. The signing key is the sum of the shares multiplied by their Lagrange
+/// coefficients (`lagrange_coefficient`) for any `threshold` participants.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SigkeyShare {
+    pub id: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub x: FieldElement,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub y: Vec<FieldElement>,
+}
+
+/// Feldman commitments to the polynomials sharing a signing key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeldmanCommitments {
+    /// Commitments to the coefficients of the polynomial for x followed by those for each y_i, constant term first
+    pub commitments: Vec<Vec<VerkeyGroup>>,
+}
+
+/// Broadcast by a participant refreshing the shares, commitments to polynomials with zero constant terms
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefreshCommitments {
+    pub sender: usize,
+    pub commitments: FeldmanCommitments,
+}
+
+/// Sent privately by a participant refreshing the shares to `receiver`, shares of zero for x and each y_i
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefreshShare {
+    pub sender: usize,
+    pub receiver: usize,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::elements"))]
+    pub shares: Vec<FieldElement>,
+}
+
+/// Signature with a key share over the agreed sigma_1, sigma_2 is sigma_1^(x + y_1*m_1 + ...) for the share
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartialSignature {
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub sigma_1: SignatureGroup,
+    #[cfg_attr(feature = "serde_hex", serde(with = "crate::serde_hex::element"))]
+    pub sigma_2: SignatureGroup,
+}
+
+impl SigkeyShare {
+    /// Shares of x followed by each y_i
+    pub(crate) fn secrets(&self) -> Vec<&FieldElement> {
+        vec![&self.x].into_iter().chain(self.y.iter()).collect()
+    }
+}
+
+/// A share signs like a signing key, the result being a partial signature
+impl Signer for SigkeyShare {
+    fn key_size(&self) -> usize {
+        self.y.len()
+    }
+
+    fn sign_exponent(
+        &self,
+        h: &SignatureGroup,
+        messages: &[(usize, &FieldElement)],
+    ) -> Result<SignatureGroup, PSError> {
+        sign_exponent_with_secrets(&self.x, &self.y, h, messages)
+    }
+}
+
+/// f(id) for f with `coefficients`, constant term first
+pub(crate) fn evaluate(coefficients: &FieldElementVector, id: usize) -> FieldElement {
+    let id = FieldElement::from(id as u64);
+    let mut res = FieldElement::zero();
+    for c in coefficients.iter().rev() {
+        res = &(&res * &id) + c;
+    }
+    res
+}
+
+/// g_tilde^f(id) computed from the commitments to the coefficients of f
+fn evaluate_commitments(commitments: &[VerkeyGroup], id: usize) -> VerkeyGroup {
+    let id = FieldElement::from(id as u64);
+    let mut powers = FieldElementVector::with_capacity(commitments.len());
+    let mut power = FieldElement::one();
+    for _ in 0..commitments.len() {
+        powers.push(power.clone());
+        power = &power * &id;
+    }
+    let mut bases = VerkeyGroupVec::with_capacity(commitments.len());
+    for c in commitments {
+        bases.push(c.clone());
+    }
+    bases.multi_scalar_mul_var_time(&powers).unwrap()
+}
+
+pub(crate) fn check_threshold(threshold: usize, total: usize) -> Result<(), PSError> {
+    if threshold == 0 || threshold > total {
+        return Err(PSError::GeneralError {
+            msg: format!("Threshold {} should be between 1 and the no of participants {}", threshold, total),
+        });
+    }
+    Ok(())
+}
+
+impl FeldmanCommitments {
+    pub(crate) fn new(coefficients: &[FieldElementVector], params: &Params) -> Self {
+        Self {
+            commitments: coefficients
+                .iter()
+                .map(|coeffs| coeffs.iter().map(|c| &params.g_tilde * c).collect())
+                .collect(),
+        }
+    }
+
+    /// Commitments to zero polynomials, to sum commitments
+    pub(crate) fn identity(secret_count: usize, threshold: usize) -> Self {
+        Self {
+            commitments: vec![vec![VerkeyGroup::identity(); threshold]; secret_count],
+        }
+    }
+
+    /// Commitments to the sum of the polynomials, both should have the same shape
+    pub(crate) fn add_assign(&mut self, other: &Self) {
+        for (c, o) in self.commitments.iter_mut().zip(other.commitments.iter()) {
+            for (c_k, o_k) in c.iter_mut().zip(o.iter()) {
+                *c_k += o_k;
+            }
+        }
+    }
+
+    /// No of shares needed to reconstruct the key
+    pub fn threshold(&self) -> usize {
+        self.commitments.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    /// No of secrets shared, x and each y_i
+    pub fn secret_count(&self) -> usize {
+        self.commitments.len()
+    }
+
+    /// Whether there are `secret_count` polynomials of degree `threshold - 1`
+    pub fn is_well_formed(&self, secret_count: usize, threshold: usize) -> bool {
+        self.commitments.len() == secret_count && self.commitments.iter().all(|c| c.len() == threshold)
+    }
+
+    /// Fails if there is no polynomial or a polynomial has no coefficient
+    fn check_not_empty(&self) -> Result<(), PSError> {
+        if self.commitments.is_empty() || self.commitments.iter().any(|c| c.is_empty()) {
+            return Err(PSError::GeneralError {
+                msg: String::from("Commitments are empty"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verkey of the shared key, the commitments to the constant terms. Fails if the commitments are empty.
+    pub fn verkey(&self) -> Result<Verkey, PSError> {
+        self.check_not_empty()?;
+        Ok(Verkey {
+            X_tilde: self.commitments[0][0].clone(),
+            Y_tilde: self.commitments[1..].iter().map(|c| c[0].clone()).collect(),
+            metadata: None,
+        })
+    }
+
+    /// Whether these are commitments to the polynomials sharing the signing key of `verkey`
+    pub fn is_for_verkey(&self, verkey: &Verkey) -> bool {
+        self.commitments.len() == verkey.Y_tilde.len() + 1
+            && self.commitments.iter().all(|c| !c.is_empty())
+            && self.commitments[0][0] == verkey.X_tilde
+            && self.commitments[1..]
+                .iter()
+                .zip(verkey.Y_tilde.iter())
+                .all(|(c, Y)| c[0] == *Y)
+    }
+
+    /// Verkey of the share of participant `id`, i.e. g_tilde^x and g_tilde^y_i for its share. Fails if the
+    /// commitments are empty.
+    pub fn share_verkey(&self, id: usize) -> Result<Verkey, PSError> {
+        self.check_not_empty()?;
+        Ok(Verkey {
+            X_tilde: evaluate_commitments(&self.commitments[0], id),
+            Y_tilde: self.commitments[1..]
+                .iter()
+                .map(|c| evaluate_commitments(c, id))
+                .collect(),
+            metadata: None,
+        })
+    }
+
+    /// Whether `shares` of x followed by each y_i for participant `id` are consistent with the commitments
+    pub(crate) fn share_matches(&self, shares: &[&FieldElement], id: usize, params: &Params) -> bool {
+        id != 0
+            && shares.len() == self.commitments.len()
+            && shares
+                .iter()
+                .zip(self.commitments.iter())
+                .all(|(s, c)| &params.g_tilde * *s == evaluate_commitments(c, id))
+    }
+}
+
+/// Whether `share` is consistent with the dealer's `commitments`
+pub fn verify_share(share: &SigkeyShare, commitments: &FeldmanCommitments, params: &Params) -> bool {
+    commitments.share_matches(&share.secrets(), share.id, params)
+}
+
+/// Called by a trusted dealer to split an existing signing key into `total` shares, any `threshold` of which
+/// determine the key. The shares should be sent privately and the commitments published. The dealer should delete
+/// the key afterwards.
+pub fn share_sigkey(
+    sigkey: &Sigkey,
+    threshold: usize,
+    total: usize,
+    params: &Params,
+) -> Result<(Vec<SigkeyShare>, FeldmanCommitments), PSError> {
+    check_threshold(threshold, total)?;
+    let coefficients = vec![&sigkey.x]
+        .into_iter()
+        .chain(sigkey.y.iter())
+        .map(|secret| {
+            let mut coeffs = FieldElementVector::with_capacity(threshold);
+            coeffs.push(secret.clone());
+            for _ in 1..threshold {
+                coeffs.push(FieldElement::random());
+            }
+            coeffs
+        })
+        .collect::<Vec<FieldElementVector>>();
+    let shares = (1..=total)
+        .map(|id| SigkeyShare {
+            id,
+            x: evaluate(&coefficients[0], id),
+            y: coefficients[1..].iter().map(|coeffs| evaluate(coeffs, id)).collect(),
+        })
+        .collect();
+    Ok((shares, FeldmanCommitments::new(&coefficients, params)))
+}
+
+/// Lagrange coefficient of `id` for interpolating at 0 from the shares of `ids`
+pub fn lagrange_coefficient(ids: &[usize], id: usize) -> Result<FieldElement, PSError> {
+    if !ids.contains(&id) {
+        return Err(PSError::GeneralError {
+            msg: format!("Id {} is not among the ids", id),
+        });
+    }
+    let mut num = FieldElement::one();
+    let mut den = FieldElement::one();
+    let i = FieldElement::from(id as u64);
+    for (k, other) in ids.iter().enumerate() {
+        if *other == 0 || ids[..k].contains(other) {
+            return Err(PSError::GeneralError {
+                msg: format!("Id {} is zero or repeated", other),
+            });
+        }
+        if *other == id {
+            continue;
+        }
+        let j = FieldElement::from(*other as u64);
+        num = &num * &j;
+        den = &den * &(&j - &i);
+    }
+    Ok(&num * &den.inverse())
+}
+
+/// Called by each participant `id` to refresh the shares with `commitments` of a key shared among `total`
+/// participants. Returns the commitments to broadcast and the shares for all participants including itself.
+pub fn refresh_dealing(
+    id: usize,
+    commitments: &FeldmanCommitments,
+    total: usize,
+    params: &Params,
+) -> Result<(RefreshCommitments, Vec<RefreshShare>), PSError> {
+    let threshold = commitments.threshold();
+    check_threshold(threshold, total)?;
+    if id == 0 || id > total {
+        return Err(PSError::GeneralError {
+            msg: format!("Participant id {} should be between 1 and {}", id, total),
+        });
+    }
+    let coefficients = (0..commitments.secret_count())
+        .map(|_| {
+            let mut coeffs = FieldElementVector::with_capacity(threshold);
+            coeffs.push(FieldElement::zero());
+            for _ in 1..threshold {
+                coeffs.push(FieldElement::random());
+            }
+            coeffs
+        })
+        .collect::<Vec<FieldElementVector>>();
+    let shares = (1..=total)
+        .map(|receiver| RefreshShare {
+            sender: id,
+            receiver,
+            shares: coefficients.iter().map(|coeffs| evaluate(coeffs, receiver)).collect(),
+        })
+        .collect();
+    Ok((
+        RefreshCommitments {
+            sender: id,
+            commitments: FeldmanCommitments::new(&coefficients, params),
+        },
+        shares,
+    ))
+}
+
+impl RefreshCommitments {
+    /// Whether these commit to shares of zero for a key with `commitments`
+    pub fn is_valid_for(&self, commitments: &FeldmanCommitments) -> bool {
+        self.commitments
+            .is_well_formed(commitments.secret_count(), commitments.threshold())
+            && self.commitments.commitments.iter().all(|c| c[0].is_identity())
+    }
+}
+
+impl RefreshShare {
+    /// Whether this share is consistent with the sender's commitments, the receiver should complain if not
+    pub fn verify(&self, commitments: &RefreshCommitments, params: &Params) -> bool {
+        self.sender == commitments.sender
+            && commitments
+                .commitments
+                .share_matches(&self.shares.iter().collect::<Vec<_>>(), self.receiver, params)
+    }
+}
+
+impl FeldmanCommitments {
+    /// Commitments after applying `refreshes`, computed by everyone to verify the refreshed shares. The verkey is
+    /// unchanged.
+    pub fn refresh(&self, refreshes: &[RefreshCommitments]) -> Result<Self, PSError> {
+        let mut commitments = self.clone();
+        for r in refreshes {
+            if !r.is_valid_for(self) {
+                return Err(PSError::GeneralError {
+                    msg: format!("Refresh commitments from {} are not to shares of zero", r.sender),
+                });
+            }
+            commitments.add_assign(&r.commitments);
+        }
+        Ok(commitments)
+    }
+}
+
+/// Called by the holder of `share` with the commitments and the share for it from each agreed dealer. Returns the
+/// refreshed share and the refreshed commitments, the old share should be deleted.
+pub fn refresh_share(
+    share: &SigkeyShare,
+    commitments: &FeldmanCommitments,
+    refreshes: &[(RefreshCommitments, RefreshShare)],
+    params: &Params,
+) -> Result<(SigkeyShare, FeldmanCommitments), PSError> {
+    let mut new_share = share.clone();
+    for (c, s) in refreshes {
+        if s.receiver != share.id || !c.is_valid_for(commitments) || !s.verify(c, params) {
+            return Err(PSError::GeneralError {
+                msg: format!("Invalid refresh share from {}", s.sender),
+            });
+        }
+        new_share.x += &s.shares[0];
+        for (y, r) in new_share.y.iter_mut().zip(s.shares[1..].iter()) {
+            *y += r;
+        }
+    }
+    let new_commitments =
+        commitments.refresh(&refreshes.iter().map(|(c, _)| c.clone()).collect::<Vec<_>>())?;
+    Ok((new_share, new_commitments))
+}
+
+impl PartialSignature {
+    /// Partial signature for the 2016 scheme over the agreed `sigma_1`
+    pub fn new(messages: &[FieldElement], share: &SigkeyShare, sigma_1: &SignatureGroup) -> Result<Self, PSError> {
+        Signature::check_sigkey_and_messages_compat(messages, share)?;
+        let sigma_2 = Signature::sign_with_given_sigma_1(messages, share, 0, sigma_1)?;
+        Ok(Self {
+            sigma_1: sigma_1.clone(),
+            sigma_2,
+        })
+    }
+
+    /// Partial signature for the 2018 scheme over the agreed `m_prime` and `sigma_1`
+    pub fn new_2018(
+        messages: &[FieldElement],
+        m_prime: &FieldElement,
+        share: &SigkeyShare,
+        sigma_1: &SignatureGroup,
+    ) -> Result<Self, PSError> {
+        let sigma_2 = Signature2018::sign_with_given_sigma_1(messages, m_prime, share, 0, sigma_1)?;
+        Ok(Self {
+            sigma_1: sigma_1.clone(),
+            sigma_2,
+        })
+    }
+
+    /// sigma_1 derived from the messages for the 2016 scheme so the signers need not communicate to agree on it.
+    /// The aggregated signature is then the same as by `Signature::new_deterministic`.
+    pub fn sigma_1_for_messages(messages: &[FieldElement]) -> SignatureGroup {
+        Signature::generate_sigma_1_from_messages(messages)
+    }
+
+    /// m' and sigma_1 derived from the messages for the 2018 scheme, as by `Signature::new_deterministic` of
+    /// that scheme
+    pub fn m_prime_and_sigma_1_for_messages(messages: &[FieldElement]) -> (FieldElement, SignatureGroup) {
+        Signature2018::generate_m_prime_and_sigma_1_from_messages(messages)
+    }
+
+    /// Verify a partial signature of the 2016 scheme with the verkey of the signer's share, i.e.
+    /// `FeldmanCommitments::share_verkey`
+    pub fn verify(&self, messages: Vec<FieldElement>, share_verkey: &Verkey, params: &Params) -> Result<bool, PSError> {
+        self.as_signature().verify(messages, share_verkey, params)
+    }
+
+    /// Same as `verify` but for the 2018 scheme with the agreed `m_prime`
+    pub fn verify_2018(
+        &self,
+        messages: Vec<FieldElement>,
+        m_prime: &FieldElement,
+        share_verkey: &Verkey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        let sig = Signature2018 {
+            m_prime: m_prime.clone(),
+            sig: self.as_signature(),
+        };
+        sig.verify(messages, share_verkey, params)
+    }
+
+    fn as_signature(&self) -> Signature {
+        Signature {
+            sigma_1: self.sigma_1.clone(),
+            sigma_2: self.sigma_2.clone(),
+        }
+    }
+}
+
+/// Split `partials` into the ones over the agreed `sigma_1` passing `verify` with the verkey of their share and the
+/// ids of the ones that don't. Only the first partial signature of an id is considered, later ones with the same
+/// id are dropped. Fails if fewer than threshold are valid.
+fn select_valid_partials<F>(
+    partials: &[PartialSignature],
+    indices: &[usize],
+    sigma_1: &SignatureGroup,
+    commitments: &FeldmanCommitments,
+    verify: F,
+) -> Result<(Vec<PartialSignature>, Vec<usize>, Vec<usize>), PSError>
+where
+    F: Fn(&PartialSignature, &Verkey) -> Result<bool, PSError>,
+{
+    if partials.len() != indices.len() {
+        return Err(PSError::GeneralError {
+            msg: format!(
+                "{} partial signatures given for {} ids, need one for each id",
+                partials.len(),
+                indices.len()
+            ),
+        });
+    }
+    let mut valid = vec![];
+    let mut valid_ids = vec![];
+    let mut cheaters = vec![];
+    for (p, id) in partials.iter().zip(indices.iter()) {
+        if valid_ids.contains(id) || cheaters.contains(id) {
+            continue;
+        }
+        // The verkey of id 0 is the verkey of the shared key
+        if *id != 0 && p.sigma_1 == *sigma_1 && verify(p, &commitments.share_verkey(*id)?)? {
+            valid.push(p.clone());
+            valid_ids.push(*id);
+        } else {
+            cheaters.push(*id);
+        }
+    }
+    if valid.len() < commitments.threshold() {
+        return Err(PSError::GeneralError {
+            msg: format!(
+                "Only {} valid partial signatures but the threshold is {}, invalid from {:?}",
+                valid.len(),
+                commitments.threshold(),
+                cheaters
+            ),
+        });
+    }
+    Ok((valid, valid_ids, cheaters))
+}
+
+/// sigma_2 of the signature by interpolating the `partials` of the share holders with ids `indices` in the exponent
+fn interpolate_partials(partials: &[PartialSignature], indices: &[usize]) -> Result<SignatureGroup, PSError> {
+    if partials.is_empty() || partials.len() != indices.len() {
+        return Err(PSError::GeneralError {
+            msg: format!(
+                "{} partial signatures given for {} ids, need one for each id",
+                partials.len(),
+                indices.len()
+            ),
+        });
+    }
+    if partials.iter().any(|p| p.sigma_1 != partials[0].sigma_1) {
+        return Err(PSError::GeneralError {
+            msg: String::from("Partial signatures are over different sigma_1"),
+        });
+    }
+    let mut coefficients = FieldElementVector::with_capacity(indices.len());
+    let mut bases = SignatureGroupVec::with_capacity(indices.len());
+    for (p, id) in partials.iter().zip(indices.iter()) {
+        coefficients.push(lagrange_coefficient(indices, *id)?);
+        bases.push(p.sigma_2.clone());
+    }
+    Ok(bases.multi_scalar_mul_var_time(&coefficients).unwrap())
+}
+
+/// Signature of the 2016 scheme from the `partials` of at least `threshold` share holders, the share holder of
+/// `partials[i]` having id `indices[i]`. The signature verifies with the shared key's verkey only if enough partial
+/// signatures are correct.
+pub fn aggregate_partials(partials: &[PartialSignature], indices: &[usize]) -> Result<Signature, PSError> {
+    let sigma_2 = interpolate_partials(partials, indices)?;
+    Ok(Signature {
+        sigma_1: partials[0].sigma_1.clone(),
+        sigma_2,
+    })
+}
+
+/// Same as `aggregate_partials` but for the 2018 scheme with the agreed `m_prime`
+pub fn aggregate_partials_2018(
+    partials: &[PartialSignature],
+    indices: &[usize],
+    m_prime: &FieldElement,
+) -> Result<Signature2018, PSError> {
+    Ok(Signature2018 {
+        m_prime: m_prime.clone(),
+        sig: aggregate_partials(partials, indices)?,
+    })
+}
+
+/// Same as `aggregate_partials` but each partial signature is first verified with the verkey of the signer's share
+/// from the `commitments` and the invalid ones, including those over a sigma_1 other than the agreed `sigma_1`, are
+/// excluded. Returns the signature and the ids of the signers of invalid partial signatures. Fails if fewer than
+/// threshold partial signatures are valid.
+pub fn aggregate_partials_robust(
+    partials: &[PartialSignature],
+    indices: &[usize],
+    messages: &[FieldElement],
+    sigma_1: &SignatureGroup,
+    commitments: &FeldmanCommitments,
+    params: &Params,
+) -> Result<(Signature, Vec<usize>), PSError> {
+    let (valid, ids, cheaters) = select_valid_partials(partials, indices, sigma_1, commitments, |p, vk| {
+        p.verify(messages.to_vec(), vk, params)
+    })?;
+    Ok((aggregate_partials(&valid, &ids)?, cheaters))
+}
+
+/// Same as `aggregate_partials_robust` but for the 2018 scheme with the agreed `m_prime` and `sigma_1`
+pub fn aggregate_partials_robust_2018(
+    partials: &[PartialSignature],
+    indices: &[usize],
+    messages: &[FieldElement],
+    m_prime: &FieldElement,
+    sigma_1: &SignatureGroup,
+    commitments: &FeldmanCommitments,
+    params: &Params,
+) -> Result<(Signature2018, Vec<usize>), PSError> {
+    let (valid, ids, cheaters) = select_valid_partials(partials, indices, sigma_1, commitments, |p, vk| {
+        p.verify_2018(messages.to_vec(), m_prime, vk, params)
+    })?;
+    Ok((aggregate_partials_2018(&valid, &ids, m_prime)?, cheaters))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dkg::tests::run_dkg;
+    use crate::dkg::DkgConfig;
+    use crate::keys::{keygen, keygen_2018, Scheme};
+
+    /// Signing key from `shares` by Lagrange interpolation
+    fn reconstruct_sigkey(shares: &[&SigkeyShare]) -> Sigkey {
+        let ids = shares.iter().map(|s| s.id).collect::<Vec<usize>>();
+        let mut x = FieldElement::zero();
+        let mut y = vec![FieldElement::zero(); shares[0].y.len()];
+        for s in shares {
+            let l = lagrange_coefficient(&ids, s.id).unwrap();
+            x += &s.x * &l;
+            for (j, y_j) in s.y.iter().enumerate() {
+                y[j] += y_j * &l;
+            }
+        }
+        Sigkey { x, y, metadata: None }
+    }
+
+    #[test]
+    fn test_feldman_vss() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let (shares, commitments) = share_sigkey(&sk, 3, 5, &params).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert_eq!(commitments.threshold(), 3);
+        assert!(commitments.is_for_verkey(&vk));
+        assert!(commitments.verkey().unwrap().Y_tilde == vk.Y_tilde);
+        for s in &shares {
+            assert!(verify_share(s, &commitments, &params));
+            assert_eq!(commitments.share_verkey(s.id).unwrap().X_tilde, &params.g_tilde * &s.x);
+        }
+
+        // Any 3 shares give the key, 2 don't
+        assert!(reconstruct_sigkey(&[&shares[0], &shares[2], &shares[4]]).is_key_for(&vk, &params));
+        assert!(reconstruct_sigkey(&[&shares[1], &shares[3], &shares[2]]).is_key_for(&vk, &params));
+        assert!(!reconstruct_sigkey(&[&shares[1], &shares[3]]).is_key_for(&vk, &params));
+
+        // Tampered share or share with another id fails
+        let mut bad = shares[1].clone();
+        bad.y[2] = FieldElement::random();
+        assert!(!verify_share(&bad, &commitments, &params));
+        let mut bad = shares[1].clone();
+        bad.id = 3;
+        assert!(!verify_share(&bad, &commitments, &params));
+
+        // Commitments for another key
+        let (_, vk_1) = keygen_2018(3, &params);
+        assert!(!commitments.is_for_verkey(&vk_1));
+
+        // Serializable
+        let json = serde_json::to_string(&commitments).unwrap();
+        let commitments_1: FeldmanCommitments = serde_json::from_str(&json).unwrap();
+        assert!(verify_share(&shares[0], &commitments_1, &params));
+
+        assert!(share_sigkey(&sk, 6, 5, &params).is_err());
+        assert!(lagrange_coefficient(&[1, 2, 2], 1).is_err());
+        assert!(lagrange_coefficient(&[1, 2], 3).is_err());
+    }
+
+    #[test]
+    fn test_share_refresh() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen_2018(3, &params);
+        let (shares, commitments) = share_sigkey(&sk, 2, 4, &params).unwrap();
+
+        // Participants 1, 2 and 4 refresh
+        let dealings = [1, 2, 4]
+            .iter()
+            .map(|id| refresh_dealing(*id, &commitments, 4, &params).unwrap())
+            .collect::<Vec<_>>();
+        let mut new_shares = vec![];
+        let mut new_commitments = vec![];
+        for s in &shares {
+            let refreshes = dealings
+                .iter()
+                .map(|(c, r)| (c.clone(), r[s.id - 1].clone()))
+                .collect::<Vec<_>>();
+            let (new_share, new_comm) = refresh_share(s, &commitments, &refreshes, &params).unwrap();
+            assert_ne!(new_share.x, s.x);
+            assert!(verify_share(&new_share, &new_comm, &params));
+            assert!(new_comm.is_for_verkey(&vk));
+            new_shares.push(new_share);
+            new_commitments.push(new_comm);
+        }
+        // Everyone computes the same commitments, old shares don't verify against them
+        for c in &new_commitments {
+            assert_eq!(c.commitments, new_commitments[0].commitments);
+        }
+        assert!(!verify_share(&shares[0], &new_commitments[0], &params));
+
+        // Refreshed shares give the same key, mixing old and new shares does not
+        assert!(reconstruct_sigkey(&[&new_shares[0], &new_shares[3]]).is_key_for(&vk, &params));
+        assert!(!reconstruct_sigkey(&[&shares[0], &new_shares[3]]).is_key_for(&vk, &params));
+
+        // Refresh messages are serializable
+        let json = serde_json::to_string(&dealings[0]).unwrap();
+        let (c, r): (RefreshCommitments, Vec<RefreshShare>) = serde_json::from_str(&json).unwrap();
+        assert!(r[2].verify(&c, &params));
+
+        // A refresh changing the key or a tampered share is rejected
+        let (_, bad_commitments) = share_sigkey(&sk, 2, 4, &params).unwrap();
+        let bad = RefreshCommitments {
+            sender: 3,
+            commitments: bad_commitments,
+        };
+        assert!(!bad.is_valid_for(&commitments));
+        assert!(commitments.refresh(&[bad]).is_err());
+        let (c, mut r) = dealings[1].clone();
+        r[0].shares[1] = FieldElement::random();
+        assert!(!r[0].verify(&c, &params));
+        assert!(refresh_share(&shares[0], &commitments, &[(c, r[0].clone())], &params).is_err());
+    }
+
+    #[test]
+    fn test_threshold_signing() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 4;
+        let messages = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (shares, _) = share_sigkey(&sk, 3, 5, &params).unwrap();
+
+        let sigma_1 = SignatureGroup::random();
+        let indices = vec![1, 3, 4];
+        let partials = indices
+            .iter()
+            .map(|i| PartialSignature::new(messages.as_slice(), &shares[i - 1], &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+        let sig = aggregate_partials(&partials, &indices).unwrap();
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+
+        // Sigma 1 derived from messages gives the deterministic signature
+        let sigma_1 = PartialSignature::sigma_1_for_messages(messages.as_slice());
+        let indices = vec![5, 2, 1];
+        let partials = indices
+            .iter()
+            .map(|i| PartialSignature::new(messages.as_slice(), &shares[i - 1], &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+        let sig = aggregate_partials(&partials, &indices).unwrap();
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+        let sig_1 = Signature::new_deterministic(messages.as_slice(), &sk).unwrap();
+        assert_eq!(sig.sigma_2, sig_1.sigma_2);
+
+        // Fewer than threshold partials or wrong ids don't give a valid signature
+        let sig = aggregate_partials(&partials[..2], &indices[..2]).unwrap();
+        assert!(!sig.verify(messages.clone(), &vk, &params).unwrap());
+        let sig = aggregate_partials(&partials, &[5, 2, 3]).unwrap();
+        assert!(!sig.verify(messages.clone(), &vk, &params).unwrap());
+        assert!(aggregate_partials(&partials, &[5, 2]).is_err());
+        let mut other = partials.clone();
+        other[1] = PartialSignature::new(messages.as_slice(), &shares[1], &SignatureGroup::random()).unwrap();
+        assert!(aggregate_partials(&other, &indices).is_err());
+
+        // 2018 scheme with shares from a DKG
+        let config = DkgConfig::new(2, 3, Scheme::PS2018, count_msgs).unwrap();
+        let outputs = run_dkg(config, &params, None, true);
+        let (m_prime, sigma_1) = PartialSignature::m_prime_and_sigma_1_for_messages(messages.as_slice());
+        let indices = vec![3, 1];
+        let partials = indices
+            .iter()
+            .map(|i| {
+                PartialSignature::new_2018(messages.as_slice(), &m_prime, &outputs[i - 1].share, &sigma_1).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let sig = aggregate_partials_2018(&partials, &indices, &m_prime).unwrap();
+        assert!(sig.verify(messages.clone(), &outputs[0].verkey, &params).unwrap());
+    }
+
+    #[test]
+    fn test_robust_threshold_signing() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let messages = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (shares, commitments) = share_sigkey(&sk, 3, 5, &params).unwrap();
+
+        let sigma_1 = PartialSignature::sigma_1_for_messages(messages.as_slice());
+        let indices = vec![1, 2, 3, 4, 5];
+        let mut partials = shares
+            .iter()
+            .map(|s| PartialSignature::new(messages.as_slice(), s, &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+        for (p, s) in partials.iter().zip(shares.iter()) {
+            assert!(p.verify(messages.clone(), &commitments.share_verkey(s.id).unwrap(), &params).unwrap());
+        }
+        // Partial signature checked against another share's verkey fails
+        assert!(!partials[0].verify(messages.clone(), &commitments.share_verkey(2).unwrap(), &params).unwrap());
+
+        // Participants 2 and 4 cheat, the other 3 are enough
+        partials[1].sigma_2 = SignatureGroup::random();
+        partials[3] = PartialSignature::new(messages.as_slice(), &shares[0], &sigma_1).unwrap();
+        let sig = aggregate_partials(&partials, &indices).unwrap();
+        assert!(!sig.verify(messages.clone(), &vk, &params).unwrap());
+        let (sig, cheaters) = aggregate_partials_robust(
+            &partials,
+            &indices,
+            messages.as_slice(),
+            &sigma_1,
+            &commitments,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(cheaters, vec![2, 4]);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+
+        // With a third cheater fewer than threshold are valid
+        let mut too_few = partials.clone();
+        too_few[4].sigma_2 = SignatureGroup::random();
+        assert!(aggregate_partials_robust(
+            &too_few,
+            &indices,
+            messages.as_slice(),
+            &sigma_1,
+            &commitments,
+            &params
+        )
+        .is_err());
+
+        // A duplicate of a valid partial signature is dropped and its id counted once
+        let mut with_duplicate = partials.clone();
+        with_duplicate.push(partials[0].clone());
+        let mut duplicate_indices = indices.clone();
+        duplicate_indices.push(1);
+        let (sig, cheaters) = aggregate_partials_robust(
+            &with_duplicate,
+            &duplicate_indices,
+            messages.as_slice(),
+            &sigma_1,
+            &commitments,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(cheaters, vec![2, 4]);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+
+        // 2018 scheme
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let (shares, commitments) = share_sigkey(&sk, 2, 3, &params).unwrap();
+        let (m_prime, sigma_1) = PartialSignature::m_prime_and_sigma_1_for_messages(messages.as_slice());
+        let mut partials = shares
+            .iter()
+            .map(|s| PartialSignature::new_2018(messages.as_slice(), &m_prime, s, &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+        partials[0].sigma_2 = SignatureGroup::random();
+        let (sig, cheaters) = aggregate_partials_robust_2018(
+            &partials,
+            &[1, 2, 3],
+            messages.as_slice(),
+            &m_prime,
+            &sigma_1,
+            &commitments,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(cheaters, vec![1]);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_robust_threshold_signing_different_sigma_1() {
+        let params = Params::new("test".as_bytes());
+        let count_msgs = 3;
+        let messages = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (shares, commitments) = share_sigkey(&sk, 3, 5, &params).unwrap();
+        let sigma_1 = PartialSignature::sigma_1_for_messages(messages.as_slice());
+        let indices = vec![1, 2, 3, 4, 5];
+        let mut partials = shares
+            .iter()
+            .map(|s| PartialSignature::new(messages.as_slice(), s, &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+
+        // Participant 3 signs correctly with its share but over another sigma_1 so its partial signature verifies
+        // with its share's verkey
+        let other_sigma_1 = SignatureGroup::random();
+        partials[2] = PartialSignature::new(messages.as_slice(), &shares[2], &other_sigma_1).unwrap();
+        assert!(partials[2]
+            .verify(messages.clone(), &commitments.share_verkey(3).unwrap(), &params)
+            .unwrap());
+        assert!(aggregate_partials(&partials, &indices).is_err());
+
+        let (sig, cheaters) = aggregate_partials_robust(
+            &partials,
+            &indices,
+            messages.as_slice(),
+            &sigma_1,
+            &commitments,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(cheaters, vec![3]);
+        assert_eq!(sig.sigma_1, sigma_1);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+
+        // Same for the 2018 scheme
+        let (sk, vk) = keygen_2018(count_msgs, &params);
+        let (shares, commitments) = share_sigkey(&sk, 2, 3, &params).unwrap();
+        let (m_prime, sigma_1) = PartialSignature::m_prime_and_sigma_1_for_messages(messages.as_slice());
+        let mut partials = shares
+            .iter()
+            .map(|s| PartialSignature::new_2018(messages.as_slice(), &m_prime, s, &sigma_1).unwrap())
+            .collect::<Vec<_>>();
+        partials[1] =
+            PartialSignature::new_2018(messages.as_slice(), &m_prime, &shares[1], &SignatureGroup::random())
+                .unwrap();
+        let (sig, cheaters) = aggregate_partials_robust_2018(
+            &partials,
+            &[1, 2, 3],
+            messages.as_slice(),
+            &m_prime,
+            &sigma_1,
+            &commitments,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(cheaters, vec![2]);
+        assert!(sig.verify(messages.clone(), &vk, &params).unwrap());
+    }
+
+    #[test]
+    fn test_empty_commitments() {
+        let commitments = FeldmanCommitments { commitments: vec![] };
+        assert!(commitments.verkey().is_err());
+        assert!(commitments.share_verkey(1).is_err());
+        let commitments = FeldmanCommitments {
+            commitments: vec![vec![], vec![]],
+        };
+        assert!(commitments.verkey().is_err());
+        assert!(commitments.share_verkey(1).is_err());
+    }
+}
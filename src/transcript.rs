@@ -0,0 +1,187 @@
+// Fiat-Shamir challenges for proofs of knowledge of signatures from a merlin `Transcript` rather than by hashing
+// `to_bytes` of the proof, so that the proof can be composed with other sigma protocols committing to the same
+// transcript and the challenge is domain separated by the transcript's labels. The prover commits with
+// `commit_to_transcript` of each proof of knowledge and gets the proof with `gen_proof_from_transcript`; the verifier
+// starts a transcript with the same label, commits the same data in the same order and verifies with
+// `verify_with_transcript`.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::pok_sig_2018::{PoKOfSignature as PoKOfSignature18, PoKOfSignatureProof as PoKOfSignatureProof18};
+use amcl_wrapper::field_elem::FieldElement;
+use merlin::Transcript;
+use std::collections::{HashMap, HashSet};
+
+/// Label of the commitments of a proof of knowledge of a signature of the 2016 scheme
+pub const POK_SIG_TRANSCRIPT_LABEL: &[u8] = b"PS-SIG-PoKOfSignature";
+
+/// Label of the commitments of a proof of knowledge of a signature of the 2018 scheme
+pub const POK_SIG_2018_TRANSCRIPT_LABEL: &[u8] = b"PS-SIG-PoKOfSignature-2018";
+
+/// Label of the challenge
+pub const CHALLENGE_TRANSCRIPT_LABEL: &[u8] = b"PS-SIG-challenge";
+
+/// Challenge as a field element from 64 bytes of the transcript
+pub fn challenge_from_transcript(transcript: &mut Transcript) -> FieldElement {
+    let mut buf = [0u8; 64];
+    transcript.challenge_bytes(CHALLENGE_TRANSCRIPT_LABEL, &mut buf);
+    FieldElement::from_msg_hash(&buf)
+}
+
+impl PoKOfSignature {
+    /// Append the randomized signature, J and the commitment of the proof to the transcript
+    pub fn commit_to_transcript(&self, transcript: &mut Transcript) {
+        transcript.append_message(POK_SIG_TRANSCRIPT_LABEL, &self.to_bytes());
+    }
+
+    /// Generate the proof with the challenge of the transcript, after all protocols committed to it
+    pub fn gen_proof_from_transcript(self, transcript: &mut Transcript) -> Result<PoKOfSignatureProof, PSError> {
+        let challenge = challenge_from_transcript(transcript);
+        self.gen_proof(&challenge)
+    }
+}
+
+impl PoKOfSignatureProof {
+    /// Append the same data as `PoKOfSignature::commit_to_transcript` to the verifier's transcript
+    pub fn commit_to_transcript(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        transcript: &mut Transcript,
+    ) {
+        transcript.append_message(
+            POK_SIG_TRANSCRIPT_LABEL,
+            &self.get_bytes_for_challenge(revealed_msg_indices, vk, params),
+        );
+    }
+
+    /// Commit to the transcript and verify with its challenge. When composed with other protocols, commit all of
+    /// them with `commit_to_transcript`, get the challenge with `challenge_from_transcript` and use `verify`.
+    pub fn verify_with_transcript(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        transcript: &mut Transcript,
+    ) -> Result<bool, PSError> {
+        self.commit_to_transcript(revealed_msgs.keys().cloned().collect(), vk, params, transcript);
+        let challenge = challenge_from_transcript(transcript);
+        self.verify(vk, params, revealed_msgs, &challenge)
+    }
+}
+
+impl PoKOfSignature18 {
+    /// Same as `PoKOfSignature::commit_to_transcript` for the 2018 scheme
+    pub fn commit_to_transcript(&self, transcript: &mut Transcript) {
+        transcript.append_message(POK_SIG_2018_TRANSCRIPT_LABEL, &self.to_bytes());
+    }
+
+    /// Same as `PoKOfSignature::gen_proof_from_transcript` for the 2018 scheme
+    pub fn gen_proof_from_transcript(self, transcript: &mut Transcript) -> Result<PoKOfSignatureProof18, PSError> {
+        let challenge = challenge_from_transcript(transcript);
+        self.gen_proof(&challenge)
+    }
+}
+
+impl PoKOfSignatureProof18 {
+    /// Same as `PoKOfSignatureProof::commit_to_transcript` for the 2018 scheme
+    pub fn commit_to_transcript(
+        &self,
+        revealed_msg_indices: HashSet<usize>,
+        vk: &Verkey,
+        params: &Params,
+        transcript: &mut Transcript,
+    ) {
+        transcript.append_message(
+            POK_SIG_2018_TRANSCRIPT_LABEL,
+            &self.get_bytes_for_challenge(revealed_msg_indices, vk, params),
+        );
+    }
+
+    /// Same as `PoKOfSignatureProof::verify_with_transcript` for the 2018 scheme
+    pub fn verify_with_transcript(
+        &self,
+        vk: &Verkey,
+        params: &Params,
+        revealed_msgs: HashMap<usize, FieldElement>,
+        transcript: &mut Transcript,
+    ) -> Result<bool, PSError> {
+        self.commit_to_transcript(revealed_msgs.keys().cloned().collect(), vk, params, transcript);
+        let challenge = challenge_from_transcript(transcript);
+        self.verify(vk, params, revealed_msgs, &challenge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{keygen, keygen_2018};
+    use crate::signature::Signature;
+    use crate::signature_2018::Signature as Signature18;
+
+    #[test]
+    fn test_pok_sig_with_transcript() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let msgs = (0..4).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(&msgs, &sk, &params).unwrap();
+        let mut revealed = HashSet::new();
+        revealed.insert(2);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(2, msgs[2].clone());
+
+        let pok = PoKOfSignature::init(&sig, &vk, &params, msgs.clone(), None, revealed.clone()).unwrap();
+        let mut transcript = Transcript::new(b"test presentation");
+        transcript.append_message(b"nonce", b"verifier nonce");
+        pok.commit_to_transcript(&mut transcript);
+        let proof = pok.gen_proof_from_transcript(&mut transcript).unwrap();
+
+        let mut transcript = Transcript::new(b"test presentation");
+        transcript.append_message(b"nonce", b"verifier nonce");
+        assert!(proof
+            .verify_with_transcript(&vk, &params, revealed_msgs.clone(), &mut transcript)
+            .unwrap());
+
+        // Different transcript label or content gives a different challenge
+        let mut transcript = Transcript::new(b"other presentation");
+        transcript.append_message(b"nonce", b"verifier nonce");
+        assert!(!proof
+            .verify_with_transcript(&vk, &params, revealed_msgs.clone(), &mut transcript)
+            .unwrap());
+        let mut transcript = Transcript::new(b"test presentation");
+        transcript.append_message(b"nonce", b"another nonce");
+        assert!(!proof
+            .verify_with_transcript(&vk, &params, revealed_msgs, &mut transcript)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_composed_proofs_with_transcript() {
+        // Proofs of both schemes under one transcript
+        let params = Params::new("test".as_bytes());
+        let (sk_1, vk_1) = keygen(3, &params);
+        let (sk_2, vk_2) = keygen_2018(2, &params);
+        let msgs_1 = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let msgs_2 = (0..2).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig_1 = Signature::new(&msgs_1, &sk_1, &params).unwrap();
+        let sig_2 = Signature18::new(&msgs_2, &sk_2).unwrap();
+
+        let pok_1 = PoKOfSignature::init(&sig_1, &vk_1, &params, msgs_1, None, HashSet::new()).unwrap();
+        let pok_2 = PoKOfSignature18::init(&sig_2, &vk_2, &params, msgs_2, None, HashSet::new()).unwrap();
+        let mut transcript = Transcript::new(b"composed");
+        pok_1.commit_to_transcript(&mut transcript);
+        pok_2.commit_to_transcript(&mut transcript);
+        let challenge = challenge_from_transcript(&mut transcript);
+        let proof_1 = pok_1.gen_proof(&challenge).unwrap();
+        let proof_2 = pok_2.gen_proof(&challenge).unwrap();
+
+        let mut transcript = Transcript::new(b"composed");
+        proof_1.commit_to_transcript(HashSet::new(), &vk_1, &params, &mut transcript);
+        proof_2.commit_to_transcript(HashSet::new(), &vk_2, &params, &mut transcript);
+        let challenge = challenge_from_transcript(&mut transcript);
+        assert!(proof_1.verify(&vk_1, &params, HashMap::new(), &challenge).unwrap());
+        assert!(proof_2.verify(&vk_2, &params, HashMap::new(), &challenge).unwrap());
+    }
+}
@@ -0,0 +1,255 @@
+// Validation of group elements received from untrusted sources. Each group element should not be the identity and
+// should be in the prime order subgroup, else a malicious party can use elements of small order to learn secrets
+// (like blindings) or make degenerate signatures and proofs verify. The `from_bytes` methods validate what they
+// parse and so does serde deserialization of `Verkey`, `Signature` (and so the 2018 signature) and
+// `PoKOfSignatureProof`; for other types, call `validate` on the result. Verifiers should validate params of
+// third-party issuers before use.
+
+use crate::errors::PSError;
+use crate::keys::{CommitmentGenerators, Params, Verkey};
+use crate::pok_sig::PoKOfSignatureProof;
+use crate::signature::Signature;
+use crate::signature_2018::Signature as Signature18;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use std::collections::HashSet;
+
+pub trait Validate {
+    fn validate(&self) -> Result<(), PSError>;
+}
+
+/// Deserialize the fields `F` of a `T`, build it with `build` and validate it. Used by the `Deserialize` of types
+/// which are received from untrusted sources so that an invalid one is rejected when parsed.
+pub(crate) fn deserialize_validated<'de, D, F, T, B>(deserializer: D, build: B) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    F: serde::Deserialize<'de>,
+    T: Validate,
+    B: FnOnce(F) -> T,
+{
+    let value = build(F::deserialize(deserializer)?);
+    value.validate().map_err(serde::de::Error::custom)?;
+    Ok(value)
+}
+
+impl Validate for G1 {
+    fn validate(&self) -> Result<(), PSError> {
+        if self.is_identity() {
+            return Err(PSError::InvalidGroupElement {
+                msg: String::from("Element of G1 is identity"),
+            });
+        }
+        if !self.has_correct_order() {
+            return Err(PSError::InvalidGroupElement {
+                msg: String::from("Element of G1 is not in the prime order subgroup"),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Validate for G2 {
+    fn validate(&self) -> Result<(), PSError> {
+        if self.is_identity() {
+            return Err(PSError::InvalidGroupElement {
+                msg: String::from("Element of G2 is identity"),
+            });
+        }
+        if !self.has_correct_order() {
+            return Err(PSError::InvalidGroupElement {
+                msg: String::from("Element of G2 is not in the prime order subgroup"),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Validate for Params {
+    fn validate(&self) -> Result<(), PSError> {
+        self.g.validate()?;
+        self.g_tilde.validate()?;
+        if let Some(gens) = &self.commitment_gens {
+            gens.validate()?;
+            if gens.h_0 == self.g || gens.h.contains(&self.g) {
+                return Err(PSError::InvalidGroupElement {
+                    msg: String::from("Commitment generator is equal to g"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for CommitmentGenerators {
+    /// Each generator is validated and should be different from the others
+    fn validate(&self) -> Result<(), PSError> {
+        self.h_0.validate()?;
+        let mut seen = HashSet::with_capacity(self.h.len() + 1);
+        seen.insert(self.h_0.to_bytes());
+        for (i, h) in self.h.iter().enumerate() {
+            h.validate()?;
+            if !seen.insert(h.to_bytes()) {
+                return Err(PSError::InvalidGroupElement {
+                    msg: format!("Commitment generator h[{}] is repeated", i),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for Verkey {
+    /// Besides validating each element, checks that Y_tilde is not empty and that no two elements among X_tilde
+    /// and Y_tilde are equal since equal elements make the corresponding messages (or x) interchangeable
+    fn validate(&self) -> Result<(), PSError> {
+        if self.Y_tilde.is_empty() {
+            return Err(PSError::InvalidVerkey {
+                msg: String::from("Y_tilde is empty"),
+            });
+        }
+        self.X_tilde.validate()?;
+        for y in &self.Y_tilde {
+            y.validate()?;
+        }
+        let mut seen = HashSet::with_capacity(self.Y_tilde.len() + 1);
+        seen.insert(self.X_tilde.to_bytes());
+        for (i, y) in self.Y_tilde.iter().enumerate() {
+            if !seen.insert(y.to_bytes()) {
+                return Err(PSError::InvalidVerkey {
+                    msg: format!("Y_tilde[{}] is equal to X_tilde or an earlier element of Y_tilde", i),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validate for Signature {
+    fn validate(&self) -> Result<(), PSError> {
+        self.sigma_1.validate()?;
+        self.sigma_2.validate()
+    }
+}
+
+impl Validate for Signature18 {
+    fn validate(&self) -> Result<(), PSError> {
+        self.sig.validate()
+    }
+}
+
+impl Validate for PoKOfSignatureProof {
+    fn validate(&self) -> Result<(), PSError> {
+        self.sig.validate()?;
+        self.J.validate()?;
+        self.proof_vc.commitment.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::{SignatureGroup, VerkeyGroup};
+    use amcl_wrapper::field_elem::FieldElement;
+
+    #[test]
+    fn test_validate() {
+        let params = Params::new("test".as_bytes());
+        params.validate().unwrap();
+        let (sk, vk) = keygen(3, &params);
+        vk.validate().unwrap();
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        sig.validate().unwrap();
+
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde[1] = VerkeyGroup::identity();
+        match bad_vk.validate() {
+            Err(PSError::InvalidGroupElement { .. }) => (),
+            _ => panic!("Identity should be rejected"),
+        }
+
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde[2] = bad_vk.Y_tilde[0].clone();
+        match bad_vk.validate() {
+            Err(PSError::InvalidVerkey { .. }) => (),
+            _ => panic!("Duplicate Y_tilde should be rejected"),
+        }
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde[1] = bad_vk.X_tilde.clone();
+        assert!(bad_vk.validate().is_err());
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde.clear();
+        match bad_vk.validate() {
+            Err(PSError::InvalidVerkey { .. }) => (),
+            _ => panic!("Empty Y_tilde should be rejected"),
+        }
+        assert!(Verkey::from_bytes(&bad_vk.to_bytes(crate::keys::Scheme::PS2016)).is_err());
+
+        let bad_params = Params {
+            g: params.g.clone(),
+            g_tilde: VerkeyGroup::identity(),
+            commitment_gens: None,
+        };
+        assert!(bad_params.validate().is_err());
+        let params_with_gens = Params::new_with_commitment_generators("test".as_bytes(), 3);
+        params_with_gens.validate().unwrap();
+        let mut bad_params = params_with_gens.clone();
+        let mut gens = bad_params.commitment_gens.unwrap();
+        gens.h[2] = gens.h[0].clone();
+        bad_params.commitment_gens = Some(gens);
+        assert!(bad_params.validate().is_err());
+        let mut bad_params = params_with_gens.clone();
+        let mut gens = bad_params.commitment_gens.unwrap();
+        gens.h_0 = params.g.clone();
+        bad_params.commitment_gens = Some(gens);
+        assert!(bad_params.validate().is_err());
+
+        let bad_sig = Signature {
+            sigma_1: SignatureGroup::identity(),
+            sigma_2: SignatureGroup::identity(),
+        };
+        assert!(bad_sig.validate().is_err());
+        // Parsing rejects identity
+        assert!(Signature::from_bytes(&bad_sig.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_validate_on_deserialize() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(3, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let pok = crate::pok_sig::PoKOfSignature::init(&sig, &vk, &params, msgs, None, HashSet::new()).unwrap();
+        let proof = pok.gen_proof(&FieldElement::random()).unwrap();
+
+        let vk_1: Verkey = serde_json::from_str(&serde_json::to_string(&vk).unwrap()).unwrap();
+        assert_eq!(vk_1.Y_tilde, vk.Y_tilde);
+        let _: Signature = serde_json::from_str(&serde_json::to_string(&sig).unwrap()).unwrap();
+        let _: PoKOfSignatureProof = serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde[1] = VerkeyGroup::identity();
+        assert!(serde_json::from_str::<Verkey>(&serde_json::to_string(&bad_vk).unwrap()).is_err());
+        let mut bad_vk = vk.clone();
+        bad_vk.Y_tilde.clear();
+        assert!(serde_json::from_str::<Verkey>(&serde_json::to_string(&bad_vk).unwrap()).is_err());
+
+        let mut bad_sig = sig.clone();
+        bad_sig.sigma_1 = SignatureGroup::identity();
+        assert!(serde_json::from_str::<Signature>(&serde_json::to_string(&bad_sig).unwrap()).is_err());
+        let bad_sig_2018 = Signature18 {
+            m_prime: FieldElement::random(),
+            sig: bad_sig.clone(),
+        };
+        assert!(serde_json::from_str::<Signature18>(&serde_json::to_string(&bad_sig_2018).unwrap()).is_err());
+
+        let mut bad_proof = proof.clone();
+        bad_proof.J = VerkeyGroup::identity();
+        assert!(serde_json::from_str::<PoKOfSignatureProof>(&serde_json::to_string(&bad_proof).unwrap()).is_err());
+        let mut bad_proof = proof;
+        bad_proof.sig = bad_sig;
+        assert!(serde_json::from_str::<PoKOfSignatureProof>(&serde_json::to_string(&bad_proof).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+// Credentials (2016 scheme) with an expiry signed as the last message. A key for n messages signs n - 1 messages
+// followed by the expiry encoded as a field element, in any unit of time agreed by the issuer and verifiers (seconds
+// since the UNIX epoch or the number of a validity epoch); the current time is passed by the caller so the library
+// does not depend on a clock. In a proof of knowledge of the signature the expiry is revealed along with the messages
+// chosen by the holder so the verifier can check it, proving the expiry is in the future without revealing it would
+// need a range proof which is not provided.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Signer, Verkey};
+use crate::pok_sig::{PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use amcl_wrapper::field_elem::FieldElement;
+use std::collections::{HashMap, HashSet};
+
+/// Index of the expiry for a key of `key_size` messages, the last one
+pub fn expiry_index(key_size: usize) -> usize {
+    key_size.saturating_sub(1)
+}
+
+/// The expiry as the message signed
+pub fn expiry_message(expiry: u64) -> FieldElement {
+    FieldElement::from(expiry)
+}
+
+fn with_expiry(messages: &[FieldElement], expiry: u64) -> Vec<FieldElement> {
+    let mut msgs = messages.to_vec();
+    msgs.push(expiry_message(expiry));
+    msgs
+}
+
+fn check_expiry(expiry: u64, now: u64) -> Result<(), PSError> {
+    if now > expiry {
+        return Err(PSError::CredentialExpired { expiry, now });
+    }
+    Ok(())
+}
+
+/// Sign `messages` followed by `expiry` with a key for one message more than `messages`
+pub fn sign_with_validity<S: Signer>(
+    messages: &[FieldElement],
+    expiry: u64,
+    sigkey: &S,
+    params: &Params,
+) -> Result<Signature, PSError> {
+    Signature::new(&with_expiry(messages, expiry), sigkey, params)
+}
+
+/// Verify a signature created with `sign_with_validity`. Fails with `PSError::CredentialExpired` if `now` is past
+/// `expiry`, otherwise returns whether the signature is valid.
+pub fn verify_not_expired(
+    sig: &Signature,
+    messages: &[FieldElement],
+    expiry: u64,
+    now: u64,
+    vk: &Verkey,
+    params: &Params,
+) -> Result<bool, PSError> {
+    check_expiry(expiry, now)?;
+    sig.verify(with_expiry(messages, expiry), vk, params)
+}
+
+/// `revealed_msg_indices` with the index of the expiry added, for the prover's and verifier's challenge
+pub fn revealed_indices_with_expiry(mut revealed_msg_indices: HashSet<usize>, vk: &Verkey) -> HashSet<usize> {
+    revealed_msg_indices.insert(expiry_index(vk.Y_tilde.len()));
+    revealed_msg_indices
+}
+
+/// Start a proof of knowledge of a signature created with `sign_with_validity` revealing the expiry and the messages
+/// at `revealed_msg_indices`
+pub fn init_pok_with_expiry(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: &[FieldElement],
+    expiry: u64,
+    revealed_msg_indices: HashSet<usize>,
+) -> Result<PoKOfSignature, PSError> {
+    PoKOfSignature::init(
+        sig,
+        vk,
+        params,
+        with_expiry(messages, expiry),
+        None,
+        revealed_indices_with_expiry(revealed_msg_indices, vk),
+    )
+}
+
+/// Verify a proof created with `init_pok_with_expiry` with the revealed `expiry` and messages. Fails with
+/// `PSError::CredentialExpired` if `now` is past `expiry`, otherwise returns whether the proof is valid.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_pok_not_expired(
+    proof: &PoKOfSignatureProof,
+    vk: &Verkey,
+    params: &Params,
+    mut revealed_msgs: HashMap<usize, FieldElement>,
+    expiry: u64,
+    now: u64,
+    challenge: &FieldElement,
+) -> Result<bool, PSError> {
+    check_expiry(expiry, now)?;
+    revealed_msgs.insert(expiry_index(vk.Y_tilde.len()), expiry_message(expiry));
+    proof.verify(vk, params, revealed_msgs, challenge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_credential_with_expiry() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(4, &params);
+        let msgs = (0..3).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let expiry = 1_700_000_000;
+        let sig = sign_with_validity(&msgs, expiry, &sk, &params).unwrap();
+        assert!(verify_not_expired(&sig, &msgs, expiry, expiry - 10, &vk, &params).unwrap());
+        assert!(verify_not_expired(&sig, &msgs, expiry, expiry, &vk, &params).unwrap());
+        match verify_not_expired(&sig, &msgs, expiry, expiry + 1, &vk, &params) {
+            Err(PSError::CredentialExpired { .. }) => (),
+            _ => panic!("Expected CredentialExpired"),
+        }
+        // Claiming a later expiry fails
+        assert!(!verify_not_expired(&sig, &msgs, expiry + 100, expiry + 1, &vk, &params).unwrap());
+        assert!(sign_with_validity(&msgs[..2], expiry, &sk, &params).is_err());
+
+        // Prove knowledge of the signature revealing only the expiry and the message at 1
+        let mut revealed = HashSet::new();
+        revealed.insert(1);
+        let pok = init_pok_with_expiry(&sig, &vk, &params, &msgs, expiry, revealed.clone()).unwrap();
+        let challenge = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof = pok.gen_proof(&challenge).unwrap();
+
+        let chal_bytes = proof.get_bytes_for_challenge(revealed_indices_with_expiry(revealed, &vk), &vk, &params);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(1, msgs[1].clone());
+        assert!(
+            verify_pok_not_expired(&proof, &vk, &params, revealed_msgs.clone(), expiry, expiry - 10, &challenge)
+                .unwrap()
+        );
+        assert!(
+            !verify_pok_not_expired(&proof, &vk, &params, revealed_msgs.clone(), expiry + 1, expiry - 10, &challenge)
+                .unwrap()
+        );
+        assert!(verify_pok_not_expired(&proof, &vk, &params, revealed_msgs, expiry, expiry + 1, &challenge).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// Credentials over large attribute sets where the issuer signs a single message, the hash of a Pedersen vector
+// commitment C = g_1^a_1 * g_2^a_2 * ... g_n^a_n * h^r to the attributes, so the issuer's key has a constant size
+// (1 message) regardless of the number of attributes. The commitment generators are the `CommitmentGenerators` of the
+// `Params` and are not part of the issuer's key. During presentation, the holder reveals C, proves knowledge of a
+// signature over the hash of C and proves knowledge of the opening of C with the selected attributes revealed.
+// Since C (and the signed message) is revealed in each presentation, presentations of the same credential are
+// linkable. Use the regular multi-message credentials when unlinkability is needed.
+
+use crate::blind_signature::ProofSignatureGroup;
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::pok_sig::{PoKOfSignatureAndCommitment, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::{SignatureGroup, SignatureGroupVec};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use std::collections::{HashMap, HashSet};
+
+/// Domain separation tag for hashing the commitment to the signed message
+pub const VECTOR_COMMITMENT_HASH_DST: &[u8] = b"PS-SIG-VECTOR-COMMITMENT";
+
+/// Generators of the vector commitment, one for each attribute and `h` for the blinding
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VectorCommitmentParams {
+    pub gens: Vec<SignatureGroup>,
+    pub h: SignatureGroup,
+}
+
+/// Credential held by the holder
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VectorCommitmentCredential {
+    pub sig: Signature,
+    pub commitment: SignatureGroup,
+    pub attributes: Vec<FieldElement>,
+    pub blinding: FieldElement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VectorCommitmentPresentation {
+    pub commitment: SignatureGroup,
+    /// Proof of knowledge of the signature over the hash of `commitment`, which is revealed
+    pub pok_sig: PoKOfSignatureProof,
+    /// Proof of knowledge of the opening of `commitment` divided by the revealed attributes' terms
+    pub pok_opening: ProofSignatureGroup,
+}
+
+impl VectorCommitmentParams {
+    /// The generators of the commitment generators of `params`, `CommitmentGenerators::h` for the attributes and `h_0`
+    /// for the blinding, so the commitment is the one computed with `CommitmentGenerators::commit`. Fails if `params`
+    /// has no commitment generators, use `Params::new_with_commitment_generators` with the no of attributes.
+    pub fn new(params: &Params) -> Result<Self, PSError> {
+        match &params.commitment_gens {
+            Some(gens) => Ok(Self {
+                gens: gens.h.clone(),
+                h: gens.h_0.clone(),
+            }),
+            None => Err(PSError::GeneralError {
+                msg: String::from("Params should have commitment generators"),
+            }),
+        }
+    }
+
+    pub fn attribute_count(&self) -> usize {
+        self.gens.len()
+    }
+
+    pub fn commit(&self, attributes: &[FieldElement], blinding: &FieldElement) -> Result<SignatureGroup, PSError> {
+        if attributes.len() != self.attribute_count() {
+            return Err(PSError::UnsupportedNoOfMessages {
+                expected: self.attribute_count(),
+                given: attributes.len(),
+            });
+        }
+        let mut bases = SignatureGroupVec::with_capacity(attributes.len() + 1);
+        let mut exponents = FieldElementVector::with_capacity(attributes.len() + 1);
+        for (g, a) in self.gens.iter().zip(attributes.iter()) {
+            bases.push(g.clone());
+            exponents.push(a.clone());
+        }
+        bases.push(self.h.clone());
+        exponents.push(blinding.clone());
+        Ok(bases.multi_scalar_mul_const_time(&exponents).unwrap())
+    }
+
+    /// Message signed by the issuer
+    pub fn commitment_to_message(commitment: &SignatureGroup) -> FieldElement {
+        FieldElement::from_msg_hash(&[VECTOR_COMMITMENT_HASH_DST, &commitment.to_bytes()].concat())
+    }
+}
+
+impl VectorCommitmentCredential {
+    /// Called by the issuer. `sigkey` should be for a single message.
+    pub fn issue(
+        attributes: Vec<FieldElement>,
+        sigkey: &Sigkey,
+        vc_params: &VectorCommitmentParams,
+        params: &Params,
+    ) -> Result<Self, PSError> {
+        let blinding = FieldElement::random();
+        let commitment = vc_params.commit(&attributes, &blinding)?;
+        let msg = VectorCommitmentParams::commitment_to_message(&commitment);
+        let sig = Signature::new(&[msg], sigkey, params)?;
+        Ok(Self {
+            sig,
+            commitment,
+            attributes,
+            blinding,
+        })
+    }
+
+    /// Called by the holder on receiving the credential
+    pub fn verify(&self, vk: &Verkey, vc_params: &VectorCommitmentParams, params: &Params) -> Result<bool, PSError> {
+        if vc_params.commit(&self.attributes, &self.blinding)? != self.commitment {
+            return Ok(false);
+        }
+        let msg = VectorCommitmentParams::commitment_to_message(&self.commitment);
+        self.sig.verify(vec![msg], vk, params)
+    }
+
+    /// Present the credential revealing the attributes at `revealed_indices`
+    pub fn present(
+        &self,
+        revealed_indices: &HashSet<usize>,
+        vk: &Verkey,
+        vc_params: &VectorCommitmentParams,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<VectorCommitmentPresentation, PSError> {
+        let hidden = hidden_indices(revealed_indices, vc_params.attribute_count())?;
+        let msg = VectorCommitmentParams::commitment_to_message(&self.commitment);
+        let mut revealed_msg = HashSet::new();
+        revealed_msg.insert(0);
+        // The commitment is to the hidden attributes and the blinding, no message of the signature is in it
+        let mut others = Vec::with_capacity(hidden.len() + 1);
+        for i in &hidden {
+            others.push((vc_params.gens[*i].clone(), self.attributes[*i].clone()));
+        }
+        others.push((vc_params.h.clone(), self.blinding.clone()));
+        let pok = PoKOfSignatureAndCommitment::init(&self.sig, vk, params, vec![msg], revealed_msg, &[], &others)?;
+
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.append(&mut self.commitment.to_bytes());
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        let (pok_sig, pok_opening) = pok.gen_proof(&challenge)?;
+        Ok(VectorCommitmentPresentation {
+            commitment: self.commitment.clone(),
+            pok_sig,
+            pok_opening,
+        })
+    }
+}
+
+impl VectorCommitmentPresentation {
+    pub fn verify(
+        &self,
+        revealed_attributes: &HashMap<usize, FieldElement>,
+        vk: &Verkey,
+        vc_params: &VectorCommitmentParams,
+        params: &Params,
+        nonce: &[u8],
+    ) -> Result<bool, PSError> {
+        let revealed_indices = revealed_attributes.keys().cloned().collect::<HashSet<usize>>();
+        let hidden = hidden_indices(&revealed_indices, vc_params.attribute_count())?;
+
+        let mut bases = Vec::with_capacity(hidden.len() + 1);
+        for i in &hidden {
+            bases.push(vc_params.gens[*i].clone());
+        }
+        bases.push(vc_params.h.clone());
+
+        // Remove the revealed attributes from the commitment
+        let mut reduced_commitment = self.commitment.clone();
+        for (i, a) in revealed_attributes {
+            reduced_commitment = reduced_commitment - (&vc_params.gens[*i] * a);
+        }
+
+        let mut revealed_msg = HashSet::new();
+        revealed_msg.insert(0);
+        let mut chal_bytes = self.pok_sig.get_bytes_for_challenge_with_commitment(
+            revealed_msg,
+            vk,
+            params,
+            &bases,
+            &self.pok_opening,
+            &reduced_commitment,
+        );
+        chal_bytes.append(&mut self.commitment.to_bytes());
+        chal_bytes.extend_from_slice(nonce);
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, VectorCommitmentParams::commitment_to_message(&self.commitment));
+        self.pok_sig.verify_with_commitment(
+            vk,
+            params,
+            revealed_msgs,
+            &self.pok_opening,
+            &bases,
+            &reduced_commitment,
+            &[],
+            &challenge,
+        )
+    }
+}
+
+/// Indices of hidden attributes in increasing order
+fn hidden_indices(revealed_indices: &HashSet<usize>, attribute_count: usize) -> Result<Vec<usize>, PSError> {
+    for i in revealed_indices {
+        if *i >= attribute_count {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, attribute_count),
+            });
+        }
+    }
+    Ok((0..attribute_count)
+        .filter(|i| !revealed_indices.contains(i))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_vector_commitment_credential() {
+        let attribute_count = 200;
+        let params = Params::new_with_commitment_generators("test".as_bytes(), attribute_count);
+        let vc_params = VectorCommitmentParams::new(&params).unwrap();
+        assert!(VectorCommitmentParams::new(&Params::new("test".as_bytes())).is_err());
+        // Issuer key is for a single message
+        let (sk, vk) = keygen(1, &params);
+
+        let attributes = (0..attribute_count)
+            .map(|_| FieldElement::random())
+            .collect::<Vec<FieldElement>>();
+        let cred = VectorCommitmentCredential::issue(attributes.clone(), &sk, &vc_params, &params).unwrap();
+        assert!(cred.verify(&vk, &vc_params, &params).unwrap());
+        // Same commitment as with the commitment generators of the params
+        let all_attributes = attributes.iter().cloned().enumerate().collect::<HashMap<usize, FieldElement>>();
+        let gens = params.commitment_gens.as_ref().unwrap();
+        assert_eq!(gens.commit(&all_attributes, &cred.blinding).unwrap(), cred.commitment);
+
+        let revealed_indices = vec![3, 50, 199].into_iter().collect::<HashSet<usize>>();
+        let revealed_attributes = revealed_indices
+            .iter()
+            .map(|i| (*i, attributes[*i].clone()))
+            .collect::<HashMap<usize, FieldElement>>();
+        let nonce = FieldElement::random().to_bytes();
+        let presentation = cred
+            .present(&revealed_indices, &vk, &vc_params, &params, &nonce)
+            .unwrap();
+        assert!(presentation
+            .verify(&revealed_attributes, &vk, &vc_params, &params, &nonce)
+            .unwrap());
+
+        // Wrong revealed attribute
+        let mut wrong_attributes = revealed_attributes.clone();
+        wrong_attributes.insert(50, FieldElement::random());
+        assert!(!presentation
+            .verify(&wrong_attributes, &vk, &vc_params, &params, &nonce)
+            .unwrap());
+
+        // Different nonce
+        assert!(!presentation
+            .verify(&revealed_attributes, &vk, &vc_params, &params, "other".as_bytes())
+            .unwrap());
+
+        // Commitment not signed by the issuer
+        let (sk_1, _) = keygen(1, &params);
+        let cred_1 = VectorCommitmentCredential::issue(attributes, &sk_1, &vc_params, &params).unwrap();
+        let presentation_1 = cred_1
+            .present(&revealed_indices, &vk, &vc_params, &params, &nonce)
+            .unwrap();
+        assert!(!presentation_1
+            .verify(&revealed_attributes, &vk, &vc_params, &params, &nonce)
+            .unwrap());
+    }
+}
@@ -0,0 +1,404 @@
+// ElGamal encryption of a message to an auditor and proof of correct decryption by the auditor.
+// A message m is encrypted "in the exponent" of a base, usually the `Y_tilde` element of the verkey corresponding
+// to the message's index, so the plaintext is `base^m`. Since messages are field elements, the auditor recovers
+// `base^m` and not m, but that is enough to check a claimed message or link the ciphertext to a presentation.
+// The auditor can prove that the decryption was done correctly using a proof of equality of discrete logs
+// (Chaum-Pedersen) which anyone with the ciphertext and the auditor's public key can verify. The challenge of the
+// decryption proof includes the challenge of the presentation the ciphertext came from, so the proof can't be
+// presented as one for the same ciphertext in another presentation.
+// The holder encrypts a hidden message of a presentation and proves, with the same blinding for the message as in the
+// proof of knowledge of the signature, that the ciphertext contains the signed message (`init_pok_with_encryption`).
+// Each presentation has a fresh ciphertext so presentations stay unlinkable for everyone but the auditor.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Verkey};
+use crate::pok_sig::{hidden_message_position, PoKOfSignature, PoKOfSignatureProof};
+use crate::signature::Signature;
+use crate::VerkeyGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditorSecretKey {
+    pub s: FieldElement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditorPublicKey {
+    pub pk: VerkeyGroup,
+}
+
+/// Generate auditor's secret key s and public key g_tilde^s
+pub fn auditor_keygen(params: &Params) -> (AuditorSecretKey, AuditorPublicKey) {
+    let s = FieldElement::random();
+    let pk = &params.g_tilde * &s;
+    (AuditorSecretKey { s }, AuditorPublicKey { pk })
+}
+
+/// ElGamal ciphertext (g_tilde^r, base^m * pk^r)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ciphertext {
+    pub c_1: VerkeyGroup,
+    pub c_2: VerkeyGroup,
+}
+
+impl Ciphertext {
+    /// Encrypt `message` as `base^message` for the auditor. Returns the ciphertext and the randomness used
+    /// which is needed to prove what the ciphertext contains.
+    pub fn new(
+        message: &FieldElement,
+        base: &VerkeyGroup,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> (Self, FieldElement) {
+        let r = FieldElement::random();
+        let c_1 = &params.g_tilde * &r;
+        let c_2 = (base * message) + (&auditor_pk.pk * &r);
+        (Self { c_1, c_2 }, r)
+    }
+
+    /// Decrypt to get `base^message`
+    pub fn decrypt(&self, auditor_sk: &AuditorSecretKey) -> VerkeyGroup {
+        &self.c_2 - (&self.c_1 * &auditor_sk.s)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.append(&mut self.c_1.to_bytes());
+        bytes.append(&mut self.c_2.to_bytes());
+        bytes
+    }
+}
+
+/// Proof that log_{g_tilde}(pk) == log_{c_1}(c_2 / plaintext), i.e. the plaintext is the correct decryption, bound to
+/// the challenge of the presentation that carried the ciphertext
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DecryptionProof {
+    pub t_1: VerkeyGroup,
+    pub t_2: VerkeyGroup,
+    pub response: FieldElement,
+}
+
+impl DecryptionProof {
+    /// Called by the auditor. Returns the decrypted value along with the proof of correct decryption.
+    /// `presentation_challenge` is the challenge of the presentation that carried the ciphertext.
+    pub fn new(
+        ciphertext: &Ciphertext,
+        presentation_challenge: &FieldElement,
+        auditor_sk: &AuditorSecretKey,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> (VerkeyGroup, Self) {
+        let plaintext = ciphertext.decrypt(auditor_sk);
+        let k = FieldElement::random();
+        let t_1 = &params.g_tilde * &k;
+        let t_2 = &ciphertext.c_1 * &k;
+        let challenge = Self::compute_challenge(
+            ciphertext,
+            presentation_challenge,
+            &plaintext,
+            &t_1,
+            &t_2,
+            auditor_pk,
+            params,
+        );
+        let response = &k - (&challenge * &auditor_sk.s);
+        (plaintext, Self { t_1, t_2, response })
+    }
+
+    /// Verify that `plaintext` is the correct decryption of `ciphertext` of the presentation with challenge
+    /// `presentation_challenge`
+    pub fn verify(
+        &self,
+        ciphertext: &Ciphertext,
+        presentation_challenge: &FieldElement,
+        plaintext: &VerkeyGroup,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> bool {
+        let challenge = Self::compute_challenge(
+            ciphertext,
+            presentation_challenge,
+            plaintext,
+            &self.t_1,
+            &self.t_2,
+            auditor_pk,
+            params,
+        );
+        // g_tilde^response * pk^challenge == t_1
+        let t_1 = (&params.g_tilde * &self.response) + (&auditor_pk.pk * &challenge);
+        // c_1^response * (c_2 / plaintext)^challenge == t_2
+        let t_2 = (&ciphertext.c_1 * &self.response)
+            + ((&ciphertext.c_2 - plaintext) * &challenge);
+        t_1 == self.t_1 && t_2 == self.t_2
+    }
+
+    /// Verify that `ciphertext` decrypts to `base^message`. Used by third parties to check the auditor's
+    /// claim that a presentation contained a particular message.
+    pub fn verify_for_message(
+        &self,
+        ciphertext: &Ciphertext,
+        presentation_challenge: &FieldElement,
+        message: &FieldElement,
+        base: &VerkeyGroup,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> Result<bool, PSError> {
+        if base.is_identity() {
+            return Err(PSError::GeneralError {
+                msg: String::from("Base for message should not be identity"),
+            });
+        }
+        Ok(self.verify(ciphertext, presentation_challenge, &(base * message), auditor_pk, params))
+    }
+
+    fn compute_challenge(
+        ciphertext: &Ciphertext,
+        presentation_challenge: &FieldElement,
+        plaintext: &VerkeyGroup,
+        t_1: &VerkeyGroup,
+        t_2: &VerkeyGroup,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> FieldElement {
+        let mut bytes = vec![];
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut auditor_pk.pk.to_bytes());
+        bytes.append(&mut ciphertext.to_bytes());
+        bytes.append(&mut presentation_challenge.to_bytes());
+        bytes.append(&mut plaintext.to_bytes());
+        bytes.append(&mut t_1.to_bytes());
+        bytes.append(&mut t_2.to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+/// Prover's state of the proof that a ciphertext contains a hidden message of a proof of knowledge of a signature
+#[derive(Clone, Debug)]
+pub struct EncryptionCommitted {
+    r: FieldElement,
+    k: FieldElement,
+    pub ciphertext: Ciphertext,
+    pub t_1: VerkeyGroup,
+    pub t_2: VerkeyGroup,
+}
+
+/// Proof of knowledge of m and r such that the ciphertext is (g_tilde^r, base^m * pk^r), the response for m is
+/// the one in the proof of knowledge of the signature
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EncryptionProof {
+    pub ciphertext: Ciphertext,
+    pub t_1: VerkeyGroup,
+    pub t_2: VerkeyGroup,
+    pub response: FieldElement,
+}
+
+impl EncryptionCommitted {
+    /// Encrypt `message` as `base^message` for the auditor and commit with `blinding`, the blinding of the message
+    /// in the proof of knowledge of the signature
+    pub fn new(
+        message: &FieldElement,
+        blinding: &FieldElement,
+        base: &VerkeyGroup,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+    ) -> Self {
+        let (ciphertext, r) = Ciphertext::new(message, base, auditor_pk, params);
+        let k = FieldElement::random();
+        let t_1 = &params.g_tilde * &k;
+        let t_2 = (base * blinding) + (&auditor_pk.pk * &k);
+        Self {
+            r,
+            k,
+            ciphertext,
+            t_1,
+            t_2,
+        }
+    }
+
+    /// Bytes to append to the challenge bytes of the proof of knowledge of the signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.ciphertext.to_bytes();
+        bytes.append(&mut self.t_1.to_bytes());
+        bytes.append(&mut self.t_2.to_bytes());
+        bytes
+    }
+
+    pub fn gen_proof(self, challenge: &FieldElement) -> EncryptionProof {
+        let response = &self.k - &(challenge * &self.r);
+        EncryptionProof {
+            ciphertext: self.ciphertext,
+            t_1: self.t_1,
+            t_2: self.t_2,
+            response,
+        }
+    }
+}
+
+impl EncryptionProof {
+    /// Same bytes as `EncryptionCommitted::to_bytes` for the verifier's challenge
+    pub fn get_bytes_for_challenge(&self) -> Vec<u8> {
+        let mut bytes = self.ciphertext.to_bytes();
+        bytes.append(&mut self.t_1.to_bytes());
+        bytes.append(&mut self.t_2.to_bytes());
+        bytes
+    }
+
+    /// Verify that the ciphertext contains the hidden message at `msg_idx` of `pok_proof`, encrypted with the base
+    /// `Y_tilde` of the message's index. `pok_proof` must be verified with the same challenge, computed over it and
+    /// this proof.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify(
+        &self,
+        pok_proof: &PoKOfSignatureProof,
+        msg_idx: usize,
+        revealed_msg_indices: &HashSet<usize>,
+        vk: &Verkey,
+        auditor_pk: &AuditorPublicKey,
+        params: &Params,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        if msg_idx >= vk.Y_tilde.len() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", msg_idx, vk.Y_tilde.len()),
+            });
+        }
+        let s_m = pok_proof.get_resp_for_hidden_message(msg_idx, revealed_msg_indices)?;
+        // g_tilde^response * c_1^challenge == t_1
+        let t_1 = (&params.g_tilde * &self.response) + (&self.ciphertext.c_1 * challenge);
+        // base^s_m * pk^response * c_2^challenge == t_2
+        let t_2 = (&vk.Y_tilde[msg_idx] * &s_m)
+            + (&auditor_pk.pk * &self.response)
+            + (&self.ciphertext.c_2 * challenge);
+        Ok(t_1 == self.t_1 && t_2 == self.t_2)
+    }
+}
+
+/// Start a proof of knowledge of `sig` revealing the messages at `revealed_msg_indices` and encrypt the hidden message
+/// at `msg_idx` for the auditor with a proof that the ciphertext contains it. The challenge is computed over the bytes
+/// of the proof of knowledge followed by those of the encryption.
+#[allow(clippy::too_many_arguments)]
+pub fn init_pok_with_encryption(
+    sig: &Signature,
+    vk: &Verkey,
+    params: &Params,
+    messages: Vec<FieldElement>,
+    revealed_msg_indices: HashSet<usize>,
+    msg_idx: usize,
+    auditor_pk: &AuditorPublicKey,
+) -> Result<(PoKOfSignature, EncryptionCommitted), PSError> {
+    if msg_idx >= messages.len() || msg_idx >= vk.Y_tilde.len() {
+        return Err(PSError::GeneralError {
+            msg: format!("Index {} should be less than {}", msg_idx, messages.len()),
+        });
+    }
+    let blindings = (0..messages.len().saturating_sub(revealed_msg_indices.len()))
+        .map(|_| FieldElement::random())
+        .collect::<Vec<FieldElement>>();
+    let blinding = &blindings[hidden_message_position(msg_idx, &revealed_msg_indices)?];
+    let committed = EncryptionCommitted::new(&messages[msg_idx], blinding, &vk.Y_tilde[msg_idx], auditor_pk, params);
+    let pok = PoKOfSignature::init(sig, vk, params, messages, Some(blindings.as_slice()), revealed_msg_indices)?;
+    Ok((pok, committed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_verifiable_decryption() {
+        let count_msgs = 5;
+        let params = Params::new("test".as_bytes());
+        let (_, vk) = keygen(count_msgs, &params);
+        let (auditor_sk, auditor_pk) = auditor_keygen(&params);
+
+        let msg = FieldElement::random();
+        let (ct, _) = Ciphertext::new(&msg, &vk.Y_tilde[2], &auditor_pk, &params);
+        // Challenge of the presentation that carried the ciphertext
+        let pres_chal = FieldElement::random();
+
+        let (plaintext, proof) = DecryptionProof::new(&ct, &pres_chal, &auditor_sk, &auditor_pk, &params);
+        assert_eq!(plaintext, &vk.Y_tilde[2] * &msg);
+        assert!(proof.verify(&ct, &pres_chal, &plaintext, &auditor_pk, &params));
+        assert!(proof
+            .verify_for_message(&ct, &pres_chal, &msg, &vk.Y_tilde[2], &auditor_pk, &params)
+            .unwrap());
+
+        // Wrong message or wrong base fails
+        assert!(!proof
+            .verify_for_message(&ct, &pres_chal, &FieldElement::random(), &vk.Y_tilde[2], &auditor_pk, &params)
+            .unwrap());
+        assert!(!proof
+            .verify_for_message(&ct, &pres_chal, &msg, &vk.Y_tilde[3], &auditor_pk, &params)
+            .unwrap());
+
+        // Proof does not verify for a different auditor key
+        let (_, other_pk) = auditor_keygen(&params);
+        assert!(!proof.verify(&ct, &pres_chal, &plaintext, &other_pk, &params));
+
+        // Proof does not verify for another presentation
+        assert!(!proof.verify(&ct, &FieldElement::random(), &plaintext, &auditor_pk, &params));
+    }
+
+    #[test]
+    fn test_verifiable_encryption_in_pok() {
+        let count_msgs = 4;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let (auditor_sk, auditor_pk) = auditor_keygen(&params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+        let mut revealed = HashSet::new();
+        revealed.insert(0);
+        let mut revealed_msgs = HashMap::new();
+        revealed_msgs.insert(0, msgs[0].clone());
+
+        // Encrypt the hidden message at 2, like a user id
+        let (pok, committed) =
+            init_pok_with_encryption(&sig, &vk, &params, msgs.clone(), revealed.clone(), 2, &auditor_pk).unwrap();
+        let mut chal_bytes = pok.to_bytes();
+        chal_bytes.append(&mut committed.to_bytes());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        let proof = pok.gen_proof(&challenge).unwrap();
+        let enc_proof = committed.gen_proof(&challenge);
+
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+        chal_bytes.append(&mut enc_proof.get_bytes_for_challenge());
+        let challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(proof.verify(&vk, &params, revealed_msgs, &challenge).unwrap());
+        assert!(enc_proof
+            .verify(&proof, 2, &revealed, &vk, &auditor_pk, &params, &challenge)
+            .unwrap());
+        // Not a proof for another message or auditor
+        assert!(!enc_proof
+            .verify(&proof, 3, &revealed, &vk, &auditor_pk, &params, &challenge)
+            .unwrap());
+        let (_, other_pk) = auditor_keygen(&params);
+        assert!(!enc_proof
+            .verify(&proof, 2, &revealed, &vk, &other_pk, &params, &challenge)
+            .unwrap());
+        assert!(enc_proof
+            .verify(&proof, 0, &revealed, &vk, &auditor_pk, &params, &challenge)
+            .is_err());
+
+        // The auditor decrypts and proves the decryption
+        let (plaintext, dec_proof) =
+            DecryptionProof::new(&enc_proof.ciphertext, &challenge, &auditor_sk, &auditor_pk, &params);
+        assert_eq!(plaintext, &vk.Y_tilde[2] * &msgs[2]);
+        assert!(dec_proof
+            .verify_for_message(&enc_proof.ciphertext, &challenge, &msgs[2], &vk.Y_tilde[2], &auditor_pk, &params)
+            .unwrap());
+
+        // The same ciphertext in another presentation, with another challenge, can't use the decryption proof
+        let mut chal_bytes = proof.get_bytes_for_challenge(revealed.clone(), &vk, &params);
+        chal_bytes.append(&mut enc_proof.get_bytes_for_challenge());
+        chal_bytes.extend_from_slice(b"another presentation");
+        let other_challenge = FieldElement::from_msg_hash(&chal_bytes);
+        assert!(!dec_proof
+            .verify_for_message(&enc_proof.ciphertext, &other_challenge, &msgs[2], &vk.Y_tilde[2], &auditor_pk, &params)
+            .unwrap());
+    }
+}
@@ -0,0 +1,282 @@
+// Borrowed views over the byte representations of a verkey (`Verkey::to_bytes`) and of a proof of knowledge of
+// signature (`PoKOfSignatureProof::to_bytes`). Creating a view only checks the header and the length,
+// elements are parsed and validated when accessed. The bytes for the challenge are slices of the input and a proof
+// can be verified against a verkey view without creating the owned `Verkey` and `PoKOfSignatureProof`, which is
+// useful for verifiers parsing many proofs.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Scheme};
+use crate::signature::Signature;
+use crate::validation::Validate;
+use crate::wire;
+use crate::{
+    ate_2_pairing, VerkeyGroup, VerkeyGroupVec, FIELD_ELEMENT_SIZE, SIGNATURE_GROUP_SIZE, VERKEY_GROUP_SIZE,
+};
+use amcl_wrapper::field_elem::{FieldElement, FieldElementVector};
+use amcl_wrapper::group_elem::{GroupElement, GroupElementVector};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug)]
+pub struct VerkeyRef<'a> {
+    scheme: Scheme,
+    /// Bytes after the header
+    bytes: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ProofRef<'a> {
+    /// Bytes after the header
+    bytes: &'a [u8],
+}
+
+fn parse_verkey_group_element(bytes: &[u8]) -> Result<VerkeyGroup, PSError> {
+    let elem = VerkeyGroup::from_bytes(bytes)?;
+    elem.validate()?;
+    Ok(elem)
+}
+
+impl<'a> VerkeyRef<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PSError> {
+        let (scheme, rest) = wire::read_header(bytes)?;
+        if rest.is_empty() || rest.len() % VERKEY_GROUP_SIZE != 0 {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE + (rest.len() / VERKEY_GROUP_SIZE + 1) * VERKEY_GROUP_SIZE,
+                given: bytes.len(),
+            });
+        }
+        Ok(Self {
+            scheme,
+            bytes: rest,
+        })
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    pub fn msg_count(&self) -> usize {
+        self.bytes.len() / VERKEY_GROUP_SIZE - 1
+    }
+
+    pub fn X_tilde(&self) -> Result<VerkeyGroup, PSError> {
+        parse_verkey_group_element(&self.bytes[..VERKEY_GROUP_SIZE])
+    }
+
+    /// Bytes of the `i`th element of Y_tilde, `i` should be less than `msg_count`
+    pub fn Y_tilde_bytes(&self, i: usize) -> &'a [u8] {
+        let start = (i + 1) * VERKEY_GROUP_SIZE;
+        &self.bytes[start..start + VERKEY_GROUP_SIZE]
+    }
+
+    pub fn Y_tilde(&self, i: usize) -> Result<VerkeyGroup, PSError> {
+        if i >= self.msg_count() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, self.msg_count()),
+            });
+        }
+        parse_verkey_group_element(self.Y_tilde_bytes(i))
+    }
+}
+
+impl<'a> ProofRef<'a> {
+    const SIG_OFFSET: usize = 0;
+    const J_OFFSET: usize = Self::SIG_OFFSET + 2 * SIGNATURE_GROUP_SIZE;
+    const COMMITMENT_OFFSET: usize = Self::J_OFFSET + VERKEY_GROUP_SIZE;
+    const COUNT_OFFSET: usize = Self::COMMITMENT_OFFSET + VERKEY_GROUP_SIZE;
+    const RESPONSES_OFFSET: usize = Self::COUNT_OFFSET + 4;
+
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PSError> {
+        let rest = wire::read_header_for_scheme(bytes, Scheme::PS2016)?;
+        if rest.len() < Self::RESPONSES_OFFSET {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE + Self::RESPONSES_OFFSET,
+                given: bytes.len(),
+            });
+        }
+        let proof = Self { bytes: rest };
+        let responses_len = rest.len() - Self::RESPONSES_OFFSET;
+        if responses_len % FIELD_ELEMENT_SIZE != 0 || responses_len / FIELD_ELEMENT_SIZE != proof.response_count() {
+            return Err(PSError::IncorrectByteLength {
+                expected: wire::HEADER_SIZE
+                    + Self::RESPONSES_OFFSET
+                    + proof.response_count().saturating_mul(FIELD_ELEMENT_SIZE),
+                given: bytes.len(),
+            });
+        }
+        Ok(proof)
+    }
+
+    /// Bytes of the randomized signature without the header
+    pub fn sig_bytes(&self) -> &'a [u8] {
+        &self.bytes[Self::SIG_OFFSET..Self::J_OFFSET]
+    }
+
+    pub fn J_bytes(&self) -> &'a [u8] {
+        &self.bytes[Self::J_OFFSET..Self::COMMITMENT_OFFSET]
+    }
+
+    pub fn commitment_bytes(&self) -> &'a [u8] {
+        &self.bytes[Self::COMMITMENT_OFFSET..Self::COUNT_OFFSET]
+    }
+
+    /// No of responses, 1 more than the no of hidden messages
+    pub fn response_count(&self) -> usize {
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&self.bytes[Self::COUNT_OFFSET..Self::RESPONSES_OFFSET]);
+        u32::from_be_bytes(count) as usize
+    }
+
+    pub fn sig(&self) -> Result<Signature, PSError> {
+        Signature::from_bytes_without_header(self.sig_bytes())
+    }
+
+    pub fn J(&self) -> Result<VerkeyGroup, PSError> {
+        parse_verkey_group_element(self.J_bytes())
+    }
+
+    pub fn response(&self, i: usize) -> Result<FieldElement, PSError> {
+        if i >= self.response_count() {
+            return Err(PSError::GeneralError {
+                msg: format!("Index {} should be less than {}", i, self.response_count()),
+            });
+        }
+        let start = Self::RESPONSES_OFFSET + i * FIELD_ELEMENT_SIZE;
+        Ok(FieldElement::from_bytes(&self.bytes[start..start + FIELD_ELEMENT_SIZE])?)
+    }
+
+    /// Same as `PoKOfSignatureProof::get_bytes_for_challenge`
+    pub fn get_bytes_for_challenge(
+        &self,
+        revealed_msg_indices: &[usize],
+        vk: &VerkeyRef,
+        params: &Params,
+    ) -> Vec<u8> {
+        // Same as `Signature::to_bytes`
+        let mut bytes = wire::header(Scheme::PS2016);
+        bytes.extend_from_slice(self.sig_bytes());
+        bytes.extend_from_slice(self.J_bytes());
+        bytes.append(&mut params.g_tilde.to_bytes());
+        for i in 0..vk.msg_count() {
+            if revealed_msg_indices.contains(&i) {
+                continue;
+            }
+            bytes.extend_from_slice(vk.Y_tilde_bytes(i));
+        }
+        bytes.extend_from_slice(self.commitment_bytes());
+        bytes
+    }
+
+    /// Same as `PoKOfSignatureProof::verify`
+    pub fn verify(
+        &self,
+        vk: &VerkeyRef,
+        params: &Params,
+        revealed_msgs: &HashMap<usize, FieldElement>,
+        challenge: &FieldElement,
+    ) -> Result<bool, PSError> {
+        for i in revealed_msgs.keys() {
+            if *i >= vk.msg_count() {
+                return Err(PSError::GeneralError {
+                    msg: format!("Index {} should be less than {}", i, vk.msg_count()),
+                });
+            }
+        }
+        // +1 for `t`
+        let base_count = vk.msg_count() - revealed_msgs.len() + 1;
+        if base_count != self.response_count() {
+            return Err(PSError::UnequalNoOfBasesExponents {
+                bases: base_count,
+                exponents: self.response_count(),
+            });
+        }
+        let sig = self.sig()?;
+        if sig.is_identity() {
+            return Ok(false);
+        }
+        let J = self.J()?;
+        let commitment = parse_verkey_group_element(self.commitment_bytes())?;
+
+        // g_tilde^responses[0] * Y_tilde_i^responses[..] * J^challenge == commitment
+        let mut points = VerkeyGroupVec::with_capacity(base_count + 1);
+        let mut scalars = FieldElementVector::with_capacity(base_count + 1);
+        points.push(params.g_tilde.clone());
+        scalars.push(self.response(0)?);
+        let mut j = 1;
+        for i in 0..vk.msg_count() {
+            if revealed_msgs.contains_key(&i) {
+                continue;
+            }
+            points.push(vk.Y_tilde(i)?);
+            scalars.push(self.response(j)?);
+            j += 1;
+        }
+        points.push(J.clone());
+        scalars.push(challenge.clone());
+        if !(points.multi_scalar_mul_var_time(&scalars).unwrap() - &commitment).is_identity() {
+            return Ok(false);
+        }
+
+        // e(sigma_1, J * X_tilde * Y_tilde_i^m_i for revealed m_i) == e(sigma_2, g_tilde)
+        let mut J = J + vk.X_tilde()?;
+        for (i, m) in revealed_msgs {
+            J += vk.Y_tilde(*i)? * m;
+        }
+        let res = ate_2_pairing(&sig.sigma_1, &J, &(-&sig.sigma_2), &params.g_tilde);
+        Ok(res.is_one())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+    use crate::pok_sig::PoKOfSignature;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_views() {
+        let count_msgs = 6;
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(count_msgs, &params);
+        let msgs = (0..count_msgs).map(|_| FieldElement::random()).collect::<Vec<FieldElement>>();
+        let sig = Signature::new(msgs.as_slice(), &sk, &params).unwrap();
+
+        let revealed_msg_indices = vec![2, 4];
+        let pok = PoKOfSignature::init(
+            &sig,
+            &vk,
+            &params,
+            msgs.clone(),
+            None,
+            revealed_msg_indices.iter().cloned().collect(),
+        )
+        .unwrap();
+        let chal_prover = FieldElement::from_msg_hash(&pok.to_bytes());
+        let proof_bytes = pok.gen_proof(&chal_prover).unwrap().to_bytes();
+        let vk_bytes = vk.to_bytes(Scheme::PS2016);
+
+        let vk_ref = VerkeyRef::new(&vk_bytes).unwrap();
+        assert_eq!(vk_ref.msg_count(), count_msgs);
+        assert_eq!(vk_ref.scheme(), Scheme::PS2016);
+        let proof_ref = ProofRef::new(&proof_bytes).unwrap();
+        assert_eq!(proof_ref.response_count(), count_msgs - revealed_msg_indices.len() + 1);
+
+        let chal_bytes = proof_ref.get_bytes_for_challenge(&revealed_msg_indices, &vk_ref, &params);
+        let revealed_set = revealed_msg_indices.iter().cloned().collect::<HashSet<usize>>();
+        let owned_proof = crate::pok_sig::PoKOfSignatureProof::from_bytes(&proof_bytes).unwrap();
+        assert_eq!(chal_bytes, owned_proof.get_bytes_for_challenge(revealed_set, &vk, &params));
+        let chal_verifier = FieldElement::from_msg_hash(&chal_bytes);
+
+        let mut revealed_msgs = HashMap::new();
+        for i in &revealed_msg_indices {
+            revealed_msgs.insert(*i, msgs[*i].clone());
+        }
+        assert!(proof_ref.verify(&vk_ref, &params, &revealed_msgs, &chal_verifier).unwrap());
+
+        revealed_msgs.insert(2, FieldElement::random());
+        assert!(!proof_ref.verify(&vk_ref, &params, &revealed_msgs, &chal_verifier).unwrap());
+
+        assert!(ProofRef::new(&proof_bytes[..proof_bytes.len() - 1]).is_err());
+        assert!(VerkeyRef::new(&vk_bytes[..vk_bytes.len() - 1]).is_err());
+    }
+}
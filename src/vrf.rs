@@ -0,0 +1,140 @@
+// Verifiable random function keyed by the secret x of a signing key (or any secret whose public key is
+// g_tilde^secret, like a holder's key). For an input, H = hash of the input to VerkeyGroup, gamma = H^x and the output
+// is the hash of gamma. The proof of correct evaluation is a Chaum-Pedersen proof that discrete log of X_tilde to
+// base g_tilde equals discrete log of gamma to base H, so it is verifiable using only the Verkey and Params.
+
+use crate::errors::PSError;
+use crate::keys::{Params, Sigkey, Verkey};
+use crate::VerkeyGroup;
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+
+/// Domain separation tag for hashing the input to the group
+pub const VRF_INPUT_DST: &[u8] = b"PS-SIG-VRF-INPUT";
+/// Domain separation tag for hashing gamma to the output
+pub const VRF_OUTPUT_DST: &[u8] = b"PS-SIG-VRF-OUTPUT";
+/// Domain separation tag for the challenge of the proof of correct evaluation
+pub const VRF_CHALLENGE_DST: &[u8] = b"PS-SIG-VRF";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VRFProof {
+    pub gamma: VerkeyGroup,
+    pub challenge: FieldElement,
+    pub response: FieldElement,
+}
+
+impl VRFProof {
+    /// Evaluate the VRF on `input` with `secret` and prove the evaluation. The output is obtained with `output`.
+    pub fn new(input: &[u8], secret: &FieldElement, params: &Params) -> Self {
+        let h = Self::hash_input(input);
+        let gamma = &h * secret;
+        let public = &params.g_tilde * secret;
+        let k = FieldElement::random();
+        let t_1 = &params.g_tilde * &k;
+        let t_2 = &h * &k;
+        let challenge = Self::compute_challenge(&h, &public, &gamma, &t_1, &t_2, params);
+        let response = &k - &(&challenge * secret);
+        Self {
+            gamma,
+            challenge,
+            response,
+        }
+    }
+
+    /// Evaluate the VRF with the secret `x` of the signing key
+    pub fn new_with_sigkey(input: &[u8], sigkey: &Sigkey, params: &Params) -> Self {
+        Self::new(input, &sigkey.x, params)
+    }
+
+    /// Verify the proof of evaluation on `input` for the public key `public` = g_tilde^secret
+    pub fn verify(&self, input: &[u8], public: &VerkeyGroup, params: &Params) -> Result<bool, PSError> {
+        if self.gamma.is_identity() {
+            return Ok(false);
+        }
+        let h = Self::hash_input(input);
+        // t_1 = g_tilde^response * public^challenge, t_2 = H^response * gamma^challenge
+        let t_1 = (&params.g_tilde * &self.response) + (public * &self.challenge);
+        let t_2 = (&h * &self.response) + (&self.gamma * &self.challenge);
+        let challenge = Self::compute_challenge(&h, public, &self.gamma, &t_1, &t_2, params);
+        Ok(challenge == self.challenge)
+    }
+
+    /// Verify the proof of evaluation done with the signing key corresponding to `vk`
+    pub fn verify_with_verkey(&self, input: &[u8], vk: &Verkey, params: &Params) -> Result<bool, PSError> {
+        self.verify(input, &vk.X_tilde, params)
+    }
+
+    /// Output of the VRF. Only meaningful after the proof has been verified.
+    pub fn output(&self) -> FieldElement {
+        FieldElement::from_msg_hash(&[VRF_OUTPUT_DST, &self.gamma.to_bytes()].concat())
+    }
+
+    fn hash_input(input: &[u8]) -> VerkeyGroup {
+        VerkeyGroup::from_msg_hash(&[VRF_INPUT_DST, input].concat())
+    }
+
+    fn compute_challenge(
+        h: &VerkeyGroup,
+        public: &VerkeyGroup,
+        gamma: &VerkeyGroup,
+        t_1: &VerkeyGroup,
+        t_2: &VerkeyGroup,
+        params: &Params,
+    ) -> FieldElement {
+        let mut bytes = VRF_CHALLENGE_DST.to_vec();
+        bytes.append(&mut params.g_tilde.to_bytes());
+        bytes.append(&mut h.to_bytes());
+        bytes.append(&mut public.to_bytes());
+        bytes.append(&mut gamma.to_bytes());
+        bytes.append(&mut t_1.to_bytes());
+        bytes.append(&mut t_2.to_bytes());
+        FieldElement::from_msg_hash(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::keygen;
+
+    #[test]
+    fn test_vrf() {
+        let params = Params::new("test".as_bytes());
+        let (sk, vk) = keygen(2, &params);
+        let (_, other_vk) = keygen(2, &params);
+
+        let input = "credential 1".as_bytes();
+        let proof = VRFProof::new_with_sigkey(input, &sk, &params);
+        assert!(proof.verify_with_verkey(input, &vk, &params).unwrap());
+        assert!(!proof.verify_with_verkey("credential 2".as_bytes(), &vk, &params).unwrap());
+        assert!(!proof.verify_with_verkey(input, &other_vk, &params).unwrap());
+
+        // Output is deterministic and differs across inputs
+        let proof_again = VRFProof::new_with_sigkey(input, &sk, &params);
+        assert_eq!(proof.output(), proof_again.output());
+        let proof_2 = VRFProof::new_with_sigkey("credential 2".as_bytes(), &sk, &params);
+        assert_ne!(proof.output(), proof_2.output());
+
+        // Holder's own key
+        let secret = FieldElement::random();
+        let public = &params.g_tilde * &secret;
+        let proof = VRFProof::new(input, &secret, &params);
+        assert!(proof.verify(input, &public, &params).unwrap());
+
+        // Wrong gamma is rejected
+        let mut bad_proof = proof.clone();
+        bad_proof.gamma = VerkeyGroup::random();
+        assert!(!bad_proof.verify(input, &public, &params).unwrap());
+
+        // The challenge is over the bytes prefixed with the DST
+        let h = VRFProof::hash_input(input);
+        let t_1 = (&params.g_tilde * &proof.response) + (&public * &proof.challenge);
+        let t_2 = (&h * &proof.response) + (&proof.gamma * &proof.challenge);
+        let mut bytes = vec![];
+        for e in &[&params.g_tilde, &h, &public, &proof.gamma, &t_1, &t_2] {
+            bytes.append(&mut e.to_bytes());
+        }
+        assert_ne!(FieldElement::from_msg_hash(&bytes), proof.challenge);
+        assert_eq!(FieldElement::from_msg_hash(&[VRF_CHALLENGE_DST, &bytes].concat()), proof.challenge);
+    }
+}
@@ -0,0 +1,173 @@
+// Header prefixed to the binary encodings (`to_bytes`) of signatures, verkeys and proofs: a version byte, the scheme
+// tag (`Scheme::to_byte`) and a marker of the group signatures are in. A future change of the encoding, like
+// compressed points, gets a new version so artifacts encoded with an older version can still be recognized, and
+// `from_bytes` fails with `PSError::UnsupportedVersion` for versions it does not know rather than misparsing the
+// bytes. Artifacts of a `SignatureG1` build can't be used by a `SignatureG2` build and vice versa, parsing them fails
+// with `PSError::GroupMismatch` and `peek_group` tells which build created them.
+
+use crate::errors::PSError;
+use crate::keys::Scheme;
+use std::io::Write;
+
+/// Current version of the binary encodings
+pub const WIRE_FORMAT_VERSION: u8 = 1;
+
+/// Size of the header in bytes
+pub const HEADER_SIZE: usize = 3;
+
+/// The group signatures are in, decided by the features `SignatureG1` and `SignatureG2`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GroupMarker {
+    SignatureG1,
+    SignatureG2,
+}
+
+#[cfg(feature = "SignatureG2")]
+pub const GROUP_MARKER: GroupMarker = GroupMarker::SignatureG2;
+#[cfg(feature = "SignatureG1")]
+pub const GROUP_MARKER: GroupMarker = GroupMarker::SignatureG1;
+
+impl GroupMarker {
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            GroupMarker::SignatureG1 => 1,
+            GroupMarker::SignatureG2 => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(GroupMarker::SignatureG1),
+            2 => Some(GroupMarker::SignatureG2),
+            _ => None,
+        }
+    }
+
+    /// Curve identifier used in DER encoding of keys, same as `CURVE_ID` of the build with this group
+    pub fn curve_id(&self) -> &'static str {
+        match self {
+            GroupMarker::SignatureG1 => "BLS12-381/SignatureG1",
+            GroupMarker::SignatureG2 => "BLS12-381/SignatureG2",
+        }
+    }
+
+    pub fn from_curve_id(curve_id: &str) -> Option<Self> {
+        match curve_id {
+            "BLS12-381/SignatureG1" => Some(GroupMarker::SignatureG1),
+            "BLS12-381/SignatureG2" => Some(GroupMarker::SignatureG2),
+            _ => None,
+        }
+    }
+}
+
+/// Error if `found` is not the group of this build
+pub fn check_group(found: GroupMarker) -> Result<(), PSError> {
+    if found != GROUP_MARKER {
+        return Err(PSError::GroupMismatch {
+            expected: format!("{:?}", GROUP_MARKER),
+            given: format!("{:?}", found),
+        });
+    }
+    Ok(())
+}
+
+pub fn header(scheme: Scheme) -> Vec<u8> {
+    vec![WIRE_FORMAT_VERSION, scheme.to_byte(), GROUP_MARKER.to_byte()]
+}
+
+pub fn write_header<W: Write>(scheme: Scheme, writer: &mut W) -> Result<(), PSError> {
+    writer.write_all(&[WIRE_FORMAT_VERSION, scheme.to_byte(), GROUP_MARKER.to_byte()])?;
+    Ok(())
+}
+
+/// Write `len` bytes with `write` to the start of `buf`, returns `len`. Used for the `write_into` methods.
+pub fn write_into<F>(len: usize, buf: &mut [u8], write: F) -> Result<usize, PSError>
+where
+    F: FnOnce(&mut &mut [u8]) -> Result<(), PSError>,
+{
+    if buf.len() < len {
+        return Err(PSError::IncorrectByteLength {
+            expected: len,
+            given: buf.len(),
+        });
+    }
+    let mut writer = &mut buf[..len];
+    write(&mut writer)?;
+    Ok(len)
+}
+
+/// The group of the build that created the encoding, works for encodings from either build
+pub fn peek_group(bytes: &[u8]) -> Result<GroupMarker, PSError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(PSError::IncorrectByteLength {
+            expected: HEADER_SIZE,
+            given: bytes.len(),
+        });
+    }
+    if bytes[0] != WIRE_FORMAT_VERSION {
+        return Err(PSError::UnsupportedVersion { version: bytes[0] });
+    }
+    GroupMarker::from_byte(bytes[2]).ok_or_else(|| PSError::InvalidBytes {
+        msg: format!("Unknown group {}", bytes[2]),
+    })
+}
+
+/// Parse the header, returns the scheme and the bytes after the header
+pub fn read_header(bytes: &[u8]) -> Result<(Scheme, &[u8]), PSError> {
+    check_group(peek_group(bytes)?)?;
+    let scheme = Scheme::from_byte(bytes[1]).ok_or_else(|| PSError::InvalidBytes {
+        msg: format!("Unknown scheme {}", bytes[1]),
+    })?;
+    Ok((scheme, &bytes[HEADER_SIZE..]))
+}
+
+/// Parse the header and check that it is for `expected` scheme, returns the bytes after the header
+pub fn read_header_for_scheme(bytes: &[u8], expected: Scheme) -> Result<&[u8], PSError> {
+    let (scheme, rest) = read_header(bytes)?;
+    if scheme != expected {
+        return Err(PSError::InvalidBytes {
+            msg: format!("Expected scheme {:?} but found {:?}", expected, scheme),
+        });
+    }
+    Ok(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        let mut bytes = header(Scheme::PS2018);
+        bytes.push(7);
+        assert_eq!(read_header(&bytes).unwrap(), (Scheme::PS2018, &[7u8][..]));
+        assert_eq!(read_header_for_scheme(&bytes, Scheme::PS2018).unwrap(), &[7u8]);
+        assert!(read_header_for_scheme(&bytes, Scheme::PS2016).is_err());
+
+        bytes[0] = WIRE_FORMAT_VERSION + 1;
+        match read_header(&bytes) {
+            Err(PSError::UnsupportedVersion { version }) => assert_eq!(version, WIRE_FORMAT_VERSION + 1),
+            _ => panic!("Expected UnsupportedVersion"),
+        }
+        assert!(read_header(&[WIRE_FORMAT_VERSION, 0, GROUP_MARKER.to_byte()]).is_err());
+        assert!(read_header(&[WIRE_FORMAT_VERSION, 1]).is_err());
+    }
+
+    #[test]
+    fn test_group_mismatch() {
+        let other_group = match GROUP_MARKER {
+            GroupMarker::SignatureG1 => GroupMarker::SignatureG2,
+            GroupMarker::SignatureG2 => GroupMarker::SignatureG1,
+        };
+        let mut bytes = header(Scheme::PS2016);
+        assert_eq!(peek_group(&bytes).unwrap(), GROUP_MARKER);
+        bytes[2] = other_group.to_byte();
+        assert_eq!(peek_group(&bytes).unwrap(), other_group);
+        match read_header(&bytes) {
+            Err(PSError::GroupMismatch { .. }) => (),
+            _ => panic!("Expected GroupMismatch"),
+        }
+        assert_eq!(GroupMarker::from_curve_id(crate::CURVE_ID), Some(GROUP_MARKER));
+        assert_eq!(GroupMarker::from_curve_id(other_group.curve_id()), Some(other_group));
+    }
+}
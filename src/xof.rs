@@ -0,0 +1,99 @@
+// Create several field and group elements from a single hash of the input. The input is absorbed once in
+// SHAKE-256 and for each requested element, a slice of `XOF_BYTES_PER_ELEMENT` bytes is squeezed out, prefixed
+// with the position of the element (for domain separation) and mapped to the element. Hashing a long input
+// (like the messages of a signature) once is cheaper than hashing it separately for each element.
+
+use amcl_wrapper::field_elem::FieldElement;
+use amcl_wrapper::group_elem::GroupElement;
+use amcl_wrapper::group_elem_g1::G1;
+use amcl_wrapper::group_elem_g2::G2;
+use sha3::digest::{ExtendableOutput, Input, XofReader};
+use sha3::Shake256;
+
+/// Number of bytes squeezed from the XOF for each element
+pub const XOF_BYTES_PER_ELEMENT: usize = 64;
+
+/// Types that can be created by hashing bytes.
+pub trait HashToElement {
+    fn hash_to_element(bytes: &[u8]) -> Self;
+}
+
+impl HashToElement for FieldElement {
+    fn hash_to_element(bytes: &[u8]) -> Self {
+        FieldElement::from_msg_hash(bytes)
+    }
+}
+
+impl HashToElement for G1 {
+    fn hash_to_element(bytes: &[u8]) -> Self {
+        G1::from_msg_hash(bytes)
+    }
+}
+
+impl HashToElement for G2 {
+    fn hash_to_element(bytes: &[u8]) -> Self {
+        G2::from_msg_hash(bytes)
+    }
+}
+
+/// Reads elements from the XOF output of the input
+pub struct ElementReader {
+    reader: <Shake256 as ExtendableOutput>::Reader,
+    count: u32,
+}
+
+impl ElementReader {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut hasher = Shake256::default();
+        hasher.input(bytes);
+        Self {
+            reader: hasher.xof_result(),
+            count: 0,
+        }
+    }
+
+    /// Squeeze the next slice from the XOF and map it to an element
+    pub fn next_element<T: HashToElement>(&mut self) -> T {
+        let mut buf = [0u8; 4 + XOF_BYTES_PER_ELEMENT];
+        buf[..4].copy_from_slice(&self.count.to_be_bytes());
+        self.reader.read(&mut buf[4..]);
+        self.count += 1;
+        T::hash_to_element(&buf)
+    }
+}
+
+/// Create a tuple of elements of the given types from a single hash of the bytes.
+/// `let (m, g) = hash_to_elements!(&bytes; FieldElement, SignatureGroup);`
+#[macro_export]
+macro_rules! hash_to_elements {
+    ( $bytes:expr; $( $t:ty ),+ ) => {{
+        let mut reader = $crate::xof::ElementReader::new($bytes);
+        ( $( reader.next_element::<$t>() ),+ )
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SignatureGroup, VerkeyGroup};
+
+    #[test]
+    fn test_hash_to_elements() {
+        let bytes = "test".as_bytes();
+        let (f_1, g_1, h_1, f_2) =
+            hash_to_elements!(bytes; FieldElement, SignatureGroup, VerkeyGroup, FieldElement);
+        // Deterministic
+        let (f_1_, g_1_, h_1_, f_2_) =
+            hash_to_elements!(bytes; FieldElement, SignatureGroup, VerkeyGroup, FieldElement);
+        assert_eq!(f_1, f_1_);
+        assert_eq!(g_1, g_1_);
+        assert_eq!(h_1, h_1_);
+        assert_eq!(f_2, f_2_);
+        // Elements at different positions are different
+        assert_ne!(f_1, f_2);
+        // Different input gives different elements
+        let (f_3, g_3) = hash_to_elements!("test1".as_bytes(); FieldElement, SignatureGroup);
+        assert_ne!(f_1, f_3);
+        assert_ne!(g_1, g_3);
+    }
+}